@@ -0,0 +1,119 @@
+//! Benchmarks of the note detection at the sample rates of common audio
+//! interfaces. With the guitar note table the FFT sizes range from about
+//! 11k samples at 22.05 kHz to 49k samples at 96 kHz.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use libreguitar_core::audio_analysis::bench;
+use libreguitar_core::{AudioAnalyzer, AudioCfg, NoteRegistry, PitchMethod, WindowFunction};
+use std::f64::consts::PI;
+
+const SAMPLE_RATES: [usize; 4] = [22050, 44100, 48000, 96000];
+/// Samples per analysis, as in the default `block_size` of the app.
+const BLOCK_SIZE: usize = 2048;
+
+/// Analysis settings of the default `cfg/audio.toml`.
+fn audio_cfg() -> AudioCfg {
+    AudioCfg {
+        fft_res_factor: 2.0,
+        fft_magnitude_gain: 10.0,
+        window_function: WindowFunction::Rectangular,
+        peak_threshold: 500.0,
+        min_peak_dist: 10,
+        num_top_peaks: 5,
+        moving_avg_window_size: 11,
+        silence_rms: 0.001,
+        noise_floor_margin: 3.0,
+        pitch_method: PitchMethod::Peaks,
+        hps_harmonics: 3,
+        auto_gain: None,
+    }
+}
+
+fn analyzer(sample_rate: usize) -> AudioAnalyzer {
+    let path = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../resources/guitar_frequencies.csv"
+    );
+    let registry = NoteRegistry::from_csv(path).unwrap();
+    AudioAnalyzer::new(sample_rate, registry.notes(), audio_cfg()).unwrap()
+}
+
+/// A string plucked at A2 with its first few harmonics fading out.
+fn pluck(sample_rate: usize) -> Vec<f64> {
+    (0..BLOCK_SIZE)
+        .map(|i| {
+            let t = i as f64 / sample_rate as f64;
+            (1..6)
+                .map(|harmonic| {
+                    let frequency = 110.0 * harmonic as f64;
+                    (2.0 * PI * frequency * t).sin() / harmonic as f64
+                })
+                .sum::<f64>()
+        })
+        .collect()
+}
+
+fn bench_compute_fft(c: &mut Criterion) {
+    c.bench_function_over_inputs(
+        "compute_fft",
+        |b, &&sample_rate| {
+            let mut analyzer = analyzer(sample_rate);
+            let samples = pluck(sample_rate);
+            b.iter(|| bench::compute_fft(&mut analyzer, black_box(&samples)))
+        },
+        &SAMPLE_RATES,
+    );
+}
+
+fn bench_moving_avg(c: &mut Criterion) {
+    c.bench_function_over_inputs(
+        "moving_avg",
+        |b, &&sample_rate| {
+            let mut analyzer = analyzer(sample_rate);
+            bench::compute_fft(&mut analyzer, &pluck(sample_rate));
+            let spectrum = analyzer.spectrogram().clone();
+            let window_size = audio_cfg().moving_avg_window_size;
+            b.iter(|| {
+                let mut spectrum = spectrum.clone();
+                bench::moving_avg(black_box(&mut spectrum), window_size);
+                spectrum
+            })
+        },
+        &SAMPLE_RATES,
+    );
+}
+
+fn bench_find_peaks(c: &mut Criterion) {
+    c.bench_function_over_inputs(
+        "find_peaks",
+        |b, &&sample_rate| {
+            let mut analyzer = analyzer(sample_rate);
+            analyzer.identify_note(pluck(sample_rate).into_iter());
+            let spectrum = analyzer.spectrogram().clone();
+            let cfg = audio_cfg();
+            b.iter(|| bench::find_peaks(black_box(&spectrum), 0.0, cfg.min_peak_dist))
+        },
+        &SAMPLE_RATES,
+    );
+}
+
+fn bench_identify_note(c: &mut Criterion) {
+    c.bench_function_over_inputs(
+        "identify_note",
+        |b, &&sample_rate| {
+            let mut analyzer = analyzer(sample_rate);
+            let samples = pluck(sample_rate);
+            b.iter(|| analyzer.identify_note(black_box(&samples).iter().cloned()))
+        },
+        &SAMPLE_RATES,
+    );
+}
+
+criterion_group!(
+    benches,
+    bench_compute_fft,
+    bench_moving_avg,
+    bench_find_peaks,
+    bench_identify_note
+);
+criterion_main!(benches);
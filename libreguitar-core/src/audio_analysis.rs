@@ -0,0 +1,22 @@
+mod algorithm;
+mod analysis_result;
+mod analyzer;
+mod auto_gain;
+#[cfg(feature = "bench")]
+#[doc(hidden)]
+pub mod bench;
+mod fft_cache;
+mod noise_floor;
+mod pitch_detector;
+mod sample_window;
+mod target_notes;
+mod window_function;
+
+pub use analysis_result::{AnalysisResult, NotePeak};
+pub use analyzer::{AudioAnalyzer, AudioParams};
+pub use noise_floor::{NoiseFloor, NoiseFloorError};
+pub use pitch_detector::{
+    pitch_detector, Autocorrelation, DetectionInput, HarmonicProduct, PitchDetector, SpectrumPeaks,
+};
+pub use sample_window::read_channel_buffered;
+pub use target_notes::{TargetNotes, TooFewNotesError};
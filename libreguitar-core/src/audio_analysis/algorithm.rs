@@ -0,0 +1,571 @@
+use crate::audio_analysis::analysis_result::NotePeak;
+use crate::audio_analysis::target_notes::TargetNotes;
+use crate::core::Note;
+use statrs::statistics::Median;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// Finds the `num_top_peaks` strongest peaks of `freq_spectrum` above the
+/// threshold and matches each of them to its closest note. The strongest peak
+/// comes first. The frequencies and magnitudes of the peaks are interpolated
+/// between the bins.
+///
+/// The threshold of every bin is given by `noise_thresholds` if the noise
+/// floor was calibrated, and is `peak_threshold_median_coeff` times the
+/// median magnitude otherwise.
+pub fn find_top_peaks(
+    freq_spectrum: &[f64],
+    delta_f: f64,
+    target_notes: &TargetNotes,
+    peak_threshold_median_coeff: f64,
+    noise_thresholds: Option<&[f64]>,
+    min_peak_dist: usize,
+    num_top_peaks: usize,
+) -> Vec<NotePeak> {
+    let mut peaks = match noise_thresholds {
+        Some(thresholds) => find_peaks_above(
+            freq_spectrum,
+            |idx| thresholds.get(idx).cloned().unwrap_or(0.0),
+            Some(min_peak_dist),
+        ),
+        None => {
+            let median = freq_spectrum.median();
+            find_peaks(
+                freq_spectrum,
+                Some(peak_threshold_median_coeff * median),
+                Some(min_peak_dist),
+            )
+        }
+    };
+    peaks.sort_unstable_by(|a, b| a.value.partial_cmp(&b.value).unwrap());
+    peaks
+        .into_iter()
+        .rev()
+        .take(num_top_peaks)
+        .map(|p| {
+            let (offset, magnitude) = interpolate_peak(freq_spectrum, p.idx);
+            let frequency = (p.idx as f64 + offset) * delta_f;
+            NotePeak {
+                frequency,
+                magnitude,
+                note: target_notes.get_closest(frequency).clone(),
+            }
+        })
+        .collect()
+}
+
+/// Offset in bins from `idx` to the top of the parabola through the peak of
+/// `spectrum` at `idx` and its two neighbours, along with the magnitude at the
+/// top. Peaks at the edges of the spectrum are not interpolated.
+fn interpolate_peak(spectrum: &[f64], idx: usize) -> (f64, f64) {
+    let peak = spectrum[idx];
+    if idx == 0 || idx + 1 >= spectrum.len() {
+        return (0.0, peak);
+    }
+    let (left, right) = (spectrum[idx - 1], spectrum[idx + 1]);
+    let curvature = left - 2.0 * peak + right;
+    if curvature >= 0.0 {
+        return (0.0, peak);
+    }
+    let offset = (0.5 * (left - right) / curvature).clamp(-0.5, 0.5);
+    (offset, peak - 0.25 * (left - right) * offset)
+}
+
+/// Finds the note played given the strongest spectrum peaks and returns it
+/// together with the frequency of the peak it was matched from.
+pub fn find_note(top_peaks: &[NotePeak]) -> Option<(Note, f64)> {
+    let top_notenames = top_peaks.iter().map(|peak| &peak.note.name);
+    if let Some(notename) = most_common(top_notenames) {
+        let top_peaks = top_peaks.iter().filter(|peak| peak.note.name == *notename);
+        let min_peak =
+            top_peaks.min_by(|a, b| a.note.frequency.partial_cmp(&b.note.frequency).unwrap());
+        if let Some(peak) = min_peak {
+            return Some((peak.note.clone(), peak.frequency));
+        }
+    }
+    None
+}
+
+/// Confidence in the range 0–1 of a note detected with its fundamental at
+/// `fundamental` hertz: the share of the magnitude of `top_peaks` at
+/// harmonics of the fundamental, times the prominence of the strongest of
+/// them over the `background` magnitude of the spectrum.
+pub fn detection_confidence(top_peaks: &[NotePeak], fundamental: f64, background: f64) -> f64 {
+    let is_harmonic = |peak: &&NotePeak| {
+        let harmonic = (peak.frequency / fundamental).round().max(1.0);
+        (1200.0 * (peak.frequency / (harmonic * fundamental)).log2()).abs() < 50.0
+    };
+    let total: f64 = top_peaks.iter().map(|peak| peak.magnitude).sum();
+    let harmonics = top_peaks.iter().filter(is_harmonic);
+    let (harmonic_total, strongest) = harmonics.fold((0.0, 0.0f64), |(sum, max), peak| {
+        (sum + peak.magnitude, max.max(peak.magnitude))
+    });
+    if total <= 0.0 || strongest <= 0.0 {
+        return 0.0;
+    }
+    let prominence = (1.0 - background / strongest).max(0.0);
+    harmonic_total / total * prominence
+}
+
+fn most_common<'a, T>(notes: impl Iterator<Item = &'a T>) -> Option<&'a T>
+where
+    T: Eq + Hash,
+{
+    let mut counts = HashMap::new();
+    for val in notes {
+        if let Some(count) = counts.get_mut(val) {
+            *count += 1;
+        } else {
+            counts.insert(val, 1);
+        }
+    }
+    if let Some((value, _)) = counts.into_iter().max_by_key(|(_, count)| *count) {
+        Some(value)
+    } else {
+        None
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) struct Peak<T> {
+    idx: usize,
+    value: T,
+}
+
+impl<T> Peak<T> {
+    fn new(idx: usize, value: T) -> Peak<T> {
+        Peak { idx, value }
+    }
+}
+
+pub(crate) fn find_peaks(
+    signal: &[f64],
+    min_height: Option<f64>,
+    min_peak_dist: Option<usize>,
+) -> Vec<Peak<f64>> {
+    let min_height = min_height.unwrap_or(0.0);
+    find_peaks_above(signal, |_| min_height, min_peak_dist)
+}
+
+/// Finds the peaks of `signal` at least as high as `min_height` of their
+/// index.
+fn find_peaks_above(
+    signal: &[f64],
+    min_height: impl Fn(usize) -> f64,
+    min_peak_dist: Option<usize>,
+) -> Vec<Peak<f64>> {
+    let n_samples = signal.len();
+    if n_samples == 0 {
+        return Vec::new();
+    } else if n_samples == 1 {
+        return vec![Peak::new(0, signal[0])];
+    }
+    let min_peak_dist = min_peak_dist.unwrap_or(0);
+    let mut out: Vec<Peak<f64>> = Vec::new();
+    for i in 0..n_samples {
+        let greater_than_left = i == 0 || signal[i] > signal[i - 1];
+        let greater_than_right = i == n_samples - 1 || signal[i] > signal[i + 1];
+        let is_peak = greater_than_left && greater_than_right && signal[i] >= min_height(i);
+        let is_far_apart = out.is_empty() || i - out[out.len() - 1].idx >= min_peak_dist;
+        if is_peak && is_far_apart {
+            out.push(Peak::new(i, signal[i]));
+        }
+    }
+    out
+}
+
+pub fn moving_avg(signal: &mut [f64], window_size: usize) {
+    assert!(
+        window_size > 0,
+        "Moving avg for zero window size is undefined."
+    );
+    if signal.is_empty() || window_size == 1 {
+        return;
+    }
+    let mut cumsum = vec![0.0f64; signal.len()];
+    cumsum[0] = signal[0];
+    for i in 1..signal.len() {
+        cumsum[i] = cumsum[i - 1] + signal[i];
+    }
+    let left_offset = window_size / 2;
+    let right_offset = window_size - 1 - left_offset;
+    for i in (0..cumsum.len()).rev() {
+        let right = (cumsum.len() - 1).min(i + right_offset);
+        let avg = if i > left_offset {
+            let left = i - left_offset - 1;
+            (cumsum[right] - cumsum[left]) / (window_size as f64)
+        } else {
+            cumsum[right] / ((right + 1) as f64)
+        };
+        signal[i] = avg;
+    }
+}
+
+/// Period of `signal` in samples, interpolated between the lags, from its
+/// normalized autocorrelation between `min_lag` and `max_lag`. The shortest
+/// lag whose correlation is a local maximum reaching 90% of the strongest one
+/// is taken, so that the multiples of the period don't win over it. `None` if
+/// no lag correlates at least `min_clarity`, from 0 to 1.
+pub fn autocorrelation_period(
+    signal: &[f64],
+    min_lag: usize,
+    max_lag: usize,
+    min_clarity: f64,
+) -> Option<f64> {
+    let min_lag = min_lag.max(1);
+    let max_lag = max_lag.min(signal.len().saturating_sub(2));
+    if min_lag > max_lag {
+        return None;
+    }
+    let correlation = |lag: usize| {
+        let (product, energy) = signal[..signal.len() - lag]
+            .iter()
+            .zip(&signal[lag..])
+            .fold((0.0, 0.0), |(product, energy), (a, b)| {
+                (product + a * b, energy + a * a + b * b)
+            });
+        if energy > 0.0 {
+            2.0 * product / energy
+        } else {
+            0.0
+        }
+    };
+    // Correlations of the lags from min_lag - 1 to max_lag + 1.
+    let correlations: Vec<f64> = (min_lag - 1..=max_lag + 1).map(correlation).collect();
+    let maxima = (1..correlations.len() - 1).filter(|&i| {
+        correlations[i] > correlations[i - 1] && correlations[i] >= correlations[i + 1]
+    });
+    let strongest = maxima
+        .clone()
+        .map(|i| correlations[i])
+        .fold(f64::NEG_INFINITY, f64::max);
+    if strongest < min_clarity {
+        return None;
+    }
+    let i = maxima
+        .into_iter()
+        .find(|&i| correlations[i] >= 0.9 * strongest)?;
+    let (offset, _) = interpolate_peak(&correlations, i);
+    Some((min_lag - 1 + i) as f64 + offset)
+}
+
+/// Writes the harmonic product spectrum of `spectrum` to `out`: every bin is
+/// multiplied with the bins at `n_harmonics - 1` multiples of its frequency.
+/// The harmonics of a note then collapse onto its fundamental, which stands
+/// out even if one of the harmonics is stronger than the fundamental itself.
+/// `out` gets the bins whose highest harmonic is still in `spectrum`.
+pub fn harmonic_product_spectrum(spectrum: &[f64], n_harmonics: usize, out: &mut Vec<f64>) {
+    let n_harmonics = n_harmonics.max(1);
+    out.clear();
+    out.extend((0..spectrum.len() / n_harmonics).map(|idx| {
+        (1..=n_harmonics)
+            .map(|h| spectrum[idx * h])
+            .product::<f64>()
+    }));
+}
+
+#[cfg(test)]
+mod tests_moving_avg {
+    use super::moving_avg;
+
+    #[test]
+    fn moving_avg_empty_arr() {
+        let mut signal = Vec::new();
+        moving_avg(&mut signal, 1);
+        assert_eq!(signal.len(), 0);
+
+        moving_avg(&mut signal, 2);
+        assert_eq!(signal.len(), 0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn moving_avg_bad_window_size() {
+        let mut signal = Vec::new();
+        moving_avg(&mut signal, 0);
+    }
+
+    #[test]
+    fn moving_avg_identity_case() {
+        let mut signal = vec![1.0, 2.0, 3.0, 4.0];
+        let expected = vec![1.0, 2.0, 3.0, 4.0];
+        moving_avg(&mut signal, 1);
+        assert_eq!(expected, signal);
+    }
+
+    #[test]
+    fn moving_avg_general_case_0() {
+        let mut signal = vec![1.0, 2.0, 3.0, 4.0];
+        let expected = vec![1.0, 1.5, 2.5, 3.5];
+        moving_avg(&mut signal, 2);
+        assert_eq!(expected, signal);
+    }
+
+    #[test]
+    fn moving_avg_general_case_1() {
+        let mut signal = vec![1.0, 5.0, 10.0, 15.0, 7.5, -5.0];
+        let expected = vec![
+            3.0,
+            16.0 / 3.0,
+            30.0 / 3.0,
+            32.5 / 3.0,
+            17.5 / 3.0,
+            2.5 / 3.0,
+        ];
+        moving_avg(&mut signal, 3);
+        assert_eq!(expected, signal);
+    }
+
+    #[test]
+    fn moving_avg_window_larger_than_arr_0() {
+        let mut signal = vec![1.0];
+        let expected = vec![1.0];
+        moving_avg(&mut signal, 52);
+        assert_eq!(expected, signal);
+    }
+
+    #[test]
+    fn moving_avg_window_larger_than_arr_1() {
+        let mut signal = vec![1.0, 5.0];
+        let expected = vec![3.0, 3.0];
+        moving_avg(&mut signal, 4);
+        assert_eq!(expected, signal);
+    }
+}
+
+#[cfg(test)]
+mod tests_find_peaks {
+    use super::{find_peaks, find_peaks_above, Peak};
+
+    #[test]
+    fn find_peaks_empty_arr() {
+        let signal = Vec::new();
+        let out = find_peaks(&signal, None, None);
+        assert_eq!(out.len(), 0);
+    }
+
+    #[test]
+    fn find_peaks_single_elem() {
+        let signal = vec![1.0];
+        let expected = vec![Peak::new(0, 1.0)];
+        let actual = find_peaks(&signal, None, None);
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn find_peaks_decreasing() {
+        let signal = vec![1.0, 0.5, 0.25];
+        let expected = vec![Peak::new(0, 1.0)];
+        let actual = find_peaks(&signal, None, None);
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn find_peaks_parabola() {
+        let signal = vec![1.0, 0.5, 0.25, 0.5, 1.0];
+        let expected = vec![Peak::new(0, 1.0), Peak::new(4, 1.0)];
+        let actual = find_peaks(&signal, None, None);
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn find_peaks_two_peaks() {
+        let signal = vec![0.5, 1.0, 2.0, 1.0, 0.0, 5.0, 2.5];
+        let expected = vec![Peak::new(2, 2.0), Peak::new(5, 5.0)];
+        let actual = find_peaks(&signal, None, None);
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn find_peaks_above_noise_floor() {
+        let signal = vec![0.5, 1.0, 2.0, 1.0, 0.0, 5.0, 2.5];
+        let noise_floor = [3.0, 3.0, 3.0, 3.0, 1.0, 1.0, 1.0];
+        let expected = vec![Peak::new(5, 5.0)];
+        let actual = find_peaks_above(&signal, |idx| noise_floor[idx], None);
+        assert_eq!(expected, actual);
+    }
+}
+
+#[cfg(test)]
+mod tests_most_common {
+    use super::most_common;
+
+    #[test]
+    fn most_common_empty_iter() {
+        let arr: Vec<i32> = Vec::new();
+        let actual = most_common(arr.iter());
+        assert_eq!(actual, None);
+    }
+
+    #[test]
+    fn most_common_same_elem_0() {
+        let arr = vec![1];
+        let expected = 1;
+        let actual = *most_common(arr.iter()).unwrap();
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn most_common_same_elem_1() {
+        let arr = vec![2, 2, 2, 2];
+        let expected = 2;
+        let actual = *most_common(arr.iter()).unwrap();
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn most_common_equal_counts() {
+        let arr = vec!["a", "b", "c"];
+        let actual = *most_common(arr.iter()).unwrap();
+        assert!(actual == "a" || actual == "b" || actual == "c");
+    }
+
+    #[test]
+    fn most_common_general_case() {
+        let arr = vec![("a", 1), ("b", -5), ("a", 1)];
+        let expected = ("a", 1);
+        let actual = *most_common(arr.iter()).unwrap();
+        assert_eq!(expected, actual);
+    }
+}
+
+#[cfg(test)]
+mod tests_harmonic_product_spectrum {
+    use super::{find_peaks, harmonic_product_spectrum, Peak};
+
+    #[test]
+    fn harmonic_product_spectrum_single_harmonic() {
+        let spectrum = vec![1.0, 2.0, 3.0];
+        let mut out = Vec::new();
+        harmonic_product_spectrum(&spectrum, 1, &mut out);
+        assert_eq!(spectrum, out);
+    }
+
+    #[test]
+    fn harmonic_product_spectrum_products() {
+        let spectrum = vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0];
+        let mut out = vec![9.0; 10];
+        harmonic_product_spectrum(&spectrum, 3, &mut out);
+        assert_eq!(vec![1.0, 2.0 * 3.0 * 4.0], out);
+    }
+
+    #[test]
+    fn harmonic_product_spectrum_finds_weak_fundamental() {
+        // Fundamental at bin 2 with a stronger second harmonic at bin 4.
+        let spectrum = vec![0.1, 0.1, 0.5, 0.1, 1.0, 0.1, 0.4, 0.1, 0.2, 0.1, 0.1, 0.1];
+        let strongest = find_peaks(&spectrum, Some(0.5), None);
+        assert_eq!(vec![Peak::new(2, 0.5), Peak::new(4, 1.0)], strongest);
+        let mut hps = Vec::new();
+        harmonic_product_spectrum(&spectrum, 3, &mut hps);
+        let peaks = find_peaks(&hps, Some(0.1), None);
+        assert_eq!(1, peaks.len());
+        assert_eq!(2, peaks[0].idx);
+    }
+}
+
+#[cfg(test)]
+mod tests_detection_confidence {
+    use super::detection_confidence;
+    use crate::audio_analysis::analysis_result::NotePeak;
+    use crate::core::{Note, NoteName};
+
+    fn peak(frequency: f64, magnitude: f64) -> NotePeak {
+        NotePeak {
+            frequency,
+            magnitude,
+            note: Note {
+                octave: 2,
+                name: NoteName::A,
+                frequency: 110.0,
+            },
+        }
+    }
+
+    #[test]
+    fn detection_confidence_no_peaks() {
+        assert_eq!(0.0, detection_confidence(&[], 110.0, 0.0));
+    }
+
+    #[test]
+    fn detection_confidence_harmonics_only() {
+        let peaks = [peak(220.0, 2.0), peak(110.5, 1.0), peak(331.0, 1.0)];
+        assert_eq!(1.0, detection_confidence(&peaks, 110.0, 0.0));
+        assert_eq!(0.75, detection_confidence(&peaks, 110.0, 0.5));
+    }
+
+    #[test]
+    fn detection_confidence_unrelated_peaks() {
+        let peaks = [peak(110.0, 1.0), peak(160.0, 1.0), peak(220.0, 2.0)];
+        assert_eq!(0.75, detection_confidence(&peaks, 110.0, 0.0));
+        assert_eq!(0.0, detection_confidence(&peaks, 110.0, 4.0));
+    }
+}
+
+#[cfg(test)]
+mod tests_interpolate_peak {
+    use super::interpolate_peak;
+
+    #[test]
+    fn interpolate_peak_parabola() {
+        // Samples of 4 - (x - 2.25)^2, whose top is at 2.25.
+        let spectrum: Vec<f64> = (0..5).map(|x| 4.0 - (x as f64 - 2.25).powi(2)).collect();
+        let (offset, magnitude) = interpolate_peak(&spectrum, 2);
+        assert!((offset - 0.25).abs() < 1e-12);
+        assert!((magnitude - 4.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn interpolate_peak_symmetric() {
+        assert_eq!((0.0, 2.0), interpolate_peak(&[1.0, 2.0, 1.0], 1));
+    }
+
+    #[test]
+    fn interpolate_peak_edges() {
+        assert_eq!((0.0, 2.0), interpolate_peak(&[2.0, 1.0], 0));
+        assert_eq!((0.0, 3.0), interpolate_peak(&[2.0, 1.0, 3.0], 2));
+        assert_eq!((0.0, 1.0), interpolate_peak(&[1.0, 1.0, 1.0], 1));
+    }
+}
+
+#[cfg(test)]
+mod tests_autocorrelation_period {
+    use super::autocorrelation_period;
+    use std::f64::consts::PI;
+
+    fn signal(period: f64, harmonics: &[f64]) -> Vec<f64> {
+        (0..1024)
+            .map(|i| {
+                harmonics
+                    .iter()
+                    .enumerate()
+                    .map(|(h, amplitude)| {
+                        amplitude * (2.0 * PI * (h + 1) as f64 * i as f64 / period).sin()
+                    })
+                    .sum()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn autocorrelation_period_sine() {
+        let period = autocorrelation_period(&signal(20.5, &[1.0]), 5, 200, 0.5).unwrap();
+        assert!((period - 20.5).abs() < 0.1, "period {}", period);
+    }
+
+    #[test]
+    fn autocorrelation_period_strong_harmonic() {
+        let signal = signal(40.0, &[0.3, 1.0, 0.6]);
+        let period = autocorrelation_period(&signal, 5, 200, 0.5).unwrap();
+        assert!((period - 40.0).abs() < 0.5, "period {}", period);
+    }
+
+    #[test]
+    fn autocorrelation_period_outside_the_lags() {
+        assert_eq!(
+            None,
+            autocorrelation_period(&signal(20.0, &[1.0]), 25, 35, 0.5)
+        );
+        assert_eq!(None, autocorrelation_period(&[0.0; 64], 5, 50, 0.5));
+        assert_eq!(None, autocorrelation_period(&[1.0; 4], 5, 50, 0.5));
+    }
+}
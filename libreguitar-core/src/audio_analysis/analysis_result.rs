@@ -0,0 +1,100 @@
+use crate::core::Note;
+
+/// One of the strongest spectrum peaks considered during note detection.
+#[derive(Debug, Clone)]
+pub struct NotePeak {
+    pub frequency: f64,
+    pub magnitude: f64,
+    /// Note closest to the peak frequency.
+    pub note: Note,
+}
+
+#[derive(Debug, Clone)]
+pub struct AnalysisResult {
+    pub note: Option<Note>,
+    /// Frequency of the spectrum peak the note was detected from.
+    pub frequency: Option<f64>,
+    /// How certain the detection is, from 0 to 1, given by the share of the
+    /// strongest peaks at harmonics of the detected frequency and by how far
+    /// they stand out of the spectrum. 0 if no note was detected.
+    pub confidence: f64,
+    /// Strongest spectrum peaks, the strongest first.
+    pub peaks: Vec<NotePeak>,
+}
+
+impl AnalysisResult {
+    /// Result of an analysis that found nothing but silence.
+    pub fn silence() -> AnalysisResult {
+        AnalysisResult {
+            note: None,
+            frequency: None,
+            confidence: 0.0,
+            peaks: Vec::new(),
+        }
+    }
+
+    /// Deviation of the detected frequency from the detected note in cents.
+    pub fn cents(&self) -> Option<f64> {
+        match (&self.note, self.frequency) {
+            (Some(note), Some(freq)) => Some(1200.0 * (freq / note.frequency).log2()),
+            _ => None,
+        }
+    }
+
+    /// Frequency of A4 in the note table the detected note was taken from,
+    /// assuming equal temperament.
+    pub fn a4_reference(&self) -> Option<f64> {
+        let note = self.note.as_ref()?;
+        Some(note.frequency * 2f64.powf(-note.semitones_from_a4() as f64 / 12.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::NoteName;
+
+    #[test]
+    fn test_cents_no_detection() {
+        assert_eq!(None, AnalysisResult::silence().cents());
+    }
+
+    #[test]
+    fn test_a4_reference() {
+        let result = AnalysisResult {
+            note: Some(Note {
+                octave: 5,
+                name: NoteName::A,
+                frequency: 864.0,
+            }),
+            frequency: Some(870.0),
+            confidence: 1.0,
+            peaks: Vec::new(),
+        };
+        assert_eq!(Some(432.0), result.a4_reference());
+    }
+
+    #[test]
+    fn test_cents() {
+        let note = Note {
+            octave: 4,
+            name: NoteName::A,
+            frequency: 440.0,
+        };
+        let in_tune = AnalysisResult {
+            note: Some(note.clone()),
+            frequency: Some(440.0),
+            confidence: 1.0,
+            peaks: Vec::new(),
+        };
+        assert_eq!(Some(0.0), in_tune.cents());
+
+        let semitone_up = AnalysisResult {
+            note: Some(note),
+            frequency: Some(440.0 * 2.0f64.powf(1.0 / 12.0)),
+            confidence: 1.0,
+            peaks: Vec::new(),
+        };
+        assert!((semitone_up.cents().unwrap() - 100.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,665 @@
+use crate::audio_analysis::algorithm::{detection_confidence, find_note, moving_avg};
+use crate::audio_analysis::analysis_result::AnalysisResult;
+use crate::audio_analysis::auto_gain::AutoGain;
+use crate::audio_analysis::fft_cache::FftCache;
+use crate::audio_analysis::noise_floor::NoiseFloor;
+use crate::audio_analysis::pitch_detector::{pitch_detector, DetectionInput, PitchDetector};
+use crate::audio_analysis::target_notes::{TargetNotes, TooFewNotesError};
+use crate::audio_analysis::window_function::window_coefficients;
+use crate::core::{AudioCfg, Note};
+use std::f64;
+
+/// Analysis parameters that can be changed while the analyzer is running.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AudioParams {
+    pub fft_magnitude_gain: f64,
+    pub peak_threshold: f64,
+    pub moving_avg_window_size: usize,
+    pub silence_rms: f64,
+}
+
+pub struct AudioAnalyzer {
+    /// Keeps the FFTs planned so far to reuse them when retargeted.
+    fft_cache: FftCache,
+    sample_rate: usize,
+    /// Samples of the last analysis, for the silence gate and the pitch
+    /// detectors working on the samples.
+    samples: Vec<f64>,
+    freq_magnitudes: Vec<f64>,
+    detector: Box<dyn PitchDetector>,
+    /// Coefficients of the window function for the number of samples of the
+    /// last analysis, recomputed when it changes.
+    window: Vec<f64>,
+    /// Mean of the window coefficients, which the magnitudes are divided by
+    /// to make them independent of the window function.
+    window_gain: f64,
+    auto_gain: Option<AutoGain>,
+    /// Samples the input advanced by since `auto_gain` was last updated, for
+    /// its time constants. Counting the samples instead of measuring the time
+    /// keeps them right when the audio is analyzed faster than real time.
+    samples_since_gain_update: usize,
+    fftsize: usize,
+    n_bins: usize,
+    delta_f: f64,
+    target_notes: TargetNotes,
+    audio_cfg: AudioCfg,
+    noise_floor: Option<NoiseFloor>,
+    /// Peak threshold of every bin derived from the noise floor.
+    noise_thresholds: Vec<f64>,
+}
+
+/// Target notes of the analysis, which needs at least two of them to derive
+/// its frequency resolution from.
+fn analysis_notes(notes: &[Note]) -> Result<TargetNotes, TooFewNotesError> {
+    if notes.len() < 2 {
+        return Err(TooFewNotesError {
+            needed: 2,
+            got: notes.len(),
+        });
+    }
+    TargetNotes::new(Vec::from(notes))
+}
+
+impl AudioAnalyzer {
+    pub fn new(
+        sample_rate: usize,
+        target_notes: &[Note],
+        audio_cfg: AudioCfg,
+    ) -> Result<AudioAnalyzer, TooFewNotesError> {
+        let mut analyzer = AudioAnalyzer {
+            fft_cache: FftCache::new(),
+            sample_rate,
+            samples: Vec::new(),
+            freq_magnitudes: Vec::new(),
+            detector: pitch_detector(audio_cfg.pitch_method),
+            window: Vec::new(),
+            window_gain: 1.0,
+            auto_gain: audio_cfg.auto_gain.map(AutoGain::new),
+            samples_since_gain_update: 0,
+            fftsize: 0,
+            n_bins: 0,
+            delta_f: 0.0,
+            target_notes: analysis_notes(target_notes)?,
+            audio_cfg,
+            noise_floor: None,
+            noise_thresholds: Vec::new(),
+        };
+        analyzer.plan_fft();
+        Ok(analyzer)
+    }
+
+    /// Analyzes the audio at `sample_rate` from the next analysis on.
+    pub fn set_sample_rate(&mut self, sample_rate: usize) {
+        self.sample_rate = sample_rate;
+        self.plan_fft();
+    }
+
+    /// Detects the notes among `target_notes` from the next analysis on.
+    pub fn set_target_notes(&mut self, target_notes: &[Note]) -> Result<(), TooFewNotesError> {
+        self.target_notes = analysis_notes(target_notes)?;
+        self.plan_fft();
+        Ok(())
+    }
+
+    /// Shifts the frequencies of the target notes so that A4 is at `a4`
+    /// hertz, assuming equal temperament.
+    pub fn set_a4_reference(&mut self, a4: f64) {
+        self.target_notes.retune(a4);
+        self.plan_fft();
+    }
+
+    /// Sizes the FFT for the sample rate and the target notes. Plans of
+    /// sizes used before are reused along with their buffers.
+    fn plan_fft(&mut self) {
+        let min_freq_diff = self.target_notes.resolution();
+        self.delta_f = min_freq_diff / self.audio_cfg.fft_res_factor;
+        self.fftsize = (self.sample_rate as f64 / self.delta_f).ceil() as usize;
+        self.n_bins = self.fft_cache.plan(self.fftsize).n_bins();
+        self.freq_magnitudes.resize(self.n_bins, 0.0);
+        self.update_noise_thresholds();
+    }
+
+    /// Detects only the peaks `noise_floor_margin` times stronger than
+    /// `noise_floor` from the next analysis on, or the peaks above the
+    /// median based threshold if `None`.
+    pub fn set_noise_floor(&mut self, noise_floor: Option<NoiseFloor>) {
+        self.noise_floor = noise_floor.filter(|noise_floor| !noise_floor.is_empty());
+        self.update_noise_thresholds();
+    }
+
+    fn update_noise_thresholds(&mut self) {
+        self.noise_thresholds.clear();
+        if let Some(noise_floor) = &self.noise_floor {
+            let margin = self.audio_cfg.noise_floor_margin;
+            let delta_f = self.delta_f;
+            self.noise_thresholds.extend(
+                (0..self.n_bins).map(|idx| margin * noise_floor.level(idx as f64 * delta_f)),
+            );
+        }
+    }
+
+    /// Picks the fundamental frequency with `detector` from the next
+    /// analysis on instead of the detector of the `pitch_method`.
+    pub fn set_pitch_detector(&mut self, detector: Box<dyn PitchDetector>) {
+        self.detector = detector;
+    }
+
+    pub fn n_bins(&self) -> usize {
+        self.n_bins
+    }
+
+    pub fn delta_f(&self) -> f64 {
+        self.delta_f
+    }
+
+    pub fn params(&self) -> AudioParams {
+        AudioParams {
+            fft_magnitude_gain: self.audio_cfg.fft_magnitude_gain,
+            peak_threshold: self.audio_cfg.peak_threshold,
+            moving_avg_window_size: self.audio_cfg.moving_avg_window_size,
+            silence_rms: self.audio_cfg.silence_rms,
+        }
+    }
+
+    /// Uses `params` from the next analysis on.
+    pub fn set_params(&mut self, params: AudioParams) {
+        self.audio_cfg.fft_magnitude_gain = params.fft_magnitude_gain;
+        self.audio_cfg.peak_threshold = params.peak_threshold;
+        self.audio_cfg.moving_avg_window_size = params.moving_avg_window_size.max(1);
+        self.audio_cfg.silence_rms = params.silence_rms;
+    }
+
+    pub(crate) fn compute_fft(&mut self, audio_data: impl ExactSizeIterator<Item = f64>) {
+        if audio_data.len() != self.window.len() {
+            window_coefficients(
+                self.audio_cfg.window_function,
+                audio_data.len(),
+                &mut self.window,
+            );
+            self.window_gain = match self.window.len() {
+                0 => 1.0,
+                len => self.window.iter().sum::<f64>() / len as f64,
+            };
+        }
+        let n_samples = audio_data.len();
+        let mut sum_of_squares = 0.0;
+        let windowed = audio_data.zip(&self.window).map(|(sample, w)| {
+            sum_of_squares += sample * sample;
+            sample * w
+        });
+        let spectrum = self.fft_cache.plan(self.fftsize).process(windowed);
+        let mut norm_factor =
+            self.audio_cfg.fft_magnitude_gain / (self.fftsize as f64 * self.window_gain);
+        // The FFT is linear, so scaling its output is the same as scaling
+        // the samples by the automatic gain.
+        if let Some(auto_gain) = &mut self.auto_gain {
+            let elapsed = self.samples_since_gain_update as f64 / self.sample_rate as f64;
+            self.samples_since_gain_update = 0;
+            let rms = (sum_of_squares / n_samples.max(1) as f64).sqrt();
+            norm_factor *= auto_gain.update(rms, elapsed);
+        }
+        for (magnitude, bin) in self.freq_magnitudes.iter_mut().zip(spectrum) {
+            *magnitude = bin.norm() * norm_factor;
+        }
+    }
+
+    pub fn spectrogram(&self) -> &Vec<f64> {
+        &self.freq_magnitudes
+    }
+
+    /// Exchanges the spectrum of the last analysis with `buffer`, so that it
+    /// can be handed to another thread without copying it. The analyzer keeps
+    /// the previous contents of `buffer`, resized to the number of bins, until
+    /// the next analysis overwrites them.
+    pub fn swap_spectrogram(&mut self, buffer: &mut Vec<f64>) {
+        std::mem::swap(&mut self.freq_magnitudes, buffer);
+        self.freq_magnitudes.resize(self.n_bins, 0.0);
+    }
+
+    /// Detects the note in `audio_data`, which is taken to follow the
+    /// previous analysis without overlapping it.
+    pub fn identify_note(
+        &mut self,
+        audio_data: impl ExactSizeIterator<Item = f64>,
+    ) -> AnalysisResult {
+        let n_samples = audio_data.len();
+        self.identify_next_note(audio_data, n_samples)
+    }
+
+    /// Detects the note in `audio_data`, a sliding window of the input that
+    /// moved by `n_new_samples` since the previous analysis.
+    pub fn identify_next_note(
+        &mut self,
+        audio_data: impl ExactSizeIterator<Item = f64>,
+        n_new_samples: usize,
+    ) -> AnalysisResult {
+        self.samples_since_gain_update += n_new_samples;
+        self.samples.clear();
+        self.samples.extend(audio_data);
+        if self.audio_cfg.silence_rms > 0.0 {
+            let sum_of_squares: f64 = self.samples.iter().map(|sample| sample * sample).sum();
+            let rms = (sum_of_squares / self.samples.len().max(1) as f64).sqrt();
+            if rms < self.audio_cfg.silence_rms {
+                for magnitude in self.freq_magnitudes.iter_mut() {
+                    *magnitude = 0.0;
+                }
+                return AnalysisResult::silence();
+            }
+        }
+        let samples = std::mem::take(&mut self.samples);
+        self.compute_fft(samples.iter().cloned());
+        self.samples = samples;
+        moving_avg(
+            &mut self.freq_magnitudes[..],
+            self.audio_cfg.moving_avg_window_size,
+        );
+        let noise_thresholds = &self.noise_thresholds[..];
+        let input = DetectionInput::new(
+            &self.samples,
+            &self.freq_magnitudes,
+            self.delta_f,
+            self.sample_rate,
+            &self.audio_cfg,
+            self.noise_floor.as_ref().map(|_| noise_thresholds),
+            &self.target_notes,
+        );
+        let peaks = self.detector.detect(&input);
+        match find_note(&peaks) {
+            Some((note, frequency)) => {
+                let background =
+                    self.freq_magnitudes.iter().sum::<f64>() / self.freq_magnitudes.len() as f64;
+                AnalysisResult {
+                    note: Some(note),
+                    frequency: Some(frequency),
+                    confidence: detection_confidence(&peaks, frequency, background),
+                    peaks,
+                }
+            }
+            None => AnalysisResult {
+                note: None,
+                frequency: None,
+                confidence: 0.0,
+                peaks,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio_analysis::analysis_result::NotePeak;
+    use crate::core::{AutoGainCfg, NoteName, PitchMethod, WindowFunction};
+
+    fn audio_cfg() -> AudioCfg {
+        AudioCfg {
+            fft_res_factor: 2.0,
+            fft_magnitude_gain: 10.0,
+            window_function: WindowFunction::Rectangular,
+            peak_threshold: 500.0,
+            min_peak_dist: 10,
+            num_top_peaks: 5,
+            moving_avg_window_size: 11,
+            silence_rms: 0.0,
+            noise_floor_margin: 2.0,
+            pitch_method: PitchMethod::Peaks,
+            hps_harmonics: 3,
+            auto_gain: None,
+        }
+    }
+
+    fn a_notes() -> Vec<Note> {
+        (1..4)
+            .map(|octave| Note {
+                octave,
+                name: NoteName::A,
+                frequency: 55.0 * 2f64.powi(octave - 1),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_set_sample_rate() {
+        let mut analyzer = AudioAnalyzer::new(8000, &a_notes(), audio_cfg()).unwrap();
+        let n_bins = analyzer.n_bins();
+        let delta_f = analyzer.delta_f();
+        analyzer.set_sample_rate(16000);
+        assert_eq!(delta_f, analyzer.delta_f());
+        assert!(analyzer.n_bins() > n_bins);
+        assert_eq!(analyzer.n_bins(), analyzer.spectrogram().len());
+        analyzer.set_sample_rate(8000);
+        assert_eq!(n_bins, analyzer.n_bins());
+    }
+
+    #[test]
+    fn test_set_a4_reference() {
+        let mut analyzer = AudioAnalyzer::new(8000, &a_notes(), audio_cfg()).unwrap();
+        let delta_f = analyzer.delta_f();
+        analyzer.set_a4_reference(220.0);
+        assert!((analyzer.delta_f() - delta_f / 2.0).abs() < 1e-9);
+        assert_eq!(analyzer.n_bins(), analyzer.spectrogram().len());
+    }
+
+    #[test]
+    fn test_swap_spectrogram() {
+        let mut analyzer = AudioAnalyzer::new(8000, &a_notes(), audio_cfg()).unwrap();
+        analyzer.identify_note(vec![1.0; 256].into_iter());
+        let spectrogram = analyzer.spectrogram().clone();
+        let mut buffer = Vec::new();
+        analyzer.swap_spectrogram(&mut buffer);
+        assert_eq!(spectrogram, buffer);
+        assert_eq!(analyzer.n_bins(), analyzer.spectrogram().len());
+    }
+
+    #[test]
+    fn test_window_function_reduces_leakage() {
+        let samples: Vec<f64> = (0..256)
+            .map(|i| (2.0 * f64::consts::PI * 110.0 * i as f64 / 8000.0).sin())
+            .collect();
+        let spectrum = |window_function| {
+            let cfg = AudioCfg {
+                window_function,
+                moving_avg_window_size: 1,
+                ..audio_cfg()
+            };
+            let mut analyzer = AudioAnalyzer::new(8000, &a_notes(), cfg).unwrap();
+            analyzer.identify_note(samples.iter().cloned());
+            let bin =
+                |frequency: f64| analyzer.spectrogram()[(frequency / analyzer.delta_f()) as usize];
+            (bin(110.0), bin(1000.0))
+        };
+        let (rect_peak, rect_leakage) = spectrum(WindowFunction::Rectangular);
+        let (hann_peak, hann_leakage) = spectrum(WindowFunction::Hann);
+        assert!(hann_leakage < rect_leakage / 10.0);
+        // The magnitudes are compensated for the gain of the window.
+        assert!((hann_peak / rect_peak - 1.0).abs() < 0.2);
+    }
+
+    #[test]
+    fn test_auto_gain_normalizes_the_level() {
+        let peak_magnitude = |amplitude: f64| {
+            let cfg = AudioCfg {
+                auto_gain: Some(AutoGainCfg {
+                    target_rms: 0.1,
+                    attack: 0.05,
+                    release: 1.0,
+                    max_gain: 1000.0,
+                }),
+                ..audio_cfg()
+            };
+            let mut analyzer = AudioAnalyzer::new(8000, &a_notes(), cfg).unwrap();
+            let samples = (0..256)
+                .map(|i| amplitude * (2.0 * f64::consts::PI * 110.0 * i as f64 / 8000.0).sin());
+            analyzer.identify_note(samples);
+            analyzer.spectrogram().iter().cloned().fold(0.0, f64::max)
+        };
+        let quiet = peak_magnitude(0.01);
+        let loud = peak_magnitude(0.5);
+        assert!((quiet / loud - 1.0).abs() < 1e-9);
+    }
+
+    /// Analyzer with an automatic gain control of `attack` and `release`
+    /// seconds.
+    fn auto_gain_analyzer(attack: f64, release: f64) -> AudioAnalyzer {
+        let cfg = AudioCfg {
+            auto_gain: Some(AutoGainCfg {
+                target_rms: 0.1,
+                attack,
+                release,
+                max_gain: 1000.0,
+            }),
+            ..audio_cfg()
+        };
+        AudioAnalyzer::new(8000, &a_notes(), cfg).unwrap()
+    }
+
+    /// Strongest spectrum bin after analyzing `n_blocks` blocks of 256
+    /// samples of a sine at `amplitude`.
+    fn peak_after_blocks(analyzer: &mut AudioAnalyzer, amplitude: f64, n_blocks: usize) -> f64 {
+        for _ in 0..n_blocks {
+            let samples = (0..256)
+                .map(|i| amplitude * (2.0 * f64::consts::PI * 110.0 * i as f64 / 8000.0).sin());
+            analyzer.identify_note(samples);
+        }
+        analyzer.spectrogram().iter().cloned().fold(0.0, f64::max)
+    }
+
+    /// Level the gain follows after `elapsed` seconds of a level of `to`
+    /// following a level of `from`, with the time constant `tau`.
+    fn followed_level(from: f64, to: f64, elapsed: f64, tau: f64) -> f64 {
+        to + (from - to) * (-elapsed / tau).exp()
+    }
+
+    #[test]
+    fn test_auto_gain_attack_over_blocks() {
+        let normalized = peak_after_blocks(&mut auto_gain_analyzer(0.05, 1.0), 0.5, 1);
+        let mut analyzer = auto_gain_analyzer(0.05, 1.0);
+        peak_after_blocks(&mut analyzer, 0.05, 1);
+        // The blocks are analyzed faster than real time, which doesn't
+        // change the time the gain takes to follow them.
+        let peak = peak_after_blocks(&mut analyzer, 0.5, 2);
+        let level = followed_level(0.1, 1.0, 2.0 * 256.0 / 8000.0, 0.05);
+        assert!((peak / normalized - 1.0 / level).abs() < 1e-6);
+        let peak = peak_after_blocks(&mut analyzer, 0.5, 20);
+        assert!((peak / normalized - 1.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_auto_gain_release_over_blocks() {
+        let normalized = peak_after_blocks(&mut auto_gain_analyzer(0.05, 1.0), 0.05, 1);
+        let mut analyzer = auto_gain_analyzer(0.05, 1.0);
+        peak_after_blocks(&mut analyzer, 0.5, 1);
+        // About one release time constant of quieter blocks.
+        let peak = peak_after_blocks(&mut analyzer, 0.05, 31);
+        let level = followed_level(10.0, 1.0, 31.0 * 256.0 / 8000.0, 1.0);
+        assert!((peak / normalized - 1.0 / level).abs() < 1e-6);
+        assert!(peak < 0.5 * normalized);
+    }
+
+    #[test]
+    fn test_auto_gain_counts_the_new_samples() {
+        let mut sliding = auto_gain_analyzer(0.05, 1.0);
+        let mut separate = auto_gain_analyzer(0.05, 1.0);
+        let block = |amplitude: f64| {
+            (0..256)
+                .map(move |i| amplitude * (2.0 * f64::consts::PI * 110.0 * i as f64 / 8000.0).sin())
+        };
+        sliding.identify_note(block(0.05));
+        separate.identify_note(block(0.05));
+        // A window that moved by 64 samples follows the level for a quarter
+        // of the time of a separate block.
+        sliding.identify_next_note(block(0.5), 64);
+        separate.identify_note(block(0.5));
+        let peak =
+            |analyzer: &AudioAnalyzer| analyzer.spectrogram().iter().cloned().fold(0.0, f64::max);
+        assert!(peak(&sliding) > peak(&separate));
+    }
+
+    #[test]
+    fn test_silence_rms() {
+        let cfg = AudioCfg {
+            silence_rms: 0.01,
+            ..audio_cfg()
+        };
+        let mut analyzer = AudioAnalyzer::new(8000, &a_notes(), cfg).unwrap();
+        let sine = |amplitude: f64| {
+            (0..256)
+                .map(move |i| amplitude * (2.0 * f64::consts::PI * 110.0 * i as f64 / 8000.0).sin())
+        };
+        assert!(analyzer.identify_note(sine(1.0)).note.is_some());
+        let quiet = analyzer.identify_note(sine(0.01));
+        assert!(quiet.note.is_none());
+        assert!(quiet.peaks.is_empty());
+        assert!(analyzer
+            .spectrogram()
+            .iter()
+            .all(|&magnitude| magnitude == 0.0));
+    }
+
+    #[test]
+    fn test_noise_floor() {
+        let mut analyzer = AudioAnalyzer::new(8000, &a_notes(), audio_cfg()).unwrap();
+        let samples: Vec<f64> = (0..256)
+            .map(|i| (2.0 * f64::consts::PI * 110.0 * i as f64 / 8000.0).sin())
+            .collect();
+        assert!(analyzer
+            .identify_note(samples.iter().cloned())
+            .note
+            .is_some());
+        // A noise floor as loud as the note hides it.
+        let mut noise_floor = NoiseFloor::new(50.0);
+        noise_floor.add_spectrum(analyzer.spectrogram(), analyzer.delta_f());
+        analyzer.set_noise_floor(Some(noise_floor));
+        assert!(analyzer
+            .identify_note(samples.iter().cloned())
+            .note
+            .is_none());
+        analyzer.set_noise_floor(None);
+        assert!(analyzer
+            .identify_note(samples.iter().cloned())
+            .note
+            .is_some());
+    }
+
+    #[test]
+    fn test_harmonic_product() {
+        // A note whose second harmonic is stronger than its fundamental.
+        let samples: Vec<f64> = (0..512)
+            .map(|i| {
+                let t = i as f64 / 8000.0;
+                let partial = |frequency: f64| (2.0 * f64::consts::PI * frequency * t).sin();
+                0.3 * partial(110.0) + partial(220.0) + 0.6 * partial(330.0)
+            })
+            .collect();
+        let notes = a_notes();
+        let cfg = AudioCfg {
+            fft_res_factor: 4.0,
+            peak_threshold: 10.0,
+            num_top_peaks: 1,
+            min_peak_dist: 2,
+            moving_avg_window_size: 1,
+            ..audio_cfg()
+        };
+        let mut analyzer = AudioAnalyzer::new(8000, &notes, cfg.clone()).unwrap();
+        let detection = analyzer.identify_note(samples.iter().cloned());
+        assert_eq!(Some(&notes[2]), detection.note.as_ref());
+        let cfg = AudioCfg {
+            pitch_method: PitchMethod::HarmonicProduct,
+            ..cfg
+        };
+        let mut analyzer = AudioAnalyzer::new(8000, &notes, cfg).unwrap();
+        let detection = analyzer.identify_note(samples.iter().cloned());
+        assert_eq!(Some(&notes[1]), detection.note.as_ref());
+        // The peak is reported with its magnitude in the spectrum.
+        let peak = &detection.peaks[0];
+        let bin = analyzer.spectrogram()[(peak.frequency / analyzer.delta_f()).round() as usize];
+        assert!((peak.magnitude - bin).abs() < 0.1 * peak.magnitude);
+    }
+
+    #[test]
+    fn test_autocorrelation() {
+        // A note whose second harmonic is stronger than its fundamental.
+        let samples = (0..512).map(|i| {
+            let t = i as f64 / 8000.0;
+            let partial = |frequency: f64| (2.0 * f64::consts::PI * frequency * t).sin();
+            0.3 * partial(110.0) + partial(220.0) + 0.6 * partial(330.0)
+        });
+        let notes = a_notes();
+        let cfg = AudioCfg {
+            fft_res_factor: 4.0,
+            pitch_method: PitchMethod::Autocorrelation,
+            ..audio_cfg()
+        };
+        let mut analyzer = AudioAnalyzer::new(8000, &notes, cfg).unwrap();
+        let detection = analyzer.identify_note(samples);
+        assert_eq!(Some(&notes[1]), detection.note.as_ref());
+        assert!((detection.frequency.unwrap() - 110.0).abs() < 1.0);
+        let silence = analyzer.identify_note(vec![0.0; 512].into_iter());
+        assert!(silence.note.is_none());
+    }
+
+    #[test]
+    fn test_set_pitch_detector() {
+        struct Fixed(f64);
+
+        impl PitchDetector for Fixed {
+            fn detect(&mut self, input: &DetectionInput) -> Vec<NotePeak> {
+                vec![input.note_peak(self.0)]
+            }
+        }
+
+        let notes = a_notes();
+        let mut analyzer = AudioAnalyzer::new(8000, &notes, audio_cfg()).unwrap();
+        analyzer.set_pitch_detector(Box::new(Fixed(220.0)));
+        let samples = (0..256).map(|i| (2.0 * f64::consts::PI * 55.0 * i as f64 / 8000.0).sin());
+        let detection = analyzer.identify_note(samples);
+        assert_eq!(Some(&notes[2]), detection.note.as_ref());
+        assert_eq!(Some(220.0), detection.frequency);
+    }
+
+    #[test]
+    fn test_confidence() {
+        let signal = |frequencies: &[f64]| -> Vec<f64> {
+            (0..2048)
+                .map(|i| {
+                    let t = i as f64 / 8000.0;
+                    frequencies
+                        .iter()
+                        .map(|f| (2.0 * f64::consts::PI * f * t).sin())
+                        .sum()
+                })
+                .collect()
+        };
+        let cfg = AudioCfg {
+            fft_res_factor: 1.0,
+            window_function: WindowFunction::Hann,
+            peak_threshold: 10.0,
+            min_peak_dist: 2,
+            moving_avg_window_size: 1,
+            ..audio_cfg()
+        };
+        let chromatic: Vec<Note> = (0..36)
+            .map(|i| Note {
+                frequency: 55.0 * 2f64.powf(i as f64 / 12.0),
+                ..a_notes()[0].add_semitone(i)
+            })
+            .collect();
+        let mut analyzer = AudioAnalyzer::new(8000, &chromatic, cfg).unwrap();
+        let harmonics = analyzer.identify_note(signal(&[110.0, 220.0]).into_iter());
+        assert!(harmonics.confidence > 0.9);
+        let unrelated = analyzer.identify_note(signal(&[110.0, 155.0, 190.0]).into_iter());
+        assert!(unrelated.confidence < 0.5);
+        let silence = analyzer.identify_note(vec![0.0; 2048].into_iter());
+        assert_eq!(0.0, silence.confidence);
+    }
+
+    #[test]
+    fn test_frequency_between_bins() {
+        let cfg = AudioCfg {
+            fft_res_factor: 4.0,
+            min_peak_dist: 2,
+            num_top_peaks: 1,
+            moving_avg_window_size: 1,
+            window_function: WindowFunction::Hann,
+            ..audio_cfg()
+        };
+        let mut analyzer = AudioAnalyzer::new(8000, &a_notes(), cfg).unwrap();
+        let frequency = 117.0;
+        let samples =
+            (0..512).map(|i| (2.0 * f64::consts::PI * frequency * i as f64 / 8000.0).sin());
+        let detection = analyzer.identify_note(samples);
+        let error = (detection.frequency.unwrap() - frequency).abs();
+        assert!(error < analyzer.delta_f() / 4.0, "off by {} Hz", error);
+    }
+
+    #[test]
+    fn test_too_few_notes() {
+        let notes = a_notes();
+        assert_eq!(
+            TooFewNotesError { needed: 2, got: 1 },
+            AudioAnalyzer::new(8000, &notes[..1], audio_cfg())
+                .err()
+                .unwrap()
+        );
+    }
+}
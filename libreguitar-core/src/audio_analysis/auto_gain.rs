@@ -0,0 +1,88 @@
+use crate::core::AutoGainCfg;
+
+/// Tracks the RMS level of the input and derives the gain scaling it to the
+/// target level, so that the thresholds of the analysis work alike for quiet
+/// and loud audio interfaces.
+#[derive(Debug, Clone)]
+pub struct AutoGain {
+    cfg: AutoGainCfg,
+    /// Smoothed RMS level, `None` until the first update.
+    level: Option<f64>,
+}
+
+impl AutoGain {
+    pub fn new(cfg: AutoGainCfg) -> AutoGain {
+        AutoGain { cfg, level: None }
+    }
+
+    /// Moves the tracked level towards `rms`, measured `elapsed` seconds after
+    /// the previous update, and returns the gain to apply to the samples. The
+    /// first update takes `rms` as it is.
+    pub fn update(&mut self, rms: f64, elapsed: f64) -> f64 {
+        let level = match self.level {
+            None => rms,
+            Some(level) => {
+                let time_constant = if rms > level {
+                    self.cfg.attack
+                } else {
+                    self.cfg.release
+                };
+                let alpha = if time_constant > 0.0 {
+                    1.0 - (-elapsed / time_constant).exp()
+                } else {
+                    1.0
+                };
+                level + alpha * (rms - level)
+            }
+        };
+        self.level = Some(level);
+        if level > 0.0 {
+            (self.cfg.target_rms / level).min(self.cfg.max_gain)
+        } else {
+            self.cfg.max_gain
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn auto_gain() -> AutoGain {
+        AutoGain::new(AutoGainCfg {
+            target_rms: 0.5,
+            attack: 0.1,
+            release: 1.0,
+            max_gain: 100.0,
+        })
+    }
+
+    #[test]
+    fn test_first_update_normalizes_right_away() {
+        let mut auto_gain = auto_gain();
+        assert_eq!(5.0, auto_gain.update(0.1, 0.0));
+    }
+
+    #[test]
+    fn test_attack_is_faster_than_release() {
+        let mut rising = auto_gain();
+        rising.update(0.1, 0.0);
+        let rising_gain = rising.update(0.2, 0.1);
+        // One time constant covers 63% of the way to the new level.
+        let level = 0.1 + 0.1 * (1.0 - (-1f64).exp());
+        assert!((rising_gain - 0.5 / level).abs() < 1e-9);
+
+        let mut falling = auto_gain();
+        falling.update(0.2, 0.0);
+        let falling_gain = falling.update(0.1, 0.1);
+        assert!(falling_gain < 0.5 / 0.19);
+        assert!(falling_gain > 0.5 / 0.2);
+    }
+
+    #[test]
+    fn test_silence_is_not_amplified_to_the_target() {
+        let mut auto_gain = auto_gain();
+        assert_eq!(100.0, auto_gain.update(0.0, 0.0));
+        assert_eq!(100.0, auto_gain.update(0.001, 0.0));
+    }
+}
@@ -0,0 +1,19 @@
+//! The steps of the analysis for the benches of the `bench` feature, which
+//! time them on their own. Not part of the supported API.
+
+use crate::audio_analysis::algorithm;
+use crate::audio_analysis::AudioAnalyzer;
+
+pub use crate::audio_analysis::algorithm::moving_avg;
+
+/// Writes the magnitude spectrum of `samples` to the spectrogram of
+/// `analyzer`, as the first step of [`AudioAnalyzer::identify_note`].
+pub fn compute_fft(analyzer: &mut AudioAnalyzer, samples: &[f64]) {
+    analyzer.compute_fft(samples.iter().cloned());
+}
+
+/// Number of peaks of `signal` at least `min_height` high and
+/// `min_peak_dist` bins apart.
+pub fn find_peaks(signal: &[f64], min_height: f64, min_peak_dist: usize) -> usize {
+    algorithm::find_peaks(signal, Some(min_height), Some(min_peak_dist)).len()
+}
@@ -0,0 +1,100 @@
+use realfft::{num_complex::Complex, RealFftPlanner, RealToComplex};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Forward real FFT of a single size along with its buffers.
+pub struct FftPlan {
+    fft: Arc<dyn RealToComplex<f64>>,
+    input: Vec<f64>,
+    spectrum: Vec<Complex<f64>>,
+    scratch: Vec<Complex<f64>>,
+}
+
+impl FftPlan {
+    fn new(fft: Arc<dyn RealToComplex<f64>>) -> FftPlan {
+        FftPlan {
+            input: fft.make_input_vec(),
+            spectrum: fft.make_output_vec(),
+            scratch: fft.make_scratch_vec(),
+            fft,
+        }
+    }
+
+    /// Number of frequency bins of the spectrum.
+    pub fn n_bins(&self) -> usize {
+        self.spectrum.len()
+    }
+
+    /// Spectrum of `samples` zero-padded to the size of the plan.
+    pub fn process(&mut self, samples: impl ExactSizeIterator<Item = f64>) -> &[Complex<f64>] {
+        let n_samples = samples.len();
+        assert!(n_samples <= self.input.len(), "Audio data is too long");
+        for (dst, val) in self.input.iter_mut().zip(samples) {
+            *dst = val;
+        }
+        for dst in self.input[n_samples..].iter_mut() {
+            *dst = 0.0;
+        }
+        self.fft
+            .process_with_scratch(&mut self.input, &mut self.spectrum, &mut self.scratch)
+            .unwrap();
+        &self.spectrum
+    }
+}
+
+/// FFT plans keyed by their size, so that switching between sizes, e.g. when
+/// the analysis is reconfigured at runtime or runs at several resolutions,
+/// neither plans the FFT again nor reallocates its buffers.
+pub struct FftCache {
+    planner: RealFftPlanner<f64>,
+    plans: HashMap<usize, FftPlan>,
+}
+
+impl FftCache {
+    pub fn new() -> FftCache {
+        FftCache {
+            planner: RealFftPlanner::new(),
+            plans: HashMap::new(),
+        }
+    }
+
+    /// Plan of `size` samples, planned on its first use.
+    pub fn plan(&mut self, size: usize) -> &mut FftPlan {
+        let planner = &mut self.planner;
+        self.plans
+            .entry(size)
+            .or_insert_with(|| FftPlan::new(planner.plan_fft_forward(size)))
+    }
+}
+
+impl Default for FftCache {
+    fn default() -> FftCache {
+        FftCache::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plans_are_reused() {
+        let mut cache = FftCache::new();
+        assert_eq!(5, cache.plan(8).n_bins());
+        assert_eq!(9, cache.plan(16).n_bins());
+        assert_eq!(5, cache.plan(8).n_bins());
+        assert_eq!(2, cache.plans.len());
+    }
+
+    #[test]
+    fn test_process_zero_pads() {
+        let mut cache = FftCache::new();
+        let plan = cache.plan(4);
+        plan.process(vec![1.0; 4].into_iter());
+        // The samples of the previous call don't leak into the padding.
+        let spectrum = plan.process(vec![1.0].into_iter());
+        for bin in spectrum {
+            assert!((bin.norm() - 1.0).abs() < 1e-12);
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::Path;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum NoiseFloorError {
+    #[error(transparent)]
+    IoError(#[from] io::Error),
+    #[error(transparent)]
+    JsonError(#[from] serde_json::Error),
+}
+
+/// Strongest magnitude of the background noise in bands of `band_width`
+/// hertz, measured while nothing is played. Once calibrated, the analyzer
+/// only detects the peaks standing out from it instead of the peaks above a
+/// multiple of the median magnitude. The bands don't depend on the FFT size,
+/// so a noise floor stays valid when the sample rate changes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NoiseFloor {
+    pub band_width: f64,
+    /// Noise level of each band, from 0 Hz up.
+    pub bands: Vec<f64>,
+}
+
+impl NoiseFloor {
+    /// Noise floor without any measurements yet.
+    pub fn new(band_width: f64) -> NoiseFloor {
+        NoiseFloor {
+            band_width,
+            bands: Vec::new(),
+        }
+    }
+
+    /// Raises the bands to the magnitudes of `spectrum`, whose bins are
+    /// `delta_f` hertz apart.
+    pub fn add_spectrum(&mut self, spectrum: &[f64], delta_f: f64) {
+        for (idx, &magnitude) in spectrum.iter().enumerate() {
+            let band = ((idx as f64 * delta_f) / self.band_width) as usize;
+            if band >= self.bands.len() {
+                self.bands.resize(band + 1, 0.0);
+            }
+            self.bands[band] = self.bands[band].max(magnitude);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bands.is_empty()
+    }
+
+    /// Noise level at `frequency`. Frequencies above the measured bands get
+    /// the level of the highest band.
+    pub fn level(&self, frequency: f64) -> f64 {
+        let band = ((frequency / self.band_width) as usize).min(self.bands.len().max(1) - 1);
+        self.bands.get(band).cloned().unwrap_or(0.0)
+    }
+
+    /// Reads a noise floor written by [`NoiseFloor::save`].
+    pub fn load(path: &Path) -> Result<NoiseFloor, NoiseFloorError> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    /// Writes the noise floor to `path` as JSON.
+    pub fn save(&self, path: &Path) -> Result<(), NoiseFloorError> {
+        fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_spectrum_keeps_the_strongest_noise() {
+        let mut noise_floor = NoiseFloor::new(20.0);
+        noise_floor.add_spectrum(&[1.0, 3.0, 2.0, 0.5], 10.0);
+        noise_floor.add_spectrum(&[2.0, 1.0, 1.0, 4.0], 10.0);
+        assert_eq!(vec![3.0, 4.0], noise_floor.bands);
+        assert_eq!(3.0, noise_floor.level(15.0));
+        assert_eq!(4.0, noise_floor.level(1000.0));
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let path = std::env::temp_dir().join("libreguitar_noise_floor_test.json");
+        let mut noise_floor = NoiseFloor::new(20.0);
+        noise_floor.add_spectrum(&[1.0, 2.0, 3.0], 15.0);
+        noise_floor.save(&path).unwrap();
+        assert_eq!(noise_floor, NoiseFloor::load(&path).unwrap());
+    }
+}
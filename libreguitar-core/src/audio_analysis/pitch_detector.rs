@@ -0,0 +1,209 @@
+use crate::audio_analysis::algorithm::{
+    autocorrelation_period, find_top_peaks, harmonic_product_spectrum,
+};
+use crate::audio_analysis::analysis_result::NotePeak;
+use crate::audio_analysis::target_notes::TargetNotes;
+use crate::core::{AudioCfg, Note, PitchMethod};
+
+/// Lowest normalized autocorrelation, from 0 to 1, of the period found by
+/// [`Autocorrelation`]. Less periodic samples are treated as noise.
+const MIN_CLARITY: f64 = 0.5;
+
+/// Everything a [`PitchDetector`] may pick the played frequency from.
+pub struct DetectionInput<'a> {
+    /// Samples of the analysis, before the window function.
+    pub samples: &'a [f64],
+    /// Smoothed magnitude spectrum of the samples.
+    pub spectrum: &'a [f64],
+    /// Distance between the bins of `spectrum` in hertz.
+    pub delta_f: f64,
+    pub sample_rate: usize,
+    /// Analysis settings, including the ones changed at runtime.
+    pub cfg: &'a AudioCfg,
+    /// Peak threshold of every bin of `spectrum` derived from the calibrated
+    /// noise floor, if there is one.
+    pub noise_thresholds: Option<&'a [f64]>,
+    target_notes: &'a TargetNotes,
+}
+
+impl<'a> DetectionInput<'a> {
+    /// Input of an analysis, also to test a [`PitchDetector`] on given
+    /// samples or a given spectrum.
+    pub fn new(
+        samples: &'a [f64],
+        spectrum: &'a [f64],
+        delta_f: f64,
+        sample_rate: usize,
+        cfg: &'a AudioCfg,
+        noise_thresholds: Option<&'a [f64]>,
+        target_notes: &'a TargetNotes,
+    ) -> DetectionInput<'a> {
+        DetectionInput {
+            samples,
+            spectrum,
+            delta_f,
+            sample_rate,
+            cfg,
+            noise_thresholds,
+            target_notes,
+        }
+    }
+
+    /// Target note closest to `frequency`.
+    pub fn closest_note(&self, frequency: f64) -> &Note {
+        self.target_notes.get_closest(frequency)
+    }
+
+    /// Frequencies of the lowest and the highest target note.
+    pub fn frequency_range(&self) -> (f64, f64) {
+        self.target_notes.frequency_range()
+    }
+
+    /// Magnitude of the spectrum bin closest to `frequency`.
+    pub fn magnitude_at(&self, frequency: f64) -> f64 {
+        let idx = (frequency / self.delta_f).round() as usize;
+        self.spectrum.get(idx).cloned().unwrap_or(0.0)
+    }
+
+    /// Peak at `frequency` with the magnitude of the spectrum there, matched
+    /// to its closest target note.
+    pub fn note_peak(&self, frequency: f64) -> NotePeak {
+        NotePeak {
+            frequency,
+            magnitude: self.magnitude_at(frequency),
+            note: self.closest_note(frequency).clone(),
+        }
+    }
+}
+
+/// Finds the candidates for the fundamental frequency of an analysis, from
+/// which the analyzer detects the note and its confidence. Applications
+/// embedding the analyzer can plug in their own with
+/// [`AudioAnalyzer::set_pitch_detector`](crate::AudioAnalyzer::set_pitch_detector).
+pub trait PitchDetector: Send {
+    /// Candidate peaks, the strongest first. The note played is the most
+    /// common note name among them, at the lowest of their frequencies.
+    fn detect(&mut self, input: &DetectionInput) -> Vec<NotePeak>;
+}
+
+/// The strongest peaks of the spectrum.
+#[derive(Debug, Default)]
+pub struct SpectrumPeaks;
+
+impl PitchDetector for SpectrumPeaks {
+    fn detect(&mut self, input: &DetectionInput) -> Vec<NotePeak> {
+        find_top_peaks(
+            input.spectrum,
+            input.delta_f,
+            input.target_notes,
+            input.cfg.peak_threshold,
+            input.noise_thresholds,
+            input.cfg.min_peak_dist,
+            input.cfg.num_top_peaks,
+        )
+    }
+}
+
+/// The peaks of the spectrum with the strongest harmonic product spectrum,
+/// which collapses the harmonics of a note onto its fundamental. Only the
+/// peaks of the spectrum itself are candidates, so that the thresholds apply
+/// as with [`SpectrumPeaks`] and a note without harmonics isn't detected an
+/// octave low, where its product is just as strong.
+#[derive(Debug, Default)]
+pub struct HarmonicProduct {
+    product: Vec<f64>,
+}
+
+impl PitchDetector for HarmonicProduct {
+    fn detect(&mut self, input: &DetectionInput) -> Vec<NotePeak> {
+        harmonic_product_spectrum(input.spectrum, input.cfg.hps_harmonics, &mut self.product);
+        let product = &self.product;
+        let mut peaks = find_top_peaks(
+            input.spectrum,
+            input.delta_f,
+            input.target_notes,
+            input.cfg.peak_threshold,
+            input.noise_thresholds,
+            input.cfg.min_peak_dist,
+            usize::MAX,
+        );
+        let product_at = |peak: &NotePeak| {
+            let idx = (peak.frequency / input.delta_f).round() as usize;
+            product.get(idx).cloned().unwrap_or(0.0)
+        };
+        peaks.sort_by(|a, b| product_at(b).partial_cmp(&product_at(a)).unwrap());
+        peaks.truncate(input.cfg.num_top_peaks);
+        peaks
+    }
+}
+
+/// The period of the samples found by their autocorrelation, which doesn't
+/// depend on the harmonics being weaker than the fundamental.
+#[derive(Debug, Default)]
+pub struct Autocorrelation;
+
+impl PitchDetector for Autocorrelation {
+    fn detect(&mut self, input: &DetectionInput) -> Vec<NotePeak> {
+        let (lowest, highest) = input.frequency_range();
+        // Half a semitone of room beyond the target notes.
+        let room = 2f64.powf(0.5 / 12.0);
+        let sample_rate = input.sample_rate as f64;
+        let min_lag = (sample_rate / (highest * room)).floor() as usize;
+        let max_lag = (sample_rate / (lowest / room)).ceil() as usize;
+        match autocorrelation_period(input.samples, min_lag, max_lag, MIN_CLARITY) {
+            Some(period) => vec![input.note_peak(sample_rate / period)],
+            None => Vec::new(),
+        }
+    }
+}
+
+/// Built in detector of `method`.
+pub fn pitch_detector(method: PitchMethod) -> Box<dyn PitchDetector> {
+    match method {
+        PitchMethod::Peaks => Box::new(SpectrumPeaks),
+        PitchMethod::HarmonicProduct => Box::new(HarmonicProduct::default()),
+        PitchMethod::Autocorrelation => Box::new(Autocorrelation),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio_analysis::TargetNotes;
+    use crate::core::{NoteName, WindowFunction};
+
+    #[test]
+    fn test_detect_on_given_spectrum() {
+        let cfg = AudioCfg {
+            fft_res_factor: 1.0,
+            fft_magnitude_gain: 1.0,
+            window_function: WindowFunction::Rectangular,
+            peak_threshold: 10.0,
+            min_peak_dist: 5,
+            num_top_peaks: 3,
+            moving_avg_window_size: 1,
+            silence_rms: 0.0,
+            noise_floor_margin: 2.0,
+            pitch_method: PitchMethod::Peaks,
+            hps_harmonics: 3,
+            auto_gain: None,
+        };
+        let notes = (1..4)
+            .map(|octave| Note {
+                octave,
+                name: NoteName::A,
+                frequency: 55.0 * 2f64.powi(octave - 1),
+            })
+            .collect();
+        let target_notes = TargetNotes::new(notes).unwrap();
+        // Peaks at 110 Hz and its second harmonic, with a bin per hertz.
+        let mut spectrum = vec![0.0; 400];
+        spectrum[110] = 100.0;
+        spectrum[220] = 50.0;
+        let input = DetectionInput::new(&[], &spectrum, 1.0, 800, &cfg, None, &target_notes);
+        let peaks = SpectrumPeaks.detect(&input);
+        let frequencies: Vec<f64> = peaks.iter().map(|peak| peak.frequency).collect();
+        assert_eq!(vec![110.0, 220.0], frequencies);
+        assert_eq!(2, peaks[0].note.octave);
+    }
+}
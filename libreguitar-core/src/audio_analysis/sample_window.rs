@@ -0,0 +1,86 @@
+use std::collections::VecDeque;
+
+/// Appends the samples of `channel` in the interleaved `data` to `buffer`,
+/// dropping as many of its oldest samples.
+pub fn read_channel_buffered(
+    data: &[f32],
+    n_channels: usize,
+    channel: usize,
+    buffer: &mut VecDeque<f64>,
+) {
+    let channel_indices = (channel..data.len()).step_by(n_channels);
+    let n_new_values = channel_indices.len();
+    if n_new_values >= buffer.len() {
+        buffer.clear();
+    } else {
+        for _ in 0..n_new_values {
+            buffer.pop_front();
+        }
+    }
+    for i in channel_indices {
+        buffer.push_back(data[i] as f64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_channel_buffered_empty_buffer_empty_data() {
+        let mut buffer = VecDeque::new();
+        let data = Vec::new();
+        read_channel_buffered(&data, 2, 0, &mut buffer);
+        assert_eq!(0, buffer.len());
+    }
+
+    #[test]
+    fn read_channel_buffered_empty_data() {
+        let mut buffer = VecDeque::from(vec![1.0f64; 64]);
+        let expected = buffer.clone();
+        let data = Vec::new();
+        read_channel_buffered(&data, 3, 1, &mut buffer);
+        assert_eq!(expected, buffer);
+    }
+
+    #[test]
+    fn read_channel_buffered_empty_buffer() {
+        let mut buffer = VecDeque::new();
+        let data: Vec<f32> = (0..100).map(|x| x as f32).collect();
+        let expected: VecDeque<f64> = data.iter().cloned().step_by(2).map(|x| x as f64).collect();
+        read_channel_buffered(&data, 2, 0, &mut buffer);
+        assert_eq!(expected, buffer);
+    }
+
+    #[test]
+    fn read_channel_buffered_less_data_than_buffer() {
+        let mut buffer = VecDeque::from(vec![5000.0f64; 200]);
+        let data: Vec<f32> = (0..100).map(|x| x as f32).collect();
+        let expected: VecDeque<f64> = buffer
+            .iter()
+            .cloned()
+            .skip(50)
+            .chain(data.iter().cloned().step_by(2).map(|x| x as f64))
+            .collect();
+        read_channel_buffered(&data, 2, 0, &mut buffer);
+        assert_eq!(expected, buffer);
+    }
+
+    #[test]
+    fn read_channel_buffered_same_data_as_buffer() {
+        let mut buffer = VecDeque::from(vec![5000.0f64; 200]);
+        let data: Vec<f32> = (0..200).map(|x| x as f32).collect();
+        let expected: VecDeque<f64> = data.iter().cloned().map(|x| x as f64).collect();
+        read_channel_buffered(&data, 1, 0, &mut buffer);
+        assert_eq!(expected, buffer);
+    }
+
+    #[test]
+    fn read_channel_buffered_more_data_than_buffer() {
+        let mut buffer = VecDeque::from(vec![5000.0f64; 50]);
+        let data: Vec<f32> = (0..200).map(|x| x as f32).collect();
+        let expected: VecDeque<f64> = data.iter().cloned().map(|x| x as f64).collect();
+        read_channel_buffered(&data, 1, 0, &mut buffer);
+        assert_eq!(expected, buffer);
+    }
+}
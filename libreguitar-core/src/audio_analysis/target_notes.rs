@@ -1,20 +1,32 @@
 use crate::core::Note;
+use thiserror::Error;
 
+/// Fewer notes than needed to detect them, e.g. from an empty frequency file.
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error("Need at least {needed} notes to detect, got {got}")]
+pub struct TooFewNotesError {
+    pub needed: usize,
+    pub got: usize,
+}
+
+/// Notes the analysis can detect, looked up by their frequency.
 pub struct TargetNotes {
     arr: Vec<Note>,
 }
 
 impl TargetNotes {
-    pub fn new(mut arr: Vec<Note>) -> TargetNotes {
-        assert!(!arr.is_empty(), "Target notes cannot be empty");
-        arr.sort_unstable_by(|p, q| p.frequency.partial_cmp(&q.frequency).unwrap());
-        TargetNotes { arr }
+    pub fn new(mut arr: Vec<Note>) -> Result<TargetNotes, TooFewNotesError> {
+        if arr.is_empty() {
+            return Err(TooFewNotesError { needed: 1, got: 0 });
+        }
+        arr.sort_unstable_by(|p, q| p.frequency.total_cmp(&q.frequency));
+        Ok(TargetNotes { arr })
     }
 
     pub fn get_closest(&self, freq: f64) -> &Note {
         let search_result = self
             .arr
-            .binary_search_by(|note| note.frequency.partial_cmp(&freq).unwrap());
+            .binary_search_by(|note| note.frequency.total_cmp(&freq));
         match search_result {
             Ok(idx) => &self.arr[idx],
             Err(idx) => {
@@ -37,6 +49,28 @@ impl TargetNotes {
         }
     }
 
+    /// Frequencies of the lowest and the highest note.
+    pub fn frequency_range(&self) -> (f64, f64) {
+        (
+            self.arr[0].frequency,
+            self.arr[self.arr.len() - 1].frequency,
+        )
+    }
+
+    /// Frequency of A4 the notes are tuned to, assuming equal temperament.
+    pub fn a4_reference(&self) -> f64 {
+        let note = &self.arr[0];
+        note.frequency * 2f64.powf(-note.semitones_from_a4() as f64 / 12.0)
+    }
+
+    /// Scales the frequencies of all notes so that A4 is at `a4`.
+    pub fn retune(&mut self, a4: f64) {
+        let ratio = a4 / self.a4_reference();
+        for note in self.arr.iter_mut() {
+            note.frequency *= ratio;
+        }
+    }
+
     pub fn resolution(&self) -> f64 {
         if self.arr.len() == 1 {
             0.0
@@ -48,14 +82,15 @@ impl TargetNotes {
 
 #[cfg(test)]
 mod tests {
-    use super::TargetNotes;
+    use super::{TargetNotes, TooFewNotesError};
     use crate::core::{Note, NoteName};
 
     #[test]
-    #[should_panic]
     fn test_empty_notes() {
-        let notes = Vec::new();
-        let _ = TargetNotes::new(notes);
+        assert_eq!(
+            TooFewNotesError { needed: 1, got: 0 },
+            TargetNotes::new(Vec::new()).err().unwrap()
+        );
     }
 
     #[test]
@@ -65,7 +100,7 @@ mod tests {
             name: NoteName::A,
             frequency: 15.0,
         }];
-        let target_notes = TargetNotes::new(notes);
+        let target_notes = TargetNotes::new(notes).unwrap();
         assert_eq!(0.0, target_notes.resolution());
     }
 
@@ -83,7 +118,7 @@ mod tests {
                 frequency: 17.0,
             },
         ];
-        let target_notes = TargetNotes::new(notes);
+        let target_notes = TargetNotes::new(notes).unwrap();
         assert_eq!(2.0, target_notes.resolution());
     }
 
@@ -106,7 +141,7 @@ mod tests {
                 frequency: 25.0,
             },
         ];
-        let target_notes = TargetNotes::new(notes.clone());
+        let target_notes = TargetNotes::new(notes.clone()).unwrap();
         assert_eq!(&notes[0], target_notes.get_closest(-30.0));
         assert_eq!(&notes[0], target_notes.get_closest(3.0));
         assert_eq!(&notes[0], target_notes.get_closest(15.0));
@@ -120,4 +155,26 @@ mod tests {
         assert_eq!(&notes[2], target_notes.get_closest(25.0));
         assert_eq!(&notes[2], target_notes.get_closest(500.0));
     }
+
+    #[test]
+    fn test_retune() {
+        let notes = vec![
+            Note {
+                octave: 4,
+                name: NoteName::A,
+                frequency: 440.0,
+            },
+            Note {
+                octave: 5,
+                name: NoteName::A,
+                frequency: 880.0,
+            },
+        ];
+        let mut target_notes = TargetNotes::new(notes).unwrap();
+        assert!((target_notes.a4_reference() - 440.0).abs() < 1e-9);
+        target_notes.retune(432.0);
+        assert!((target_notes.a4_reference() - 432.0).abs() < 1e-9);
+        assert!((target_notes.get_closest(900.0).frequency - 864.0).abs() < 1e-9);
+        assert_eq!(432.0, target_notes.resolution());
+    }
 }
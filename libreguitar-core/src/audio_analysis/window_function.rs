@@ -0,0 +1,58 @@
+use crate::core::WindowFunction;
+use std::f64::consts::PI;
+
+/// Writes the `len` coefficients of the symmetric `function` window to `out`.
+pub fn window_coefficients(function: WindowFunction, len: usize, out: &mut Vec<f64>) {
+    out.clear();
+    if len == 1 || function == WindowFunction::Rectangular {
+        out.resize(len, 1.0);
+        return;
+    }
+    let cosine_sum = |coeffs: &[f64], n: usize| {
+        let x = 2.0 * PI * n as f64 / (len - 1) as f64;
+        coeffs
+            .iter()
+            .enumerate()
+            .map(|(k, a)| if k % 2 == 0 { 1.0 } else { -1.0 } * a * (k as f64 * x).cos())
+            .sum::<f64>()
+    };
+    let coeffs: &[f64] = match function {
+        WindowFunction::Rectangular => unreachable!(),
+        WindowFunction::Hann => &[0.5, 0.5],
+        WindowFunction::Hamming => &[0.54, 0.46],
+        WindowFunction::BlackmanHarris => &[0.35875, 0.48829, 0.14128, 0.01168],
+    };
+    out.extend((0..len).map(|n| cosine_sum(coeffs, n)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(expected: &[f64], actual: &[f64]) {
+        assert_eq!(expected.len(), actual.len());
+        for (e, a) in expected.iter().zip(actual) {
+            assert!((e - a).abs() < 1e-9, "{:?} != {:?}", expected, actual);
+        }
+    }
+
+    #[test]
+    fn test_rectangular() {
+        let mut out = vec![0.5; 8];
+        window_coefficients(WindowFunction::Rectangular, 3, &mut out);
+        assert_eq!(vec![1.0; 3], out);
+    }
+
+    #[test]
+    fn test_cosine_windows() {
+        let mut out = Vec::new();
+        window_coefficients(WindowFunction::Hann, 5, &mut out);
+        assert_close(&[0.0, 0.5, 1.0, 0.5, 0.0], &out);
+        window_coefficients(WindowFunction::Hamming, 3, &mut out);
+        assert_close(&[0.08, 1.0, 0.08], &out);
+        window_coefficients(WindowFunction::BlackmanHarris, 3, &mut out);
+        assert_close(&[0.00006, 1.0, 0.00006], &out);
+        window_coefficients(WindowFunction::BlackmanHarris, 1, &mut out);
+        assert_eq!(vec![1.0], out);
+    }
+}
@@ -0,0 +1,21 @@
+mod cfg;
+mod csv;
+pub(crate) mod duration_secs;
+mod fret_loc;
+mod fret_range;
+mod note;
+mod note_name;
+mod note_registry;
+mod range_error;
+mod string_range;
+mod tuning;
+
+pub use cfg::{AudioCfg, AutoGainCfg, GameCfg, PitchMethod, RangePreset, WindowFunction};
+pub use fret_loc::FretLoc;
+pub use fret_range::FretRange;
+pub use note::Note;
+pub use note_name::NoteName;
+pub use note_registry::{DuplicateNoteError, NoteRegistry, NoteRegistryError};
+pub use range_error::RangeError;
+pub use string_range::StringRange;
+pub use tuning::{InvalidTuningError, Tuning, TuningError, TuningSpecification};
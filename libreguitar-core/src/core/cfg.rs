@@ -0,0 +1,133 @@
+use crate::core::{FretRange, RangeError, StringRange};
+use serde::Deserialize;
+use std::path::PathBuf;
+
+/// How the fundamental frequency is picked from the spectrum.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PitchMethod {
+    /// The strongest peaks of the spectrum.
+    Peaks,
+    /// The spectrum peaks with the strongest harmonic product spectrum,
+    /// which detects notes whose harmonics are stronger than their
+    /// fundamental, such as open strings, an octave lower than `Peaks` would.
+    HarmonicProduct,
+    /// The period of the samples found by their autocorrelation.
+    Autocorrelation,
+}
+
+/// Window applied to the samples before the FFT. The tapered windows reduce
+/// the spectral leakage of the peaks into the neighbouring bins.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum WindowFunction {
+    /// The samples as they are.
+    Rectangular,
+    Hann,
+    Hamming,
+    /// Four term Blackman-Harris window, with the lowest leakage and the
+    /// widest peaks.
+    BlackmanHarris,
+}
+
+/// Automatic gain control scaling the input to a common level before the
+/// FFT.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+pub struct AutoGainCfg {
+    /// RMS level the input is scaled to.
+    pub target_rms: f64,
+    /// Seconds for the tracked level to rise to a louder input.
+    pub attack: f64,
+    /// Seconds for the tracked level to fall to a quieter input.
+    pub release: f64,
+    /// Largest gain applied, which keeps silence and background noise from
+    /// being amplified to the target level.
+    pub max_gain: f64,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct AudioCfg {
+    pub fft_res_factor: f64,
+    pub fft_magnitude_gain: f64,
+    pub window_function: WindowFunction,
+    pub peak_threshold: f64,
+    pub min_peak_dist: usize,
+    pub num_top_peaks: usize,
+    pub moving_avg_window_size: usize,
+    /// Sample buffers whose RMS level is below this are treated as silence
+    /// without computing their spectrum. 0 disables the gate.
+    pub silence_rms: f64,
+    /// Once the noise floor is calibrated, peaks have to be this many times
+    /// stronger than the noise to be detected, instead of peak_threshold
+    /// times the median.
+    pub noise_floor_margin: f64,
+    pub pitch_method: PitchMethod,
+    /// Number of harmonics multiplied by the `harmonic_product` method,
+    /// including the fundamental.
+    pub hps_harmonics: usize,
+    /// Normalizes the input level on top of `fft_magnitude_gain`. Disabled if
+    /// not given.
+    #[serde(default)]
+    pub auto_gain: Option<AutoGainCfg>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GameCfg {
+    pub fret_range: (usize, usize),
+    pub string_range: (usize, usize),
+    pub note_count_for_acceptance: usize,
+    pub state_update_period: usize,
+    pub target_time_limit: f64,
+    pub upcoming_target_count: usize,
+    /// Detections less confident than this, from 0 to 1, are ignored
+    /// instead of being counted.
+    pub min_confidence: f64,
+    /// Exercise file whose notes are played as targets in order instead of
+    /// picking random targets.
+    #[serde(default)]
+    pub exercise_path: Option<PathBuf>,
+    /// Directory of the game mode scripts. Relative paths are looked up in the
+    /// config directory first.
+    #[cfg(feature = "plugins")]
+    #[serde(default)]
+    pub plugins_dir: Option<PathBuf>,
+    /// Game mode script in `plugins_dir` picking the targets, by file name
+    /// without the extension. Targets are random if not given.
+    #[cfg(feature = "plugins")]
+    #[serde(default)]
+    pub game_mode: Option<String>,
+    /// Other fret and string ranges the targets can be switched to during the
+    /// game, in turn after the ranges above.
+    #[serde(default)]
+    pub presets: Vec<RangePreset>,
+}
+
+/// Fret and string ranges the targets are picked from after switching to the
+/// next preset, e.g. with a foot switch.
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+pub struct RangePreset {
+    pub fret_range: (usize, usize),
+    pub string_range: (usize, usize),
+}
+
+impl RangePreset {
+    pub fn frets(&self) -> Result<FretRange, RangeError> {
+        FretRange::new(self.fret_range.0, self.fret_range.1)
+    }
+
+    pub fn strings(&self) -> Result<StringRange, RangeError> {
+        StringRange::new(self.string_range.0, self.string_range.1)
+    }
+}
+
+impl GameCfg {
+    /// Frets of `fret_range`, which the targets are picked from.
+    pub fn frets(&self) -> Result<FretRange, RangeError> {
+        FretRange::new(self.fret_range.0, self.fret_range.1)
+    }
+
+    /// Strings of `string_range`, which the targets are picked from.
+    pub fn strings(&self) -> Result<StringRange, RangeError> {
+        StringRange::new(self.string_range.0, self.string_range.1)
+    }
+}
@@ -1,7 +1,7 @@
 use serde::de::DeserializeOwned;
-use std::error::Error;
+use std::path::Path;
 
-fn parse_csv_impl<R, T>(mut rdr: csv::Reader<R>) -> Result<Vec<T>, Box<dyn Error>>
+fn parse_csv_impl<R, T>(mut rdr: csv::Reader<R>) -> Result<Vec<T>, csv::Error>
 where
     R: std::io::Read,
     T: DeserializeOwned,
@@ -14,7 +14,7 @@ where
     Ok(out)
 }
 
-pub fn parse_csv<T>(csv_path: &str) -> Result<Vec<T>, Box<dyn Error>>
+pub fn parse_csv<T>(csv_path: impl AsRef<Path>) -> Result<Vec<T>, csv::Error>
 where
     T: DeserializeOwned,
 {
@@ -22,6 +22,13 @@ where
     parse_csv_impl(rdr)
 }
 
+pub fn parse_csv_str<T>(text: &str) -> Result<Vec<T>, csv::Error>
+where
+    T: DeserializeOwned,
+{
+    parse_csv_impl(csv::Reader::from_reader(text.as_bytes()))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
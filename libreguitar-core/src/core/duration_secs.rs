@@ -0,0 +1,61 @@
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serializer};
+use std::time::Duration;
+
+/// Duration of `secs` seconds, or `None` if it is negative or not finite.
+pub fn checked_from_secs(secs: f64) -> Option<Duration> {
+    if secs.is_finite() && secs >= 0.0 {
+        Some(Duration::from_secs_f64(secs))
+    } else {
+        None
+    }
+}
+
+fn from_secs<E: Error>(secs: f64) -> Result<Duration, E> {
+    checked_from_secs(secs).ok_or_else(|| E::custom(format!("Invalid duration {}s", secs)))
+}
+
+/// Writes a duration as a number of seconds, for `#[serde(with = ...)]`.
+pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f64(duration.as_secs_f64())
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+    from_secs(f64::deserialize(deserializer)?)
+}
+
+/// Optional duration as a number of seconds or `null`.
+pub mod option {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(
+        duration: &Option<Duration>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match duration {
+            Some(duration) => serializer.serialize_some(&duration.as_secs_f64()),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Duration>, D::Error> {
+        Option::<f64>::deserialize(deserializer)?
+            .map(super::from_secs)
+            .transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checked_from_secs() {
+        assert_eq!(Some(Duration::from_millis(1500)), checked_from_secs(1.5));
+        assert_eq!(None, checked_from_secs(-1.0));
+        assert_eq!(None, checked_from_secs(f64::NAN));
+    }
+}
@@ -0,0 +1,22 @@
+use crate::core::RangeError;
+use std::ops::Range;
+
+#[derive(Clone)]
+pub struct FretRange {
+    range: Range<usize>,
+}
+
+impl FretRange {
+    pub fn new(beg_fret: usize, end_fret: usize) -> Result<FretRange, RangeError> {
+        if beg_fret >= end_fret {
+            return Err(RangeError::NoFrets(beg_fret, end_fret));
+        }
+        Ok(FretRange {
+            range: beg_fret..end_fret,
+        })
+    }
+
+    pub fn r(&self) -> Range<usize> {
+        self.range.clone()
+    }
+}
@@ -1,7 +1,7 @@
 use crate::core::NoteName;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Note {
     pub octave: i32,
     pub name: NoteName,
@@ -13,6 +13,16 @@ impl Note {
         format!("{}{}", self.name, self.octave)
     }
 
+    /// Number of semitones from A4 up to this note, negative below A4.
+    pub fn semitones_from_a4(&self) -> i32 {
+        (self.octave - 4) * 12 + pos_in_octave(self.name) as i32 - pos_in_octave(NoteName::A) as i32
+    }
+
+    /// Position of the note in its octave, 0 for C up to 11 for B.
+    pub fn pitch_class(&self) -> usize {
+        pos_in_octave(self.name)
+    }
+
     pub fn add_semitone(&self, semitones: i32) -> Note {
         let pos = pos_in_octave(self.name) as i32;
         let new_pos = pos + semitones;
@@ -112,6 +122,18 @@ mod tests {
         assert_ne!(note_a4, note_b4);
     }
 
+    #[test]
+    fn test_semitones_from_a4() {
+        let note = |octave, name| Note {
+            octave,
+            name,
+            frequency: f64::NAN,
+        };
+        assert_eq!(0, note(4, NoteName::A).semitones_from_a4());
+        assert_eq!(3, note(5, NoteName::C).semitones_from_a4());
+        assert_eq!(-29, note(2, NoteName::E).semitones_from_a4());
+    }
+
     #[test]
     fn test_add_semitone_same_octave() {
         let note = Note {
@@ -1,8 +1,10 @@
-use crate::core::csv::parse_csv;
+use crate::core::csv::{parse_csv, parse_csv_str};
 use crate::core::{Note, NoteName};
 use std::collections::HashMap;
-use std::error::Error;
 use std::fmt;
+use std::ops::RangeInclusive;
+use std::path::Path;
+use thiserror::Error;
 
 #[derive(Debug)]
 pub struct DuplicateNoteError(String);
@@ -12,7 +14,15 @@ impl fmt::Display for DuplicateNoteError {
     }
 }
 
-impl Error for DuplicateNoteError {}
+impl std::error::Error for DuplicateNoteError {}
+
+#[derive(Debug, Error)]
+pub enum NoteRegistryError {
+    #[error(transparent)]
+    CsvError(#[from] csv::Error),
+    #[error(transparent)]
+    DuplicateNoteError(#[from] DuplicateNoteError),
+}
 
 pub struct NoteRegistry {
     note2idx: HashMap<(i32, NoteName), usize>,
@@ -20,12 +30,37 @@ pub struct NoteRegistry {
 }
 
 impl NoteRegistry {
-    pub fn from_csv(csv_path: &str) -> Result<NoteRegistry, Box<dyn Error>> {
+    pub fn from_csv(csv_path: impl AsRef<Path>) -> Result<NoteRegistry, NoteRegistryError> {
         let notes = parse_csv(csv_path)?;
-        match NoteRegistry::from_notes(notes) {
-            Ok(v) => Ok(v),
-            Err(e) => Err(Box::new(e)),
-        }
+        Ok(NoteRegistry::from_notes(notes)?)
+    }
+
+    /// Registry of the notes in the CSV `text`, e.g. a note table bundled
+    /// with the binary.
+    pub fn from_csv_str(text: &str) -> Result<NoteRegistry, NoteRegistryError> {
+        let notes = parse_csv_str(text)?;
+        Ok(NoteRegistry::from_notes(notes)?)
+    }
+
+    /// Registry of every note from C to B of the `octaves`, tuned in equal
+    /// temperament with A4 at `a4` hertz, e.g. 432, 440 or 442.
+    pub fn equal_temperament(a4: f64, octaves: RangeInclusive<i32>) -> NoteRegistry {
+        let lowest = Note {
+            octave: *octaves.start(),
+            name: NoteName::C,
+            frequency: 0.0,
+        };
+        let n_notes = 12 * (octaves.end() - octaves.start() + 1).max(0);
+        let notes = (0..n_notes)
+            .map(|semitones| {
+                let note = lowest.add_semitone(semitones);
+                Note {
+                    frequency: a4 * 2f64.powf(note.semitones_from_a4() as f64 / 12.0),
+                    ..note
+                }
+            })
+            .collect();
+        NoteRegistry::from_notes(notes).expect("Notes of different pitches are never duplicates")
     }
 
     pub fn from_notes(mut notes: Vec<Note>) -> Result<NoteRegistry, DuplicateNoteError> {
@@ -95,6 +130,27 @@ mod tests {
         assert_eq!(None, reg.get(NoteName::GSharp, 1));
     }
 
+    #[test]
+    fn test_note_registry_from_csv_str() {
+        let text = "octave,name,frequency\n2,E,82.41\n2,A,110.0\n";
+        let reg = NoteRegistry::from_csv_str(text).unwrap();
+        assert_eq!(2, reg.notes().len());
+        assert_eq!(110.0, reg.get(NoteName::A, 2).unwrap().frequency);
+    }
+
+    #[test]
+    fn test_note_registry_equal_temperament() {
+        let reg = NoteRegistry::equal_temperament(442.0, 2..=6);
+        assert_eq!(60, reg.notes().len());
+        assert_eq!(442.0, reg.get(NoteName::A, 4).unwrap().frequency);
+        assert!((reg.get(NoteName::A, 2).unwrap().frequency - 110.5).abs() < 1e-9);
+        let e2 = reg.get(NoteName::E, 2).unwrap().frequency;
+        assert!((e2 - 442.0 * 2f64.powf(-29.0 / 12.0)).abs() < 1e-9);
+        assert_eq!(NoteName::C, reg.notes()[0].name);
+        assert_eq!(None, reg.get(NoteName::B, 1));
+        assert_eq!(None, reg.get(NoteName::C, 7));
+    }
+
     // #[test]
     // fn test_note_registry_iter_from_empty() {
     //     let note = Note {
@@ -0,0 +1,13 @@
+use thiserror::Error;
+
+/// Range of frets or strings without anything to play, e.g. from the ranges
+/// of the game config.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum RangeError {
+    #[error("Fret range {0}..{1} must include at least one fret")]
+    NoFrets(usize, usize),
+    #[error("String range {0}..{1} must include at least one string")]
+    NoStrings(usize, usize),
+    #[error("Strings are numbered from 1, but the string range starts at 0")]
+    ZeroString,
+}
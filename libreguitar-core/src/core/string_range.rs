@@ -0,0 +1,25 @@
+use crate::core::RangeError;
+use std::ops::Range;
+
+#[derive(Clone)]
+pub struct StringRange {
+    range: Range<usize>,
+}
+
+impl StringRange {
+    pub fn new(beg_string: usize, end_string: usize) -> Result<StringRange, RangeError> {
+        if beg_string == 0 {
+            return Err(RangeError::ZeroString);
+        }
+        if beg_string >= end_string {
+            return Err(RangeError::NoStrings(beg_string, end_string));
+        }
+        Ok(StringRange {
+            range: beg_string..end_string,
+        })
+    }
+
+    pub fn r(&self) -> Range<usize> {
+        self.range.clone()
+    }
+}
@@ -0,0 +1,583 @@
+use crate::core::csv::parse_csv;
+use crate::core::{Note, NoteName, NoteRegistry};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+use thiserror::Error;
+
+#[derive(Debug)]
+pub struct InvalidTuningError(String);
+impl fmt::Display for InvalidTuningError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "InvalidTuningError: {}", self.0)
+    }
+}
+impl std::error::Error for InvalidTuningError {}
+
+#[derive(Debug, Error)]
+pub enum TuningError {
+    #[error(transparent)]
+    CsvError(#[from] csv::Error),
+    #[error(transparent)]
+    InvalidTuningError(#[from] InvalidTuningError),
+}
+
+/// Names of common six string tunings, from the lowest to the highest string.
+const KNOWN_TUNINGS: [(&str, [NoteName; 6]); 6] = [
+    (
+        "Standard",
+        [
+            NoteName::E,
+            NoteName::A,
+            NoteName::D,
+            NoteName::G,
+            NoteName::B,
+            NoteName::E,
+        ],
+    ),
+    (
+        "Half step down",
+        [
+            NoteName::DSharp,
+            NoteName::GSharp,
+            NoteName::CSharp,
+            NoteName::FSharp,
+            NoteName::ASharp,
+            NoteName::DSharp,
+        ],
+    ),
+    (
+        "Drop D",
+        [
+            NoteName::D,
+            NoteName::A,
+            NoteName::D,
+            NoteName::G,
+            NoteName::B,
+            NoteName::E,
+        ],
+    ),
+    (
+        "DADGAD",
+        [
+            NoteName::D,
+            NoteName::A,
+            NoteName::D,
+            NoteName::G,
+            NoteName::A,
+            NoteName::D,
+        ],
+    ),
+    (
+        "Open D",
+        [
+            NoteName::D,
+            NoteName::A,
+            NoteName::D,
+            NoteName::FSharp,
+            NoteName::A,
+            NoteName::D,
+        ],
+    ),
+    (
+        "Open G",
+        [
+            NoteName::D,
+            NoteName::G,
+            NoteName::D,
+            NoteName::G,
+            NoteName::B,
+            NoteName::D,
+        ],
+    ),
+];
+
+/// Lowest string of compact tunings without an octave hint is placed in the
+/// octave that brings it closest to E2, in semitones above C0.
+const COMPACT_LOWEST_STRING_CENTER: i32 = 2 * 12 + 4;
+
+/// Splits a compact tuning such as `EADGBE`, `D A D G A D`, `Eb Ab Db Gb Bb Eb`
+/// or `D2 A2 D3 F#3 A3 D4` into its notes.
+///
+/// Notes separated by spaces or commas may use lowercase letters. Without
+/// separators, every uppercase letter starts a new note so that a lowercase
+/// `b` can stand for a flat.
+fn split_compact(tuning: &str) -> Vec<String> {
+    let tuning = tuning.trim();
+    if tuning.contains(|c: char| c.is_whitespace() || c == ',') {
+        return tuning
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|token| !token.is_empty())
+            .map(String::from)
+            .collect();
+    }
+    let mut tokens: Vec<String> = Vec::new();
+    for c in tuning.chars() {
+        match tokens.last_mut() {
+            Some(token) if !c.is_ascii_uppercase() => token.push(c),
+            _ => tokens.push(c.to_string()),
+        }
+    }
+    tokens
+}
+
+/// Semitones above C of the pitch class of `token` and its octave, if given,
+/// e.g. `(10, None)` for `Bb` and `(6, Some(3))` for `F#3`.
+fn parse_compact_note(token: &str) -> Option<(i32, Option<i32>)> {
+    let mut chars = token.chars();
+    let mut semitones = match chars.next()?.to_ascii_uppercase() {
+        'C' => 0,
+        'D' => 2,
+        'E' => 4,
+        'F' => 5,
+        'G' => 7,
+        'A' => 9,
+        'B' => 11,
+        _ => return None,
+    };
+    let rest = chars.as_str();
+    let octave_start = rest
+        .find(|c: char| c.is_ascii_digit() || c == '-')
+        .unwrap_or(rest.len());
+    let (accidentals, octave) = rest.split_at(octave_start);
+    for accidental in accidentals.chars() {
+        semitones += match accidental {
+            '#' | '♯' => 1,
+            'b' | '♭' => -1,
+            _ => return None,
+        };
+    }
+    let octave = if octave.is_empty() {
+        None
+    } else {
+        Some(octave.parse().ok()?)
+    };
+    Some((semitones, octave))
+}
+
+#[derive(Deserialize, PartialEq)]
+pub struct TuningSpecification {
+    pub string: usize,
+    pub octave: i32,
+    pub name: NoteName,
+}
+
+#[derive(Clone)]
+pub struct Tuning {
+    values: BTreeMap<usize, Note>,
+}
+
+impl Tuning {
+    pub fn from_csv(
+        csv_path: impl AsRef<Path>,
+        note_registry: &NoteRegistry,
+    ) -> Result<Tuning, TuningError> {
+        let tuning_spec: Vec<TuningSpecification> = parse_csv(csv_path)?;
+        Ok(Tuning::from_specification(&tuning_spec[..], note_registry)?)
+    }
+
+    pub fn from_specification(
+        tuning_spec: &[TuningSpecification],
+        note_registry: &NoteRegistry,
+    ) -> Result<Tuning, InvalidTuningError> {
+        let mut map = BTreeMap::new();
+        for row in tuning_spec.iter() {
+            if let Some(note) = note_registry.get(row.name, row.octave) {
+                map.insert(row.string, note.clone());
+            } else {
+                return Err(InvalidTuningError(String::from(
+                    "Tuning specification contains a note not given in note frequency list",
+                )));
+            }
+        }
+        Ok(Tuning { values: map })
+    }
+
+    /// Parses a compact tuning listing the open strings from the lowest to the
+    /// highest, such as `EADGBE` or `D A D G A D`, as players usually write it.
+    ///
+    /// Notes may carry an octave, e.g. `B1 E2 A2 D3 G3 B3 E4`. Otherwise the
+    /// lowest string is placed near E2 and every other string is tuned up to
+    /// 12 semitones above the previous one.
+    pub fn from_compact(
+        tuning: &str,
+        note_registry: &NoteRegistry,
+    ) -> Result<Tuning, InvalidTuningError> {
+        let tokens = split_compact(tuning);
+        if tokens.is_empty() {
+            return Err(InvalidTuningError(String::from("Tuning is empty")));
+        }
+        let c0 = Note {
+            octave: 0,
+            name: NoteName::C,
+            frequency: f64::NAN,
+        };
+        let n_strings = tokens.len();
+        let mut tuning_spec = Vec::with_capacity(n_strings);
+        let mut previous: Option<i32> = None;
+        for (i, token) in tokens.iter().enumerate() {
+            let (pitch_class, octave) = parse_compact_note(token).ok_or_else(|| {
+                InvalidTuningError(format!("{} in tuning {} is not a note", token, tuning))
+            })?;
+            let from_c0 = match (octave, previous) {
+                (Some(octave), _) => octave
+                    .checked_mul(12)
+                    .and_then(|semitones| semitones.checked_add(pitch_class)),
+                (None, Some(previous)) => {
+                    let up = 1 + (pitch_class - previous.rem_euclid(12) - 1).rem_euclid(12);
+                    previous.checked_add(up)
+                }
+                (None, None) => {
+                    let below_center = COMPACT_LOWEST_STRING_CENTER - pitch_class;
+                    Some(pitch_class + (below_center + 5).div_euclid(12) * 12)
+                }
+            }
+            .ok_or_else(|| {
+                InvalidTuningError(format!("{} in tuning {} is out of range", token, tuning))
+            })?;
+            previous = Some(from_c0);
+            let note = c0.add_semitone(from_c0);
+            tuning_spec.push(TuningSpecification {
+                string: n_strings - i,
+                octave: note.octave,
+                name: note.name,
+            });
+        }
+        Tuning::from_specification(&tuning_spec, note_registry).map_err(|_| {
+            InvalidTuningError(format!(
+                "Tuning {} contains a note not given in note frequency list",
+                tuning
+            ))
+        })
+    }
+
+    pub fn note(&self, string_idx: usize) -> Option<&Note> {
+        self.values.get(&string_idx)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Note> {
+        self.values.values()
+    }
+
+    /// Describes the tuning with its common name, if it has one, followed by
+    /// the open string notes from the lowest to the highest string, e.g.
+    /// `Drop D (D2 A2 D3 G3 B3 E4)`.
+    pub fn name(&self) -> String {
+        let names: Vec<NoteName> = self.values.values().rev().map(|note| note.name).collect();
+        let notes: Vec<String> = self
+            .values
+            .values()
+            .rev()
+            .map(|note| note.name_octave())
+            .collect();
+        match KNOWN_TUNINGS
+            .iter()
+            .find(|(_, tuning_names)| names[..] == tuning_names[..])
+        {
+            Some((name, _)) => format!("{} ({})", name, notes.join(" ")),
+            None => format!("Custom ({})", notes.join(" ")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tuning_empty() {
+        let tuning_spec = vec![];
+        let note_reg = NoteRegistry::from_notes(vec![]).unwrap();
+        let tuning = Tuning::from_specification(&tuning_spec[..], &note_reg).unwrap();
+        assert_eq!(None, tuning.iter().next());
+    }
+
+    #[test]
+    fn test_tuning_empty_panic() {
+        let tuning_spec = vec![];
+        let note_reg = NoteRegistry::from_notes(vec![]).unwrap();
+        let tuning = Tuning::from_specification(&tuning_spec[..], &note_reg).unwrap();
+        assert_eq!(None, tuning.note(1));
+    }
+
+    #[test]
+    fn test_tuning_nonempty_panic_wrong_idx() {
+        let tuning_spec = vec![TuningSpecification {
+            octave: 1,
+            string: 1,
+            name: NoteName::E,
+        }];
+        let note_reg = NoteRegistry::from_notes(vec![Note {
+            name: NoteName::E,
+            octave: 1,
+            frequency: 53.5,
+        }])
+        .unwrap();
+        let tuning = Tuning::from_specification(&tuning_spec[..], &note_reg).unwrap();
+        assert_eq!(None, tuning.note(0));
+    }
+
+    #[test]
+    fn test_tuning_nonempty_panic() {
+        let tuning_spec = vec![TuningSpecification {
+            octave: 1,
+            string: 1,
+            name: NoteName::E,
+        }];
+        let note_reg = NoteRegistry::from_notes(vec![Note {
+            name: NoteName::E,
+            octave: 1,
+            frequency: 53.5,
+        }])
+        .unwrap();
+        let tuning = Tuning::from_specification(&tuning_spec[..], &note_reg).unwrap();
+        assert_eq!(None, tuning.note(2));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_tuning_incorrect_order() {
+        let tuning_spec = vec![
+            TuningSpecification {
+                string: 1,
+                octave: 4,
+                name: NoteName::E,
+            },
+            TuningSpecification {
+                string: 3,
+                octave: 4,
+                name: NoteName::E,
+            },
+            TuningSpecification {
+                string: 2,
+                octave: 4,
+                name: NoteName::E,
+            },
+        ];
+        let note_reg = NoteRegistry::from_notes(vec![
+            Note {
+                frequency: 35.5,
+                octave: 4,
+                name: NoteName::E,
+            },
+            Note {
+                frequency: 35.5,
+                octave: 4,
+                name: NoteName::E,
+            },
+            Note {
+                frequency: 35.5,
+                octave: 4,
+                name: NoteName::E,
+            },
+        ])
+        .unwrap();
+        Tuning::from_specification(&tuning_spec[..], &note_reg).unwrap();
+    }
+
+    #[test]
+    fn test_tuning_note() {
+        let tuning_spec = vec![
+            TuningSpecification {
+                string: 1,
+                octave: 4,
+                name: NoteName::E,
+            },
+            TuningSpecification {
+                string: 2,
+                octave: 4,
+                name: NoteName::F,
+            },
+            TuningSpecification {
+                string: 3,
+                octave: 4,
+                name: NoteName::G,
+            },
+        ];
+        let note_vec = vec![
+            Note {
+                frequency: 35.5,
+                octave: 4,
+                name: NoteName::E,
+            },
+            Note {
+                frequency: 36.0,
+                octave: 4,
+                name: NoteName::A,
+            },
+            Note {
+                frequency: 36.5,
+                octave: 4,
+                name: NoteName::F,
+            },
+            Note {
+                frequency: 37.0,
+                octave: 4,
+                name: NoteName::B,
+            },
+            Note {
+                frequency: 37.5,
+                octave: 4,
+                name: NoteName::G,
+            },
+        ];
+        let note_reg = NoteRegistry::from_notes(note_vec.clone()).unwrap();
+        let tuning = Tuning::from_specification(&tuning_spec[..], &note_reg).unwrap();
+        assert_eq!(&note_vec[0], tuning.note(1).unwrap());
+        assert_eq!(&note_vec[2], tuning.note(2).unwrap());
+        assert_eq!(&note_vec[4], tuning.note(3).unwrap());
+    }
+
+    #[test]
+    fn test_tuning_iter() {
+        let tuning_spec = vec![
+            TuningSpecification {
+                string: 1,
+                octave: 4,
+                name: NoteName::E,
+            },
+            TuningSpecification {
+                string: 2,
+                octave: 4,
+                name: NoteName::F,
+            },
+            TuningSpecification {
+                string: 3,
+                octave: 4,
+                name: NoteName::G,
+            },
+        ];
+        let note_vec = vec![
+            Note {
+                frequency: 35.5,
+                octave: 4,
+                name: NoteName::E,
+            },
+            Note {
+                frequency: 36.0,
+                octave: 4,
+                name: NoteName::A,
+            },
+            Note {
+                frequency: 36.5,
+                octave: 4,
+                name: NoteName::F,
+            },
+            Note {
+                frequency: 37.0,
+                octave: 4,
+                name: NoteName::B,
+            },
+            Note {
+                frequency: 37.5,
+                octave: 4,
+                name: NoteName::G,
+            },
+        ];
+        let note_reg = NoteRegistry::from_notes(note_vec.clone()).unwrap();
+        let tuning = Tuning::from_specification(&tuning_spec[..], &note_reg).unwrap();
+        let mut iter = tuning.iter();
+        assert_eq!(Some(&note_vec[0]), iter.next());
+        assert_eq!(Some(&note_vec[2]), iter.next());
+        assert_eq!(Some(&note_vec[4]), iter.next());
+        assert_eq!(None, iter.next());
+    }
+
+    #[test]
+    fn test_tuning_name() {
+        let open_notes = [
+            (6, NoteName::D, 2),
+            (5, NoteName::A, 2),
+            (4, NoteName::D, 3),
+            (3, NoteName::G, 3),
+            (2, NoteName::B, 3),
+            (1, NoteName::E, 4),
+        ];
+        let tuning_spec: Vec<TuningSpecification> = open_notes
+            .iter()
+            .map(|&(string, name, octave)| TuningSpecification {
+                string,
+                octave,
+                name,
+            })
+            .collect();
+        let note_reg = NoteRegistry::from_notes(
+            open_notes
+                .iter()
+                .map(|&(_, name, octave)| Note {
+                    name,
+                    octave,
+                    frequency: 0.0,
+                })
+                .collect(),
+        )
+        .unwrap();
+        let tuning = Tuning::from_specification(&tuning_spec[..], &note_reg).unwrap();
+        assert_eq!("Drop D (D2 A2 D3 G3 B3 E4)", tuning.name());
+
+        let tuning = Tuning::from_specification(&tuning_spec[1..], &note_reg).unwrap();
+        assert_eq!("Custom (A2 D3 G3 B3 E4)", tuning.name());
+    }
+
+    fn chromatic_registry() -> NoteRegistry {
+        let c0 = Note {
+            octave: 0,
+            name: NoteName::C,
+            frequency: f64::NAN,
+        };
+        let notes = (12..84)
+            .map(|semitones| Note {
+                frequency: 16.35 * 2f64.powf(semitones as f64 / 12.0),
+                ..c0.add_semitone(semitones)
+            })
+            .collect();
+        NoteRegistry::from_notes(notes).unwrap()
+    }
+
+    fn compact_notes(tuning: &str) -> Vec<String> {
+        let tuning = Tuning::from_compact(tuning, &chromatic_registry()).unwrap();
+        let mut notes: Vec<String> = tuning.iter().map(|note| note.name_octave()).collect();
+        notes.reverse();
+        notes
+    }
+
+    #[test]
+    fn test_tuning_from_compact() {
+        let standard = vec!["E2", "A2", "D3", "G3", "B3", "E4"];
+        assert_eq!(standard, compact_notes("EADGBE"));
+        assert_eq!(standard, compact_notes(" e a d g b e "));
+        assert_eq!(
+            vec!["D2", "A2", "D3", "G3", "A3", "D4"],
+            compact_notes("D A D G A D")
+        );
+        assert_eq!(
+            vec!["D♯2", "G♯2", "C♯3", "F♯3", "A♯3", "D♯4"],
+            compact_notes("EbAbDbGbBbEb")
+        );
+        assert_eq!(
+            vec!["B1", "E2", "A2", "D3", "G3", "B3", "E4"],
+            compact_notes("B,E,A,D,G,B,E")
+        );
+        assert_eq!(
+            vec!["D3", "A3", "D4", "F♯4", "A4", "D5"],
+            compact_notes("D3 A D F# A D")
+        );
+        assert_eq!(vec!["C2", "C3"], compact_notes("C C"));
+        let tuning = Tuning::from_compact("DADGBE", &chromatic_registry()).unwrap();
+        assert_eq!("Drop D (D2 A2 D3 G3 B3 E4)", tuning.name());
+        assert_eq!(Some(NoteName::D), tuning.note(6).map(|note| note.name));
+    }
+
+    #[test]
+    fn test_tuning_from_compact_invalid() {
+        let registry = chromatic_registry();
+        assert!(Tuning::from_compact("", &registry).is_err());
+        assert!(Tuning::from_compact("E A H", &registry).is_err());
+        assert!(Tuning::from_compact("E A D#x", &registry).is_err());
+        assert!(Tuning::from_compact("E9 A D", &registry).is_err());
+        assert!(Tuning::from_compact("E999999999", &registry).is_err());
+        assert!(Tuning::from_compact("B178956969 B", &registry).is_err());
+        assert!(Tuning::from_compact("C-999999999 D", &registry).is_err());
+    }
+}
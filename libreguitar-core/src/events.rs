@@ -0,0 +1,5 @@
+mod game_events;
+pub mod status_text;
+
+pub use game_events::{GameEvent, GameEventTracker, EVENT_VERSION};
+pub use status_text::TargetResult;
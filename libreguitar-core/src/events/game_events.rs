@@ -0,0 +1,328 @@
+use crate::audio_analysis::AnalysisResult;
+use crate::core::{duration_secs, FretLoc};
+use crate::events::TargetResult;
+use crate::game::GameState;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::convert::TryFrom;
+
+/// Version of the JSON shape of the [`GameEvent`]s and the
+/// [`GameState`](crate::game::GameState), sent as `version` in every event.
+/// It is raised whenever a field is renamed, changes its meaning or is
+/// removed, while new fields and event types may be added within a version.
+pub const EVENT_VERSION: u32 = 1;
+
+/// Change in the game or a detection, published to external listeners.
+///
+/// It is (de)serialized as a JSON object with the kind of the event under
+/// `type` and the [`EVENT_VERSION`] under `version`:
+///
+/// | `type`      | fields                                                 |
+/// |-------------|--------------------------------------------------------|
+/// | `target`    | `note`, `string`, `fret`                               |
+/// | `progress`  | `count`, `needed`                                      |
+/// | `result`    | `note`, `string`, `fret`, `correct`, `response_time` s |
+/// | `paused`    | `paused`                                               |
+/// | `detection` | `note`, `frequency` Hz, `cents`                        |
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(into = "EventMessage", try_from = "EventMessage")]
+pub enum GameEvent {
+    /// A new target to play.
+    Target {
+        note: String,
+        loc: FretLoc,
+    },
+    /// Detections of the current target so far.
+    Progress {
+        count: usize,
+        needed: usize,
+    },
+    /// Outcome of the previous target, sent before the next target.
+    Result(TargetResult),
+    Paused(bool),
+    /// Detected note and its deviation from the note in cents.
+    Detection {
+        note: String,
+        frequency: f64,
+        cents: f64,
+    },
+}
+
+/// JSON shape of a [`GameEvent`].
+#[derive(Serialize, Deserialize)]
+struct EventMessage {
+    version: u32,
+    #[serde(flatten)]
+    body: EventBody,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum EventBody {
+    Target {
+        note: String,
+        string: usize,
+        fret: usize,
+    },
+    Progress {
+        count: usize,
+        needed: usize,
+    },
+    Result {
+        note: String,
+        string: usize,
+        fret: usize,
+        correct: bool,
+        response_time: f64,
+    },
+    Paused {
+        paused: bool,
+    },
+    Detection {
+        note: String,
+        frequency: f64,
+        cents: f64,
+    },
+}
+
+impl From<GameEvent> for EventMessage {
+    fn from(event: GameEvent) -> EventMessage {
+        let body = match event {
+            GameEvent::Target { note, loc } => EventBody::Target {
+                note,
+                string: loc.string_idx,
+                fret: loc.fret_idx,
+            },
+            GameEvent::Progress { count, needed } => EventBody::Progress { count, needed },
+            GameEvent::Result(result) => EventBody::Result {
+                note: result.note,
+                string: result.loc.string_idx,
+                fret: result.loc.fret_idx,
+                correct: result.correct,
+                response_time: result.response_time.as_secs_f64(),
+            },
+            GameEvent::Paused(paused) => EventBody::Paused { paused },
+            GameEvent::Detection {
+                note,
+                frequency,
+                cents,
+            } => EventBody::Detection {
+                note,
+                frequency,
+                cents,
+            },
+        };
+        EventMessage {
+            version: EVENT_VERSION,
+            body,
+        }
+    }
+}
+
+impl TryFrom<EventMessage> for GameEvent {
+    type Error = String;
+
+    fn try_from(message: EventMessage) -> Result<GameEvent, String> {
+        if message.version != EVENT_VERSION {
+            return Err(format!(
+                "Event version {} is not supported, expected {}",
+                message.version, EVENT_VERSION
+            ));
+        }
+        let loc = |string_idx, fret_idx| FretLoc {
+            string_idx,
+            fret_idx,
+        };
+        Ok(match message.body {
+            EventBody::Target { note, string, fret } => GameEvent::Target {
+                note,
+                loc: loc(string, fret),
+            },
+            EventBody::Progress { count, needed } => GameEvent::Progress { count, needed },
+            EventBody::Result {
+                note,
+                string,
+                fret,
+                correct,
+                response_time,
+            } => GameEvent::Result(TargetResult {
+                note,
+                loc: loc(string, fret),
+                correct,
+                response_time: duration_secs::checked_from_secs(response_time)
+                    .ok_or_else(|| format!("Invalid response time {}", response_time))?,
+            }),
+            EventBody::Paused { paused } => GameEvent::Paused(paused),
+            EventBody::Detection {
+                note,
+                frequency,
+                cents,
+            } => GameEvent::Detection {
+                note,
+                frequency,
+                cents,
+            },
+        })
+    }
+}
+
+impl GameEvent {
+    /// Detection event of `analysis`, or `None` if no note was detected.
+    pub fn detection(analysis: &AnalysisResult) -> Option<GameEvent> {
+        let note = analysis.note.as_ref()?;
+        Some(GameEvent::Detection {
+            note: note.name_octave(),
+            frequency: analysis.frequency?,
+            cents: analysis.cents()?,
+        })
+    }
+
+    /// JSON text of [`to_json_value`](GameEvent::to_json_value).
+    pub fn to_json(&self) -> String {
+        self.to_json_value().to_string()
+    }
+
+    /// JSON object of the event, e.g.
+    /// `{"type":"progress","count":2,"needed":3,"version":1}`.
+    pub fn to_json_value(&self) -> Value {
+        serde_json::to_value(self).expect("Events are always valid JSON")
+    }
+}
+
+/// Turns the stream of game states into the events that happened between
+/// them.
+#[derive(Debug, Default)]
+pub struct GameEventTracker {
+    last_state: Option<GameState>,
+}
+
+impl GameEventTracker {
+    pub fn update(&mut self, state: GameState) -> Vec<GameEvent> {
+        let mut events = Vec::new();
+        let is_new_target = match &self.last_state {
+            Some(last_state) if last_state.target_loc == state.target_loc => {
+                if last_state.paused != state.paused {
+                    events.push(GameEvent::Paused(state.paused));
+                }
+                false
+            }
+            Some(last_state) => {
+                events.push(GameEvent::Result(TargetResult::between(last_state, &state)));
+                true
+            }
+            None => true,
+        };
+        if is_new_target {
+            events.push(GameEvent::Target {
+                note: state.target_note.name_octave(),
+                loc: state.target_loc.clone(),
+            });
+        }
+        let last_count = match (&self.last_state, is_new_target) {
+            (Some(last_state), false) => last_state.curr_detection_count,
+            _ => 0,
+        };
+        if state.curr_detection_count != last_count {
+            events.push(GameEvent::Progress {
+                count: state.curr_detection_count,
+                needed: state.needed_detection_count,
+            });
+        }
+        self.last_state = Some(state);
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Score;
+    use std::time::Duration;
+
+    fn state(fret_idx: usize, count: usize, score: Score) -> GameState {
+        GameState {
+            curr_detection_count: count,
+            score,
+            ..GameState::fixture(FretLoc {
+                string_idx: 5,
+                fret_idx,
+            })
+        }
+    }
+
+    #[test]
+    fn test_events_between_states() {
+        let mut tracker = GameEventTracker::default();
+        let target = |fret_idx| GameEvent::Target {
+            note: String::from("C4"),
+            loc: FretLoc {
+                string_idx: 5,
+                fret_idx,
+            },
+        };
+        assert_eq!(
+            vec![target(0)],
+            tracker.update(state(0, 0, Score::default()))
+        );
+        assert_eq!(
+            vec![GameEvent::Progress {
+                count: 2,
+                needed: 3
+            }],
+            tracker.update(state(0, 2, Score::default()))
+        );
+        let mut paused = state(0, 2, Score::default());
+        paused.paused = true;
+        assert_eq!(vec![GameEvent::Paused(true)], tracker.update(paused));
+
+        let mut score = Score::default();
+        score.record(true, Duration::from_secs(2));
+        let events = tracker.update(state(1, 0, score));
+        assert_eq!(2, events.len());
+        match &events[0] {
+            GameEvent::Result(result) => {
+                assert!(result.correct);
+                assert_eq!(Duration::from_secs(2), result.response_time);
+            }
+            event => panic!("Unexpected event {:?}", event),
+        }
+        assert_eq!(target(1), events[1]);
+    }
+
+    #[test]
+    fn test_to_json() {
+        let event = GameEvent::Target {
+            note: String::from("C4"),
+            loc: FretLoc {
+                string_idx: 5,
+                fret_idx: 3,
+            },
+        };
+        assert_eq!(
+            r#"{"fret":3,"note":"C4","string":5,"type":"target","version":1}"#,
+            event.to_json()
+        );
+        assert_eq!(
+            r#"{"paused":false,"type":"paused","version":1}"#,
+            GameEvent::Paused(false).to_json()
+        );
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let result = GameEvent::Result(TargetResult {
+            note: String::from("E2"),
+            loc: FretLoc {
+                string_idx: 6,
+                fret_idx: 0,
+            },
+            correct: false,
+            response_time: Duration::from_millis(2500),
+        });
+        let json = result.to_json();
+        assert_eq!(result, serde_json::from_str(&json).unwrap());
+
+        let unsupported = json.replace(r#""version":1"#, r#""version":2"#);
+        assert!(serde_json::from_str::<GameEvent>(&unsupported).is_err());
+    }
+}
@@ -0,0 +1,194 @@
+use crate::audio_analysis::AnalysisResult;
+use crate::core::FretLoc;
+use crate::game::{GameState, Score};
+use std::time::Duration;
+
+/// Outcome of a finished target.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TargetResult {
+    pub note: String,
+    pub loc: FretLoc,
+    pub correct: bool,
+    pub response_time: Duration,
+}
+
+impl TargetResult {
+    /// Derives the outcome of the target of `finished` from the score sent
+    /// along with the `next` target.
+    pub fn between(finished: &GameState, next: &GameState) -> TargetResult {
+        TargetResult {
+            note: finished.target_note.name_octave(),
+            loc: finished.target_loc.clone(),
+            correct: next.score.n_correct > finished.score.n_correct,
+            response_time: next
+                .score
+                .total_response_time
+                .saturating_sub(finished.score.total_response_time),
+        }
+    }
+}
+
+/// Largest cents deviation shown on the tuner bar.
+pub const TUNER_RANGE_CENTS: f64 = 50.0;
+
+/// Draws a tuner bar such as `♭ ----|--*-- ♯` where `*` marks the cents
+/// deviation of the detected pitch. The marker is left out if nothing is
+/// detected and sticks to the edges for deviations beyond the bar range.
+pub fn draw_tuner_bar(cents: Option<f64>, half_width: usize) -> String {
+    let mut bar: Vec<char> = (0..2 * half_width + 1).map(|_| '-').collect();
+    bar[half_width] = '|';
+    if let Some(cents) = cents {
+        let offset = (cents / TUNER_RANGE_CENTS * half_width as f64).round() as i64;
+        let offset = offset.max(-(half_width as i64)).min(half_width as i64);
+        bar[(half_width as i64 + offset) as usize] = '*';
+    }
+    format!("♭ {} ♯", bar.into_iter().collect::<String>())
+}
+
+/// Formats the session statistics as a single status line.
+pub fn format_score(score: &Score) -> String {
+    let accuracy = match score.accuracy() {
+        Some(accuracy) => format!("{:.0}%", accuracy * 100.0),
+        None => String::from("-"),
+    };
+    let avg_response_time = match score.avg_response_time() {
+        Some(time) => format!("{:.1}s", time.as_secs_f64()),
+        None => String::from("-"),
+    };
+    format!(
+        "Streak: {} (best {}) | Accuracy: {} ({}/{}) | Avg. response: {}",
+        score.streak,
+        score.best_streak,
+        accuracy,
+        score.n_correct,
+        score.n_completed,
+        avg_response_time
+    )
+}
+
+/// Formats the countdown of the current target and the elapsed session time,
+/// e.g. `Time left: 4.2s | Session: 01:05`.
+pub fn format_timer(remaining: Duration, session_elapsed: Duration) -> String {
+    let session_secs = session_elapsed.as_secs();
+    format!(
+        "Time left: {:.1}s | Session: {:02}:{:02}",
+        remaining.as_secs_f64(),
+        session_secs / 60,
+        session_secs % 60
+    )
+}
+
+/// Formats a finished target, e.g. `C4 on string 5: correct (2.1s)`.
+pub fn format_target_result(result: &TargetResult) -> String {
+    format!(
+        "{} on string {}: {} ({:.1}s)",
+        result.note,
+        result.loc.string_idx,
+        if result.correct { "correct" } else { "missed" },
+        result.response_time.as_secs_f64()
+    )
+}
+
+/// Formats the detected note and its cents deviation, e.g. `G3 (+12¢)`.
+pub fn format_detection(detection: Option<&AnalysisResult>) -> String {
+    let detection = match detection {
+        Some(detection) => detection,
+        None => return String::from("-"),
+    };
+    match (&detection.note, detection.cents()) {
+        (Some(note), Some(cents)) => format!("{} ({:+.0}¢)", note.name_octave(), cents),
+        (Some(note), None) => note.name_octave(),
+        _ => String::from("-"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{Note, NoteName};
+
+    #[test]
+    fn test_draw_tuner_bar_nothing_detected() {
+        assert_eq!("♭ --|-- ♯", draw_tuner_bar(None, 2));
+    }
+
+    #[test]
+    fn test_draw_tuner_bar() {
+        assert_eq!("♭ --*-- ♯", draw_tuner_bar(Some(3.0), 2));
+        assert_eq!("♭ --|*- ♯", draw_tuner_bar(Some(25.0), 2));
+        assert_eq!("♭ *-|-- ♯", draw_tuner_bar(Some(-50.0), 2));
+        assert_eq!("♭ --|-* ♯", draw_tuner_bar(Some(400.0), 2));
+    }
+
+    #[test]
+    fn test_format_timer() {
+        assert_eq!(
+            "Time left: 4.2s | Session: 01:05",
+            format_timer(Duration::from_millis(4200), Duration::from_secs(65))
+        );
+    }
+
+    #[test]
+    fn test_format_target_result() {
+        let result = TargetResult {
+            note: String::from("C4"),
+            loc: FretLoc {
+                string_idx: 5,
+                fret_idx: 0,
+            },
+            correct: false,
+            response_time: Duration::from_millis(2140),
+        };
+        assert_eq!(
+            "C4 on string 5: missed (2.1s)",
+            format_target_result(&result)
+        );
+    }
+
+    #[test]
+    fn test_format_score_empty() {
+        assert_eq!(
+            "Streak: 0 (best 0) | Accuracy: - (0/0) | Avg. response: -",
+            format_score(&Score::default())
+        );
+    }
+
+    #[test]
+    fn test_format_score() {
+        let mut score = Score::default();
+        score.record(true, Duration::from_millis(1500));
+        score.record(false, Duration::from_millis(2500));
+        score.record(true, Duration::from_millis(3200));
+        assert_eq!(
+            "Streak: 1 (best 1) | Accuracy: 67% (2/3) | Avg. response: 2.4s",
+            format_score(&score)
+        );
+    }
+
+    #[test]
+    fn test_format_detection_nothing_detected() {
+        assert_eq!("-", format_detection(None));
+        let result = AnalysisResult {
+            note: None,
+            frequency: None,
+            confidence: 0.0,
+            peaks: Vec::new(),
+        };
+        assert_eq!("-", format_detection(Some(&result)));
+    }
+
+    #[test]
+    fn test_format_detection_with_cents() {
+        let result = AnalysisResult {
+            note: Some(Note {
+                octave: 3,
+                name: NoteName::G,
+                frequency: 196.0,
+            }),
+            frequency: Some(196.0 * 2.0f64.powf(12.0 / 1200.0)),
+            confidence: 1.0,
+            peaks: Vec::new(),
+        };
+        assert_eq!("G3 (+12¢)", format_detection(Some(&result)));
+    }
+}
@@ -0,0 +1,26 @@
+mod active_notes;
+mod exercise;
+#[cfg(feature = "analysis")]
+mod game_session;
+mod game_state;
+mod guitar_pro;
+mod history;
+mod musicxml;
+mod score;
+#[cfg(feature = "plugins")]
+mod script_selector;
+mod target_selector;
+
+pub use active_notes::ActiveNotes;
+pub use exercise::{load_exercise, ExerciseError, ExerciseNote, LoadExerciseError};
+#[cfg(feature = "analysis")]
+pub use game_session::{GameCommand, GameSession};
+pub use game_state::GameState;
+pub use history::{
+    append_targets, append_to_history, load_history, load_targets, write_csv, write_history,
+    write_targets, SessionRecord, TargetRecord,
+};
+pub use score::Score;
+#[cfg(feature = "plugins")]
+pub use script_selector::{PluginError, ScriptSelector};
+pub use target_selector::{ExerciseSelector, RandomSelector, TargetSelector};
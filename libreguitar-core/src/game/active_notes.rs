@@ -39,6 +39,17 @@ impl ActiveNotes {
     pub fn get<'a>(&'a self, loc: &FretLoc) -> Option<&'a Note> {
         self.notes.get(loc)
     }
+
+    /// Location of `note` on the lowest fret it can be played on, preferring
+    /// the higher string if it can be played on several.
+    pub fn locate(&self, note: &Note) -> Option<FretLoc> {
+        self.notes
+            .iter()
+            .filter(|(_, active_note)| *active_note == note)
+            .map(|(loc, _)| loc)
+            .min_by_key(|loc| (loc.fret_idx, loc.string_idx))
+            .cloned()
+    }
 }
 
 fn locs2notes<'a>(
@@ -77,8 +88,8 @@ mod active_note_tests {
 
     #[test]
     fn test_active_locations_one_cell() {
-        let string_range = StringRange::new(1, 2);
-        let fret_range = FretRange::new(3, 4);
+        let string_range = StringRange::new(1, 2).unwrap();
+        let fret_range = FretRange::new(3, 4).unwrap();
         let active_locs = active_locations(&string_range, &fret_range);
         assert_eq!(1, active_locs.len());
         assert_eq!(
@@ -92,8 +103,8 @@ mod active_note_tests {
 
     #[test]
     fn test_active_locations_open_strings() {
-        let string_range = StringRange::new(1, 4);
-        let fret_range = FretRange::new(0, 1);
+        let string_range = StringRange::new(1, 4).unwrap();
+        let fret_range = FretRange::new(0, 1).unwrap();
         let active_locs = active_locations(&string_range, &fret_range);
         assert_eq!(3, active_locs.len());
         assert_eq!(
@@ -117,8 +128,8 @@ mod active_note_tests {
 
     #[test]
     fn test_active_locations_fifth_pos() {
-        let string_range = StringRange::new(1, 7);
-        let fret_range = FretRange::new(5, 9);
+        let string_range = StringRange::new(1, 7).unwrap();
+        let fret_range = FretRange::new(5, 9).unwrap();
         let active_locs = active_locations(&string_range, &fret_range);
         assert_eq!(24, active_locs.len());
         let mut expected = Vec::new();
@@ -133,10 +144,57 @@ mod active_note_tests {
         assert_eq!(expected, active_locs);
     }
 
+    #[test]
+    fn test_locate() {
+        let g3 = Note {
+            octave: 3,
+            name: NoteName::G,
+            frequency: 196.0,
+        };
+        let notes = (0..12)
+            .map(|semitones| Note {
+                frequency: 196.0 * 2f64.powf(semitones as f64 / 12.0),
+                ..g3.add_semitone(semitones)
+            })
+            .collect();
+        let registry = NoteRegistry::from_notes(notes).unwrap();
+        let tuning = Tuning::from_specification(
+            &[
+                TuningSpecification {
+                    name: NoteName::B,
+                    octave: 3,
+                    string: 2,
+                },
+                TuningSpecification {
+                    name: NoteName::G,
+                    octave: 3,
+                    string: 3,
+                },
+            ],
+            &registry,
+        )
+        .unwrap();
+        let notes = ActiveNotes::new(
+            &registry,
+            &tuning,
+            StringRange::new(2, 4).unwrap(),
+            FretRange::new(0, 5).unwrap(),
+        );
+        let loc = |string_idx, fret_idx| {
+            Some(FretLoc {
+                string_idx,
+                fret_idx,
+            })
+        };
+        assert_eq!(loc(2, 0), notes.locate(&g3.add_semitone(4)));
+        assert_eq!(loc(3, 2), notes.locate(&g3.add_semitone(2)));
+        assert_eq!(None, notes.locate(&g3.add_semitone(11)));
+    }
+
     #[test]
     fn test_locs2notes_empty() {
-        let string_range = StringRange::new(1, 7);
-        let fret_range = FretRange::new(5, 9);
+        let string_range = StringRange::new(1, 7).unwrap();
+        let fret_range = FretRange::new(5, 9).unwrap();
         let active_locs = active_locations(&string_range, &fret_range);
         assert_eq!(24, active_locs.len());
         let mut expected = Vec::new();
@@ -237,8 +295,8 @@ mod active_note_tests {
         let active_notes = ActiveNotes::new(
             &registry,
             &tuning,
-            StringRange::new(1, 7),
-            FretRange::new(0, 12),
+            StringRange::new(1, 7).unwrap(),
+            FretRange::new(0, 12).unwrap(),
         );
         assert_eq!(0, active_notes.notes.len());
     }
@@ -314,8 +372,8 @@ mod active_note_tests {
         let active_notes = ActiveNotes::new(
             &registry,
             &tuning,
-            StringRange::new(1, 7),
-            FretRange::new(0, 12),
+            StringRange::new(1, 7).unwrap(),
+            FretRange::new(0, 12).unwrap(),
         );
         for i in 0..notes.len() {
             assert_eq!(&notes[i], active_notes.get(&locs[i]).unwrap());
@@ -0,0 +1,96 @@
+use crate::core::{FretLoc, Note, NoteName, NoteRegistry};
+use crate::game::guitar_pro::{parse_gp, parse_gpif};
+use crate::game::musicxml::{parse_musicxml, parse_mxl};
+use std::fmt;
+use std::fs::{self, File};
+use std::io;
+use std::path::Path;
+use thiserror::Error;
+
+#[derive(Debug)]
+pub struct ExerciseError(pub String);
+
+impl fmt::Display for ExerciseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ExerciseError: {}", self.0)
+    }
+}
+
+impl std::error::Error for ExerciseError {}
+
+#[derive(Debug, Error)]
+pub enum LoadExerciseError {
+    #[error(transparent)]
+    IoError(#[from] io::Error),
+    #[error(transparent)]
+    ExerciseError(#[from] ExerciseError),
+}
+
+/// A note of an exercise that is played as a target.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExerciseNote {
+    pub note: Note,
+    /// Location given in the exercise file. The note is played wherever it
+    /// first occurs on the fretboard if missing.
+    pub loc: Option<FretLoc>,
+    /// Length of the note in quarter notes.
+    pub duration: f64,
+}
+
+/// Note of the registry `semitones` above C0.
+pub fn note_from_c0(semitones: i32, registry: &NoteRegistry) -> Result<Note, ExerciseError> {
+    let c0 = Note {
+        octave: 0,
+        name: NoteName::C,
+        frequency: f64::NAN,
+    };
+    let note = c0.add_semitone(semitones);
+    registry
+        .get(note.name, note.octave)
+        .cloned()
+        .ok_or_else(|| {
+            ExerciseError(format!(
+                "{} is not in the frequency list",
+                note.name_octave()
+            ))
+        })
+}
+
+/// Registry of the notes from C0 up to B7, for the tests of the exercise
+/// formats.
+#[cfg(test)]
+pub(crate) fn registry() -> NoteRegistry {
+    let c0 = Note {
+        octave: 0,
+        name: NoteName::C,
+        frequency: f64::NAN,
+    };
+    let notes = (0..96)
+        .map(|semitones| Note {
+            frequency: 16.35 * 2f64.powf(semitones as f64 / 12.0),
+            ..c0.add_semitone(semitones)
+        })
+        .collect();
+    NoteRegistry::from_notes(notes).unwrap()
+}
+
+/// Reads the exercise at `path` as an ordered list of targets. MusicXML
+/// files (`.musicxml` or `.xml`, or compressed `.mxl`), Guitar Pro 7 files
+/// (`.gp`) and the score they contain (`.gpif`) are supported.
+pub fn load_exercise(
+    path: &Path,
+    registry: &NoteRegistry,
+) -> Result<Vec<ExerciseNote>, LoadExerciseError> {
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    match extension.as_str() {
+        "musicxml" | "xml" => Ok(parse_musicxml(&fs::read_to_string(path)?, registry)?),
+        "mxl" => Ok(parse_mxl(File::open(path)?, registry)?),
+        "gp" => Ok(parse_gp(File::open(path)?, registry)?),
+        "gpif" => Ok(parse_gpif(&fs::read_to_string(path)?, registry)?),
+        _ => Err(ExerciseError(format!("Unsupported exercise file {}", path.display())).into()),
+    }
+}
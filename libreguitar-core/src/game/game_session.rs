@@ -0,0 +1,506 @@
+use crate::audio_analysis::AnalysisResult;
+use crate::core::{FretLoc, GameCfg, Note};
+use crate::events::{GameEvent, TargetResult};
+use crate::game::target_selector::pick_note;
+use crate::game::{ActiveNotes, GameState, Score, TargetSelector};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::collections::VecDeque;
+use std::time::Duration;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+#[cfg(target_arch = "wasm32")]
+use web_time::Instant;
+
+/// Command to a running game, e.g. published on the control topic of the
+/// event bus by the frontends.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GameCommand {
+    Start,
+    Pause,
+    Resume,
+    TogglePause,
+    Skip,
+    /// Switches the targets to the next range preset.
+    NextPreset,
+    /// Ends the game, leaving the current target unfinished.
+    Stop,
+}
+
+/// Queue of the upcoming targets.
+struct Targets {
+    active_notes: ActiveNotes,
+    /// Notes of the other range presets, in the order they are switched to.
+    presets: VecDeque<ActiveNotes>,
+    selector: Box<dyn TargetSelector>,
+    rng: StdRng,
+    upcoming: VecDeque<(Note, FretLoc)>,
+    upcoming_target_count: usize,
+}
+
+impl Targets {
+    /// Next target, keeping `upcoming_target_count` targets queued after it.
+    fn pop(&mut self, score: &Score) -> (Note, FretLoc) {
+        while self.upcoming.len() <= self.upcoming_target_count {
+            let target = match self.selector.next_target(&self.active_notes, score) {
+                Some(target) => target,
+                None => {
+                    let (note, loc) = pick_note(&self.active_notes, &mut self.rng);
+                    (note.clone(), loc)
+                }
+            };
+            self.upcoming.push_back(target);
+        }
+        self.upcoming.pop_front().unwrap()
+    }
+
+    fn upcoming_locs(&self) -> Vec<FretLoc> {
+        self.upcoming.iter().map(|(_, loc)| loc.clone()).collect()
+    }
+
+    /// Switches to the next preset, dropping the targets picked from the
+    /// current one. Returns false if there are no other presets.
+    fn next_preset(&mut self) -> bool {
+        let next = match self.presets.pop_front() {
+            Some(next) => next,
+            None => return false,
+        };
+        let prev = std::mem::replace(&mut self.active_notes, next);
+        self.presets.push_back(prev);
+        self.upcoming.clear();
+        self.selector.notes_changed();
+        true
+    }
+}
+
+/// Rules of the game as a synchronous state machine, fed with the detections
+/// and commands by the game thread of the app, or directly by the host, e.g.
+/// in the browser.
+///
+/// The `_at` methods take the time of the detection or command, so that the
+/// game can be played deterministically, e.g. in tests.
+pub struct GameSession {
+    targets: Targets,
+    state_update_period: usize,
+    min_confidence: f64,
+    state: GameState,
+    /// Time the current target was shown, moved forward by the pauses.
+    start_time: Instant,
+    paused_at: Option<Instant>,
+    wrong_detection_count: usize,
+}
+
+impl GameSession {
+    /// Starts a game with its first target shown at `now`.
+    pub fn new(
+        active_notes: ActiveNotes,
+        config: &GameCfg,
+        selector: Box<dyn TargetSelector>,
+        now: Instant,
+    ) -> GameSession {
+        let mut targets = Targets {
+            active_notes,
+            presets: VecDeque::new(),
+            selector,
+            rng: StdRng::from_entropy(),
+            upcoming: VecDeque::with_capacity(config.upcoming_target_count + 1),
+            upcoming_target_count: config.upcoming_target_count,
+        };
+        let score = Score::default();
+        let (target_note, target_loc) = targets.pop(&score);
+        GameSession {
+            state: GameState {
+                target_note,
+                target_loc,
+                upcoming_locs: targets.upcoming_locs(),
+                needed_detection_count: config.note_count_for_acceptance,
+                curr_detection_count: 0,
+                score,
+                time_limit: if config.target_time_limit > 0.0 {
+                    Some(Duration::from_secs_f64(config.target_time_limit))
+                } else {
+                    None
+                },
+                paused: false,
+            },
+            targets,
+            state_update_period: config.state_update_period,
+            min_confidence: config.min_confidence,
+            start_time: now,
+            paused_at: None,
+            wrong_detection_count: 0,
+        }
+    }
+
+    /// Adds the notes of the range presets that [`GameCommand::NextPreset`]
+    /// switches to in turn, after the starting notes.
+    pub fn with_presets(mut self, presets: Vec<ActiveNotes>) -> GameSession {
+        self.targets.presets = presets.into();
+        self
+    }
+
+    /// Current state of the game, changed by the calls that return events.
+    pub fn state(&self) -> &GameState {
+        &self.state
+    }
+
+    pub fn handle_analysis(&mut self, analysis: AnalysisResult) -> Vec<GameEvent> {
+        self.handle_analysis_at(analysis, Instant::now())
+    }
+
+    /// Counts the detection made at `now` towards the current target and
+    /// moves on to the next target once it is found or its time is up.
+    /// Detections are ignored while the game is paused and when they are
+    /// less confident than `min_confidence`.
+    pub fn handle_analysis_at(&mut self, analysis: AnalysisResult, now: Instant) -> Vec<GameEvent> {
+        let mut events = Vec::new();
+        if self.state.paused {
+            return events;
+        }
+        let confident = analysis.confidence >= self.min_confidence;
+        if let Some(note) = analysis.note.filter(|_| confident) {
+            if note == self.state.target_note {
+                self.state.curr_detection_count += 1;
+                if self.state.curr_detection_count % self.state_update_period == 0 {
+                    events.push(GameEvent::Progress {
+                        count: self.state.curr_detection_count,
+                        needed: self.state.needed_detection_count,
+                    });
+                }
+            } else {
+                self.wrong_detection_count += 1;
+            }
+        }
+        let needed_detection_count = self.state.needed_detection_count;
+        if self.state.curr_detection_count == needed_detection_count {
+            let correct = self.wrong_detection_count < needed_detection_count;
+            events.extend(self.finish_target(correct, now));
+        } else if let Some(limit) = self.state.time_limit {
+            if self.elapsed(now) >= limit {
+                events.extend(self.finish_target(false, now));
+            }
+        }
+        events
+    }
+
+    pub fn handle_command(&mut self, command: &GameCommand) -> Vec<GameEvent> {
+        self.handle_command_at(command, Instant::now())
+    }
+
+    /// Pauses, resumes, skips or switches presets at `now`. Skipping and
+    /// switching presets are ignored while paused.
+    /// [`GameCommand::Stop`] has to be handled by the caller by dropping the
+    /// session.
+    pub fn handle_command_at(&mut self, command: &GameCommand, now: Instant) -> Vec<GameEvent> {
+        match (command, self.paused_at) {
+            (GameCommand::Pause, None) | (GameCommand::TogglePause, None) => {
+                self.paused_at = Some(now);
+                self.state.paused = true;
+                vec![GameEvent::Paused(true)]
+            }
+            (GameCommand::Resume, Some(paused_at))
+            | (GameCommand::TogglePause, Some(paused_at)) => {
+                self.start_time += now.saturating_duration_since(paused_at);
+                self.paused_at = None;
+                self.state.paused = false;
+                vec![GameEvent::Paused(false)]
+            }
+            // The skipped target counts as missed.
+            (GameCommand::Skip, None) => self.finish_target(false, now),
+            // The target of the previous preset is dropped without a result.
+            (GameCommand::NextPreset, None) if self.targets.next_preset() => {
+                self.next_target(now);
+                vec![GameEvent::Target {
+                    note: self.state.target_note.name_octave(),
+                    loc: self.state.target_loc.clone(),
+                }]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn elapsed(&self, now: Instant) -> Duration {
+        now.saturating_duration_since(self.start_time)
+    }
+
+    /// Records the outcome of the current target and shows the next one.
+    fn finish_target(&mut self, correct: bool, now: Instant) -> Vec<GameEvent> {
+        let finished = self.state.clone();
+        self.state.score.record(correct, self.elapsed(now));
+        self.next_target(now);
+        vec![
+            GameEvent::Result(TargetResult::between(&finished, &self.state)),
+            GameEvent::Target {
+                note: self.state.target_note.name_octave(),
+                loc: self.state.target_loc.clone(),
+            },
+        ]
+    }
+
+    fn next_target(&mut self, now: Instant) {
+        let (target_note, target_loc) = self.targets.pop(&self.state.score);
+        self.state.target_note = target_note;
+        self.state.target_loc = target_loc;
+        self.state.upcoming_locs = self.targets.upcoming_locs();
+        self.state.curr_detection_count = 0;
+        self.start_time = now;
+        self.wrong_detection_count = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{
+        FretRange, NoteName, NoteRegistry, StringRange, Tuning, TuningSpecification,
+    };
+    use crate::game::{ExerciseNote, ExerciseSelector};
+
+    /// Walks up the frets of the only active string.
+    struct FretWalk {
+        fret_idx: usize,
+    }
+
+    impl TargetSelector for FretWalk {
+        fn next_target(&mut self, notes: &ActiveNotes, _: &Score) -> Option<(Note, FretLoc)> {
+            let loc = FretLoc {
+                string_idx: 3,
+                fret_idx: self.fret_idx,
+            };
+            self.fret_idx += 1;
+            Some((notes.get(&loc)?.clone(), loc))
+        }
+    }
+
+    fn g3() -> Note {
+        Note {
+            octave: 3,
+            name: NoteName::G,
+            frequency: 196.0,
+        }
+    }
+
+    /// Notes of the given frets of the G string, tuned to G3.
+    fn active_notes(fret_range: FretRange) -> ActiveNotes {
+        let notes = (0..12)
+            .map(|semitones| Note {
+                frequency: 196.0 * 2f64.powf(semitones as f64 / 12.0),
+                ..g3().add_semitone(semitones)
+            })
+            .collect();
+        let registry = NoteRegistry::from_notes(notes).unwrap();
+        let tuning = Tuning::from_specification(
+            &[TuningSpecification {
+                name: NoteName::G,
+                octave: 3,
+                string: 3,
+            }],
+            &registry,
+        )
+        .unwrap();
+        ActiveNotes::new(
+            &registry,
+            &tuning,
+            StringRange::new(3, 4).unwrap(),
+            fret_range,
+        )
+    }
+
+    fn session(target_time_limit: f64, now: Instant) -> GameSession {
+        session_with(Box::new(FretWalk { fret_idx: 0 }), target_time_limit, now)
+    }
+
+    fn session_with(
+        selector: Box<dyn TargetSelector>,
+        target_time_limit: f64,
+        now: Instant,
+    ) -> GameSession {
+        let active_notes = active_notes(FretRange::new(0, 12).unwrap());
+        let config = GameCfg {
+            fret_range: (0, 12),
+            string_range: (3, 4),
+            note_count_for_acceptance: 2,
+            state_update_period: 1,
+            target_time_limit,
+            upcoming_target_count: 1,
+            min_confidence: 0.5,
+            exercise_path: None,
+            #[cfg(feature = "plugins")]
+            plugins_dir: None,
+            #[cfg(feature = "plugins")]
+            game_mode: None,
+            presets: Vec::new(),
+        };
+        GameSession::new(active_notes, &config, selector, now)
+    }
+
+    fn detection(note: Note) -> AnalysisResult {
+        AnalysisResult {
+            frequency: Some(note.frequency),
+            note: Some(note),
+            confidence: 1.0,
+            peaks: Vec::new(),
+        }
+    }
+
+    fn result(events: &[GameEvent]) -> &TargetResult {
+        match &events[0] {
+            GameEvent::Result(result) => result,
+            event => panic!("Unexpected event {:?}", event),
+        }
+    }
+
+    #[test]
+    fn test_target_found() {
+        let start = Instant::now();
+        let mut session = session(0.0, start);
+        assert_eq!(g3(), session.state().target_note);
+        assert_eq!(1, session.state().upcoming_locs[0].fret_idx);
+        let events = session.handle_analysis_at(detection(g3()), start);
+        assert_eq!(
+            vec![GameEvent::Progress {
+                count: 1,
+                needed: 2
+            }],
+            events
+        );
+        let events = session.handle_analysis_at(detection(g3()), start + Duration::from_secs(2));
+        assert_eq!(3, events.len());
+        assert!(result(&events[1..]).correct);
+        assert_eq!(Duration::from_secs(2), result(&events[1..]).response_time);
+        assert_eq!(
+            GameEvent::Target {
+                note: g3().add_semitone(1).name_octave(),
+                loc: FretLoc {
+                    string_idx: 3,
+                    fret_idx: 1
+                }
+            },
+            events[2]
+        );
+        assert_eq!(0, session.state().curr_detection_count);
+        assert_eq!(1, session.state().score.n_correct);
+    }
+
+    #[test]
+    fn test_wrong_detections() {
+        let start = Instant::now();
+        let mut session = session(0.0, start);
+        for _ in 0..2 {
+            session.handle_analysis_at(detection(g3().add_semitone(1)), start);
+        }
+        assert_eq!(1, session.handle_analysis_at(detection(g3()), start).len());
+        let events = session.handle_analysis_at(detection(g3()), start);
+        assert!(!result(&events[1..]).correct);
+    }
+
+    #[test]
+    fn test_shaky_detections_are_ignored() {
+        let start = Instant::now();
+        let mut session = session(0.0, start);
+        let shaky = |note| AnalysisResult {
+            confidence: 0.2,
+            ..detection(note)
+        };
+        for _ in 0..3 {
+            assert!(session.handle_analysis_at(shaky(g3()), start).is_empty());
+            assert!(session
+                .handle_analysis_at(shaky(g3().add_semitone(1)), start)
+                .is_empty());
+        }
+        assert_eq!(0, session.state().curr_detection_count);
+        assert_eq!(1, session.handle_analysis_at(detection(g3()), start).len());
+    }
+
+    #[test]
+    fn test_time_limit() {
+        let start = Instant::now();
+        let mut session = session(5.0, start);
+        let no_note = AnalysisResult {
+            note: None,
+            frequency: None,
+            confidence: 0.0,
+            peaks: Vec::new(),
+        };
+        assert!(session
+            .handle_analysis_at(no_note.clone(), start + Duration::from_secs(4))
+            .is_empty());
+        let events = session.handle_analysis_at(no_note, start + Duration::from_secs(5));
+        assert!(!result(&events).correct);
+        assert_eq!(1, session.state().target_loc.fret_idx);
+    }
+
+    #[test]
+    fn test_pause() {
+        let start = Instant::now();
+        let mut session = session(5.0, start);
+        let paused_at = start + Duration::from_secs(1);
+        assert_eq!(
+            vec![GameEvent::Paused(true)],
+            session.handle_command_at(&GameCommand::TogglePause, paused_at)
+        );
+        assert!(session
+            .handle_analysis_at(detection(g3()), paused_at)
+            .is_empty());
+        assert!(session
+            .handle_command_at(&GameCommand::Skip, paused_at)
+            .is_empty());
+        let resumed_at = start + Duration::from_secs(11);
+        assert_eq!(
+            vec![GameEvent::Paused(false)],
+            session.handle_command_at(&GameCommand::Resume, resumed_at)
+        );
+        // the time spent paused doesn't count towards the time limit
+        let events = session.handle_command_at(&GameCommand::Skip, resumed_at);
+        assert_eq!(Duration::from_secs(1), result(&events).response_time);
+        assert_eq!(0, session.state().score.n_correct);
+    }
+
+    #[test]
+    fn test_next_preset() {
+        let start = Instant::now();
+        let preset = active_notes(FretRange::new(5, 8).unwrap());
+        let mut session = session(0.0, start).with_presets(vec![preset]);
+        session.handle_command_at(&GameCommand::Pause, start);
+        assert!(session
+            .handle_command_at(&GameCommand::NextPreset, start)
+            .is_empty());
+        session.handle_command_at(&GameCommand::Resume, start);
+        // the walk leaves the frets of the preset, so they are picked at random
+        let events = session.handle_command_at(&GameCommand::NextPreset, start);
+        assert!(matches!(events.as_slice(), [GameEvent::Target { .. }]));
+        let loc = &session.state().target_loc;
+        assert!((5..8).contains(&loc.fret_idx));
+        assert!(session
+            .state()
+            .upcoming_locs
+            .iter()
+            .all(|loc| (5..8).contains(&loc.fret_idx)));
+        // back to the starting frets, where the walk goes on
+        session.handle_command_at(&GameCommand::NextPreset, start);
+        assert_eq!(4, session.state().target_loc.fret_idx);
+        assert_eq!(0, session.state().score.n_completed);
+
+        // Exercises are located again on the frets of the preset. A3 is on
+        // fret 2 and C4 on fret 5 of the G string.
+        let exercise = [2, 5]
+            .iter()
+            .map(|&semitones| ExerciseNote {
+                note: g3().add_semitone(semitones),
+                loc: None,
+                duration: 1.0,
+            })
+            .collect();
+        let mut session = session_with(Box::new(ExerciseSelector::new(exercise)), 0.0, start)
+            .with_presets(vec![active_notes(FretRange::new(5, 8).unwrap())]);
+        assert_eq!(2, session.state().target_loc.fret_idx);
+        // A3 is outside of the preset, which only leaves C4.
+        session.handle_command_at(&GameCommand::NextPreset, start);
+        assert_eq!(5, session.state().target_loc.fret_idx);
+        assert!(session
+            .state()
+            .upcoming_locs
+            .iter()
+            .all(|loc| loc.fret_idx == 5));
+    }
+}
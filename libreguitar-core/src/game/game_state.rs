@@ -0,0 +1,48 @@
+#[cfg(any(test, feature = "test-util"))]
+use crate::core::NoteName;
+use crate::core::{duration_secs, FretLoc, Note};
+use crate::game::Score;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Snapshot of the game, published after every change.
+///
+/// Its JSON shape has the field names below, with the durations in seconds,
+/// and is versioned along with the game events (see `EVENT_VERSION`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameState {
+    pub target_note: Note,
+    pub target_loc: FretLoc,
+    /// Targets that will follow the current one, in order.
+    pub upcoming_locs: Vec<FretLoc>,
+    pub needed_detection_count: usize,
+    pub curr_detection_count: usize,
+    pub score: Score,
+    /// Time the player has to find the target in timed mode.
+    #[serde(with = "duration_secs::option")]
+    pub time_limit: Option<Duration>,
+    /// Whether the game is paused and ignoring detections.
+    pub paused: bool,
+}
+
+#[cfg(any(test, feature = "test-util"))]
+impl GameState {
+    /// State of a running game with C4 as the target on `target_loc`, which
+    /// needs 3 detections and has none yet, for the tests to adapt.
+    pub fn fixture(target_loc: FretLoc) -> GameState {
+        GameState {
+            target_note: Note {
+                name: NoteName::C,
+                octave: 4,
+                frequency: 261.63,
+            },
+            target_loc,
+            upcoming_locs: Vec::new(),
+            needed_detection_count: 3,
+            curr_detection_count: 0,
+            score: Score::default(),
+            time_limit: None,
+            paused: false,
+        }
+    }
+}
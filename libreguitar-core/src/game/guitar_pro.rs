@@ -0,0 +1,311 @@
+use crate::core::{FretLoc, NoteRegistry};
+use crate::game::exercise::{note_from_c0, ExerciseError, ExerciseNote};
+use crate::game::musicxml::{child, child_text, parse_number, read_zip_entry};
+use roxmltree::{Document, Node};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::io::{Read, Seek};
+
+/// Path of the score inside a Guitar Pro 7 archive.
+const GPIF_PATH: &str = "Content/score.gpif";
+/// MIDI note number of C0.
+const MIDI_C0: i32 = 12;
+
+/// Elements of a GPIF section, e.g. the `<Beat>`s of `<Beats>`, by their id.
+fn by_id<'a, 'input>(root: Node<'a, 'input>, section: &str) -> HashMap<&'a str, Node<'a, 'input>> {
+    child(root, section)
+        .map(|section| {
+            section
+                .children()
+                .filter(|node| node.is_element())
+                .filter_map(|node| Some((node.attribute("id")?, node)))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Space separated ids in the `name` child of `node`. Missing elements are
+/// written as -1.
+fn ids<'a>(node: Node<'a, '_>, name: &str) -> Vec<&'a str> {
+    child_text(node, name)
+        .unwrap_or("")
+        .split_whitespace()
+        .filter(|id| *id != "-1")
+        .collect()
+}
+
+/// `<Property name="...">` of a note or staff.
+fn property<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    child(node, "Properties")?
+        .children()
+        .find(|prop| prop.has_tag_name("Property") && prop.attribute("name") == Some(name))
+}
+
+/// Length of a `<Rhythm>` in quarter notes.
+fn rhythm_duration(rhythm: Node) -> Result<f64, ExerciseError> {
+    let value = child_text(rhythm, "NoteValue").unwrap_or("Quarter");
+    let mut duration = match value {
+        "Whole" => 4.0,
+        "Half" => 2.0,
+        "Quarter" => 1.0,
+        "Eighth" => 0.5,
+        "16th" => 0.25,
+        "32nd" => 0.125,
+        "64th" => 0.0625,
+        "128th" => 0.03125,
+        _ => return Err(ExerciseError(format!("Unknown note value {:?}", value))),
+    };
+    if let Some(dots) = child(rhythm, "AugmentationDot") {
+        let count = dots.attribute("count").unwrap_or("1");
+        let count: i32 = count
+            .parse()
+            .ok()
+            .filter(|count| *count >= 0)
+            .ok_or_else(|| ExerciseError(format!("Invalid augmentation dot count {:?}", count)))?;
+        duration *= 2.0 - 0.5f64.powi(count);
+    }
+    if let Some(tuplet) = child(rhythm, "PrimaryTuplet") {
+        duration *= tuplet_part(tuplet, "den")? / tuplet_part(tuplet, "num")?;
+    }
+    Ok(duration)
+}
+
+/// Numerator or denominator of a `<PrimaryTuplet>`, 1 if missing.
+fn tuplet_part(tuplet: Node, name: &str) -> Result<f64, ExerciseError> {
+    let value = tuplet.attribute(name).unwrap_or("1");
+    value
+        .parse()
+        .ok()
+        .filter(|value: &f64| value.is_finite() && *value > 0.0)
+        .ok_or_else(|| ExerciseError(format!("Invalid tuplet {} {:?}", name, value)))
+}
+
+/// MIDI note numbers of the open strings of the first track, the lowest
+/// string first.
+fn track_tuning(track: Node) -> Result<Vec<i32>, ExerciseError> {
+    let staff_tuning = child(track, "Staves")
+        .and_then(|staves| staves.children().find(|staff| staff.has_tag_name("Staff")))
+        .and_then(|staff| property(staff, "Tuning"));
+    // Older files keep the tuning with the track properties.
+    let tuning = staff_tuning
+        .or_else(|| property(track, "Tuning"))
+        .ok_or_else(|| ExerciseError(String::from("The track has no string tuning")))?;
+    child_text(tuning, "Pitches")
+        .unwrap_or("")
+        .split_whitespace()
+        .map(|pitch| {
+            pitch
+                .parse()
+                .map_err(|_| ExerciseError(format!("Invalid tuning pitch {:?}", pitch)))
+        })
+        .collect()
+}
+
+/// Converts the first voice of the first track of a Guitar Pro score
+/// (`score.gpif`) into an ordered list of targets, keeping the string and
+/// fret of every note. Rests are skipped, only the first note of each beat is
+/// kept and tied notes are merged into one.
+pub fn parse_gpif(xml: &str, registry: &NoteRegistry) -> Result<Vec<ExerciseNote>, ExerciseError> {
+    let doc = Document::parse(xml).map_err(|err| ExerciseError(err.to_string()))?;
+    let root = doc.root_element();
+    let track = child(root, "Tracks")
+        .and_then(|tracks| tracks.children().find(|track| track.has_tag_name("Track")))
+        .ok_or_else(|| ExerciseError(String::from("The score has no tracks")))?;
+    let tuning = track_tuning(track)?;
+    let bars = by_id(root, "Bars");
+    let voices = by_id(root, "Voices");
+    let beats = by_id(root, "Beats");
+    let notes = by_id(root, "Notes");
+    let rhythms = by_id(root, "Rhythms");
+    let missing = |kind: &str, id: &str| ExerciseError(format!("Missing {} {}", kind, id));
+
+    let mut out: Vec<ExerciseNote> = Vec::new();
+    let master_bars = child(root, "MasterBars")
+        .into_iter()
+        .flat_map(|master_bars| master_bars.children())
+        .filter(|node| node.has_tag_name("MasterBar"));
+    for master_bar in master_bars {
+        // The bars of the tracks in track order.
+        let bar_id = match ids(master_bar, "Bars").first() {
+            Some(id) => *id,
+            None => continue,
+        };
+        let bar = bars.get(bar_id).ok_or_else(|| missing("bar", bar_id))?;
+        let voice_id = match ids(*bar, "Voices").first() {
+            Some(id) => *id,
+            None => continue,
+        };
+        let voice = voices
+            .get(voice_id)
+            .ok_or_else(|| missing("voice", voice_id))?;
+        for beat_id in ids(*voice, "Beats") {
+            let beat = beats.get(beat_id).ok_or_else(|| missing("beat", beat_id))?;
+            let rhythm_id = child(*beat, "Rhythm")
+                .and_then(|rhythm| rhythm.attribute("ref"))
+                .unwrap_or("");
+            let duration = match rhythms.get(rhythm_id) {
+                Some(rhythm) => rhythm_duration(*rhythm)?,
+                None => 0.0,
+            };
+            let note_id = match ids(*beat, "Notes").first() {
+                Some(id) => *id,
+                // Rest.
+                None => continue,
+            };
+            let note = notes.get(note_id).ok_or_else(|| missing("note", note_id))?;
+            let ties_previous =
+                child(*note, "Tie").and_then(|tie| tie.attribute("destination")) == Some("true");
+            if ties_previous {
+                if let Some(last) = out.last_mut() {
+                    last.duration += duration;
+                    continue;
+                }
+            }
+            let string: usize = property(*note, "String")
+                .map_or(Ok(None), |prop| parse_number(prop, "String"))?
+                .ok_or_else(|| missing("string of note", note_id))?;
+            let fret_idx: usize = property(*note, "Fret")
+                .map_or(Ok(None), |prop| parse_number(prop, "Fret"))?
+                .ok_or_else(|| missing("fret of note", note_id))?;
+            let open_pitch = tuning
+                .get(string)
+                .ok_or_else(|| ExerciseError(format!("Invalid string {}", string)))?;
+            let from_c0 = i32::try_from(fret_idx)
+                .ok()
+                .and_then(|fret| open_pitch.checked_add(fret))
+                .and_then(|pitch| pitch.checked_sub(MIDI_C0))
+                .ok_or_else(|| {
+                    ExerciseError(format!(
+                        "Fret {} of note {} is out of range",
+                        fret_idx, note_id
+                    ))
+                })?;
+            out.push(ExerciseNote {
+                note: note_from_c0(from_c0, registry)?,
+                // GPIF counts the strings from the lowest one.
+                loc: Some(FretLoc {
+                    string_idx: tuning.len() - string,
+                    fret_idx,
+                }),
+                duration,
+            });
+        }
+    }
+    Ok(out)
+}
+
+/// Reads the score of a Guitar Pro 7 (`.gp`) archive and converts it like
+/// [`parse_gpif`].
+pub fn parse_gp(
+    archive: impl Read + Seek,
+    registry: &NoteRegistry,
+) -> Result<Vec<ExerciseNote>, ExerciseError> {
+    let mut archive =
+        zip::ZipArchive::new(archive).map_err(|err| ExerciseError(err.to_string()))?;
+    parse_gpif(&read_zip_entry(&mut archive, GPIF_PATH)?, registry)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::exercise::registry;
+    use std::io::{Cursor, Write};
+
+    fn note(id: usize, string: usize, fret: usize, tie: &str) -> String {
+        format!(
+            r#"<Note id="{}">{}<Properties>
+                <Property name="String"><String>{}</String></Property>
+                <Property name="Fret"><Fret>{}</Fret></Property>
+            </Properties></Note>"#,
+            id, tie, string, fret
+        )
+    }
+
+    /// One bar with a dotted quarter on the open low E string, a rest, an
+    /// eighth note triplet on the B string tied to the next beat and a chord.
+    fn gpif() -> String {
+        format!(
+            r#"<?xml version="1.0" encoding="utf-8"?>
+<GPIF>
+  <Tracks><Track id="0"><Staves><Staff><Properties>
+    <Property name="Tuning"><Pitches>40 45 50 55 59 64</Pitches></Property>
+  </Properties></Staff></Staves></Track></Tracks>
+  <MasterBars><MasterBar><Bars>0</Bars></MasterBar></MasterBars>
+  <Bars><Bar id="0"><Voices>0 -1 -1 -1</Voices></Bar></Bars>
+  <Voices><Voice id="0"><Beats>0 1 2 3 4</Beats></Voice></Voices>
+  <Beats>
+    <Beat id="0"><Rhythm ref="0"/><Notes>0</Notes></Beat>
+    <Beat id="1"><Rhythm ref="1"/></Beat>
+    <Beat id="2"><Rhythm ref="1"/><Notes>1</Notes></Beat>
+    <Beat id="3"><Rhythm ref="1"/><Notes>2</Notes></Beat>
+    <Beat id="4"><Rhythm ref="1"/><Notes>3 4</Notes></Beat>
+  </Beats>
+  <Notes>{}{}{}{}{}</Notes>
+  <Rhythms>
+    <Rhythm id="0"><NoteValue>Quarter</NoteValue><AugmentationDot count="1"/></Rhythm>
+    <Rhythm id="1"><NoteValue>Eighth</NoteValue><PrimaryTuplet num="3" den="2"/></Rhythm>
+  </Rhythms>
+</GPIF>"#,
+            note(0, 0, 0, ""),
+            note(1, 4, 1, r#"<Tie origin="true" destination="false"/>"#),
+            note(2, 4, 1, r#"<Tie origin="false" destination="true"/>"#),
+            note(3, 5, 3, ""),
+            note(4, 4, 3, ""),
+        )
+    }
+
+    fn summary(exercise: &[ExerciseNote]) -> Vec<(String, FretLoc, f64)> {
+        exercise
+            .iter()
+            .map(|n| (n.note.name_octave(), n.loc.clone().unwrap(), n.duration))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_gpif() {
+        let exercise = parse_gpif(&gpif(), &registry()).unwrap();
+        let loc = |string_idx, fret_idx| FretLoc {
+            string_idx,
+            fret_idx,
+        };
+        assert_eq!(
+            vec![
+                (String::from("E2"), loc(6, 0), 1.5),
+                (String::from("C4"), loc(2, 1), 2.0 / 3.0),
+                (String::from("G4"), loc(1, 3), 1.0 / 3.0),
+            ],
+            summary(&exercise)
+        );
+    }
+
+    #[test]
+    fn test_parse_gp_archive() {
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        writer
+            .start_file(GPIF_PATH, zip::write::FileOptions::default())
+            .unwrap();
+        writer.write_all(gpif().as_bytes()).unwrap();
+        let archive = writer.finish().unwrap();
+        let registry = registry();
+        assert_eq!(
+            parse_gpif(&gpif(), &registry).unwrap(),
+            parse_gp(Cursor::new(archive.into_inner()), &registry).unwrap()
+        );
+        assert!(parse_gp(Cursor::new(b"not a zip".to_vec()), &registry).is_err());
+    }
+
+    #[test]
+    fn test_parse_gpif_errors() {
+        let registry = registry();
+        let zero_tuplet = gpif().replace(r#"num="3""#, r#"num="0""#);
+        assert!(parse_gpif(&zero_tuplet, &registry).is_err());
+        let infinite_tuplet = gpif().replace(r#"den="2""#, r#"den="inf""#);
+        assert!(parse_gpif(&infinite_tuplet, &registry).is_err());
+        let negative_dots = gpif().replace(r#"count="1""#, r#"count="-2""#);
+        assert!(parse_gpif(&negative_dots, &registry).is_err());
+        let huge_fret = gpif().replace("<Fret>3</Fret>", "<Fret>4294967296</Fret>");
+        assert!(parse_gpif(&huge_fret, &registry).is_err());
+        let highest_pitch = gpif().replace("55 59 64", "55 2147483647 64");
+        assert!(parse_gpif(&highest_pitch, &registry).is_err());
+    }
+}
@@ -0,0 +1,189 @@
+use crate::game::Score;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Statistics of a finished session, stored as one row of the history file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionRecord {
+    /// End of the session in seconds since the Unix epoch.
+    pub timestamp: u64,
+    pub n_completed: usize,
+    pub n_correct: usize,
+    pub best_streak: usize,
+    /// Average response time in seconds.
+    pub avg_response_time: f64,
+}
+
+impl SessionRecord {
+    pub fn new(score: &Score, end: SystemTime) -> SessionRecord {
+        SessionRecord {
+            timestamp: end
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |since_epoch| since_epoch.as_secs()),
+            n_completed: score.n_completed,
+            n_correct: score.n_correct,
+            best_streak: score.best_streak,
+            avg_response_time: score
+                .avg_response_time()
+                .map_or(0.0, |time| time.as_secs_f64()),
+        }
+    }
+
+    pub fn accuracy(&self) -> Option<f64> {
+        if self.n_completed == 0 {
+            None
+        } else {
+            Some(self.n_correct as f64 / self.n_completed as f64)
+        }
+    }
+}
+
+/// Outcome of a single target, stored as one row of the targets file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TargetRecord {
+    /// End of the session the target was played in, in seconds since the
+    /// Unix epoch.
+    pub timestamp: u64,
+    pub note: String,
+    pub string_idx: usize,
+    pub fret_idx: usize,
+    pub correct: bool,
+    /// Response time in seconds.
+    pub response_time: f64,
+}
+
+/// Reads the rows of the CSV file at `path`. A missing file has no rows.
+fn load_rows<T: DeserializeOwned>(path: &Path) -> Result<Vec<T>, csv::Error> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let mut rdr = csv::Reader::from_path(path)?;
+    let mut out = Vec::new();
+    for result in rdr.deserialize() {
+        out.push(result?);
+    }
+    Ok(out)
+}
+
+/// Appends `rows` to the CSV file at `path`, creating the file with a header
+/// row if it doesn't exist yet.
+fn append_rows<T: Serialize>(path: &Path, rows: &[T]) -> Result<(), csv::Error> {
+    let is_new = !path.exists() || path.metadata()?.len() == 0;
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let mut wtr = csv::WriterBuilder::new()
+        .has_headers(is_new)
+        .from_writer(file);
+    for row in rows.iter() {
+        wtr.serialize(row)?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+/// Replaces the CSV file at `path` with `rows`. The rows are written to a
+/// temporary file next to it first, so that the file is never left half
+/// written, e.g. when the disk is full.
+pub fn write_csv<T: Serialize>(path: &Path, rows: &[T]) -> Result<(), csv::Error> {
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    let file = File::create(&tmp_path)?;
+    let mut wtr = csv::Writer::from_writer(&file);
+    for row in rows.iter() {
+        wtr.serialize(row)?;
+    }
+    wtr.flush()?;
+    file.sync_all()?;
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+/// Reads the sessions stored in the history file at `path`, the oldest
+/// first. A missing file is an empty history.
+pub fn load_history(path: &Path) -> Result<Vec<SessionRecord>, csv::Error> {
+    load_rows(path)
+}
+
+/// Appends `record` to the history file at `path`, creating the file with a
+/// header row if it doesn't exist yet.
+pub fn append_to_history(path: &Path, record: &SessionRecord) -> Result<(), csv::Error> {
+    append_rows(path, &[record])
+}
+
+/// Reads the targets stored in the targets file at `path`, the oldest first.
+pub fn load_targets(path: &Path) -> Result<Vec<TargetRecord>, csv::Error> {
+    load_rows(path)
+}
+
+/// Appends the targets of a session to the targets file at `path`.
+pub fn append_targets(path: &Path, records: &[TargetRecord]) -> Result<(), csv::Error> {
+    append_rows(path, records)
+}
+
+/// Replaces the history file at `path` with `records`.
+pub fn write_history(path: &Path, records: &[SessionRecord]) -> Result<(), csv::Error> {
+    write_csv(path, records)
+}
+
+/// Replaces the targets file at `path` with `records`.
+pub fn write_targets(path: &Path, records: &[TargetRecord]) -> Result<(), csv::Error> {
+    write_csv(path, records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::time::Duration;
+
+    #[test]
+    fn test_history_round_trip() {
+        let dir = std::env::temp_dir().join("libreguitar_test_history");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("history.csv");
+        let _ = fs::remove_file(&path);
+        assert!(load_history(&path).unwrap().is_empty());
+
+        let mut score = Score::default();
+        score.record(true, Duration::from_secs(1));
+        score.record(false, Duration::from_secs(3));
+        let first = SessionRecord::new(&score, UNIX_EPOCH + Duration::from_secs(100));
+        let second = SessionRecord::new(&Score::default(), UNIX_EPOCH);
+        append_to_history(&path, &first).unwrap();
+        append_to_history(&path, &second).unwrap();
+
+        let history = load_history(&path).unwrap();
+        assert_eq!(vec![first, second], history);
+        assert_eq!(100, history[0].timestamp);
+        assert_eq!(Some(0.5), history[0].accuracy());
+        assert_eq!(2.0, history[0].avg_response_time);
+        assert_eq!(None, history[1].accuracy());
+    }
+
+    #[test]
+    fn test_targets_round_trip() {
+        let dir = std::env::temp_dir().join("libreguitar_test_targets");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("targets.csv");
+        let _ = fs::remove_file(&path);
+        let record = TargetRecord {
+            timestamp: 100,
+            note: String::from("C4"),
+            string_idx: 5,
+            fret_idx: 3,
+            correct: true,
+            response_time: 1.5,
+        };
+        append_targets(&path, &[record.clone(), record.clone()]).unwrap();
+        append_targets(&path, std::slice::from_ref(&record)).unwrap();
+        assert_eq!(
+            vec![record.clone(), record.clone(), record.clone()],
+            load_targets(&path).unwrap()
+        );
+        write_targets(&path, std::slice::from_ref(&record)).unwrap();
+        assert_eq!(vec![record], load_targets(&path).unwrap());
+        assert!(!dir.join("targets.csv.tmp").exists());
+    }
+}
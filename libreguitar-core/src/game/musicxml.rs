@@ -0,0 +1,326 @@
+use crate::core::{FretLoc, NoteRegistry};
+use crate::game::exercise::{note_from_c0, ExerciseError, ExerciseNote};
+use roxmltree::{Document, Node};
+use std::io::{Read, Seek};
+
+/// Path of the file listing the score files of a compressed MusicXML archive.
+const CONTAINER_PATH: &str = "META-INF/container.xml";
+
+pub(crate) fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|child| child.has_tag_name(name))
+}
+
+pub(crate) fn child_text<'a>(node: Node<'a, '_>, name: &str) -> Option<&'a str> {
+    child(node, name)
+        .and_then(|child| child.text())
+        .map(str::trim)
+}
+
+pub(crate) fn parse_number<T: std::str::FromStr>(
+    node: Node,
+    name: &str,
+) -> Result<Option<T>, ExerciseError> {
+    match child_text(node, name) {
+        Some(text) => text.parse().map(Some).map_err(|_| {
+            ExerciseError(format!(
+                "Invalid <{}> value {:?} on line {}",
+                name,
+                text,
+                node.document().text_pos_at(node.range().start).row
+            ))
+        }),
+        None => Ok(None),
+    }
+}
+
+/// Text of the file `name` in a zip archive, e.g. of a compressed score.
+pub(crate) fn read_zip_entry<R: Read + Seek>(
+    archive: &mut zip::ZipArchive<R>,
+    name: &str,
+) -> Result<String, ExerciseError> {
+    let mut text = String::new();
+    archive
+        .by_name(name)
+        .map_err(|err| ExerciseError(format!("{}: {}", name, err)))?
+        .read_to_string(&mut text)
+        .map_err(|err| ExerciseError(err.to_string()))?;
+    Ok(text)
+}
+
+/// Number of semitones from C up to the natural note `step`.
+fn step_semitones(step: &str) -> Option<i32> {
+    match step {
+        "C" => Some(0),
+        "D" => Some(2),
+        "E" => Some(4),
+        "F" => Some(5),
+        "G" => Some(7),
+        "A" => Some(9),
+        "B" => Some(11),
+        _ => None,
+    }
+}
+
+/// Semitones from C0 to the written pitch of a `<pitch>` element.
+fn pitch_semitones(pitch: Node) -> Result<i32, ExerciseError> {
+    let step = child_text(pitch, "step").unwrap_or("");
+    let step = step_semitones(step)
+        .ok_or_else(|| ExerciseError(format!("Invalid pitch step {:?}", step)))?;
+    // Microtonal alterations are rounded to the nearest semitone.
+    let alter: f64 = parse_number(pitch, "alter")?.unwrap_or(0.0);
+    let octave: i32 = parse_number(pitch, "octave")?
+        .ok_or_else(|| ExerciseError(String::from("Pitch without an octave")))?;
+    octave
+        .checked_mul(12)
+        .and_then(|semitones| semitones.checked_add(step))
+        .and_then(|semitones| semitones.checked_add(alter.round() as i32))
+        .ok_or_else(|| ExerciseError(format!("Pitch octave {} is out of range", octave)))
+}
+
+/// Semitones added to the written pitch by a `<transpose>` element, e.g. -12
+/// for guitar parts written an octave above their sound.
+fn transpose_semitones(transpose: Node) -> Result<i32, ExerciseError> {
+    let chromatic: i32 = parse_number(transpose, "chromatic")?.unwrap_or(0);
+    let octave_change: i32 = parse_number(transpose, "octave-change")?.unwrap_or(0);
+    octave_change
+        .checked_mul(12)
+        .and_then(|semitones| semitones.checked_add(chromatic))
+        .ok_or_else(|| {
+            ExerciseError(format!(
+                "Transposition by {} octaves is out of range",
+                octave_change
+            ))
+        })
+}
+
+/// String and fret of a note from its tablature notation, if given.
+fn tab_location(note: Node) -> Result<Option<FretLoc>, ExerciseError> {
+    let technical = match child(note, "notations").and_then(|n| child(n, "technical")) {
+        Some(technical) => technical,
+        None => return Ok(None),
+    };
+    let string_idx = parse_number(technical, "string")?;
+    let fret_idx = parse_number(technical, "fret")?;
+    match (string_idx, fret_idx) {
+        (Some(string_idx), Some(fret_idx)) => Ok(Some(FretLoc {
+            string_idx,
+            fret_idx,
+        })),
+        _ => Ok(None),
+    }
+}
+
+/// Converts the first part of a partwise MusicXML score into an ordered list
+/// of targets. Rests and grace notes are skipped, only the first note of each
+/// chord is kept and tied notes are merged into one.
+pub fn parse_musicxml(
+    xml: &str,
+    registry: &NoteRegistry,
+) -> Result<Vec<ExerciseNote>, ExerciseError> {
+    let doc = Document::parse(xml).map_err(|err| ExerciseError(err.to_string()))?;
+    let root = doc.root_element();
+    if !root.has_tag_name("score-partwise") {
+        return Err(ExerciseError(format!(
+            "Expected a partwise score, found <{}>",
+            root.tag_name().name()
+        )));
+    }
+    let part =
+        child(root, "part").ok_or_else(|| ExerciseError(String::from("The score has no parts")))?;
+    let mut divisions = 1.0;
+    let mut transposition = 0;
+    let mut out: Vec<ExerciseNote> = Vec::new();
+    let measures = part.children().filter(|node| node.has_tag_name("measure"));
+    for element in measures.flat_map(|measure| measure.children()) {
+        if element.has_tag_name("attributes") {
+            if let Some(value) = parse_number::<f64>(element, "divisions")? {
+                if !value.is_finite() || value <= 0.0 {
+                    return Err(ExerciseError(format!(
+                        "Invalid <divisions> value {} on line {}",
+                        value,
+                        element.document().text_pos_at(element.range().start).row
+                    )));
+                }
+                divisions = value;
+            }
+            if let Some(transpose) = child(element, "transpose") {
+                transposition = transpose_semitones(transpose)?;
+            }
+            continue;
+        }
+        if !element.has_tag_name("note")
+            || child(element, "chord").is_some()
+            || child(element, "grace").is_some()
+        {
+            continue;
+        }
+        let pitch = match child(element, "pitch") {
+            Some(pitch) => pitch,
+            // Rest or unpitched percussion.
+            None => continue,
+        };
+        let duration = parse_number::<f64>(element, "duration")?.unwrap_or(0.0) / divisions;
+        let ties_previous = element
+            .children()
+            .any(|tie| tie.has_tag_name("tie") && tie.attribute("type") == Some("stop"));
+        if ties_previous {
+            if let Some(last) = out.last_mut() {
+                last.duration += duration;
+                continue;
+            }
+        }
+        let semitones = pitch_semitones(pitch)?
+            .checked_add(transposition)
+            .ok_or_else(|| ExerciseError(String::from("Transposed pitch is out of range")))?;
+        let note = note_from_c0(semitones, registry)?;
+        out.push(ExerciseNote {
+            note,
+            loc: tab_location(element)?,
+            duration,
+        });
+    }
+    Ok(out)
+}
+
+/// Reads the score of a compressed MusicXML (`.mxl`) archive, as exported by
+/// MuseScore by default, and converts it like [`parse_musicxml`].
+pub fn parse_mxl(
+    archive: impl Read + Seek,
+    registry: &NoteRegistry,
+) -> Result<Vec<ExerciseNote>, ExerciseError> {
+    let mut archive =
+        zip::ZipArchive::new(archive).map_err(|err| ExerciseError(err.to_string()))?;
+    let container = read_zip_entry(&mut archive, CONTAINER_PATH)?;
+    let container = Document::parse(&container).map_err(|err| ExerciseError(err.to_string()))?;
+    // The first root file is the score, the others are alternative versions.
+    let score_path = container
+        .descendants()
+        .find(|node| node.has_tag_name("rootfile"))
+        .and_then(|rootfile| rootfile.attribute("full-path"))
+        .ok_or_else(|| ExerciseError(format!("{} names no score", CONTAINER_PATH)))?;
+    parse_musicxml(&read_zip_entry(&mut archive, score_path)?, registry)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::exercise::registry;
+    use std::io::{Cursor, Write};
+
+    fn score(attributes: &str, notes: &str) -> String {
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<score-partwise version="3.1">
+  <part-list><score-part id="P1"><part-name>Guitar</part-name></score-part></part-list>
+  <part id="P1">
+    <measure number="1">
+      <attributes><divisions>2</divisions>{}</attributes>
+      {}
+    </measure>
+  </part>
+</score-partwise>"#,
+            attributes, notes
+        )
+    }
+
+    fn note(step: &str, alter: i32, octave: i32, duration: usize, extra: &str) -> String {
+        format!(
+            "<note><pitch><step>{}</step><alter>{}</alter><octave>{}</octave></pitch>\
+             <duration>{}</duration>{}</note>",
+            step, alter, octave, duration, extra
+        )
+    }
+
+    fn names(exercise: &[ExerciseNote]) -> Vec<String> {
+        exercise.iter().map(|n| n.note.name_octave()).collect()
+    }
+
+    #[test]
+    fn test_parse_sequence() {
+        let notes = [
+            note("C", 0, 4, 2, ""),
+            String::from("<note><rest/><duration>2</duration></note>"),
+            note("F", 1, 3, 1, "<tie type=\"start\"/>"),
+            note("F", 1, 3, 2, "<tie type=\"stop\"/>"),
+            note("A", 0, 3, 4, ""),
+            note("C", 0, 4, 4, "<chord/>"),
+            note("B", -1, 2, 1, ""),
+        ]
+        .concat();
+        let exercise = parse_musicxml(&score("", &notes), &registry()).unwrap();
+        assert_eq!(vec!["C4", "F♯3", "A3", "A♯2"], names(&exercise));
+        let durations: Vec<f64> = exercise.iter().map(|n| n.duration).collect();
+        assert_eq!(vec![1.0, 1.5, 2.0, 0.5], durations);
+    }
+
+    #[test]
+    fn test_parse_transposed_tab() {
+        let transpose =
+            "<transpose><chromatic>0</chromatic><octave-change>-1</octave-change></transpose>";
+        let notes = note(
+            "E",
+            0,
+            3,
+            2,
+            "<notations><technical><string>6</string><fret>0</fret></technical></notations>",
+        );
+        let exercise = parse_musicxml(&score(transpose, &notes), &registry()).unwrap();
+        assert_eq!(vec!["E2"], names(&exercise));
+        assert_eq!(
+            Some(FretLoc {
+                string_idx: 6,
+                fret_idx: 0
+            }),
+            exercise[0].loc
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let registry = registry();
+        assert!(parse_musicxml("<score-timewise/>", &registry).is_err());
+        assert!(parse_musicxml("<score-partwise>", &registry).is_err());
+        let bad_octave = score("", &note("C", 0, 4, 2, "").replace(">4<", ">x<"));
+        assert!(parse_musicxml(&bad_octave, &registry).is_err());
+        let out_of_range = score("", &note("C", 0, 9, 2, ""));
+        assert!(parse_musicxml(&out_of_range, &registry).is_err());
+        let no_divisions = score("", &note("C", 0, 4, 2, ""))
+            .replace("<divisions>2</divisions>", "<divisions>0</divisions>");
+        assert!(parse_musicxml(&no_divisions, &registry).is_err());
+        let huge_octave = score("", &note("C", 0, 999_999_999, 2, ""));
+        assert!(parse_musicxml(&huge_octave, &registry).is_err());
+        let transpose = "<transpose><octave-change>999999999</octave-change></transpose>";
+        let huge_transposition = score(transpose, &note("C", 0, 4, 2, ""));
+        assert!(parse_musicxml(&huge_transposition, &registry).is_err());
+        let beyond_transposition = score(
+            "<transpose><octave-change>178956970</octave-change></transpose>",
+            &note("C", 0, 4, 2, ""),
+        );
+        assert!(parse_musicxml(&beyond_transposition, &registry).is_err());
+    }
+
+    #[test]
+    fn test_parse_mxl_archive() {
+        let xml = score("", &note("C", 0, 4, 2, ""));
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        let options = zip::write::FileOptions::default();
+        writer.start_file(CONTAINER_PATH, options).unwrap();
+        writer
+            .write_all(
+                br#"<?xml version="1.0" encoding="UTF-8"?>
+<container><rootfiles>
+  <rootfile full-path="drill.musicxml" media-type="application/vnd.recordare.musicxml+xml"/>
+</rootfiles></container>"#,
+            )
+            .unwrap();
+        writer.start_file("drill.musicxml", options).unwrap();
+        writer.write_all(xml.as_bytes()).unwrap();
+        let archive = writer.finish().unwrap();
+        let registry = registry();
+        assert_eq!(
+            parse_musicxml(&xml, &registry).unwrap(),
+            parse_mxl(Cursor::new(archive.into_inner()), &registry).unwrap()
+        );
+        assert!(parse_mxl(Cursor::new(b"not a zip".to_vec()), &registry).is_err());
+    }
+}
@@ -0,0 +1,76 @@
+use crate::core::duration_secs;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Running statistics of a game session.
+///
+/// A target counts as correct if it was completed before any wrong note
+/// was detected often enough to be accepted.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Score {
+    pub n_completed: usize,
+    pub n_correct: usize,
+    pub streak: usize,
+    pub best_streak: usize,
+    /// Sum of the response times, in seconds in JSON.
+    #[serde(with = "duration_secs")]
+    pub total_response_time: Duration,
+}
+
+impl Score {
+    pub fn record(&mut self, correct: bool, response_time: Duration) {
+        self.n_completed += 1;
+        self.total_response_time += response_time;
+        if correct {
+            self.n_correct += 1;
+            self.streak += 1;
+            self.best_streak = self.best_streak.max(self.streak);
+        } else {
+            self.streak = 0;
+        }
+    }
+
+    pub fn accuracy(&self) -> Option<f64> {
+        if self.n_completed == 0 {
+            None
+        } else {
+            Some(self.n_correct as f64 / self.n_completed as f64)
+        }
+    }
+
+    pub fn avg_response_time(&self) -> Option<Duration> {
+        if self.n_completed == 0 {
+            None
+        } else {
+            Some(self.total_response_time / self.n_completed as u32)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_score_empty() {
+        let score = Score::default();
+        assert_eq!(None, score.accuracy());
+        assert_eq!(None, score.avg_response_time());
+        assert_eq!(0, score.streak);
+    }
+
+    #[test]
+    fn test_score_record() {
+        let mut score = Score::default();
+        score.record(true, Duration::from_secs(1));
+        score.record(true, Duration::from_secs(2));
+        score.record(false, Duration::from_secs(6));
+        score.record(true, Duration::from_secs(3));
+        assert_eq!(4, score.n_completed);
+        assert_eq!(3, score.n_correct);
+        assert_eq!(1, score.streak);
+        assert_eq!(2, score.best_streak);
+        assert_eq!(Some(0.75), score.accuracy());
+        assert_eq!(Some(Duration::from_secs(3)), score.avg_response_time());
+    }
+}
@@ -0,0 +1,256 @@
+use crate::core::{FretLoc, Note};
+use crate::game::{ActiveNotes, Score, TargetSelector};
+use log::*;
+use rhai::{Array, Dynamic, Engine, Map, Scope, AST};
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+
+/// File extension of the game mode scripts in the plugins directory.
+const SCRIPT_EXTENSION: &str = "rhai";
+const ENTRY_POINT: &str = "next_target";
+/// Operations a script may run to pick one target, so that a script that
+/// doesn't return, e.g. with an endless loop, can't hang the game thread.
+const MAX_OPERATIONS: u64 = 100_000;
+/// Deepest nesting of function calls in a script, which bounds its recursion.
+const MAX_CALL_LEVELS: usize = 32;
+
+#[derive(Debug)]
+pub struct PluginError(pub String);
+
+impl fmt::Display for PluginError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "PluginError: {}", self.0)
+    }
+}
+
+impl Error for PluginError {}
+
+/// Game mode implemented by a Rhai script.
+///
+/// The script defines `fn next_target(locations, score)`, where `locations`
+/// holds the active positions as `#{string, fret, note}` maps ordered by
+/// string and then by fret, and `score` is a `#{completed, correct, streak}`
+/// map of the session so far. It returns the index of the next target in
+/// `locations`, or `()` to pick a random target.
+///
+/// A call that runs more than a fixed number of operations or nests its
+/// function calls too deep is stopped and fails like any other script error,
+/// in which case a random target is picked.
+pub struct ScriptSelector {
+    name: String,
+    engine: Engine,
+    ast: AST,
+    /// Active positions in the order they are passed to the script, once the
+    /// active notes are known.
+    locations: Option<(Vec<(Note, FretLoc)>, Array)>,
+}
+
+impl ScriptSelector {
+    /// Loads the game mode `name` from `<name>.rhai` in `plugins_dir`.
+    pub fn load(plugins_dir: &Path, name: &str) -> Result<ScriptSelector, PluginError> {
+        let path = plugins_dir.join(name).with_extension(SCRIPT_EXTENSION);
+        let source = std::fs::read_to_string(&path).map_err(|err| {
+            PluginError(format!(
+                "Could not read game mode {}: {}",
+                path.display(),
+                err
+            ))
+        })?;
+        ScriptSelector::from_source(name, &source)
+    }
+
+    pub fn from_source(name: &str, source: &str) -> Result<ScriptSelector, PluginError> {
+        let mut engine = Engine::new();
+        engine
+            .set_max_operations(MAX_OPERATIONS)
+            .set_max_call_levels(MAX_CALL_LEVELS);
+        let ast = engine
+            .compile(source)
+            .map_err(|err| PluginError(format!("Could not compile game mode {}: {}", name, err)))?;
+        if !ast.iter_functions().any(|f| f.name == ENTRY_POINT) {
+            return Err(PluginError(format!(
+                "Game mode {} does not define {}",
+                name, ENTRY_POINT
+            )));
+        }
+        Ok(ScriptSelector {
+            name: String::from(name),
+            engine,
+            ast,
+            locations: None,
+        })
+    }
+}
+
+fn active_locations(notes: &ActiveNotes) -> (Vec<(Note, FretLoc)>, Array) {
+    let mut targets = Vec::new();
+    let mut locations = Array::new();
+    for string_idx in notes.string_range.r() {
+        for fret_idx in notes.fret_range.r() {
+            let loc = FretLoc {
+                string_idx,
+                fret_idx,
+            };
+            if let Some(note) = notes.get(&loc) {
+                let mut location = Map::new();
+                location.insert("string".into(), Dynamic::from(string_idx as i64));
+                location.insert("fret".into(), Dynamic::from(fret_idx as i64));
+                location.insert("note".into(), Dynamic::from(note.name_octave()));
+                locations.push(Dynamic::from(location));
+                targets.push((note.clone(), loc));
+            }
+        }
+    }
+    (targets, locations)
+}
+
+fn score_map(score: &Score) -> Map {
+    let mut map = Map::new();
+    map.insert("completed".into(), Dynamic::from(score.n_completed as i64));
+    map.insert("correct".into(), Dynamic::from(score.n_correct as i64));
+    map.insert("streak".into(), Dynamic::from(score.streak as i64));
+    map
+}
+
+impl TargetSelector for ScriptSelector {
+    fn next_target(&mut self, notes: &ActiveNotes, score: &Score) -> Option<(Note, FretLoc)> {
+        let (targets, locations) = self
+            .locations
+            .get_or_insert_with(|| active_locations(notes));
+        let result = self.engine.call_fn::<Dynamic>(
+            &mut Scope::new(),
+            &self.ast,
+            ENTRY_POINT,
+            (locations.clone(), score_map(score)),
+        );
+        match result {
+            Ok(idx) if idx.is_unit() => None,
+            Ok(idx) => match idx.as_int() {
+                Ok(idx) if idx >= 0 && (idx as usize) < targets.len() => {
+                    Some(targets[idx as usize].clone())
+                }
+                _ => {
+                    warn!(
+                        "Game mode {} returned {} instead of an index below {}",
+                        self.name,
+                        idx,
+                        targets.len()
+                    );
+                    None
+                }
+            },
+            Err(err) => {
+                warn!("Game mode {} failed: {}", self.name, err);
+                None
+            }
+        }
+    }
+
+    fn notes_changed(&mut self) {
+        self.locations = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{
+        FretRange, NoteName, NoteRegistry, StringRange, Tuning, TuningSpecification,
+    };
+
+    fn active_notes() -> ActiveNotes {
+        let g3 = Note {
+            octave: 3,
+            name: NoteName::G,
+            frequency: 196.0,
+        };
+        let notes = (0..12)
+            .map(|semitones| Note {
+                frequency: 196.0 * 2f64.powf(semitones as f64 / 12.0),
+                ..g3.add_semitone(semitones)
+            })
+            .collect();
+        let registry = NoteRegistry::from_notes(notes).unwrap();
+        let tuning = Tuning::from_specification(
+            &[
+                TuningSpecification {
+                    name: NoteName::B,
+                    octave: 3,
+                    string: 2,
+                },
+                TuningSpecification {
+                    name: NoteName::G,
+                    octave: 3,
+                    string: 3,
+                },
+            ],
+            &registry,
+        )
+        .unwrap();
+        ActiveNotes::new(
+            &registry,
+            &tuning,
+            StringRange::new(2, 4).unwrap(),
+            FretRange::new(0, 3).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_script_selector() {
+        let notes = active_notes();
+        let mut selector = ScriptSelector::from_source(
+            "backwards",
+            "fn next_target(locations, score) { locations.len() - 1 - score.completed }",
+        )
+        .unwrap();
+        let mut score = Score::default();
+        let (note, loc) = selector.next_target(&notes, &score).unwrap();
+        assert_eq!((3, 2), (loc.string_idx, loc.fret_idx));
+        assert_eq!("A3", note.name_octave());
+        score.n_completed = 3;
+        let (_, loc) = selector.next_target(&notes, &score).unwrap();
+        assert_eq!((2, 2), (loc.string_idx, loc.fret_idx));
+        score.n_completed = 6;
+        assert_eq!(None, selector.next_target(&notes, &score));
+    }
+
+    #[test]
+    fn test_load_example() {
+        // The example scripts are shipped in the config directory of the app.
+        let plugins_dir = Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/../cfg/plugins"));
+        let mut selector = ScriptSelector::load(plugins_dir, "string_walk").unwrap();
+        let (_, loc) = selector
+            .next_target(&active_notes(), &Score::default())
+            .unwrap();
+        assert_eq!((2, 0), (loc.string_idx, loc.fret_idx));
+    }
+
+    #[test]
+    fn test_script_selector_errors() {
+        assert!(ScriptSelector::from_source("empty", "let x = 1;").is_err());
+        assert!(ScriptSelector::from_source("broken", "fn next_target(").is_err());
+        let mut selector =
+            ScriptSelector::from_source("random", "fn next_target(locations, score) { () }")
+                .unwrap();
+        assert_eq!(
+            None,
+            selector.next_target(&active_notes(), &Score::default())
+        );
+    }
+
+    #[test]
+    fn test_script_selector_limits() {
+        let notes = active_notes();
+        let mut endless =
+            ScriptSelector::from_source("endless", "fn next_target(locations, score) { loop {} }")
+                .unwrap();
+        assert_eq!(None, endless.next_target(&notes, &Score::default()));
+        let mut recursive = ScriptSelector::from_source(
+            "recursive",
+            "fn deeper(n) { deeper(n + 1) } fn next_target(locations, score) { deeper(0) }",
+        )
+        .unwrap();
+        assert_eq!(None, recursive.next_target(&notes, &Score::default()));
+    }
+}
@@ -0,0 +1,110 @@
+use crate::core::{FretLoc, Note};
+use crate::game::{ActiveNotes, ExerciseNote, Score};
+use log::*;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+/// Game mode deciding which note the player has to find next.
+pub trait TargetSelector: Send {
+    /// Next target among the active notes, given the score of the session so
+    /// far. Returning `None` picks a random target instead.
+    fn next_target(&mut self, notes: &ActiveNotes, score: &Score) -> Option<(Note, FretLoc)>;
+
+    /// Called when the active notes change, e.g. to another range preset, so
+    /// that positions cached from the previous ones can be dropped.
+    fn notes_changed(&mut self) {}
+}
+
+/// Picks every target at random.
+pub struct RandomSelector {
+    rng: StdRng,
+}
+
+impl Default for RandomSelector {
+    fn default() -> RandomSelector {
+        RandomSelector {
+            rng: StdRng::from_entropy(),
+        }
+    }
+}
+
+impl TargetSelector for RandomSelector {
+    fn next_target(&mut self, notes: &ActiveNotes, _: &Score) -> Option<(Note, FretLoc)> {
+        let (note, loc) = pick_note(notes, &mut self.rng);
+        Some((note.clone(), loc))
+    }
+}
+
+pub fn pick_note<'a>(notes: &'a ActiveNotes, rng: &mut impl rand::Rng) -> (&'a Note, FretLoc) {
+    let string_idx = rng.gen_range(notes.string_range.r());
+    let fret_idx = rng.gen_range(notes.fret_range.r());
+    let key = FretLoc {
+        string_idx,
+        fret_idx,
+    };
+    (notes.get(&key).unwrap(), key)
+}
+
+/// Plays the notes of an exercise in order, repeating it from the start until
+/// the game ends.
+pub struct ExerciseSelector {
+    exercise: Vec<ExerciseNote>,
+    /// Exercise notes located on the active fretboard, once the active notes
+    /// are known.
+    targets: Option<Vec<(Note, FretLoc)>>,
+    next_idx: usize,
+}
+
+impl ExerciseSelector {
+    pub fn new(exercise: Vec<ExerciseNote>) -> ExerciseSelector {
+        ExerciseSelector {
+            exercise,
+            targets: None,
+            next_idx: 0,
+        }
+    }
+}
+
+impl TargetSelector for ExerciseSelector {
+    fn next_target(&mut self, notes: &ActiveNotes, _: &Score) -> Option<(Note, FretLoc)> {
+        let exercise = &self.exercise;
+        let targets = self
+            .targets
+            .get_or_insert_with(|| locate_exercise(exercise, notes));
+        if targets.is_empty() {
+            return None;
+        }
+        let target = targets[self.next_idx % targets.len()].clone();
+        self.next_idx += 1;
+        Some(target)
+    }
+
+    fn notes_changed(&mut self) {
+        self.targets = None;
+    }
+}
+
+/// Targets of the exercise in order. The location given in the exercise is
+/// used if it holds the note within the active range, otherwise the note is
+/// located on the fretboard. Notes outside the active range are skipped.
+fn locate_exercise(exercise: &[ExerciseNote], notes: &ActiveNotes) -> Vec<(Note, FretLoc)> {
+    exercise
+        .iter()
+        .filter_map(|exercise_note| {
+            let given_loc = exercise_note
+                .loc
+                .clone()
+                .filter(|loc| notes.get(loc) == Some(&exercise_note.note));
+            match given_loc.or_else(|| notes.locate(&exercise_note.note)) {
+                Some(loc) => Some((exercise_note.note.clone(), loc)),
+                None => {
+                    warn!(
+                        "{} is outside the active fretboard range. Skipping...",
+                        exercise_note.note.name_octave()
+                    );
+                    None
+                }
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,41 @@
+//! Note detection and fretboard game engine of libreguitar.
+//!
+//! The music model ([`Note`], [`NoteRegistry`], [`Tuning`], [`FretLoc`]),
+//! the exercises and the practice history are always built, the note
+//! detection ([`AudioAnalyzer`], [`AnalysisResult`]), the game rules
+//! ([`GameSession`], [`GameState`]) and the [`GameEvent`]s with the default
+//! `analysis` feature. The crate has no audio, threads or user interface;
+//! the `libreguitar` app adds those on top of it.
+//!
+//! The supported API is re-exported here. The modules are public for the
+//! frontends of the app, which also use the texts of [`events::status_text`].
+
+#[cfg(feature = "analysis")]
+pub mod audio_analysis;
+pub mod core;
+#[cfg(feature = "analysis")]
+pub mod events;
+pub mod game;
+
+#[cfg(feature = "analysis")]
+pub use crate::audio_analysis::{
+    read_channel_buffered, AnalysisResult, AudioAnalyzer, AudioParams, DetectionInput, NoiseFloor,
+    NoiseFloorError, NotePeak, PitchDetector, TargetNotes, TooFewNotesError,
+};
+pub use crate::core::{
+    AudioCfg, AutoGainCfg, DuplicateNoteError, FretLoc, FretRange, GameCfg, InvalidTuningError,
+    Note, NoteName, NoteRegistry, NoteRegistryError, PitchMethod, RangeError, RangePreset,
+    StringRange, Tuning, TuningError, TuningSpecification, WindowFunction,
+};
+#[cfg(feature = "analysis")]
+pub use crate::events::{GameEvent, GameEventTracker, TargetResult, EVENT_VERSION};
+pub use crate::game::{
+    append_targets, append_to_history, load_exercise, load_history, load_targets, write_csv,
+    write_history, write_targets, ActiveNotes, ExerciseError, ExerciseNote, ExerciseSelector,
+    GameState, LoadExerciseError, RandomSelector, Score, SessionRecord, TargetRecord,
+    TargetSelector,
+};
+#[cfg(feature = "analysis")]
+pub use crate::game::{GameCommand, GameSession};
+#[cfg(feature = "plugins")]
+pub use crate::game::{PluginError, ScriptSelector};
@@ -1,20 +1,64 @@
-use crate::audio_analysis::AudioAnalyzer;
-use crate::core::{Cfg, NoteRegistry, Tuning};
-use crate::game::{GameError, GameLogic};
-use crate::visualization::{ConsoleVisualizer, Visualizer};
+use crate::audio_analysis::AnalysisResult;
+use crate::audio_analysis::{
+    read_channel_buffered, AudioAnalyzer, NoiseFloorError, PitchDetector, TooFewNotesError,
+};
+use crate::audio_output::OutputStreamError;
+use crate::calibration::load_noise_floor;
 #[cfg(feature = "gui")]
-use crate::visualization::{FrameData, GUIVisualizer, GuiCfg};
+use crate::core::KeysCfg;
+#[cfg(feature = "sync")]
+use crate::core::SyncCfg;
+use crate::core::{
+    Cfg, ConsoleCfg, ConsoleFrontend, FretRange, GameCfg, MetronomeCfg, NoteRegistryError,
+    RangeError, ReferenceToneCfg, StringRange, Tuning, TuningError,
+};
+use crate::event_bus::{EventBus, Subscription, Topic};
+#[cfg(feature = "gui")]
+use crate::game::load_history;
+#[cfg(feature = "sync")]
+use crate::game::sync_files;
+use crate::game::{
+    append_targets, append_to_history, load_exercise, spawn_midi_control, ExerciseSelector,
+    GameController, GameError, GameLogic, GameState, LoadExerciseError, PipeControl,
+    RandomSelector, Score, SessionRecord, TargetRecord, TargetSelector,
+};
+#[cfg(feature = "plugins")]
+use crate::game::{PluginError, ScriptSelector};
+use crate::metrics::{serve_prometheus, Metrics};
+use crate::metronome::{Beat, Metronome};
+use crate::note_source::{AudioSource, NoteSource, SampleCallback, SourceErrorCallback};
+use crate::reference_tone::{ReferenceTone, TonePlayer};
+#[cfg(feature = "http_api")]
+use crate::visualization::HttpApi;
+#[cfg(feature = "tui")]
+use crate::visualization::TuiVisualizer;
+#[cfg(feature = "websocket")]
+use crate::visualization::WebSocketServer;
+use crate::visualization::{
+    downsample_spectrum, ConsoleVisualizer, FrameData, GameEvent, GameEventTracker, OscSender,
+    OverlayWriter, PlainVisualizer, PracticeLog, TargetResult, ThreadedVisualizer, Visualizer,
+};
+#[cfg(feature = "gui")]
+use crate::visualization::{EventLoop, GUIVisualizer, GuiCfg, GuiChannels};
 use log::*;
+use rtrb::{Consumer, Producer, RingBuffer};
+use signal_hook::consts::TERM_SIGNALS;
 use std::collections::VecDeque;
-use std::error::Error;
+#[cfg(feature = "plugins")]
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(feature = "gui")]
 use std::sync::mpsc;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle, Thread};
+use std::time::{Duration, Instant, SystemTime};
 use thiserror::Error;
+#[cfg(feature = "gui")]
+use triple_buffer::TripleBuffer;
 
-use cpal::traits::DeviceTrait;
-use cpal::traits::StreamTrait;
-use cpal::BuildStreamError;
+use cpal::traits::HostTrait;
 use cpal::Device;
-use cpal::Stream;
 use cpal::StreamConfig;
 
 #[derive(Error, Debug)]
@@ -26,216 +70,1054 @@ pub enum AppError {
     #[error(transparent)]
     GameError(#[from] GameError),
     #[error(transparent)]
-    UnknownError(#[from] Box<dyn Error>),
+    IoError(#[from] std::io::Error),
+    #[error(transparent)]
+    NoteRegistryError(#[from] NoteRegistryError),
+    #[error(transparent)]
+    TuningError(#[from] TuningError),
+    #[error(transparent)]
+    RangeError(#[from] RangeError),
+    #[error(transparent)]
+    TooFewNotesError(#[from] TooFewNotesError),
+    #[error(transparent)]
+    LoadExerciseError(#[from] LoadExerciseError),
+    #[cfg(feature = "plugins")]
+    #[error(transparent)]
+    PluginError(#[from] PluginError),
+    #[error(transparent)]
+    OutputStreamError(#[from] OutputStreamError),
+    #[error(transparent)]
+    NoiseFloorError(#[from] NoiseFloorError),
+    #[error("The input device has no channel {channel}, only {n_channels}")]
+    InvalidChannel { channel: usize, n_channels: usize },
 }
 
+/// Longest time the app loop waits for a game state before checking whether
+/// to stop.
+const STATE_POLL_PERIOD: Duration = Duration::from_millis(50);
+
 pub struct App {
-    audio_stream: Stream,
-    visualizers: Vec<Box<dyn Visualizer>>,
+    note_source: Box<dyn NoteSource>,
+    /// Callbacks the note source is started with when the game is run.
+    source_callbacks: Option<(Box<SampleCallback>, Box<SourceErrorCallback>)>,
+    /// Clicks along with the game while it lives.
+    _metronome: Option<Metronome>,
+    /// Plays the reference tones while it lives.
+    _reference_tone: Option<ReferenceTone>,
+    /// Removes the control pipe when the app is dropped.
+    _pipe_control: Option<PipeControl>,
+    /// Event loop of the GUI, run on the thread calling [`App::run`].
+    #[cfg(feature = "gui")]
+    gui_event_loop: Option<EventLoop>,
+    main_loop: MainLoop,
+}
+
+/// Part of the app that keeps track of the game until it is stopped. It runs
+/// on a thread of its own, while the note source and the audio outputs stay
+/// on the thread that created them, which some audio hosts require.
+struct MainLoop {
+    /// Plays every new target if auto play is on.
+    auto_tone_player: Option<TonePlayer>,
+    /// Visualizers drawn on their own threads.
+    visualizers: Vec<ThreadedVisualizer>,
     game_logic: GameLogic,
-    frame_period: f64,
+    history_path: Option<PathBuf>,
+    targets_path: Option<PathBuf>,
+    /// Server the history is synced with at the end of the session.
+    #[cfg(feature = "sync")]
+    auto_sync: Option<SyncCfg>,
+    /// Receives the game states to keep track of the score and the targets
+    /// that are stored in the history at the end of the session.
+    history_rx: Subscription<GameState>,
+    event_tracker: GameEventTracker,
+    /// Publishes the events told apart by `event_tracker`.
+    events: Topic<GameEvent>,
+    score: Score,
+    targets: Vec<TargetResult>,
+    metrics: Arc<Metrics>,
+    /// Time between writing the metrics to the log, if they are logged
+    /// periodically.
+    metrics_log_period: Option<Duration>,
 }
 
-impl App {
-    pub fn new(device: Device, device_config: StreamConfig, cfg: Cfg) -> Result<App, AppError> {
+/// Handle of an app running in the background, returned by [`App::spawn`].
+/// Dropping it stops the app and waits for it to finish.
+pub struct AppHandle {
+    note_source: Box<dyn NoteSource>,
+    _metronome: Option<Metronome>,
+    _reference_tone: Option<ReferenceTone>,
+    _pipe_control: Option<PipeControl>,
+    controller: GameController,
+    events: Topic<GameEvent>,
+    /// Makes the main loop end the game.
+    stop: Arc<AtomicBool>,
+    main_loop: Option<JoinHandle<()>>,
+    /// Thread of the GUI if it wasn't run by [`App::run`].
+    #[cfg(feature = "gui")]
+    gui_thread: Option<JoinHandle<()>>,
+}
+
+/// Builds an [`App`] from the configuration, replacing the note source,
+/// visualizers and target selector of the bundled binary with the ones
+/// given by the host application.
+pub struct AppBuilder {
+    cfg: Cfg,
+    note_source: Box<dyn NoteSource>,
+    visualizers: Vec<Box<dyn Visualizer + Send>>,
+    bus: EventBus,
+    selector: Option<Box<dyn TargetSelector>>,
+    pitch_detector: Option<Box<dyn PitchDetector>>,
+    console: bool,
+    gui: bool,
+}
+
+impl AppBuilder {
+    pub fn new(cfg: Cfg, note_source: Box<dyn NoteSource>) -> AppBuilder {
+        AppBuilder {
+            cfg,
+            note_source,
+            visualizers: Vec::new(),
+            bus: EventBus::default(),
+            selector: None,
+            pitch_detector: None,
+            console: true,
+            gui: true,
+        }
+    }
+
+    /// Draws `visualizer` on its own thread at the `fps` of the app along
+    /// with the configured ones. The game stops once any visualizer is
+    /// closed.
+    pub fn visualizer(mut self, visualizer: Box<dyn Visualizer + Send>) -> AppBuilder {
+        self.visualizers.push(visualizer);
+        self
+    }
+
+    /// Receiver of every game state, e.g. for a custom visualizer to draw.
+    pub fn state_receiver(&self) -> Subscription<GameState> {
+        self.bus.states.subscribe()
+    }
+
+    /// Receiver of the analysis and spectrum of every audio block.
+    pub fn frame_receiver(&self) -> Subscription<FrameData> {
+        self.bus.frames.subscribe()
+    }
+
+    /// Bus carrying the events of the app, e.g. to subscribe to the
+    /// detections or to publish game commands.
+    pub fn event_bus(&self) -> EventBus {
+        self.bus.clone()
+    }
+
+    /// Picks the targets with `selector` instead of the configured exercise
+    /// or game mode.
+    pub fn target_selector(mut self, selector: Box<dyn TargetSelector>) -> AppBuilder {
+        self.selector = Some(selector);
+        self
+    }
+
+    /// Picks the played frequency with `detector` instead of the configured
+    /// `pitch_method`.
+    pub fn pitch_detector(mut self, detector: Box<dyn PitchDetector>) -> AppBuilder {
+        self.pitch_detector = Some(detector);
+        self
+    }
+
+    /// Whether the configured console frontend is drawn. Host applications
+    /// with their own user interface usually turn it off.
+    pub fn console(mut self, enabled: bool) -> AppBuilder {
+        self.console = enabled;
+        self
+    }
+
+    /// Whether the GUI window is opened when built with the `gui` feature,
+    /// e.g. turned off to run the app without a display.
+    pub fn gui(mut self, enabled: bool) -> AppBuilder {
+        self.gui = enabled;
+        self
+    }
+
+    pub fn build(self) -> Result<App, AppError> {
+        let AppBuilder {
+            cfg,
+            note_source,
+            visualizers: custom_visualizers,
+            bus,
+            selector: custom_selector,
+            pitch_detector: custom_pitch_detector,
+            console,
+            gui,
+        } = self;
+        #[cfg(not(feature = "gui"))]
+        let _ = gui;
         let app_cfg = cfg.app;
-        let note_registry = NoteRegistry::from_csv(&app_cfg.frequencies_path)?;
-        let tuning = Tuning::from_csv(&app_cfg.tuning_path, &note_registry)?;
-        let mut analyzer = AudioAnalyzer::new(
-            device_config.sample_rate.0 as usize,
-            note_registry.notes(),
-            cfg.audio,
-        );
-        let (analysis_tx, analysis_rx) = mpsc::channel();
-        let (console_tx, console_rx) = mpsc::channel();
-        let game_logic = GameLogic::new(
-            analysis_rx,
-            vec![console_tx],
-            note_registry,
-            tuning.clone(),
-            cfg.game,
-        );
-        let console_visualizer = ConsoleVisualizer::new(
-            console_rx,
-            game_logic.fret_range().clone(),
-            game_logic.string_range().clone(),
-            cfg.console,
-            tuning,
-        );
-        let visualizers: Vec<Box<dyn Visualizer>> = vec![Box::new(console_visualizer)];
+        let metrics = Arc::new(Metrics::default());
+        if let Some(address) = &app_cfg.metrics.prometheus_address {
+            serve_prometheus(address, metrics.clone())?;
+        }
+        let note_registry = app_cfg.note_registry()?;
+        let tuning = match &app_cfg.tuning {
+            Some(tuning) => {
+                Tuning::from_compact(tuning, &note_registry).map_err(TuningError::from)?
+            }
+            None => Tuning::from_csv(&app_cfg.tuning_path, &note_registry)?,
+        };
+        let mut analyzer =
+            AudioAnalyzer::new(note_source.sample_rate(), note_registry.notes(), cfg.audio)?;
+        analyzer.set_noise_floor(load_noise_floor(&app_cfg.calibration));
+        if let Some(detector) = custom_pitch_detector {
+            analyzer.set_pitch_detector(detector);
+        }
+        let console_spectrum_width = cfg.console.spectrum_width;
+        let console_spectrum_max_freq = cfg.console.spectrum_max_freq;
+        let history_rx = bus.states.subscribe();
+        let events = bus.events.clone();
+        let console_rxs = if console {
+            Some((
+                bus.states.subscribe(),
+                bus.analysis.subscribe(),
+                bus.spectrum.subscribe(),
+            ))
+        } else {
+            None
+        };
+        let osc_sender = match app_cfg.osc {
+            Some(osc_cfg) => Some(OscSender::new(
+                bus.states.subscribe(),
+                bus.analysis.subscribe(),
+                osc_cfg,
+            )?),
+            None => None,
+        };
+        let practice_log = match &app_cfg.practice_log_path {
+            Some(path) => Some(PracticeLog::new(
+                bus.states.subscribe(),
+                bus.analysis.subscribe(),
+                path,
+            )?),
+            None => None,
+        };
+        let overlay_writer = match app_cfg.overlay {
+            Some(overlay_cfg) => Some(OverlayWriter::new(bus.states.subscribe(), overlay_cfg)?),
+            None => None,
+        };
+        #[cfg(feature = "websocket")]
+        let websocket_server = match app_cfg.websocket {
+            Some(websocket_cfg) => {
+                let server = WebSocketServer::new(
+                    bus.states.subscribe(),
+                    bus.analysis.subscribe(),
+                    websocket_cfg,
+                )?;
+                // The port may have been picked by the system for port 0.
+                info!(
+                    "Serving the remote display on http://{}",
+                    server.local_addr()
+                );
+                Some(server)
+            }
+            None => None,
+        };
+        let selector: Box<dyn TargetSelector> = match (custom_selector, &cfg.game.exercise_path) {
+            (Some(selector), _) => selector,
+            (None, Some(path)) => {
+                let exercise = load_exercise(path, &note_registry)?;
+                let n_beats: f64 = exercise.iter().map(|note| note.duration).sum();
+                info!(
+                    "Loaded {} notes spanning {} beats from {}",
+                    exercise.len(),
+                    n_beats,
+                    path.display()
+                );
+                Box::new(ExerciseSelector::new(exercise))
+            }
+            (None, None) => game_mode_selector(&cfg.game)?,
+        };
+        let game_logic = GameLogic::new(&bus, note_registry, tuning.clone(), cfg.game, selector)?;
+        if let Err(err) = spawn_midi_control(&app_cfg.midi, game_logic.controller()) {
+            warn!("Could not open the MIDI device: {}", err);
+        }
+        let pipe_control = match &app_cfg.control_pipe {
+            Some(path) => match PipeControl::spawn(path, game_logic.controller()) {
+                Ok(pipe_control) => Some(pipe_control),
+                Err(err) => {
+                    warn!(
+                        "Could not create the control pipe {}: {}",
+                        path.display(),
+                        err
+                    );
+                    None
+                }
+            },
+            None => None,
+        };
+        let redraw_console_on_events = cfg.console.redraw_on_events;
+        let console_visualizer = match console_rxs {
+            Some((console_rx, console_analysis_rx, console_spectrum_rx)) => {
+                Some(create_console_visualizer(
+                    console_rx,
+                    console_analysis_rx,
+                    console_spectrum_rx,
+                    game_logic.fret_range().clone(),
+                    game_logic.string_range().clone(),
+                    cfg.console,
+                    tuning,
+                )?)
+            }
+            None => None,
+        };
+        let mut visualizers: Vec<Box<dyn Visualizer + Send>> = Vec::new();
+        if let Some(osc_sender) = osc_sender {
+            visualizers.push(Box::new(osc_sender));
+        }
+        #[cfg(feature = "websocket")]
+        if let Some(websocket_server) = websocket_server {
+            visualizers.push(Box::new(websocket_server));
+        }
+        if let Some(practice_log) = practice_log {
+            visualizers.push(Box::new(practice_log));
+        }
+        if let Some(overlay_writer) = overlay_writer {
+            visualizers.push(Box::new(overlay_writer));
+        }
+        #[cfg(feature = "http_api")]
+        if let Some(http_api_cfg) = app_cfg.http_api.clone() {
+            let http_api = HttpApi::new(
+                bus.states.subscribe(),
+                game_logic.controller(),
+                http_api_cfg,
+            )?;
+            visualizers.push(Box::new(http_api));
+        }
+        visualizers.extend(custom_visualizers);
+        let fps = app_cfg.fps;
+        let console_visualizer = console_visualizer.map(|visualizer| {
+            if redraw_console_on_events {
+                let wakeup = events.subscribe();
+                ThreadedVisualizer::spawn_with_wakeup(fps, wakeup, metrics.clone(), move || {
+                    visualizer
+                })
+            } else {
+                ThreadedVisualizer::spawn(fps, metrics.clone(), move || visualizer)
+            }
+        });
+        let visualizers = console_visualizer
+            .into_iter()
+            .chain(visualizers.into_iter().map(|visualizer| {
+                ThreadedVisualizer::spawn(fps, metrics.clone(), move || visualizer)
+            }))
+            .collect();
+        let reference_tone = if app_cfg.reference_tone.enabled {
+            start_reference_tone(&app_cfg.reference_tone)
+        } else {
+            None
+        };
+        let tone_player = reference_tone.as_ref().map(|tone| tone.player());
+        let auto_tone_player = if app_cfg.reference_tone.auto_play {
+            tone_player.clone()
+        } else {
+            None
+        };
         #[cfg(feature = "gui")]
-        let (gui_tx, gui_rx) = mpsc::channel();
+        let (params_tx, params_rx) = mpsc::channel();
+        // The GUI draws the latest frame at its own pace, so the spectrum is
+        // handed over through a triple buffer instead of the frames topic
+        // to neither copy it on every analysis nor block the analysis.
         #[cfg(feature = "gui")]
-        let visualizers = add_gui_visualizer(
-            visualizers,
-            analyzer.n_bins(),
-            analyzer.delta_f(),
-            gui_rx,
-            cfg.gui,
-        );
-        let audio_read_callback: Box<CallbackFn> =
-            Box::new(move |data: Box<dyn ExactSizeIterator<Item = f64>>| {
-                let analysis = analyzer.identify_note(data);
-                // send data to game logic
-                analysis_tx.send(analysis).unwrap();
+        let (mut frame_input, frame_output) = TripleBuffer::new(&FrameData {
+            spectrogram: Vec::new(),
+            analysis: AnalysisResult::silence(),
+        })
+        .split();
+        #[cfg(feature = "gui")]
+        let (visualizers, gui_event_loop) = if gui {
+            add_gui_visualizer(
+                visualizers,
+                &analyzer,
+                GuiChannels {
+                    frames: frame_output,
+                    state_rx: bus.states.subscribe(),
+                    params_tx,
+                    controller: game_logic.controller(),
+                    tone_player,
+                    metrics: metrics.clone(),
+                },
+                &game_logic,
+                load_gui_history(&app_cfg.history_path),
+                &app_cfg.keys,
+                cfg.gui,
+            )
+        } else {
+            (visualizers, None)
+        };
+        let metronome = if app_cfg.metronome.enabled {
+            start_metronome(&app_cfg.metronome, bus.beats.clone())
+        } else {
+            None
+        };
+        let callback_metrics = metrics.clone();
+        let analysis_callback: Box<CallbackFn> = Box::new(
+            move |data: Box<dyn ExactSizeIterator<Item = f64>>, n_new_samples| {
+                // apply the parameters changed in the GUI
                 #[cfg(feature = "gui")]
-                {
-                    // send data to GUI
-                    let frame_data = FrameData {
+                if let Some(params) = params_rx.try_iter().last() {
+                    analyzer.set_params(params);
+                }
+                let analysis_start = Instant::now();
+                let analysis = analyzer.identify_next_note(data, n_new_samples);
+                callback_metrics.record_analysis(analysis_start.elapsed());
+                if console_spectrum_width > 0 && bus.spectrum.has_subscribers() {
+                    bus.spectrum.publish(downsample_spectrum(
+                        analyzer.spectrogram(),
+                        analyzer.delta_f(),
+                        console_spectrum_max_freq,
+                        console_spectrum_width,
+                    ));
+                }
+                if bus.frames.has_subscribers() {
+                    bus.frames.publish(FrameData {
                         spectrogram: analyzer.spectrogram().clone(),
-                    };
-                    gui_tx.send(frame_data).unwrap();
+                        analysis: analysis.clone(),
+                    });
+                }
+                #[cfg(feature = "gui")]
+                {
+                    let frame = frame_input.input_buffer();
+                    analyzer.swap_spectrogram(&mut frame.spectrogram);
+                    frame.analysis = analysis.clone();
+                    frame_input.publish();
                 }
-            });
-        let audio_stream = create_audio_stream(
-            device,
-            device_config,
+                bus.analysis.publish(analysis);
+            },
+        );
+        let source_callbacks = create_source_callbacks(
+            note_source.sample_rate(),
+            note_source.n_channels(),
+            &app_cfg.input_channels,
             app_cfg.block_size,
-            audio_read_callback,
+            analysis_callback,
+            metrics.clone(),
         )?;
         Ok(App {
-            audio_stream,
-            visualizers,
-            game_logic,
-            frame_period: 1.0 / app_cfg.fps,
+            note_source,
+            source_callbacks: Some(source_callbacks),
+            _metronome: metronome,
+            _reference_tone: reference_tone,
+            _pipe_control: pipe_control,
+            #[cfg(feature = "gui")]
+            gui_event_loop,
+            main_loop: MainLoop {
+                auto_tone_player,
+                visualizers,
+                game_logic,
+                #[cfg(feature = "sync")]
+                auto_sync: app_cfg.sync.clone().filter(|sync_cfg| sync_cfg.auto_sync),
+                history_path: app_cfg.history_path,
+                targets_path: app_cfg.targets_path,
+                history_rx,
+                event_tracker: GameEventTracker::default(),
+                events,
+                score: Score::default(),
+                targets: Vec::new(),
+                metrics,
+                metrics_log_period: if app_cfg.metrics.log_period > 0.0 {
+                    Some(Duration::from_secs_f64(app_cfg.metrics.log_period))
+                } else {
+                    None
+                },
+            },
+        })
+    }
+}
+
+impl App {
+    pub fn new(device: Device, device_config: StreamConfig, cfg: Cfg) -> Result<App, AppError> {
+        App::builder(cfg, Box::new(AudioSource::new(device, device_config))).build()
+    }
+
+    /// Builder of an app reading the played notes from `note_source`.
+    pub fn builder(cfg: Cfg, note_source: Box<dyn NoteSource>) -> AppBuilder {
+        AppBuilder::new(cfg, note_source)
+    }
+
+    /// Starts the note source and the game, and keeps track of the game on
+    /// a new thread until a visualizer is closed or the returned handle is
+    /// stopped. The GUI is opened on a thread of its own, which macOS
+    /// doesn't allow, so it is left out there; use [`App::run`] to open it
+    /// on the main thread instead.
+    #[cfg_attr(not(feature = "gui"), allow(unused_mut))]
+    pub fn spawn(mut self) -> Result<AppHandle, AppError> {
+        #[cfg(feature = "gui")]
+        let gui_event_loop = self.gui_event_loop.take();
+        let mut handle = self.spawn_main_loop()?;
+        #[cfg(feature = "gui")]
+        {
+            handle.gui_thread = gui_event_loop.and_then(spawn_gui);
+        }
+        Ok(handle)
+    }
+
+    fn spawn_main_loop(mut self) -> Result<AppHandle, AppError> {
+        if let Some((callback, on_error)) = self.source_callbacks.take() {
+            self.note_source.start(callback, on_error)?;
+        }
+        let mut main_loop = self.main_loop;
+        main_loop.game_logic.play()?;
+        let controller = main_loop.game_logic.controller();
+        let events = main_loop.events.clone();
+        let stop = Arc::new(AtomicBool::new(false));
+        let loop_stop = stop.clone();
+        let main_loop = thread::spawn(move || main_loop.run(&loop_stop));
+        Ok(AppHandle {
+            note_source: self.note_source,
+            _metronome: self._metronome,
+            _reference_tone: self._reference_tone,
+            _pipe_control: self._pipe_control,
+            controller,
+            events,
+            stop,
+            main_loop: Some(main_loop),
+            #[cfg(feature = "gui")]
+            gui_thread: None,
         })
     }
 
+    /// Runs the game until a visualizer is closed or the process receives a
+    /// termination signal. The GUI runs on the calling thread, which has to
+    /// be the main thread on macOS, while the game is kept track of on a new
+    /// thread.
+    #[cfg_attr(not(feature = "gui"), allow(unused_mut))]
+    pub fn run(mut self) -> Result<(), AppError> {
+        #[cfg(feature = "gui")]
+        let gui_event_loop = self.gui_event_loop.take();
+        let handle = self.spawn_main_loop()?;
+        handle_term_signals(&handle.stop)?;
+        #[cfg(feature = "gui")]
+        if let Some(event_loop) = gui_event_loop {
+            event_loop.run();
+        }
+        handle.wait();
+        Ok(())
+    }
+}
+
+impl AppHandle {
+    /// Ends the game, which stores the session and closes the visualizers,
+    /// without waiting for it (see [`AppHandle::wait`]).
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+
+    /// Stops the target timer and ignores detections until resumed.
+    pub fn pause(&self) -> Result<(), GameError> {
+        self.controller.pause()
+    }
+
+    pub fn resume(&self) -> Result<(), GameError> {
+        self.controller.resume()
+    }
+
+    /// Controller of the running game, e.g. to skip targets.
+    pub fn controller(&self) -> GameController {
+        self.controller.clone()
+    }
+
+    /// Receiver of the targets, results and pauses of the game from now on.
+    pub fn events(&self) -> Subscription<GameEvent> {
+        self.events.subscribe()
+    }
+
+    /// Waits until the game has ended, either by [`AppHandle::stop`] or by
+    /// closing a visualizer, and stops the note source.
+    pub fn wait(mut self) {
+        if let Err(panic) = self.join() {
+            std::panic::resume_unwind(panic);
+        }
+    }
+
+    fn join(&mut self) -> thread::Result<()> {
+        let result = match self.main_loop.take() {
+            Some(main_loop) => main_loop.join(),
+            None => return Ok(()),
+        };
+        self.note_source.stop();
+        // The main loop has closed the GUI when it ended.
+        #[cfg(feature = "gui")]
+        if let Some(gui_thread) = self.gui_thread.take() {
+            if gui_thread.join().is_err() {
+                warn!("GUI thread panicked");
+            }
+        }
+        result
+    }
+}
+
+impl Drop for AppHandle {
+    fn drop(&mut self) {
+        self.stop();
+        // A panic of the main loop was already reported on its thread.
+        let _ = self.join();
+    }
+}
+
+impl MainLoop {
     fn is_running(&self) -> bool {
         self.visualizers.iter().all(|v| v.is_open())
     }
 
-    pub fn run(&mut self) -> Result<(), AppError> {
-        self.audio_stream.play()?;
-        self.game_logic.play()?;
-        while self.is_running() {
-            for visualizer in self.visualizers.iter_mut() {
-                visualizer.draw();
+    fn run(&mut self, stop: &AtomicBool) {
+        let mut last_metrics_log = Instant::now();
+        while self.is_running() && !stop.load(Ordering::Relaxed) {
+            // The visualizers draw on their own threads, so the loop only
+            // wakes up for new game states or to check whether to stop.
+            let states: Vec<GameState> = self
+                .history_rx
+                .recv_timeout(STATE_POLL_PERIOD)
+                .into_iter()
+                .chain(self.history_rx.try_iter())
+                .collect();
+            self.metrics.record_state_backlog(states.len());
+            if let Some(period) = self.metrics_log_period {
+                if last_metrics_log.elapsed() >= period {
+                    info!("Metrics: {}", self.metrics.snapshot());
+                    last_metrics_log = Instant::now();
+                }
+            }
+            self.track_states(states);
+        }
+        // The target being played is left out of the statistics.
+        self.game_logic.stop();
+        let states: Vec<GameState> = self.history_rx.try_iter().collect();
+        self.track_states(states);
+        for visualizer in self.visualizers.iter_mut() {
+            visualizer.finish();
+        }
+        info!("Metrics: {}", self.metrics.snapshot());
+        self.save_history();
+        #[cfg(feature = "sync")]
+        if let Some(sync_cfg) = &self.auto_sync {
+            match sync_files(
+                sync_cfg,
+                self.history_path.as_deref(),
+                self.targets_path.as_deref(),
+            ) {
+                Ok(summary) => info!(
+                    "Synced the history: {} records downloaded, {} uploaded",
+                    summary.downloaded, summary.uploaded
+                ),
+                Err(err) => warn!("Could not sync the history: {}", err),
             }
-            std::thread::sleep(std::time::Duration::from_secs_f64(self.frame_period));
         }
-        Ok(())
+    }
+
+    /// Keeps track of the score and the finished targets, and plays every new
+    /// target if auto play is on.
+    fn track_states(&mut self, states: Vec<GameState>) {
+        for state in states {
+            self.score = state.score.clone();
+            let target_note = state.target_note.clone();
+            for event in self.event_tracker.update(state) {
+                match &event {
+                    GameEvent::Result(result) => self.targets.push(result.clone()),
+                    GameEvent::Target { .. } => {
+                        if let Some(player) = &self.auto_tone_player {
+                            player.play(&target_note);
+                        }
+                    }
+                    _ => {}
+                }
+                self.events.publish(event);
+            }
+        }
+    }
+
+    fn save_history(&self) {
+        if let (Some(path), true) = (&self.history_path, self.score.n_completed > 0) {
+            let record = SessionRecord::new(&self.score, SystemTime::now());
+            if let Err(err) = append_to_history(path, &record) {
+                warn!("Could not save the session to {}: {}", path.display(), err);
+            }
+        }
+        if let (Some(path), false) = (&self.targets_path, self.targets.is_empty()) {
+            let timestamp = SessionRecord::new(&self.score, SystemTime::now()).timestamp;
+            let records: Vec<TargetRecord> = self
+                .targets
+                .iter()
+                .map(|result| TargetRecord {
+                    timestamp,
+                    note: result.note.clone(),
+                    string_idx: result.loc.string_idx,
+                    fret_idx: result.loc.fret_idx,
+                    correct: result.correct,
+                    response_time: result.response_time.as_secs_f64(),
+                })
+                .collect();
+            if let Err(err) = append_targets(path, &records) {
+                warn!("Could not save the targets to {}: {}", path.display(), err);
+            }
+        }
+    }
+}
+
+fn create_console_visualizer(
+    rx: Subscription<GameState>,
+    analysis_rx: Subscription<AnalysisResult>,
+    spectrum_rx: Subscription<Vec<f64>>,
+    fret_range: FretRange,
+    string_range: StringRange,
+    cfg: ConsoleCfg,
+    tuning: Tuning,
+) -> Result<Box<dyn Visualizer + Send>, AppError> {
+    match cfg.frontend {
+        #[cfg(feature = "tui")]
+        ConsoleFrontend::Tui => Ok(Box::new(TuiVisualizer::new(
+            rx,
+            analysis_rx,
+            spectrum_rx,
+            fret_range,
+            string_range,
+            cfg,
+            tuning,
+        )?)),
+        #[cfg(not(feature = "tui"))]
+        ConsoleFrontend::Tui => {
+            warn!("Built without the tui feature. Falling back to the simple console frontend.");
+            Ok(Box::new(ConsoleVisualizer::new(
+                rx,
+                analysis_rx,
+                spectrum_rx,
+                fret_range,
+                string_range,
+                cfg,
+                tuning,
+            )))
+        }
+        ConsoleFrontend::Plain => Ok(Box::new(PlainVisualizer::new(
+            rx,
+            analysis_rx,
+            spectrum_rx,
+            cfg,
+        ))),
+        ConsoleFrontend::Simple => Ok(Box::new(ConsoleVisualizer::new(
+            rx,
+            analysis_rx,
+            spectrum_rx,
+            fret_range,
+            string_range,
+            cfg,
+            tuning,
+        ))),
     }
 }
 
 #[cfg(feature = "gui")]
 fn add_gui_visualizer(
-    mut visualizers: Vec<Box<dyn Visualizer>>,
-    n_bins: usize,
-    delta_f: f64,
-    gui_rx: mpsc::Receiver<FrameData>,
+    mut visualizers: Vec<ThreadedVisualizer>,
+    analyzer: &AudioAnalyzer,
+    channels: GuiChannels,
+    game_logic: &GameLogic,
+    history: Vec<SessionRecord>,
+    keys: &KeysCfg,
     cfg: GuiCfg,
-) -> Vec<Box<dyn Visualizer>> {
-    let xaxis_props = (0.0, n_bins as f64 / delta_f, delta_f);
-    let gui_visualizer = GUIVisualizer::new(gui_rx, xaxis_props, cfg);
-    visualizers.push(Box::new(gui_visualizer));
-    visualizers
+) -> (Vec<ThreadedVisualizer>, Option<EventLoop>) {
+    let delta_f = analyzer.delta_f();
+    let xaxis_props = (0.0, analyzer.n_bins() as f64 / delta_f, delta_f);
+    let audio_params = analyzer.params();
+    let keys = keys.clone();
+    let ranges = (
+        game_logic.fret_range().clone(),
+        game_logic.string_range().clone(),
+    );
+    let (gui_visualizer, event_loop) = ThreadedVisualizer::event_loop(move |stop| {
+        GUIVisualizer::new(
+            channels,
+            xaxis_props,
+            audio_params,
+            ranges,
+            history,
+            &keys,
+            cfg,
+        )
+        .run(stop)
+    });
+    visualizers.push(gui_visualizer);
+    (visualizers, Some(event_loop))
 }
 
-type CallbackFn = dyn for<'a> FnMut(Box<dyn ExactSizeIterator<Item = f64> + 'a>) + Send;
+/// Runs the GUI on a new thread, which winit only allows on Linux and
+/// Windows. Elsewhere the game is played without it.
+#[cfg(feature = "gui")]
+fn spawn_gui(event_loop: EventLoop) -> Option<JoinHandle<()>> {
+    if cfg!(any(target_os = "linux", windows)) {
+        Some(thread::spawn(move || event_loop.run()))
+    } else {
+        warn!("The GUI can only be opened on the main thread here, see App::run");
+        None
+    }
+}
 
-fn create_audio_stream(
-    device: Device,
-    device_config: StreamConfig,
-    block_size: usize,
-    mut callback: Box<CallbackFn>,
-) -> Result<Stream, BuildStreamError> {
+#[cfg(feature = "gui")]
+fn load_gui_history(history_path: &Option<PathBuf>) -> Vec<SessionRecord> {
+    let path = match history_path {
+        Some(path) => path,
+        None => return Vec::new(),
+    };
+    load_history(path).unwrap_or_else(|err| {
+        warn!(
+            "Could not load the history from {}: {}",
+            path.display(),
+            err
+        );
+        Vec::new()
+    })
+}
+
+/// Starts the metronome on the default output device, publishing its beats to
+/// `beats`. The game is played without it if it can't be started.
+fn start_metronome(cfg: &MetronomeCfg, beats: Topic<Beat>) -> Option<Metronome> {
+    let device = default_output_device("metronome")?;
+    Metronome::start(&device, cfg, move |beat| {
+        beats.publish(beat);
+    })
+    .map_err(|err| warn!("Could not start the metronome: {}", err))
+    .ok()
+}
+
+/// Opens the default output device for the reference tones. The game is
+/// played without them if it can't be opened.
+fn start_reference_tone(cfg: &ReferenceToneCfg) -> Option<ReferenceTone> {
+    let device = default_output_device("reference tones")?;
+    ReferenceTone::start(&device, cfg)
+        .map_err(|err| warn!("Could not start the reference tones: {}", err))
+        .ok()
+}
+
+/// Raises `stop` on the first Ctrl-C or termination signal, so that the game
+/// is stopped and saved in order. A second signal exits right away.
+fn handle_term_signals(stop: &Arc<AtomicBool>) -> std::io::Result<()> {
+    for &signal in TERM_SIGNALS {
+        signal_hook::flag::register_conditional_shutdown(signal, 1, stop.clone())?;
+        signal_hook::flag::register(signal, stop.clone())?;
+    }
+    Ok(())
+}
+
+fn default_output_device(user: &str) -> Option<Device> {
+    let device = cpal::default_host().default_output_device();
+    if device.is_none() {
+        warn!("No output device for the {}", user);
+    }
+    device
+}
+
+/// Analysis of the last block of samples, given along with the number of
+/// samples that are new since the previous call.
+pub(crate) type CallbackFn =
+    dyn for<'a> FnMut(Box<dyn ExactSizeIterator<Item = f64> + 'a>, usize) + Send;
+
+/// Selector of the game mode script configured in `game_cfg`, or of random
+/// targets if there is none.
+#[cfg(feature = "plugins")]
+fn game_mode_selector(game_cfg: &GameCfg) -> Result<Box<dyn TargetSelector>, PluginError> {
+    match &game_cfg.game_mode {
+        Some(game_mode) => {
+            let plugins_dir = game_cfg
+                .plugins_dir
+                .as_deref()
+                .unwrap_or_else(|| Path::new("."));
+            let selector = ScriptSelector::load(plugins_dir, game_mode)?;
+            info!("Playing the {} game mode", game_mode);
+            Ok(Box::new(selector))
+        }
+        None => Ok(Box::new(RandomSelector::default())),
+    }
+}
+
+#[cfg(not(feature = "plugins"))]
+fn game_mode_selector(_: &GameCfg) -> Result<Box<dyn TargetSelector>, AppError> {
+    Ok(Box::new(RandomSelector::default()))
+}
+
+/// Seconds of samples kept for the analysis when it falls behind the audio.
+const MAX_BUFFERED_SECONDS: usize = 1;
+
+/// Audio side of the lock-free ring buffer of the samples to analyze.
+struct SampleWriter {
+    /// Dropped before waking up the reader so that it sees the end.
+    producer: Option<Producer<f32>>,
+    reader: Thread,
+}
+
+impl SampleWriter {
+    /// Writes the mixdown of `channels` in the interleaved `data` and wakes up
+    /// the reader, returning whether all of them fit in the buffer. Does not
+    /// allocate or block.
+    fn write(&mut self, data: &[f32], n_channels: usize, channels: &[usize]) -> bool {
+        let samples = data.chunks_exact(n_channels).map(|frame| {
+            channels.iter().map(|&channel| frame[channel]).sum::<f32>() / channels.len() as f32
+        });
+        let n_samples = samples.len();
+        let n_written = match &mut self.producer {
+            Some(producer) => match producer.write_chunk_uninit(n_samples.min(producer.slots())) {
+                Ok(chunk) => chunk.fill_from_iter(samples),
+                Err(_) => 0,
+            },
+            None => 0,
+        };
+        self.reader.unpark();
+        n_written == n_samples
+    }
+}
+
+impl Drop for SampleWriter {
+    fn drop(&mut self) {
+        self.producer = None;
+        self.reader.unpark();
+    }
+}
+
+/// Reads the samples from `consumer` until the writer is dropped, calling
+/// `callback` with the last `block_size` samples and the number of samples
+/// read after every read.
+fn read_samples(mut consumer: Consumer<f32>, block_size: usize, mut callback: Box<CallbackFn>) {
     let mut audio_buffer = VecDeque::from(vec![0.0f64; block_size]);
     audio_buffer.shrink_to_fit();
-    let n_channels = device_config.channels as usize;
-    // TODO: get from user
-    let listened_channel = 0;
-    device.build_input_stream(
-        &device_config,
-        move |data: &[f32], _: &cpal::InputCallbackInfo| {
-            read_channel_buffered(data, n_channels, listened_channel, &mut audio_buffer);
-            callback(Box::new(audio_buffer.iter().cloned()));
-        },
-        move |_err| {
-            // Mainly happens if we miss some audio frames.
-            info!("Error reading data from device {}", _err);
-        },
-    )
-}
-
-fn read_channel_buffered(
-    data: &[f32],
-    n_channels: usize,
-    channel: usize,
-    buffer: &mut VecDeque<f64>,
-) {
-    let channel_indices = (channel..data.len()).step_by(n_channels);
-    let n_new_values = channel_indices.len();
-    if n_new_values >= buffer.len() {
-        buffer.clear();
-    } else {
-        for _ in 0..n_new_values {
-            buffer.pop_front();
+    loop {
+        let n_samples = consumer.slots();
+        if n_samples == 0 {
+            if consumer.is_abandoned() {
+                return;
+            }
+            thread::park();
+            continue;
         }
+        let chunk = consumer.read_chunk(n_samples).unwrap();
+        let (first, second) = chunk.as_slices();
+        read_channel_buffered(first, 1, 0, &mut audio_buffer);
+        read_channel_buffered(second, 1, 0, &mut audio_buffer);
+        chunk.commit_all();
+        callback(Box::new(audio_buffer.iter().cloned()), n_samples);
     }
-    for i in channel_indices {
-        buffer.push_back(data[i] as f64);
+}
+
+/// Indices of the `input_channels` counted from 1, or of the first channel
+/// if none are given.
+fn channel_indices(input_channels: &[usize], n_channels: usize) -> Result<Vec<usize>, AppError> {
+    if input_channels.is_empty() {
+        return Ok(vec![0]);
     }
+    input_channels
+        .iter()
+        .map(|&channel| {
+            if channel >= 1 && channel <= n_channels {
+                Ok(channel - 1)
+            } else {
+                Err(AppError::InvalidChannel {
+                    channel,
+                    n_channels,
+                })
+            }
+        })
+        .collect()
+}
+
+/// Callbacks of the note source passing the mixdown of the `input_channels`
+/// through a ring buffer to a new analysis thread, so that the audio thread
+/// neither allocates nor waits for the analysis.
+pub(crate) fn create_source_callbacks(
+    sample_rate: usize,
+    n_channels: usize,
+    input_channels: &[usize],
+    block_size: usize,
+    callback: Box<CallbackFn>,
+    metrics: Arc<Metrics>,
+) -> Result<(Box<SampleCallback>, Box<SourceErrorCallback>), AppError> {
+    let listened_channels = channel_indices(input_channels, n_channels)?;
+    let error_metrics = metrics.clone();
+    let (producer, consumer) = RingBuffer::new(sample_rate * MAX_BUFFERED_SECONDS);
+    let reader = thread::spawn(move || read_samples(consumer, block_size, callback));
+    let mut writer = SampleWriter {
+        producer: Some(producer),
+        reader: reader.thread().clone(),
+    };
+    let sample_callback: Box<SampleCallback> = Box::new(move |data: &[f32]| {
+        let start = Instant::now();
+        if !writer.write(data, n_channels, &listened_channels) {
+            // The analysis fell behind and the samples were dropped.
+            metrics.record_stream_error();
+        }
+        metrics.record_callback(start.elapsed());
+    });
+    let error_callback: Box<SourceErrorCallback> = Box::new(move |err: String| {
+        // Mainly happens if we miss some audio frames.
+        error_metrics.record_stream_error();
+        info!("Error reading data from the note source {}", err);
+    });
+    Ok((sample_callback, error_callback))
 }
 
 #[cfg(test)]
 mod game_tests {
     use super::*;
-    #[test]
-    fn read_channel_buffered_empty_buffer_empty_data() {
-        let mut buffer = VecDeque::new();
-        let data = Vec::new();
-        read_channel_buffered(&data, 2, 0, &mut buffer);
-        assert_eq!(0, buffer.len());
-    }
-
-    #[test]
-    fn read_channel_buffered_empty_data() {
-        let mut buffer = VecDeque::from(vec![1.0f64; 64]);
-        let expected = buffer.clone();
-        let data = Vec::new();
-        read_channel_buffered(&data, 3, 1, &mut buffer);
-        assert_eq!(expected, buffer);
-    }
+    use std::sync::mpsc;
 
     #[test]
-    fn read_channel_buffered_empty_buffer() {
-        let mut buffer = VecDeque::new();
-        let data: Vec<f32> = (0..100).map(|x| x as f32).collect();
-        let expected: VecDeque<f64> = data.iter().cloned().step_by(2).map(|x| x as f64).collect();
-        read_channel_buffered(&data, 2, 0, &mut buffer);
-        assert_eq!(expected, buffer);
+    fn sample_ring_buffer() {
+        let (producer, consumer) = RingBuffer::new(4);
+        let (block_tx, block_rx) = mpsc::channel();
+        let reader = thread::spawn(move || {
+            read_samples(
+                consumer,
+                3,
+                Box::new(move |data: Box<dyn ExactSizeIterator<Item = f64>>, _| {
+                    block_tx.send(data.collect::<Vec<f64>>()).unwrap()
+                }),
+            )
+        });
+        let mut writer = SampleWriter {
+            producer: Some(producer),
+            reader: reader.thread().clone(),
+        };
+        assert!(writer.write(&[1.0, -1.0, 2.0, -2.0], 2, &[0]));
+        assert_eq!(vec![0.0, 1.0, 2.0], block_rx.recv().unwrap());
+        drop(writer);
+        reader.join().unwrap();
+        assert_eq!(0, block_rx.try_iter().count());
     }
 
     #[test]
-    fn read_channel_buffered_less_data_than_buffer() {
-        let mut buffer = VecDeque::from(vec![5000.0f64; 200]);
-        let data: Vec<f32> = (0..100).map(|x| x as f32).collect();
-        let expected: VecDeque<f64> = buffer
-            .iter()
-            .cloned()
-            .skip(50)
-            .chain(data.iter().cloned().step_by(2).map(|x| x as f64))
-            .collect();
-        read_channel_buffered(&data, 2, 0, &mut buffer);
-        assert_eq!(expected, buffer);
+    fn sample_ring_buffer_overflow() {
+        let (producer, _consumer) = RingBuffer::new(2);
+        let mut writer = SampleWriter {
+            producer: Some(producer),
+            reader: thread::current(),
+        };
+        assert!(!writer.write(&[1.0, 2.0, 3.0], 1, &[0]));
     }
 
     #[test]
-    fn read_channel_buffered_same_data_as_buffer() {
-        let mut buffer = VecDeque::from(vec![5000.0f64; 200]);
-        let data: Vec<f32> = (0..200).map(|x| x as f32).collect();
-        let expected: VecDeque<f64> = data.iter().cloned().map(|x| x as f64).collect();
-        read_channel_buffered(&data, 1, 0, &mut buffer);
-        assert_eq!(expected, buffer);
+    fn sample_writer_mixdown() {
+        let (producer, mut consumer) = RingBuffer::new(4);
+        let mut writer = SampleWriter {
+            producer: Some(producer),
+            reader: thread::current(),
+        };
+        assert!(writer.write(&[1.0, 3.0, 9.0, -2.0, 4.0, 9.0], 3, &[0, 1]));
+        assert!(writer.write(&[1.0, 3.0, 9.0], 3, &[2]));
+        let chunk = consumer.read_chunk(3).unwrap();
+        assert_eq!(vec![2.0, 1.0, 9.0], chunk.into_iter().collect::<Vec<f32>>());
     }
 
     #[test]
-    fn read_channel_buffered_more_data_than_buffer() {
-        let mut buffer = VecDeque::from(vec![5000.0f64; 50]);
-        let data: Vec<f32> = (0..200).map(|x| x as f32).collect();
-        let expected: VecDeque<f64> = data.iter().cloned().map(|x| x as f64).collect();
-        read_channel_buffered(&data, 1, 0, &mut buffer);
-        assert_eq!(expected, buffer);
+    fn test_channel_indices() {
+        assert_eq!(vec![0], channel_indices(&[], 2).unwrap());
+        assert_eq!(vec![1, 0], channel_indices(&[2, 1], 2).unwrap());
+        match channel_indices(&[1, 3], 2) {
+            Err(AppError::InvalidChannel {
+                channel: 3,
+                n_channels: 2,
+            }) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
     }
 }
@@ -1,7 +0,0 @@
-mod algorithm;
-mod analysis_result;
-mod analyzer;
-mod target_notes;
-
-pub use analysis_result::AnalysisResult;
-pub use analyzer::AudioAnalyzer;
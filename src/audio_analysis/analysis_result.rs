@@ -1,5 +0,0 @@
-use crate::core::Note;
-
-pub struct AnalysisResult {
-    pub note: Option<Note>,
-}
@@ -0,0 +1,66 @@
+use cpal::traits::{DeviceTrait, StreamTrait};
+use cpal::{
+    BuildStreamError, DefaultStreamConfigError, Device, PlayStreamError, Sample, SampleFormat,
+    Stream, StreamConfig,
+};
+use log::*;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum OutputStreamError {
+    #[error(transparent)]
+    DefaultStreamConfigError(#[from] DefaultStreamConfigError),
+    #[error(transparent)]
+    BuildStreamError(#[from] BuildStreamError),
+    #[error(transparent)]
+    PlayStreamError(#[from] PlayStreamError),
+}
+
+/// Plays the samples of a mono source on all channels of the default output
+/// config of `device` until the returned stream is dropped. `make_source`
+/// receives the sample rate and returns the function producing the samples,
+/// which runs on the audio thread.
+pub fn start_output_stream<F>(
+    device: &Device,
+    make_source: impl FnOnce(f64) -> F,
+) -> Result<Stream, OutputStreamError>
+where
+    F: FnMut() -> f64 + Send + 'static,
+{
+    let supported_config = device.default_output_config()?;
+    let sample_format = supported_config.sample_format();
+    let config: StreamConfig = supported_config.into();
+    let source = make_source(config.sample_rate.0 as f64);
+    let stream = match sample_format {
+        SampleFormat::F32 => build_stream::<f32, F>(device, &config, source),
+        SampleFormat::I16 => build_stream::<i16, F>(device, &config, source),
+        SampleFormat::U16 => build_stream::<u16, F>(device, &config, source),
+    }?;
+    stream.play()?;
+    Ok(stream)
+}
+
+fn build_stream<T: Sample, F>(
+    device: &Device,
+    config: &StreamConfig,
+    mut source: F,
+) -> Result<Stream, BuildStreamError>
+where
+    F: FnMut() -> f64 + Send + 'static,
+{
+    let n_channels = config.channels as usize;
+    device.build_output_stream(
+        config,
+        move |data: &mut [T], _: &cpal::OutputCallbackInfo| {
+            for frame in data.chunks_mut(n_channels) {
+                let value = T::from(&(source() as f32));
+                for out in frame.iter_mut() {
+                    *out = value;
+                }
+            }
+        },
+        move |err| {
+            info!("Error writing to the output device: {}", err);
+        },
+    )
+}
@@ -0,0 +1,81 @@
+use crate::app::{create_source_callbacks, AppError, CallbackFn};
+use crate::audio_analysis::{AudioAnalyzer, NoiseFloor};
+use crate::core::{CalibrationCfg, Cfg};
+use crate::metrics::Metrics;
+use crate::note_source::NoteSource;
+use log::*;
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Width of the frequency bands of the noise floor in hertz, about the
+/// distance between the two lowest notes of a guitar in standard tuning.
+const BAND_WIDTH: f64 = 5.0;
+
+/// Measures the noise floor of `note_source` while nothing is played and
+/// saves it to the path of the `[calibration]` table.
+pub fn calibrate(note_source: &mut dyn NoteSource, cfg: &Cfg) -> Result<NoiseFloor, AppError> {
+    let calibration = &cfg.app.calibration;
+    let note_registry = cfg.app.note_registry()?;
+    let mut analyzer = AudioAnalyzer::new(
+        note_source.sample_rate(),
+        note_registry.notes(),
+        cfg.audio.clone(),
+    )?;
+    let delta_f = analyzer.delta_f();
+    let (spectrum_tx, spectrum_rx) = mpsc::channel();
+    let analysis_callback: Box<CallbackFn> = Box::new(
+        move |data: Box<dyn ExactSizeIterator<Item = f64>>, n_new_samples| {
+            analyzer.identify_next_note(data, n_new_samples);
+            let _ = spectrum_tx.send(analyzer.spectrogram().clone());
+        },
+    );
+    let (sample_callback, error_callback) = create_source_callbacks(
+        note_source.sample_rate(),
+        note_source.n_channels(),
+        &cfg.app.input_channels,
+        cfg.app.block_size,
+        analysis_callback,
+        Arc::new(Metrics::default()),
+    )?;
+    info!("Measuring the noise floor for {:.0}s", calibration.duration);
+    note_source.start(sample_callback, error_callback)?;
+    let mut noise_floor = NoiseFloor::new(BAND_WIDTH);
+    let mut n_spectra = 0;
+    let end = Instant::now() + Duration::from_secs_f64(calibration.duration);
+    while let Some(timeout) = end.checked_duration_since(Instant::now()) {
+        match spectrum_rx.recv_timeout(timeout) {
+            Ok(spectrum) => {
+                noise_floor.add_spectrum(&spectrum, delta_f);
+                n_spectra += 1;
+            }
+            Err(_) => break,
+        }
+    }
+    note_source.stop();
+    info!("Measured the noise floor in {} spectra", n_spectra);
+    noise_floor.save(&calibration.path)?;
+    info!("Saved the noise floor to {}", calibration.path.display());
+    Ok(noise_floor)
+}
+
+/// Noise floor saved by the last calibration, if there is one.
+pub fn load_noise_floor(cfg: &CalibrationCfg) -> Option<NoiseFloor> {
+    if !cfg.path.is_file() {
+        return None;
+    }
+    match NoiseFloor::load(&cfg.path) {
+        Ok(noise_floor) => {
+            info!("Using the noise floor in {}", cfg.path.display());
+            Some(noise_floor)
+        }
+        Err(err) => {
+            warn!(
+                "Could not load the noise floor from {}: {}",
+                cfg.path.display(),
+                err
+            );
+            None
+        }
+    }
+}
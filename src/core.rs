@@ -1,18 +1,6 @@
+//! The music model of `libreguitar-core` and the config of the app.
+
 mod cfg;
-mod csv;
-mod fret_loc;
-mod fret_range;
-mod note;
-mod note_name;
-mod note_registry;
-mod string_range;
-mod tuning;
 
 pub use cfg::*;
-pub use fret_loc::FretLoc;
-pub use fret_range::FretRange;
-pub use note::Note;
-pub use note_name::NoteName;
-pub use note_registry::NoteRegistry;
-pub use string_range::StringRange;
-pub use tuning::{Tuning, TuningSpecification};
+pub use libreguitar_core::core::*;
@@ -1,21 +1,436 @@
+use crate::core::{AudioCfg, GameCfg, NoteRegistry, NoteRegistryError};
 #[cfg(feature = "gui")]
 use crate::visualization::GuiCfg;
 use config::{Config, ConfigError, File};
 use serde::de::DeserializeOwned;
 use serde::Deserialize;
-use std::path::Path;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+
+/// Key holding a list of config files to merge before the file itself.
+const INCLUDE_KEY: &str = "include";
 
 #[derive(Debug, Deserialize)]
 pub struct AppCfg {
     pub fps: f64,
-    pub frequencies_path: String,
-    pub tuning_path: String,
+    pub frequencies_path: PathBuf,
+    /// Pitch of A4 in hertz the note frequencies are generated from in equal
+    /// temperament instead of reading them from `frequencies_path`.
+    #[serde(default)]
+    pub a4_reference: Option<f64>,
+    pub tuning_path: PathBuf,
+    /// Compact tuning such as `"DADGAD"` or `"D2 A2 D3 G3 A3 D4"`, from the
+    /// lowest to the highest string. Overrides the tuning file if given.
+    #[serde(default)]
+    pub tuning: Option<String>,
     pub block_size: usize,
+    /// Channels of the input device to analyze, counted from 1. Several
+    /// channels are mixed down to their average, e.g. for stereo DI setups.
+    /// Asked for during the device setup if not given, and the first channel
+    /// is analyzed if left empty elsewhere.
+    #[serde(default)]
+    pub input_channels: Vec<usize>,
     pub log_path: String,
+    /// JSON Lines file the practice log is appended to. Nothing is logged if
+    /// missing.
+    pub practice_log_path: Option<PathBuf>,
+    /// CSV file the statistics of every session are appended to. Sessions
+    /// are not recorded if missing.
+    pub history_path: Option<PathBuf>,
+    /// CSV file the outcome of every target is appended to, for the per-note
+    /// statistics of `stats export`. Targets are not recorded if missing.
+    pub targets_path: Option<PathBuf>,
+    #[serde(default)]
+    pub keys: KeysCfg,
+    #[serde(default)]
+    pub midi: MidiCfg,
+    /// Named pipe the game commands are read from. Not created if missing,
+    /// and rejected on systems other than Unix.
+    #[serde(default)]
+    pub control_pipe: Option<PathBuf>,
+    #[serde(default)]
+    pub metronome: MetronomeCfg,
+    #[serde(default)]
+    pub reference_tone: ReferenceToneCfg,
+    #[serde(default)]
+    pub metrics: MetricsCfg,
+    #[serde(default)]
+    pub calibration: CalibrationCfg,
+    /// OSC messages are only sent if this table is given.
+    pub osc: Option<OscCfg>,
+    /// The WebSocket server is only started if this table is given.
+    #[cfg(feature = "websocket")]
+    pub websocket: Option<WebSocketCfg>,
+    /// The stream overlay is only written if this table is given.
+    pub overlay: Option<OverlayCfg>,
+    /// The HTTP API is only served if this table is given.
+    #[cfg(feature = "http_api")]
+    pub http_api: Option<HttpApiCfg>,
+    /// The history is only synced if this table is given.
+    #[cfg(feature = "sync")]
+    pub sync: Option<SyncCfg>,
+}
+
+/// Files with the game state for overlays of streaming software such as OBS.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct OverlayCfg {
+    /// Directory of `overlay.txt`, `overlay.json` and `overlay.html`.
+    pub dir: PathBuf,
+    /// Also write a self-refreshing page for browser sources.
+    pub write_html: bool,
+}
+
+/// Octaves of the generated note table, the same as of the bundled
+/// `guitar_frequencies.csv`.
+const GENERATED_OCTAVES: RangeInclusive<i32> = 2..=6;
+
+impl AppCfg {
+    /// Notes generated from `a4_reference` if given, or read from
+    /// `frequencies_path` otherwise.
+    pub fn note_registry(&self) -> Result<NoteRegistry, NoteRegistryError> {
+        match self.a4_reference {
+            Some(a4) => Ok(NoteRegistry::equal_temperament(a4, GENERATED_OCTAVES)),
+            None => NoteRegistry::from_csv(&self.frequencies_path),
+        }
+    }
+}
+
+impl Default for OverlayCfg {
+    fn default() -> Self {
+        OverlayCfg {
+            dir: PathBuf::from("~/.libreguitar_overlay"),
+            write_html: true,
+        }
+    }
+}
+
+/// Embedded WebSocket server streaming the game events and detections as
+/// JSON to remote displays.
+#[cfg(feature = "websocket")]
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct WebSocketCfg {
+    /// Address and TCP port the server listens on.
+    pub address: String,
+    /// Also send every detected note, not only the game events.
+    pub send_detections: bool,
+}
+
+#[cfg(feature = "websocket")]
+impl Default for WebSocketCfg {
+    fn default() -> Self {
+        WebSocketCfg {
+            address: String::from("0.0.0.0:8080"),
+            send_detections: true,
+        }
+    }
+}
+
+/// Local HTTP API for querying and controlling the game.
+#[cfg(feature = "http_api")]
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct HttpApiCfg {
+    /// Address and TCP port the API listens on. Anyone who can reach it can
+    /// control the game, so it is bound to the local host by default.
+    pub address: String,
+}
+
+#[cfg(feature = "http_api")]
+impl Default for HttpApiCfg {
+    fn default() -> Self {
+        HttpApiCfg {
+            address: String::from("127.0.0.1:8081"),
+        }
+    }
+}
+
+/// Server the session history and targets are synced with, so that they
+/// follow the player between computers.
+#[cfg(feature = "sync")]
+#[derive(Debug, Deserialize, Clone)]
+pub struct SyncCfg {
+    /// Base URL of the server, e.g. `https://example.com/libreguitar`.
+    pub url: String,
+    /// Sent as a bearer token with every request if given.
+    #[serde(default)]
+    pub token: Option<String>,
+    /// Sync at the end of every session instead of only with `libreguitar sync`.
+    #[serde(default)]
+    pub auto_sync: bool,
+}
+
+/// Publishing of the game events and detections as OSC messages.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct OscCfg {
+    /// Host and UDP port the messages are sent to, e.g. `127.0.0.1:9000`.
+    pub address: String,
+    /// Address prefix of the messages, e.g. `/libreguitar/target`.
+    pub prefix: String,
+    /// Also send every detected note, not only the game events.
+    pub send_detections: bool,
+}
+
+impl Default for OscCfg {
+    fn default() -> Self {
+        OscCfg {
+            address: String::from("127.0.0.1:9000"),
+            prefix: String::from("/libreguitar"),
+            send_detections: true,
+        }
+    }
+}
+
+/// MIDI message type a game command can be bound to.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MidiMessageKind {
+    /// Control change with the given controller number, triggered when its
+    /// value rises from below 64 to 64 or above, i.e. once per press of a
+    /// foot switch.
+    ControlChange,
+    /// Program change to the given program number.
+    ProgramChange,
+}
+
+/// Game command triggered by a MIDI message.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MidiCommand {
+    Skip,
+    /// Pauses the game or resumes it if it is paused.
+    Pause,
+    /// Switches the targets to the next range preset of the game config.
+    NextPreset,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct MidiBinding {
+    pub message: MidiMessageKind,
+    /// Controller or program number.
+    pub number: u8,
+    /// Channel from 1 to 16. Messages on all channels match if missing.
+    pub channel: Option<u8>,
+    pub command: MidiCommand,
+}
+
+/// Metronome clicking on the default output device, either during the game
+/// or standalone with `libreguitar metronome`.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct MetronomeCfg {
+    /// Click along while playing the game.
+    pub enabled: bool,
+    /// Beats per minute.
+    pub tempo: f64,
+    pub beats_per_bar: usize,
+    /// Beats of the bar that are accented, counted from 1.
+    pub accents: Vec<usize>,
+    /// Loudness of the accented clicks between 0 and 1. The other clicks
+    /// are played at 60% of it.
+    pub volume: f64,
+}
+
+impl Default for MetronomeCfg {
+    fn default() -> Self {
+        MetronomeCfg {
+            enabled: false,
+            tempo: 80.0,
+            beats_per_bar: 4,
+            accents: vec![1],
+            volume: 0.5,
+        }
+    }
+}
+
+/// Reporting of the performance counters.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct MetricsCfg {
+    /// Seconds between writing the counters to the log. 0 only writes them
+    /// at the end of the session.
+    pub log_period: f64,
+    /// Address and TCP port the counters are served on for Prometheus. Not
+    /// served if missing.
+    pub prometheus_address: Option<String>,
+}
+
+impl Default for MetricsCfg {
+    fn default() -> Self {
+        MetricsCfg {
+            log_period: 60.0,
+            prometheus_address: None,
+        }
+    }
+}
+
+/// Measurement of the noise floor, either with `libreguitar calibrate` or
+/// every time the game starts.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct CalibrationCfg {
+    /// File the noise floor is saved to and loaded from when the game starts.
+    pub path: PathBuf,
+    /// Seconds of silence the noise floor is measured over.
+    pub duration: f64,
+    /// Measure the noise floor before every game.
+    pub on_start: bool,
+}
+
+impl Default for CalibrationCfg {
+    fn default() -> Self {
+        CalibrationCfg {
+            path: PathBuf::from("~/.libreguitar_noise_floor.json"),
+            duration: 3.0,
+            on_start: false,
+        }
+    }
+}
+
+/// Sound of the reference tones.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Waveform {
+    Sine,
+    /// Plucked string synthesized with the Karplus-Strong algorithm.
+    Pluck,
+}
+
+/// Playback of the target pitch on the default output device, for ear
+/// training and checking the intonation by ear.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct ReferenceToneCfg {
+    /// Open the output device so that the GUI can play the target on demand.
+    pub enabled: bool,
+    /// Play every new target as soon as it is shown.
+    pub auto_play: bool,
+    pub waveform: Waveform,
+    /// Length of a tone in seconds.
+    pub duration: f64,
+    /// Loudness between 0 and 1.
+    pub volume: f64,
+}
+
+impl Default for ReferenceToneCfg {
+    fn default() -> Self {
+        ReferenceToneCfg {
+            enabled: false,
+            auto_play: false,
+            waveform: Waveform::Pluck,
+            duration: 2.0,
+            volume: 0.5,
+        }
+    }
+}
+
+/// Game control from a MIDI controller such as a foot switch.
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct MidiCfg {
+    /// Raw MIDI device the messages are read from, e.g. `/dev/snd/midiC1D0`.
+    /// MIDI control is disabled if missing.
+    pub device: Option<PathBuf>,
+    pub bindings: Vec<MidiBinding>,
+}
+
+/// Keyboard shortcuts of the GUI, given as key names such as `"n"`,
+/// `"space"` or `"f1"`.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct KeysCfg {
+    pub quit: String,
+    pub pause: String,
+    pub skip: String,
+    /// Shows or hides the current target on the fretboard.
+    pub hint: String,
+    pub view: String,
+    pub theme: String,
+    pub average: String,
+    pub peak_hold: String,
+    pub screenshot: String,
+    /// Plays the pitch of the current target.
+    pub reference_tone: String,
+    /// Switches the statistic shown on the fretboard heatmap.
+    pub heatmap: String,
+    /// Zoom and pan of the spectrum frequency axis.
+    pub zoom_in: String,
+    pub zoom_out: String,
+    pub zoom_reset: String,
+    pub pan_left: String,
+    pub pan_right: String,
+}
+
+impl Default for KeysCfg {
+    fn default() -> Self {
+        KeysCfg {
+            quit: String::from("q"),
+            pause: String::from("space"),
+            skip: String::from("n"),
+            hint: String::from("i"),
+            view: String::from("v"),
+            theme: String::from("t"),
+            average: String::from("a"),
+            peak_hold: String::from("h"),
+            screenshot: String::from("p"),
+            reference_tone: String::from("r"),
+            heatmap: String::from("m"),
+            zoom_in: String::from("equal"),
+            zoom_out: String::from("minus"),
+            zoom_reset: String::from("0"),
+            pan_left: String::from("comma"),
+            pan_right: String::from("period"),
+        }
+    }
+}
+
+/// Console styles given as dot separated `console` crate style strings,
+/// e.g. `"green.bold"` or `"dim"`.
+#[derive(Debug, Deserialize)]
+pub struct ConsoleTheme {
+    pub target: String,
+    pub success: String,
+    pub failure: String,
+    pub fretboard: String,
+}
+
+/// Which location is marked on the console fretboard.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum HintMode {
+    /// Mark the previously played target.
+    Previous,
+    /// Mark the current target once the hint delay has passed.
+    Target,
+}
+
+/// How the target note is presented to the player.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PromptStyle {
+    /// Name the note and the string to play it on.
+    Fret,
+    /// Draw the note on a staff for sight-reading practice.
+    Staff,
+}
+
+/// Terminal frontend used to display the game.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ConsoleFrontend {
+    /// Clear the screen and print the game state on every update.
+    Simple,
+    /// Full screen panels; requires the `tui` feature.
+    Tui,
+    /// One plain line per prompt and result, for screen readers and logs.
+    Plain,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct ConsoleCfg {
+    pub frontend: ConsoleFrontend,
     pub fret_size: usize,
     pub string_char: String,
     pub fret_char: String,
@@ -24,24 +439,30 @@ pub struct ConsoleCfg {
     pub open_sep_str: String,
     pub frets_to_number: Vec<usize>,
     pub n_space_between_strings: usize,
-}
-
-#[derive(Debug, Deserialize)]
-pub struct AudioCfg {
-    pub fft_res_factor: f64,
-    pub fft_magnitude_gain: f64,
-    pub peak_threshold: f64,
-    pub min_peak_dist: usize,
-    pub num_top_peaks: usize,
-    pub moving_avg_window_size: usize,
-}
-
-#[derive(Debug, Deserialize)]
-pub struct GameCfg {
-    pub fret_range: (usize, usize),
-    pub string_range: (usize, usize),
-    pub note_count_for_acceptance: usize,
-    pub state_update_period: usize,
+    pub label_notes: bool,
+    pub left_handed: bool,
+    pub proportional_frets: bool,
+    pub marker_frames: Vec<String>,
+    pub marker_frame_duration: f64,
+    pub completed_char: String,
+    pub wrong_char: String,
+    pub prompt: PromptStyle,
+    pub staff_key_sharps: usize,
+    pub hint_mode: HintMode,
+    pub hint_delay: f64,
+    pub tuner_half_width: usize,
+    pub spectrum_width: usize,
+    pub spectrum_max_freq: f64,
+    pub spectrum_max_magnitude: f64,
+    pub flash_duration: f64,
+    pub history_length: usize,
+    pub ascii: bool,
+    pub bell_on_success: bool,
+    pub bell_on_failure: bool,
+    /// Draw the console as soon as there is a new target, result or pause
+    /// instead of at the next frame.
+    pub redraw_on_events: bool,
+    pub theme: ConsoleTheme,
 }
 
 #[derive(Debug, Deserialize)]
@@ -54,30 +475,306 @@ pub struct Cfg {
     pub gui: GuiCfg,
 }
 
-fn get_cfg<T>(path: &str) -> Result<T, ConfigError>
+/// Merges the config file at `path` into `cfg`.
+///
+/// Files listed under the `include` key are merged first, so that the
+/// including file overrides the values it inherits. Include paths are
+/// resolved relative to the directory of the including file.
+fn merge_with_includes(
+    cfg: &mut Config,
+    path: &Path,
+    visited: &mut Vec<PathBuf>,
+) -> Result<(), ConfigError> {
+    if visited.iter().any(|p| p == path) {
+        return Err(ConfigError::Message(format!(
+            "Config include cycle detected at {}",
+            path.display()
+        )));
+    }
+    visited.push(path.to_path_buf());
+
+    let mut own_cfg = Config::default();
+    own_cfg.merge(File::from(path))?;
+    let includes: Vec<String> = match own_cfg.get(INCLUDE_KEY) {
+        Ok(includes) => includes,
+        Err(ConfigError::NotFound(_)) => Vec::new(),
+        Err(e) => return Err(e),
+    };
+    let parent_dir = path.parent().unwrap_or_else(|| Path::new(""));
+    for include in includes.iter() {
+        merge_with_includes(cfg, &parent_dir.join(include), visited)?;
+    }
+    cfg.merge(own_cfg)?;
+
+    visited.pop();
+    Ok(())
+}
+
+/// Reads `<name>.toml` from `dir`, followed by the optional personal
+/// overrides in `<name>.local.toml`.
+fn get_cfg<T>(dir: &Path, name: &str) -> Result<T, ConfigError>
 where
     T: DeserializeOwned,
 {
     let mut s = Config::default();
-    s.merge(File::with_name(path))?;
+    merge_with_includes(&mut s, &dir.join(format!("{}.toml", name)), &mut Vec::new())?;
+    let local_path = dir.join(format!("{}.local.toml", name));
+    if local_path.is_file() {
+        merge_with_includes(&mut s, &local_path, &mut Vec::new())?;
+    }
     s.try_into()
 }
 
+/// Replaces a leading `~` in `path` with the user's home directory.
+fn expand_home(path: &Path) -> PathBuf {
+    let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"));
+    match (path.strip_prefix("~"), home) {
+        (Ok(rest), Some(home)) => Path::new(&home).join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Finds the data file referred to by `path`.
+///
+/// Absolute paths are used as they are. Relative paths are looked up first
+/// in the config directory and then in the current working directory.
+fn resolve_data_path(path: &Path, cfg_dir: &Path) -> Result<PathBuf, ConfigError> {
+    let path = expand_home(path);
+    let candidates = if path.is_absolute() {
+        vec![path.clone()]
+    } else {
+        vec![cfg_dir.join(&path), path.clone()]
+    };
+    if let Some(found) = candidates.iter().find(|p| p.is_file()) {
+        return Ok(found.clone());
+    }
+    let searched: Vec<String> = candidates.iter().map(|p| p.display().to_string()).collect();
+    Err(ConfigError::Message(format!(
+        "Could not find data file {}. Searched locations: {}",
+        path.display(),
+        searched.join(", ")
+    )))
+}
+
+/// Like `get_cfg`, but falls back to the default value of `T` if neither
+/// `<name>.toml` nor `<name>.local.toml` exists in `dir`.
+#[cfg(any(feature = "gui", test))]
+fn get_optional_cfg<T>(dir: &Path, name: &str) -> Result<T, ConfigError>
+where
+    T: DeserializeOwned + Default,
+{
+    let has_cfg_file = [format!("{}.toml", name), format!("{}.local.toml", name)]
+        .iter()
+        .any(|file_name| dir.join(file_name).is_file());
+    if has_cfg_file {
+        get_cfg(dir, name)
+    } else {
+        Ok(T::default())
+    }
+}
+
 impl Cfg {
     pub fn new(path: &str) -> Result<Self, ConfigError> {
         let base_path = Path::new(path);
-        let app_cfg = get_cfg(base_path.join(Path::new("app.toml")).to_str().unwrap())?;
-        let audio_cfg = get_cfg(base_path.join(Path::new("audio.toml")).to_str().unwrap())?;
-        let game_cfg = get_cfg(base_path.join(Path::new("game.toml")).to_str().unwrap())?;
-        let console_cfg = get_cfg(base_path.join(Path::new("console.toml")).to_str().unwrap())?;
-
+        let mut app_cfg: AppCfg = get_cfg(base_path, "app")?;
+        app_cfg.frequencies_path = resolve_data_path(&app_cfg.frequencies_path, base_path)?;
+        app_cfg.tuning_path = resolve_data_path(&app_cfg.tuning_path, base_path)?;
+        if let Some(a4) = app_cfg.a4_reference {
+            if a4.is_nan() || a4 <= 0.0 {
+                return Err(ConfigError::Message(format!(
+                    "a4_reference must be a positive frequency, got {}",
+                    a4
+                )));
+            }
+        }
+        app_cfg.history_path = app_cfg.history_path.map(|path| expand_home(&path));
+        app_cfg.targets_path = app_cfg.targets_path.map(|path| expand_home(&path));
+        app_cfg.practice_log_path = app_cfg.practice_log_path.map(|path| expand_home(&path));
+        app_cfg.control_pipe = app_cfg.control_pipe.map(|path| expand_home(&path));
+        #[cfg(not(unix))]
+        if app_cfg.control_pipe.is_some() {
+            return Err(ConfigError::Message(String::from(
+                "control_pipe is only supported on Unix systems",
+            )));
+        }
+        app_cfg.calibration.path = expand_home(&app_cfg.calibration.path);
+        if let Some(overlay) = &mut app_cfg.overlay {
+            overlay.dir = expand_home(&overlay.dir);
+        }
+        let mut game_cfg: GameCfg = get_cfg(base_path, "game")?;
+        game_cfg.exercise_path = game_cfg.exercise_path.map(|path| expand_home(&path));
+        #[cfg(feature = "plugins")]
+        {
+            game_cfg.plugins_dir = game_cfg.plugins_dir.map(|path| {
+                let path = expand_home(&path);
+                let in_cfg_dir = base_path.join(&path);
+                if path.is_relative() && in_cfg_dir.is_dir() {
+                    in_cfg_dir
+                } else {
+                    path
+                }
+            });
+        }
+        #[cfg(feature = "gui")]
+        let mut gui: GuiCfg = get_optional_cfg(base_path, "gui")?;
+        #[cfg(feature = "gui")]
+        {
+            gui.screenshot_dir = expand_home(&gui.screenshot_dir);
+        }
         Ok(Cfg {
             app: app_cfg,
-            audio: audio_cfg,
+            audio: get_cfg(base_path, "audio")?,
             game: game_cfg,
-            console: console_cfg,
+            console: get_cfg(base_path, "console")?,
             #[cfg(feature = "gui")]
-            gui: get_cfg(base_path.join(Path::new("gui.toml")).to_str().unwrap())?,
+            gui,
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::NoteName;
+    use std::fs;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct TestCfg {
+        a: i32,
+        b: String,
+    }
+
+    fn make_cfg_dir(test_name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("libreguitar_cfg_{}", test_name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        for (name, content) in files.iter() {
+            fs::write(dir.join(name), content).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn test_get_cfg_single_file() {
+        let dir = make_cfg_dir("single_file", &[("test.toml", "a = 1\nb = \"x\"")]);
+        let expected = TestCfg {
+            a: 1,
+            b: String::from("x"),
+        };
+        assert_eq!(expected, get_cfg(&dir, "test").unwrap());
+    }
+
+    #[test]
+    fn test_get_cfg_include_is_overridden() {
+        let dir = make_cfg_dir(
+            "include",
+            &[
+                ("base.toml", "a = 1\nb = \"base\""),
+                ("test.toml", "include = [\"base.toml\"]\na = 2"),
+            ],
+        );
+        let expected = TestCfg {
+            a: 2,
+            b: String::from("base"),
+        };
+        assert_eq!(expected, get_cfg(&dir, "test").unwrap());
+    }
+
+    #[test]
+    fn test_get_cfg_local_overrides() {
+        let dir = make_cfg_dir(
+            "local",
+            &[
+                ("test.toml", "a = 1\nb = \"shared\""),
+                ("test.local.toml", "b = \"personal\""),
+            ],
+        );
+        let expected = TestCfg {
+            a: 1,
+            b: String::from("personal"),
+        };
+        assert_eq!(expected, get_cfg(&dir, "test").unwrap());
+    }
+
+    #[test]
+    fn test_get_cfg_include_cycle() {
+        let dir = make_cfg_dir(
+            "cycle",
+            &[
+                ("base.toml", "include = [\"test.toml\"]\na = 1"),
+                ("test.toml", "include = [\"base.toml\"]\nb = \"x\""),
+            ],
+        );
+        assert!(get_cfg::<TestCfg>(&dir, "test").is_err());
+    }
+
+    #[derive(Debug, Default, Deserialize, PartialEq)]
+    struct TestOptionalCfg {
+        a: i32,
+    }
+
+    #[test]
+    fn test_get_optional_cfg_missing_file() {
+        let dir = make_cfg_dir("optional_missing", &[]);
+        assert_eq!(
+            TestOptionalCfg::default(),
+            get_optional_cfg(&dir, "test").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_get_optional_cfg_existing_file() {
+        let dir = make_cfg_dir("optional_existing", &[("test.toml", "a = 3")]);
+        assert_eq!(
+            TestOptionalCfg { a: 3 },
+            get_optional_cfg(&dir, "test").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_resolve_data_path_relative_to_cfg_dir() {
+        let dir = make_cfg_dir("resolve_cfg_dir", &[("notes.csv", "")]);
+        let expected = dir.join("notes.csv");
+        assert_eq!(
+            expected,
+            resolve_data_path(Path::new("notes.csv"), &dir).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_resolve_data_path_absolute() {
+        let dir = make_cfg_dir("resolve_absolute", &[("notes.csv", "")]);
+        let path = dir.join("notes.csv");
+        assert_eq!(path, resolve_data_path(&path, Path::new("cfg")).unwrap());
+    }
+
+    #[test]
+    fn test_resolve_data_path_missing() {
+        let dir = make_cfg_dir("resolve_missing", &[]);
+        let err = resolve_data_path(Path::new("missing.csv"), &dir).unwrap_err();
+        let msg = err.to_string();
+        assert!(msg.contains("missing.csv"));
+        assert!(msg.contains(&dir.join("missing.csv").display().to_string()));
+    }
+
+    #[test]
+    fn test_note_registry_from_a4_reference() {
+        let mut cfg = Cfg::new(concat!(env!("CARGO_MANIFEST_DIR"), "/cfg")).unwrap();
+        cfg.app.a4_reference = None;
+        let a4 = |cfg: &Cfg| {
+            let registry = cfg.app.note_registry().unwrap();
+            registry.get(NoteName::A, 4).unwrap().frequency
+        };
+        assert_eq!(440.0, a4(&cfg));
+        cfg.app.a4_reference = Some(432.0);
+        assert_eq!(432.0, a4(&cfg));
+    }
+
+    #[test]
+    fn test_expand_home() {
+        assert_eq!(PathBuf::from("a/b"), expand_home(Path::new("a/b")));
+        if let Some(home) = std::env::var_os("HOME") {
+            assert_eq!(Path::new(&home).join("a"), expand_home(Path::new("~/a")));
+        }
+    }
+}
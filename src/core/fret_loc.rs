@@ -1,5 +0,0 @@
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub struct FretLoc {
-    pub string_idx: usize,
-    pub fret_idx: usize,
-}
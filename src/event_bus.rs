@@ -0,0 +1,468 @@
+use crate::audio_analysis::AnalysisResult;
+use crate::game::{GameCommand, GameState};
+use crate::metronome::Beat;
+use crate::visualization::{FrameData, GameEvent};
+use log::*;
+use std::collections::VecDeque;
+use std::sync::mpsc::{RecvError, RecvTimeoutError, TryRecvError};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+/// Messages a subscriber can fall behind by before the overflow policy of the
+/// topic applies.
+pub(crate) const SUBSCRIBER_CAPACITY: usize = 1024;
+/// Frames queued for a subscriber. Kept small since every frame carries a
+/// whole spectrum.
+const FRAME_CAPACITY: usize = 64;
+/// Longest time a publisher waits for a full subscriber of a
+/// [`Overflow::BlockOrCoalesce`] topic to catch up.
+const BLOCK_TIMEOUT: Duration = Duration::from_millis(10);
+/// Longest time a publisher waits for a full subscriber of an
+/// [`Overflow::Block`] topic before it considers the subscriber stalled.
+const STALL_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// What happens to a message published to a subscriber whose queue is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overflow {
+    /// The oldest queued message is dropped to make room for the new one.
+    /// Suits the frame data, of which only the latest is drawn.
+    DropOldest,
+    /// The publisher waits for the subscriber to catch up, and if it doesn't
+    /// within a short timeout, the new message replaces the newest queued
+    /// one. A stalled subscriber thus misses only intermediate messages and
+    /// always gets the latest one, which suits the game states, of which
+    /// every one replaces the previous.
+    BlockOrCoalesce,
+    /// The publisher waits until the subscriber catches up, so that no
+    /// message is lost while the subscriber keeps receiving. Suits the
+    /// commands and events, which don't replace each other. A subscriber that
+    /// doesn't catch up within a second is stalled, e.g. a host that stopped
+    /// reading, and misses the messages published until it receives again
+    /// instead of holding up the publisher.
+    Block,
+}
+
+struct Queue<T> {
+    messages: VecDeque<T>,
+    /// Cleared once every handle of the topic is dropped.
+    connected: bool,
+    /// Cleared once the subscription is dropped.
+    subscribed: bool,
+    /// Set once the subscriber of an [`Overflow::Block`] topic didn't catch
+    /// up in time, and cleared when it receives again.
+    stalled: bool,
+}
+
+/// Bounded queue between a topic and one of its subscribers.
+struct Channel<T> {
+    queue: Mutex<Queue<T>>,
+    not_empty: Condvar,
+    not_full: Condvar,
+}
+
+impl<T> Channel<T> {
+    /// Queues `msg` according to `overflow` and returns whether the
+    /// subscriber is still there to receive it.
+    fn push(&self, msg: T, capacity: usize, overflow: Overflow) -> bool {
+        let mut queue = self.queue.lock().unwrap();
+        if queue.messages.len() >= capacity {
+            match overflow {
+                Overflow::DropOldest => {
+                    queue.messages.pop_front();
+                }
+                Overflow::BlockOrCoalesce => {
+                    queue = self
+                        .not_full
+                        .wait_timeout_while(queue, BLOCK_TIMEOUT, |queue| {
+                            queue.subscribed && queue.messages.len() >= capacity
+                        })
+                        .unwrap()
+                        .0;
+                    if queue.messages.len() >= capacity {
+                        queue.messages.pop_back();
+                    }
+                }
+                Overflow::Block => {
+                    if !queue.stalled {
+                        queue = self
+                            .not_full
+                            .wait_timeout_while(queue, STALL_TIMEOUT, |queue| {
+                                queue.subscribed && queue.messages.len() >= capacity
+                            })
+                            .unwrap()
+                            .0;
+                    }
+                    if queue.subscribed && queue.messages.len() >= capacity {
+                        if !queue.stalled {
+                            warn!(
+                                "A subscriber stalled, dropping its messages until it catches up"
+                            );
+                            queue.stalled = true;
+                        }
+                        return true;
+                    }
+                }
+            }
+        }
+        if !queue.subscribed {
+            return false;
+        }
+        queue.messages.push_back(msg);
+        self.not_empty.notify_one();
+        true
+    }
+
+    fn pop(&self, queue: &mut Queue<T>) -> Option<T> {
+        let msg = queue.messages.pop_front();
+        if msg.is_some() {
+            queue.stalled = false;
+            self.not_full.notify_one();
+        }
+        msg
+    }
+}
+
+/// Receiving end of a subscription to a [`Topic`], with the same methods as
+/// [`std::sync::mpsc::Receiver`]. The subscription ends when it is dropped.
+pub struct Subscription<T> {
+    channel: Arc<Channel<T>>,
+}
+
+impl<T> Subscription<T> {
+    /// Next message if there is one, without blocking.
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        let mut queue = self.channel.queue.lock().unwrap();
+        match self.channel.pop(&mut queue) {
+            Some(msg) => Ok(msg),
+            None if queue.connected => Err(TryRecvError::Empty),
+            None => Err(TryRecvError::Disconnected),
+        }
+    }
+
+    /// Waits for the next message. Fails once the topic is dropped and every
+    /// queued message is received.
+    pub fn recv(&self) -> Result<T, RecvError> {
+        let queue = self.channel.queue.lock().unwrap();
+        let mut queue = self
+            .channel
+            .not_empty
+            .wait_while(queue, |queue| queue.messages.is_empty() && queue.connected)
+            .unwrap();
+        self.channel.pop(&mut queue).ok_or(RecvError)
+    }
+
+    /// Waits for the next message for at most `timeout`.
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        let queue = self.channel.queue.lock().unwrap();
+        let mut queue = self
+            .channel
+            .not_empty
+            .wait_timeout_while(queue, timeout, |queue| {
+                queue.messages.is_empty() && queue.connected
+            })
+            .unwrap()
+            .0;
+        match self.channel.pop(&mut queue) {
+            Some(msg) => Ok(msg),
+            None if queue.connected => Err(RecvTimeoutError::Timeout),
+            None => Err(RecvTimeoutError::Disconnected),
+        }
+    }
+
+    /// Iterates over the queued messages without blocking.
+    pub fn try_iter(&self) -> impl Iterator<Item = T> + '_ {
+        std::iter::from_fn(move || self.try_recv().ok())
+    }
+}
+
+impl<T> Drop for Subscription<T> {
+    fn drop(&mut self) {
+        let mut queue = self.channel.queue.lock().unwrap();
+        queue.subscribed = false;
+        queue.messages.clear();
+        // wake up a publisher waiting for room
+        self.channel.not_full.notify_all();
+    }
+}
+
+struct Subscribers<T> {
+    channels: Mutex<Vec<Arc<Channel<T>>>>,
+    capacity: usize,
+    overflow: Overflow,
+}
+
+impl<T> Drop for Subscribers<T> {
+    /// Disconnects the subscriptions once the last handle of the topic is
+    /// dropped.
+    fn drop(&mut self) {
+        for channel in self.channels.lock().unwrap().iter() {
+            channel.queue.lock().unwrap().connected = false;
+            channel.not_empty.notify_all();
+        }
+    }
+}
+
+/// Broadcasts every published message to all of its subscribers through
+/// bounded queues, so that a stalled subscriber can't make the memory grow
+/// without limit. What happens once a queue is full is set by the
+/// [`Overflow`] policy of the topic.
+///
+/// Cloning a topic gives another handle to the same subscribers.
+pub struct Topic<T> {
+    subscribers: Arc<Subscribers<T>>,
+}
+
+impl<T: Clone> Topic<T> {
+    /// Topic queueing up to `capacity` messages for every subscriber.
+    pub fn new(capacity: usize, overflow: Overflow) -> Topic<T> {
+        Topic {
+            subscribers: Arc::new(Subscribers {
+                channels: Mutex::new(Vec::new()),
+                capacity,
+                overflow,
+            }),
+        }
+    }
+
+    /// Receiver of every message published from now on.
+    pub fn subscribe(&self) -> Subscription<T> {
+        let channel = Arc::new(Channel {
+            queue: Mutex::new(Queue {
+                messages: VecDeque::new(),
+                connected: true,
+                subscribed: true,
+                stalled: false,
+            }),
+            not_empty: Condvar::new(),
+            not_full: Condvar::new(),
+        });
+        self.subscribers
+            .channels
+            .lock()
+            .unwrap()
+            .push(channel.clone());
+        Subscription { channel }
+    }
+
+    /// Sends `msg` to every subscriber and returns the number of subscribers
+    /// it was sent to, stalled ones included. Only
+    /// blocks, for a bounded time, if a subscriber of a
+    /// [`Overflow::BlockOrCoalesce`] or [`Overflow::Block`] topic is full.
+    pub fn publish(&self, msg: T) -> usize {
+        let Subscribers {
+            channels,
+            capacity,
+            overflow,
+        } = &*self.subscribers;
+        // The subscribers are pushed to without holding the list, so that a
+        // waiting publisher doesn't hold up subscribing.
+        let targets: Vec<Arc<Channel<T>>> = channels.lock().unwrap().clone();
+        let n_received = targets
+            .iter()
+            .filter(|channel| channel.push(msg.clone(), *capacity, *overflow))
+            .count();
+        if n_received < targets.len() {
+            self.forget_unsubscribed();
+        }
+        n_received
+    }
+
+    fn forget_unsubscribed(&self) {
+        let mut channels = self.subscribers.channels.lock().unwrap();
+        channels.retain(|channel| channel.queue.lock().unwrap().subscribed);
+    }
+
+    /// Whether anyone listens, to skip building messages nobody reads.
+    pub fn has_subscribers(&self) -> bool {
+        self.forget_unsubscribed();
+        !self.subscribers.channels.lock().unwrap().is_empty()
+    }
+}
+
+impl<T> Clone for Topic<T> {
+    fn clone(&self) -> Topic<T> {
+        Topic {
+            subscribers: self.subscribers.clone(),
+        }
+    }
+}
+
+impl<T: Clone> Default for Topic<T> {
+    fn default() -> Topic<T> {
+        Topic::new(SUBSCRIBER_CAPACITY, Overflow::BlockOrCoalesce)
+    }
+}
+
+/// Typed topics connecting the audio thread, the game thread and the
+/// frontends, so that a new consumer only has to subscribe to the events it
+/// needs.
+///
+/// The per-block topics drop the oldest messages of a subscriber that falls
+/// behind and the game states are coalesced, while the events and commands
+/// are only dropped for a stalled subscriber (see [`Overflow`]).
+#[derive(Clone)]
+pub struct EventBus {
+    /// Note detected in every audio block.
+    pub analysis: Topic<AnalysisResult>,
+    /// Analysis of every audio block along with its spectrum.
+    pub frames: Topic<FrameData>,
+    /// Spectrum of every audio block reduced to the width of the console
+    /// spectrum.
+    pub spectrum: Topic<Vec<f64>>,
+    /// Every state of the game.
+    pub states: Topic<GameState>,
+    /// Targets, results and pauses, as told apart from the game states by
+    /// the app.
+    pub events: Topic<GameEvent>,
+    /// Commands to the game thread.
+    pub control: Topic<GameCommand>,
+    /// Every beat of the metronome as it is clicked, the clock of the game
+    /// modes played in time.
+    pub beats: Topic<Beat>,
+}
+
+impl Default for EventBus {
+    fn default() -> EventBus {
+        EventBus {
+            analysis: Topic::new(SUBSCRIBER_CAPACITY, Overflow::DropOldest),
+            frames: Topic::new(FRAME_CAPACITY, Overflow::DropOldest),
+            spectrum: Topic::new(FRAME_CAPACITY, Overflow::DropOldest),
+            states: Topic::new(SUBSCRIBER_CAPACITY, Overflow::BlockOrCoalesce),
+            events: Topic::new(SUBSCRIBER_CAPACITY, Overflow::Block),
+            control: Topic::new(SUBSCRIBER_CAPACITY, Overflow::Block),
+            beats: Topic::new(SUBSCRIBER_CAPACITY, Overflow::DropOldest),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[test]
+    fn test_publish_to_every_subscriber() {
+        let topic = Topic::default();
+        let first = topic.subscribe();
+        let second = topic.subscribe();
+        assert_eq!(2, topic.publish(3));
+        assert_eq!(vec![3], first.try_iter().collect::<Vec<i32>>());
+        assert_eq!(vec![3], second.try_iter().collect::<Vec<i32>>());
+    }
+
+    #[test]
+    fn test_dropped_subscriber_is_forgotten() {
+        let topic = Topic::default();
+        let kept = topic.subscribe();
+        drop(topic.subscribe());
+        assert_eq!(1, topic.publish(1));
+        assert!(topic.has_subscribers());
+        drop(kept);
+        assert_eq!(0, topic.publish(2));
+        assert!(!topic.has_subscribers());
+    }
+
+    #[test]
+    fn test_drop_oldest() {
+        let topic = Topic::new(2, Overflow::DropOldest);
+        let rx = topic.subscribe();
+        for i in 0..4 {
+            assert_eq!(1, topic.publish(i));
+        }
+        assert_eq!(vec![2, 3], rx.try_iter().collect::<Vec<i32>>());
+    }
+
+    #[test]
+    fn test_block_or_coalesce() {
+        let topic = Topic::new(2, Overflow::BlockOrCoalesce);
+        let rx = topic.subscribe();
+        for i in 0..4 {
+            assert_eq!(1, topic.publish(i));
+        }
+        assert_eq!(vec![0, 3], rx.try_iter().collect::<Vec<i32>>());
+    }
+
+    #[test]
+    fn test_blocked_publisher_resumes() {
+        let topic = Topic::new(1, Overflow::BlockOrCoalesce);
+        let rx = topic.subscribe();
+        topic.publish(0);
+        let publisher = topic.clone();
+        let handle = std::thread::spawn(move || publisher.publish(1));
+        assert_eq!(Ok(0), rx.recv());
+        assert_eq!(1, handle.join().unwrap());
+        assert_eq!(Ok(1), rx.recv());
+    }
+
+    #[test]
+    fn test_block_keeps_every_message() {
+        let topic = Topic::new(1, Overflow::Block);
+        let rx = topic.subscribe();
+        let publisher = topic.clone();
+        let handle =
+            std::thread::spawn(move || (0..4).map(|i| publisher.publish(i)).sum::<usize>());
+        let received: Vec<i32> = (0..4).map(|_| rx.recv().unwrap()).collect();
+        assert_eq!(vec![0, 1, 2, 3], received);
+        assert_eq!(4, handle.join().unwrap());
+    }
+
+    #[test]
+    fn test_block_ends_with_subscription() {
+        let topic = Topic::new(1, Overflow::Block);
+        let rx = topic.subscribe();
+        topic.publish(0);
+        let publisher = topic.clone();
+        let handle = std::thread::spawn(move || publisher.publish(1));
+        std::thread::sleep(Duration::from_millis(10));
+        drop(rx);
+        assert_eq!(0, handle.join().unwrap());
+    }
+
+    #[test]
+    fn test_stalled_subscriber() {
+        let topic = Topic::new(2, Overflow::Block);
+        let stalled = topic.subscribe();
+        topic.publish(0);
+        topic.publish(1);
+        let publisher = topic.clone();
+        let handle = std::thread::spawn(move || publisher.publish(2));
+        std::thread::sleep(Duration::from_millis(10));
+        // Subscribing doesn't wait for the blocked publisher.
+        let subscribed = Instant::now();
+        let late = topic.subscribe();
+        assert!(subscribed.elapsed() < STALL_TIMEOUT / 2);
+        assert!(handle.join().unwrap() >= 1);
+        // Once stalled, the subscriber doesn't hold up the publisher anymore.
+        let published = Instant::now();
+        assert_eq!(2, topic.publish(3));
+        assert!(published.elapsed() < STALL_TIMEOUT / 2);
+        assert_eq!(Some(3), late.try_iter().last());
+        assert_eq!(vec![0, 1], stalled.try_iter().collect::<Vec<i32>>());
+        // Receiving again ends the stall.
+        topic.publish(4);
+        assert_eq!(Ok(4), stalled.try_recv());
+    }
+
+    #[test]
+    fn test_disconnected_after_topic_dropped() {
+        let topic = Topic::default();
+        let rx = topic.subscribe();
+        topic.clone().publish(1);
+        drop(topic);
+        assert_eq!(Ok(1), rx.recv());
+        assert_eq!(Err(RecvError), rx.recv());
+        assert_eq!(
+            Err(RecvTimeoutError::Disconnected),
+            rx.recv_timeout(Duration::from_millis(1))
+        );
+    }
+
+    #[test]
+    fn test_no_messages_before_subscribing() {
+        let topic = Topic::default();
+        assert_eq!(0, topic.publish(1));
+        let rx = topic.subscribe();
+        topic.clone().publish(2);
+        assert_eq!(vec![2], rx.try_iter().collect::<Vec<i32>>());
+    }
+}
@@ -0,0 +1,134 @@
+use crate::audio_analysis::AnalysisResult;
+use crate::event_bus::{EventBus, Subscription, Topic};
+use crate::game::{GameCommand, GameState};
+use crate::metronome::Beat;
+use crate::visualization::GameEvent;
+use futures_core::Stream;
+use std::pin::Pin;
+use std::sync::mpsc::RecvTimeoutError;
+use std::task::{Context, Poll};
+use std::thread;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// Messages buffered for a stream. A stream that falls behind further fills
+/// its topic subscription, which then overflows as the topic sets. A stream
+/// of the events that isn't polled anymore is thus stalled and misses events
+/// instead of holding up the game (see [`crate::Overflow::Block`]).
+const STREAM_CAPACITY: usize = 16;
+/// Longest time a forwarding thread waits for a message before checking
+/// whether its stream or channel was dropped.
+const FORWARD_POLL_PERIOD: Duration = Duration::from_millis(100);
+
+/// Messages of a topic as a [`Stream`], which ends when the topic is dropped.
+///
+/// The messages are forwarded from a blocking subscription by a thread of
+/// the stream, so that no async runtime has to run the blocking receivers.
+pub struct EventStream<T> {
+    rx: mpsc::Receiver<T>,
+}
+
+impl<T: Clone + Send + 'static> EventStream<T> {
+    /// Stream of the messages published to `topic` from now on.
+    pub fn new(topic: &Topic<T>) -> EventStream<T> {
+        let subscription = topic.subscribe();
+        let (tx, rx) = mpsc::channel(STREAM_CAPACITY);
+        thread::spawn(move || forward(subscription, tx));
+        EventStream { rx }
+    }
+}
+
+fn forward<T>(subscription: Subscription<T>, tx: mpsc::Sender<T>) {
+    loop {
+        match subscription.recv_timeout(FORWARD_POLL_PERIOD) {
+            Ok(msg) => {
+                if tx.blocking_send(msg).is_err() {
+                    return;
+                }
+            }
+            Err(RecvTimeoutError::Timeout) if !tx.is_closed() => {}
+            Err(_) => return,
+        }
+    }
+}
+
+impl<T> Stream for EventStream<T> {
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        self.rx.poll_recv(cx)
+    }
+}
+
+/// Async facade of an [`EventBus`] for GUIs and network integrations built
+/// on tokio: the detections and the game as streams, and the game commands
+/// through an async channel.
+#[derive(Clone)]
+pub struct AsyncEventBus {
+    bus: EventBus,
+}
+
+impl AsyncEventBus {
+    pub fn new(bus: EventBus) -> AsyncEventBus {
+        AsyncEventBus { bus }
+    }
+
+    /// Note detected in every audio block.
+    pub fn analyses(&self) -> EventStream<AnalysisResult> {
+        EventStream::new(&self.bus.analysis)
+    }
+
+    /// Every state of the game.
+    pub fn states(&self) -> EventStream<GameState> {
+        EventStream::new(&self.bus.states)
+    }
+
+    /// Targets, results and pauses of the game.
+    pub fn events(&self) -> EventStream<GameEvent> {
+        EventStream::new(&self.bus.events)
+    }
+
+    /// Every beat of the metronome.
+    pub fn beats(&self) -> EventStream<Beat> {
+        EventStream::new(&self.bus.beats)
+    }
+
+    /// Channel whose commands are passed on to the game until it is
+    /// dropped.
+    pub fn commands(&self) -> mpsc::Sender<GameCommand> {
+        let (tx, mut rx) = mpsc::channel(STREAM_CAPACITY);
+        let control = self.bus.control.clone();
+        thread::spawn(move || {
+            while let Some(command) = rx.blocking_recv() {
+                control.publish(command);
+            }
+        });
+        tx
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::future::poll_fn;
+
+    async fn next<T>(stream: &mut EventStream<T>) -> Option<T> {
+        poll_fn(|cx| Pin::new(&mut *stream).poll_next(cx)).await
+    }
+
+    #[tokio::test]
+    async fn test_events_and_commands() {
+        let bus = EventBus::default();
+        let async_bus = AsyncEventBus::new(bus.clone());
+        let mut events = async_bus.events();
+        bus.events.publish(GameEvent::Paused(true));
+        assert_eq!(Some(GameEvent::Paused(true)), next(&mut events).await);
+
+        let control = bus.control.subscribe();
+        async_bus.commands().send(GameCommand::Skip).await.unwrap();
+        let command = tokio::task::spawn_blocking(move || control.recv().unwrap())
+            .await
+            .unwrap();
+        assert_eq!(GameCommand::Skip, command);
+    }
+}
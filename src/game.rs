@@ -1,7 +1,25 @@
-mod active_notes;
+//! The game rules of `libreguitar-core` with the controls and the history
+//! files of the app.
+
+#[cfg(feature = "audio")]
 mod game_logic;
-mod game_state;
+#[cfg(feature = "sync")]
+mod history_sync;
+#[cfg(feature = "audio")]
+mod midi_control;
+#[cfg(feature = "audio")]
+mod pipe_control;
+mod stats_export;
 
-pub use active_notes::ActiveNotes;
-pub use game_logic::{GameError, GameLogic};
-pub use game_state::GameState;
+#[cfg(feature = "audio")]
+pub use game_logic::{GameController, GameError, GameLogic};
+#[cfg(all(feature = "sync", feature = "audio"))]
+pub use history_sync::sync_files;
+#[cfg(feature = "sync")]
+pub use history_sync::{sync_history, SyncError, SyncSummary};
+pub use libreguitar_core::game::*;
+#[cfg(feature = "audio")]
+pub use midi_control::spawn_midi_control;
+#[cfg(feature = "audio")]
+pub use pipe_control::PipeControl;
+pub use stats_export::{export_anki, export_stats, StatsExportError};
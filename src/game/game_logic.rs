@@ -1,10 +1,11 @@
-use crate::audio_analysis::AnalysisResult;
-use crate::core::{FretLoc, FretRange, GameCfg, Note, NoteRegistry, StringRange, Tuning};
-use crate::game::{ActiveNotes, GameState};
+use crate::core::{FretRange, GameCfg, NoteRegistry, RangeError, StringRange, Tuning};
+use crate::event_bus::{EventBus, Subscription, Topic};
+use crate::game::{ActiveNotes, GameCommand, GameSession, TargetSelector};
 use std::error::Error;
 use std::fmt;
 use std::sync::mpsc;
 use std::thread;
+use std::time::Instant;
 
 #[derive(Debug)]
 pub struct GameError(String);
@@ -17,84 +18,144 @@ impl fmt::Display for GameError {
 
 impl Error for GameError {}
 
-enum ThreadCtrl {
-    Start,
-    // Stop,
+/// Handle for controlling a running game from another thread, e.g. from the
+/// GUI or a MIDI controller.
+#[derive(Clone)]
+pub struct GameController {
+    control: Topic<GameCommand>,
+}
+
+impl GameController {
+    /// Controller publishing on `control` without a game thread, for the
+    /// tests of the frontends.
+    #[cfg(all(test, feature = "http_api"))]
+    pub(crate) fn with_topic(control: Topic<GameCommand>) -> GameController {
+        GameController { control }
+    }
+
+    fn send(&self, ctrl: GameCommand) -> Result<(), GameError> {
+        if self.control.publish(ctrl) == 0 {
+            return Err(GameError(String::from("Game thread is not running")));
+        }
+        Ok(())
+    }
+
+    /// Stops the target timer and ignores detections until resumed.
+    pub fn pause(&self) -> Result<(), GameError> {
+        self.send(GameCommand::Pause)
+    }
+
+    pub fn resume(&self) -> Result<(), GameError> {
+        self.send(GameCommand::Resume)
+    }
+
+    /// Pauses a running game or resumes a paused one.
+    pub fn toggle_pause(&self) -> Result<(), GameError> {
+        self.send(GameCommand::TogglePause)
+    }
+
+    /// Moves on to the next target. The skipped target counts as missed.
+    pub fn skip(&self) -> Result<(), GameError> {
+        self.send(GameCommand::Skip)
+    }
+
+    /// Switches the targets to the next range preset of the game config.
+    pub fn next_preset(&self) -> Result<(), GameError> {
+        self.send(GameCommand::NextPreset)
+    }
+
+    /// Ends the game without recording the current target.
+    pub fn stop(&self) -> Result<(), GameError> {
+        self.send(GameCommand::Stop)
+    }
 }
 
 pub struct GameLogic {
-    ctrl_tx: mpsc::Sender<ThreadCtrl>,
+    control: Topic<GameCommand>,
     fret_range: FretRange,
     string_range: StringRange,
 }
 
-fn wait_until_start(rx: &mpsc::Receiver<ThreadCtrl>) -> Result<(), mpsc::RecvError> {
+/// Waits until one of `ctrls` or a stop command is received, ignoring the
+/// others, and returns the received command.
+fn wait_until(
+    rx: &Subscription<GameCommand>,
+    ctrls: &[GameCommand],
+) -> Result<GameCommand, mpsc::RecvError> {
     loop {
-        let res = rx.recv();
-        if let Ok(ThreadCtrl::Start) = res {
-            return Ok(());
-        } else if let Err(err) = res {
-            return Err(err);
+        let ctrl = rx.recv()?;
+        if ctrl == GameCommand::Stop || ctrls.contains(&ctrl) {
+            return Ok(ctrl);
         }
     }
 }
 
 impl GameLogic {
+    /// Starts the game thread, which plays on the detections of the analysis
+    /// topic of `bus` and publishes every game state on its states topic.
     pub fn new(
-        rx: mpsc::Receiver<AnalysisResult>,
-        tx_vec: Vec<mpsc::Sender<GameState>>,
+        bus: &EventBus,
         note_registry: NoteRegistry,
         tuning: Tuning,
         config: GameCfg,
-    ) -> GameLogic {
-        let fret_range = FretRange::new(config.fret_range.0, config.fret_range.1);
-        let string_range = StringRange::new(config.string_range.0, config.string_range.1);
+        selector: Box<dyn TargetSelector>,
+    ) -> Result<GameLogic, RangeError> {
+        let fret_range = config.frets()?;
+        let string_range = config.strings()?;
         let active_notes = ActiveNotes::new(
             &note_registry,
             &tuning,
             string_range.clone(),
             fret_range.clone(),
         );
-        let (ctrl_tx, ctrl_rx) = mpsc::channel();
-        let needed_detection_count = config.note_count_for_acceptance;
+        let presets = config
+            .presets
+            .iter()
+            .map(|preset| {
+                Ok(ActiveNotes::new(
+                    &note_registry,
+                    &tuning,
+                    preset.strings()?,
+                    preset.frets()?,
+                ))
+            })
+            .collect::<Result<Vec<_>, RangeError>>()?;
+        let rx = bus.analysis.subscribe();
+        let ctrl_rx = bus.control.subscribe();
+        let states = bus.states.clone();
         thread::spawn(move || {
-            wait_until_start(&ctrl_rx).unwrap();
-            let mut rng = rand::thread_rng();
-            loop {
-                // if let Ok(ThreadCtrl::Stop) = ctrl_rx.try_recv() {
-                //     wait_until_start(&ctrl_rx).unwrap();
-                // }
-                let (target_note, target_loc) = pick_note(&active_notes, &mut rng);
-                let mut state = GameState {
-                    target_note: target_note.clone(),
-                    target_loc,
-                    needed_detection_count,
-                    curr_detection_count: 0,
-                };
-                for tx in tx_vec.iter() {
-                    tx.send(state.clone()).unwrap();
-                }
-                for analysis in rx.iter() {
-                    if let Some(note) = analysis.note {
-                        state.curr_detection_count += (note == state.target_note) as usize;
-                    }
-                    if state.curr_detection_count > 0
-                        && state.curr_detection_count % config.state_update_period == 0
-                    {
-                        for tx in tx_vec.iter() {
-                            tx.send(state.clone()).unwrap();
-                        }
-                    }
-                    if state.curr_detection_count == needed_detection_count {
-                        break;
+            match wait_until(&ctrl_rx, &[GameCommand::Start]) {
+                Ok(GameCommand::Stop) | Err(_) => return,
+                _ => {}
+            }
+            let mut session = GameSession::new(active_notes, &config, selector, Instant::now())
+                .with_presets(presets);
+            states.publish(session.state().clone());
+            // The game ends once the note source stops.
+            while let Ok(analysis) = rx.recv() {
+                let mut events = Vec::new();
+                for ctrl in ctrl_rx.try_iter() {
+                    if ctrl == GameCommand::Stop {
+                        return;
                     }
+                    events.extend(session.handle_command(&ctrl));
+                }
+                events.extend(session.handle_analysis(analysis));
+                if !events.is_empty() {
+                    states.publish(session.state().clone());
                 }
             }
         });
-        GameLogic {
-            ctrl_tx,
+        Ok(GameLogic {
+            control: bus.control.clone(),
             fret_range,
             string_range,
+        })
+    }
+
+    pub fn controller(&self) -> GameController {
+        GameController {
+            control: self.control.clone(),
         }
     }
 
@@ -106,27 +167,18 @@ impl GameLogic {
         &self.string_range
     }
 
-    pub fn play(&mut self) -> Result<(), GameError> {
-        self.ctrl_tx
-            .send(ThreadCtrl::Start)
-            .map_err(|_| GameError(String::from("Could not start thread")))
+    /// Ends the game thread. It may already have ended, e.g. if the note
+    /// source was stopped first.
+    pub fn stop(&mut self) {
+        self.control.publish(GameCommand::Stop);
     }
 
-    // pub fn pause(&mut self) -> Result<(), GameError> {
-    //     self.ctrl_tx
-    //         .send(ThreadCtrl::Stop)
-    //         .map_err(|_| GameError(String::from("Could not stop thread")))
-    // }
-}
-
-fn pick_note<'a>(notes: &'a ActiveNotes, rng: &mut impl rand::Rng) -> (&'a Note, FretLoc) {
-    let string_idx = rng.gen_range(notes.string_range.r());
-    let fret_idx = rng.gen_range(notes.fret_range.r());
-    let key = FretLoc {
-        string_idx,
-        fret_idx,
-    };
-    (notes.get(&key).unwrap(), key)
+    pub fn play(&mut self) -> Result<(), GameError> {
+        if self.control.publish(GameCommand::Start) == 0 {
+            return Err(GameError(String::from("Could not start thread")));
+        }
+        Ok(())
+    }
 }
 
 #[derive(Debug)]
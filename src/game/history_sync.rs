@@ -0,0 +1,412 @@
+use crate::core::{AppCfg, SyncCfg};
+use crate::game::{load_history, load_targets, write_history, write_targets};
+use log::*;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+use thiserror::Error;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const SESSIONS_ENDPOINT: &str = "sessions";
+const TARGETS_ENDPOINT: &str = "targets";
+/// Times a file is synced again when the records of the server changed
+/// between downloading and uploading them.
+const MAX_SYNC_ATTEMPTS: usize = 3;
+
+type LoadFn<T> = fn(&Path) -> Result<Vec<T>, csv::Error>;
+type WriteFn<T> = fn(&Path, &[T]) -> Result<(), csv::Error>;
+
+#[derive(Debug, Error)]
+pub enum SyncError {
+    #[error("No sync server is configured in the [sync] table")]
+    NotConfigured,
+    #[error("The records on the sync server kept changing during the sync")]
+    Conflict,
+    #[error(transparent)]
+    HttpError(#[from] Box<ureq::Error>),
+    #[error(transparent)]
+    CsvError(#[from] csv::Error),
+    #[error(transparent)]
+    IoError(#[from] io::Error),
+}
+
+/// Number of records a sync added to each side.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SyncSummary {
+    pub downloaded: usize,
+    pub uploaded: usize,
+}
+
+impl SyncSummary {
+    fn add(&mut self, other: SyncSummary) {
+        self.downloaded += other.downloaded;
+        self.uploaded += other.uploaded;
+    }
+}
+
+/// Client of the REST interface of a sync server.
+///
+/// The server keeps a JSON array of records per endpoint, which is returned
+/// by `GET <url>/<endpoint>` and replaced by `PUT <url>/<endpoint>`. A
+/// missing endpoint answered with 404 holds no records.
+///
+/// If the server sends an `ETag` with the records, it is sent back in the
+/// `If-Match` header of the upload, so that the server can refuse it with
+/// 412 when another client changed the records in the meantime. Servers
+/// without `ETag`s are overwritten unconditionally, and concurrent syncs of
+/// two clients may then lose the records uploaded by one of them.
+struct SyncClient {
+    agent: ureq::Agent,
+    url: String,
+    token: Option<String>,
+}
+
+impl SyncClient {
+    fn new(cfg: &SyncCfg) -> SyncClient {
+        SyncClient {
+            agent: ureq::AgentBuilder::new().timeout(REQUEST_TIMEOUT).build(),
+            url: String::from(cfg.url.trim_end_matches('/')),
+            token: cfg.token.clone(),
+        }
+    }
+
+    fn request(&self, method: &str, endpoint: &str) -> ureq::Request {
+        let request = self
+            .agent
+            .request(method, &format!("{}/{}", self.url, endpoint));
+        match &self.token {
+            Some(token) => request.set("Authorization", &format!("Bearer {}", token)),
+            None => request,
+        }
+    }
+
+    /// Records of `endpoint` and the `ETag` of their version, if the server
+    /// sent one.
+    fn download<T: DeserializeOwned>(
+        &self,
+        endpoint: &str,
+    ) -> Result<(Vec<T>, Option<String>), SyncError> {
+        match self.request("GET", endpoint).call() {
+            Ok(response) => {
+                let etag = response.header("ETag").map(String::from);
+                Ok((response.into_json()?, etag))
+            }
+            Err(ureq::Error::Status(404, _)) => Ok((Vec::new(), None)),
+            Err(err) => Err(Box::new(err).into()),
+        }
+    }
+
+    /// Replaces the records of `endpoint` if they are still at the version
+    /// of `etag`. Returns false if the server refused it as they have changed.
+    fn upload<T: Serialize>(
+        &self,
+        endpoint: &str,
+        records: &[T],
+        etag: Option<&str>,
+    ) -> Result<bool, SyncError> {
+        let mut request = self.request("PUT", endpoint);
+        if let Some(etag) = etag {
+            request = request.set("If-Match", etag);
+        }
+        match request.send_json(records) {
+            Ok(_) => Ok(true),
+            Err(ureq::Error::Status(412, _)) => Ok(false),
+            Err(err) => Err(Box::new(err).into()),
+        }
+    }
+}
+
+/// Records of `local` followed by the records of `remote` that are missing
+/// locally, ordered by their timestamp.
+fn merge_records<T, F>(local: &[T], remote: &[T], timestamp: F) -> Vec<T>
+where
+    T: Clone + PartialEq,
+    F: Fn(&T) -> u64,
+{
+    // Records are only compared with the ones of the same timestamp.
+    let mut by_timestamp: HashMap<u64, Vec<&T>> = HashMap::new();
+    for record in local.iter() {
+        by_timestamp
+            .entry(timestamp(record))
+            .or_default()
+            .push(record);
+    }
+    let mut merged = local.to_vec();
+    for record in remote.iter() {
+        let same_time = by_timestamp.entry(timestamp(record)).or_default();
+        if !same_time.contains(&record) {
+            same_time.push(record);
+            merged.push(record.clone());
+        }
+    }
+    merged.sort_by_key(|record| timestamp(record));
+    merged
+}
+
+/// Merges the records of the local file at `path` with the records of the
+/// server at `endpoint` and stores the result on both sides.
+fn sync_file<T, F>(
+    client: &SyncClient,
+    endpoint: &str,
+    path: &Path,
+    load: LoadFn<T>,
+    write: WriteFn<T>,
+    timestamp: F,
+) -> Result<SyncSummary, SyncError>
+where
+    T: Clone + PartialEq + Serialize + DeserializeOwned,
+    F: Fn(&T) -> u64,
+{
+    // Records added to the local file, also by the attempts that had to start
+    // over.
+    let mut downloaded = 0;
+    for _ in 0..MAX_SYNC_ATTEMPTS {
+        let local = load(path)?;
+        let (remote, etag): (Vec<T>, _) = client.download(endpoint)?;
+        let merged = merge_records(&local, &remote, &timestamp);
+        // Duplicates in either file are merged, so the counts can't go below 0.
+        let summary = SyncSummary {
+            downloaded: merged.len().saturating_sub(local.len()),
+            uploaded: merged.len().saturating_sub(remote.len()),
+        };
+        if summary.downloaded > 0 {
+            write(path, &merged)?;
+            downloaded += summary.downloaded;
+        }
+        if summary.uploaded > 0 && !client.upload(endpoint, &merged, etag.as_deref())? {
+            debug!(
+                "Records of {}/{} changed during the sync",
+                client.url, endpoint
+            );
+            continue;
+        }
+        debug!(
+            "Synced {} with {}/{}: {:?}",
+            path.display(),
+            client.url,
+            endpoint,
+            summary
+        );
+        return Ok(SyncSummary {
+            downloaded,
+            ..summary
+        });
+    }
+    Err(SyncError::Conflict)
+}
+
+/// Syncs the session history and the targets with the configured server, so
+/// that both hold the records of either side.
+pub fn sync_history(cfg: &AppCfg) -> Result<SyncSummary, SyncError> {
+    let sync_cfg = cfg.sync.as_ref().ok_or(SyncError::NotConfigured)?;
+    sync_files(
+        sync_cfg,
+        cfg.history_path.as_deref(),
+        cfg.targets_path.as_deref(),
+    )
+}
+
+/// Syncs the history file at `history_path` and the targets file at
+/// `targets_path` with the server of `cfg`.
+pub fn sync_files(
+    cfg: &SyncCfg,
+    history_path: Option<&Path>,
+    targets_path: Option<&Path>,
+) -> Result<SyncSummary, SyncError> {
+    let client = SyncClient::new(cfg);
+    let mut summary = SyncSummary::default();
+    if let Some(path) = history_path {
+        summary.add(sync_file(
+            &client,
+            SESSIONS_ENDPOINT,
+            path,
+            load_history,
+            write_history,
+            |record| record.timestamp,
+        )?);
+    }
+    if let Some(path) = targets_path {
+        summary.add(sync_file(
+            &client,
+            TARGETS_ENDPOINT,
+            path,
+            load_targets,
+            write_targets,
+            |record| record.timestamp,
+        )?);
+    }
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::SessionRecord;
+    use std::fs;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::path::PathBuf;
+    use std::thread::{self, JoinHandle};
+
+    /// Request line and `If-Match` header of a request to the stub server.
+    type Request = (String, Option<String>);
+
+    /// Answers one connection after the other with `responses`, and returns
+    /// the requests.
+    fn serve(responses: Vec<String>) -> (SyncClient, JoinHandle<Vec<Request>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let cfg = SyncCfg {
+            url: format!("http://{}/", listener.local_addr().unwrap()),
+            token: None,
+            auto_sync: false,
+        };
+        let server = thread::spawn(move || {
+            let mut requests = Vec::new();
+            for response in responses {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(&stream);
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let mut if_match = None;
+                let mut content_length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    let line = line.trim();
+                    if line.is_empty() {
+                        break;
+                    }
+                    let (name, value) = line.split_at(line.find(':').unwrap());
+                    let value = value[1..].trim();
+                    match name.to_ascii_lowercase().as_str() {
+                        "if-match" => if_match = Some(String::from(value)),
+                        "content-length" => content_length = value.parse().unwrap(),
+                        _ => {}
+                    }
+                }
+                let mut body = vec![0; content_length];
+                reader.read_exact(&mut body).unwrap();
+                (&stream).write_all(response.as_bytes()).unwrap();
+                requests.push((String::from(request_line.trim()), if_match));
+            }
+            requests
+        });
+        (SyncClient::new(&cfg), server)
+    }
+
+    fn response(status: &str, etag: Option<&str>, body: &str) -> String {
+        let etag = etag.map_or(String::new(), |etag| format!("ETag: {}\r\n", etag));
+        format!(
+            "HTTP/1.1 {}\r\n{}Content-Type: application/json\r\nContent-Length: {}\r\n\
+             Connection: close\r\n\r\n{}",
+            status,
+            etag,
+            body.len(),
+            body
+        )
+    }
+
+    fn history_file(test_name: &str, records: &[SessionRecord]) -> PathBuf {
+        let dir = std::env::temp_dir().join(test_name);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("history.csv");
+        write_history(&path, records).unwrap();
+        path
+    }
+
+    fn record(timestamp: u64) -> SessionRecord {
+        SessionRecord {
+            timestamp,
+            n_completed: 2,
+            n_correct: 1,
+            best_streak: 1,
+            avg_response_time: 1.5,
+        }
+    }
+
+    fn sync_history_file(client: &SyncClient, path: &Path) -> Result<SyncSummary, SyncError> {
+        sync_file(
+            client,
+            SESSIONS_ENDPOINT,
+            path,
+            load_history,
+            write_history,
+            |record| record.timestamp,
+        )
+    }
+
+    #[test]
+    fn test_sync_to_missing_endpoint() {
+        let path = history_file("libreguitar_test_sync_missing", &[record(1)]);
+        let (client, server) = serve(vec![
+            response("404 Not Found", None, ""),
+            response("200 OK", None, ""),
+        ]);
+        let summary = sync_history_file(&client, &path).unwrap();
+        assert_eq!(
+            SyncSummary {
+                downloaded: 0,
+                uploaded: 1
+            },
+            summary
+        );
+        let requests = server.join().unwrap();
+        assert_eq!(
+            vec![
+                (String::from("GET /sessions HTTP/1.1"), None),
+                (String::from("PUT /sessions HTTP/1.1"), None),
+            ],
+            requests
+        );
+        assert_eq!(vec![record(1)], load_history(&path).unwrap());
+    }
+
+    #[test]
+    fn test_sync_conflict() {
+        let path = history_file("libreguitar_test_sync_conflict", &[record(1)]);
+        let remote = serde_json::to_string(&[record(2)]).unwrap();
+        let mut responses = Vec::new();
+        for _ in 0..MAX_SYNC_ATTEMPTS {
+            responses.push(response("200 OK", Some("\"v1\""), &remote));
+            responses.push(response("412 Precondition Failed", None, ""));
+        }
+        let (client, server) = serve(responses);
+        assert!(matches!(
+            sync_history_file(&client, &path),
+            Err(SyncError::Conflict)
+        ));
+        let requests = server.join().unwrap();
+        assert_eq!(2 * MAX_SYNC_ATTEMPTS, requests.len());
+        for upload in requests.iter().skip(1).step_by(2) {
+            assert_eq!(
+                (
+                    String::from("PUT /sessions HTTP/1.1"),
+                    Some(String::from("\"v1\""))
+                ),
+                *upload
+            );
+        }
+        // The downloaded records are kept locally all the same.
+        assert_eq!(vec![record(1), record(2)], load_history(&path).unwrap());
+    }
+
+    #[test]
+    fn test_merge_records() {
+        let local = vec![(1, "a"), (3, "c"), (3, "d")];
+        let remote = vec![(0, "x"), (1, "a"), (3, "d"), (4, "e")];
+        let merged = merge_records(&local, &remote, |record| record.0);
+        assert_eq!(
+            vec![(0, "x"), (1, "a"), (3, "c"), (3, "d"), (4, "e")],
+            merged
+        );
+        assert_eq!(local, merge_records(&local, &[], |record| record.0));
+        let remote = vec![(2, "b"), (2, "b"), (1, "a")];
+        assert_eq!(
+            vec![(1, "a"), (2, "b"), (3, "c"), (3, "d")],
+            merge_records(&local, &remote, |record| record.0)
+        );
+    }
+}
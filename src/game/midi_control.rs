@@ -0,0 +1,298 @@
+use crate::core::{MidiBinding, MidiCfg, MidiCommand, MidiMessageKind};
+use crate::game::GameController;
+use log::*;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, Read};
+use std::thread;
+
+/// Number of bytes read from the device at once.
+const READ_BUF_SIZE: usize = 64;
+/// Smallest control change value that triggers a command, once the value
+/// rises to it.
+const CC_ON_THRESHOLD: u8 = 64;
+
+/// Channel messages the game commands are bound to. Channels are 0 based.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MidiMessage {
+    ControlChange { channel: u8, number: u8, value: u8 },
+    ProgramChange { channel: u8, program: u8 },
+}
+
+/// Assembles MIDI messages from a raw byte stream, following running status
+/// and skipping system messages.
+#[derive(Debug, Default)]
+struct MidiParser {
+    status: Option<u8>,
+    data: Vec<u8>,
+}
+
+impl MidiParser {
+    fn push(&mut self, byte: u8) -> Option<MidiMessage> {
+        match byte {
+            // Real-time messages may appear anywhere and don't affect the
+            // running status.
+            0xF8..=0xFF => return None,
+            // System exclusive and common messages cancel the running status.
+            0xF0..=0xF7 => {
+                self.status = None;
+                self.data.clear();
+                return None;
+            }
+            0x80..=0xEF => {
+                self.status = Some(byte);
+                self.data.clear();
+                return None;
+            }
+            _ => {}
+        }
+        let status = self.status?;
+        let n_data_bytes = match status & 0xF0 {
+            0xC0 | 0xD0 => 1,
+            _ => 2,
+        };
+        self.data.push(byte);
+        if self.data.len() < n_data_bytes {
+            return None;
+        }
+        let data = std::mem::take(&mut self.data);
+        let channel = status & 0x0F;
+        // Messages other than control and program changes are dropped.
+        match status & 0xF0 {
+            0xB0 => Some(MidiMessage::ControlChange {
+                channel,
+                number: data[0],
+                value: data[1],
+            }),
+            0xC0 => Some(MidiMessage::ProgramChange {
+                channel,
+                program: data[0],
+            }),
+            _ => None,
+        }
+    }
+}
+
+impl MidiBinding {
+    fn matches(&self, message: &MidiMessage) -> bool {
+        let (kind, channel, number) = match *message {
+            MidiMessage::ControlChange {
+                channel,
+                number,
+                value,
+            } if value >= CC_ON_THRESHOLD => (MidiMessageKind::ControlChange, channel, number),
+            MidiMessage::ControlChange { .. } => return false,
+            MidiMessage::ProgramChange { channel, program } => {
+                (MidiMessageKind::ProgramChange, channel, program)
+            }
+        };
+        kind == self.message
+            && number == self.number
+            && match self.channel {
+                Some(ch) => ch == channel + 1,
+                None => true,
+            }
+    }
+}
+
+/// Finds the commands bound to the messages, triggering control changes only
+/// when their value rises across [`CC_ON_THRESHOLD`], so that a foot switch
+/// held down or a continuous controller sending every value triggers once.
+struct MidiTriggers {
+    bindings: Vec<MidiBinding>,
+    /// Last value of each controller, by channel and controller number.
+    cc_values: HashMap<(u8, u8), u8>,
+}
+
+impl MidiTriggers {
+    fn new(bindings: Vec<MidiBinding>) -> MidiTriggers {
+        MidiTriggers {
+            bindings,
+            cc_values: HashMap::new(),
+        }
+    }
+
+    fn command_for(&mut self, message: &MidiMessage) -> Option<MidiCommand> {
+        if let MidiMessage::ControlChange {
+            channel,
+            number,
+            value,
+        } = *message
+        {
+            // Controllers that haven't sent a value yet count as off.
+            let prev = self.cc_values.insert((channel, number), value);
+            if prev.is_some_and(|prev| prev >= CC_ON_THRESHOLD) {
+                return None;
+            }
+        }
+        self.bindings
+            .iter()
+            .find(|binding| binding.matches(message))
+            .map(|binding| binding.command)
+    }
+}
+
+/// Reads MIDI messages from the configured device on a new thread and sends
+/// the commands they are bound to to the game. Does nothing if no device is
+/// configured.
+pub fn spawn_midi_control(cfg: &MidiCfg, controller: GameController) -> io::Result<()> {
+    let path = match &cfg.device {
+        Some(path) => path.clone(),
+        None => return Ok(()),
+    };
+    let mut device = File::open(&path)?;
+    let mut triggers = MidiTriggers::new(cfg.bindings.clone());
+    info!("Reading MIDI commands from {}", path.display());
+    thread::spawn(move || {
+        let mut parser = MidiParser::default();
+        let mut buf = [0u8; READ_BUF_SIZE];
+        loop {
+            let n_read = match device.read(&mut buf) {
+                Ok(0) => return,
+                Ok(n_read) => n_read,
+                Err(err) => {
+                    warn!("Stopped reading MIDI from {}: {}", path.display(), err);
+                    return;
+                }
+            };
+            let commands = buf[..n_read]
+                .iter()
+                .filter_map(|byte| parser.push(*byte))
+                .filter_map(|message| triggers.command_for(&message));
+            for command in commands {
+                let res = match command {
+                    MidiCommand::Skip => controller.skip(),
+                    MidiCommand::Pause => controller.toggle_pause(),
+                    MidiCommand::NextPreset => controller.next_preset(),
+                };
+                // The game has ended.
+                if res.is_err() {
+                    return;
+                }
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(bytes: &[u8]) -> Vec<MidiMessage> {
+        let mut parser = MidiParser::default();
+        bytes.iter().filter_map(|byte| parser.push(*byte)).collect()
+    }
+
+    #[test]
+    fn test_parse_running_status() {
+        // Two control changes on channel 2 sharing a status byte, with a
+        // timing clock in between, and a program change.
+        let messages = parse(&[0xB1, 64, 127, 0xF8, 64, 0, 0xC0, 5]);
+        assert_eq!(
+            vec![
+                MidiMessage::ControlChange {
+                    channel: 1,
+                    number: 64,
+                    value: 127
+                },
+                MidiMessage::ControlChange {
+                    channel: 1,
+                    number: 64,
+                    value: 0
+                },
+                MidiMessage::ProgramChange {
+                    channel: 0,
+                    program: 5
+                },
+            ],
+            messages
+        );
+    }
+
+    #[test]
+    fn test_parse_skips_other_messages() {
+        // A note on, a system exclusive message and data without a status.
+        let messages = parse(&[0x90, 60, 100, 0xF0, 1, 2, 0xF7, 10, 0xD0, 3, 0xB0, 1, 90]);
+        assert_eq!(
+            vec![MidiMessage::ControlChange {
+                channel: 0,
+                number: 1,
+                value: 90
+            }],
+            messages
+        );
+    }
+
+    #[test]
+    fn test_command_for() {
+        let mut triggers = MidiTriggers::new(vec![
+            MidiBinding {
+                message: MidiMessageKind::ControlChange,
+                number: 64,
+                channel: Some(1),
+                command: MidiCommand::Pause,
+            },
+            MidiBinding {
+                message: MidiMessageKind::ProgramChange,
+                number: 2,
+                channel: None,
+                command: MidiCommand::Skip,
+            },
+        ]);
+        let cc = |channel, value| MidiMessage::ControlChange {
+            channel,
+            number: 64,
+            value,
+        };
+        assert_eq!(Some(MidiCommand::Pause), triggers.command_for(&cc(0, 127)));
+        assert_eq!(None, triggers.command_for(&cc(0, 0)));
+        assert_eq!(None, triggers.command_for(&cc(3, 127)));
+        let program_change = MidiMessage::ProgramChange {
+            channel: 9,
+            program: 2,
+        };
+        assert_eq!(
+            Some(MidiCommand::Skip),
+            triggers.command_for(&program_change)
+        );
+        assert_eq!(
+            Some(MidiCommand::Skip),
+            triggers.command_for(&program_change)
+        );
+    }
+
+    #[test]
+    fn test_control_change_rising_edge() {
+        let mut triggers = MidiTriggers::new(vec![MidiBinding {
+            message: MidiMessageKind::ControlChange,
+            number: 1,
+            channel: None,
+            command: MidiCommand::NextPreset,
+        }]);
+        // An expression pedal swept up and down twice, on two channels.
+        let values = [0, 40, 63, 64, 90, 127, 100, 64, 63, 20, 80, 127, 30];
+        let n_triggered = |triggers: &mut MidiTriggers, channel| {
+            values
+                .iter()
+                .filter_map(|&value| {
+                    triggers.command_for(&MidiMessage::ControlChange {
+                        channel,
+                        number: 1,
+                        value,
+                    })
+                })
+                .count()
+        };
+        assert_eq!(2, n_triggered(&mut triggers, 0));
+        assert_eq!(2, n_triggered(&mut triggers, 5));
+        // A switch that sends the same on value again stays pressed.
+        let on = MidiMessage::ControlChange {
+            channel: 0,
+            number: 1,
+            value: 127,
+        };
+        assert_eq!(Some(MidiCommand::NextPreset), triggers.command_for(&on));
+        assert_eq!(None, triggers.command_for(&on));
+    }
+}
@@ -0,0 +1,166 @@
+use crate::game::{GameController, GameError};
+use log::*;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::thread;
+
+/// Commands accepted on the control pipe, one per line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PipeCommand {
+    Pause,
+    Resume,
+    TogglePause,
+    Skip,
+    NextPreset,
+}
+
+impl PipeCommand {
+    fn parse(line: &str) -> Option<PipeCommand> {
+        match line.trim().to_ascii_lowercase().as_str() {
+            "pause" => Some(PipeCommand::Pause),
+            "resume" => Some(PipeCommand::Resume),
+            "toggle" => Some(PipeCommand::TogglePause),
+            "skip" => Some(PipeCommand::Skip),
+            "preset" => Some(PipeCommand::NextPreset),
+            _ => None,
+        }
+    }
+
+    fn send(self, controller: &GameController) -> Result<(), GameError> {
+        match self {
+            PipeCommand::Pause => controller.pause(),
+            PipeCommand::Resume => controller.resume(),
+            PipeCommand::TogglePause => controller.toggle_pause(),
+            PipeCommand::Skip => controller.skip(),
+            PipeCommand::NextPreset => controller.next_preset(),
+        }
+    }
+}
+
+/// Named pipe the game commands are read from, e.g. written by a window
+/// manager keybinding with `echo skip > /tmp/libreguitar.ctl`. The pipe is
+/// removed when this is dropped.
+pub struct PipeControl {
+    path: PathBuf,
+}
+
+impl PipeControl {
+    /// Creates the named pipe at `path` and reads commands from it on a new
+    /// thread until the game ends. A stale pipe left at `path` is reused.
+    pub fn spawn(path: &Path, controller: GameController) -> io::Result<PipeControl> {
+        create_fifo(path)?;
+        let pipe_path = path.to_path_buf();
+        info!("Reading game commands from {}", path.display());
+        thread::spawn(move || loop {
+            // Opening blocks until a writer connects, and reading ends when
+            // the last writer closes the pipe.
+            let pipe = match File::open(&pipe_path) {
+                Ok(pipe) => pipe,
+                Err(err) => {
+                    warn!("Stopped reading {}: {}", pipe_path.display(), err);
+                    return;
+                }
+            };
+            for line in BufReader::new(pipe).lines() {
+                let line = match line {
+                    Ok(line) => line,
+                    Err(_) => break,
+                };
+                match PipeCommand::parse(&line) {
+                    Some(command) => {
+                        // The game has ended.
+                        if command.send(&controller).is_err() {
+                            return;
+                        }
+                    }
+                    None if line.trim().is_empty() => {}
+                    None => warn!(
+                        "Unknown command {:?} on {}. Expected pause, resume, toggle, skip or preset",
+                        line,
+                        pipe_path.display()
+                    ),
+                }
+            }
+        });
+        Ok(PipeControl {
+            path: path.to_path_buf(),
+        })
+    }
+}
+
+impl Drop for PipeControl {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(unix)]
+fn create_fifo(path: &Path) -> io::Result<()> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::fs::FileTypeExt;
+
+    if let Ok(metadata) = std::fs::metadata(path) {
+        if metadata.file_type().is_fifo() {
+            return Ok(());
+        }
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} exists and is not a named pipe", path.display()),
+        ));
+    }
+    let c_path = CString::new(path.as_os_str().as_bytes())
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+    // Only the user may send commands.
+    if unsafe { libc::mkfifo(c_path.as_ptr(), 0o600) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn create_fifo(_: &Path) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Other,
+        "Control pipes are only supported on Unix systems",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Some(PipeCommand::Skip), PipeCommand::parse("skip\n"));
+        assert_eq!(Some(PipeCommand::Pause), PipeCommand::parse(" PAUSE "));
+        assert_eq!(Some(PipeCommand::TogglePause), PipeCommand::parse("toggle"));
+        assert_eq!(Some(PipeCommand::Resume), PipeCommand::parse("resume"));
+        assert_eq!(
+            Some(PipeCommand::NextPreset),
+            PipeCommand::parse("preset\n")
+        );
+        assert_eq!(None, PipeCommand::parse("quit"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_create_fifo() {
+        use std::os::unix::fs::FileTypeExt;
+
+        let dir = std::env::temp_dir().join("libreguitar_test_pipe_control");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("control");
+        let _ = std::fs::remove_file(&path);
+        create_fifo(&path).unwrap();
+        // A pipe left behind by an earlier session is reused.
+        create_fifo(&path).unwrap();
+        let file_type = std::fs::metadata(&path).unwrap().file_type();
+        assert!(file_type.is_fifo());
+        std::fs::remove_file(&path).unwrap();
+        std::fs::write(&path, "").unwrap();
+        assert!(create_fifo(&path).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,263 @@
+use crate::core::AppCfg;
+use crate::game::{load_history, load_targets, write_csv, SessionRecord, TargetRecord};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum StatsExportError {
+    #[error(transparent)]
+    CsvError(#[from] csv::Error),
+    #[error(transparent)]
+    JsonError(#[from] serde_json::Error),
+    #[error(transparent)]
+    IoError(#[from] io::Error),
+}
+
+/// Header lines telling Anki how to import the deck.
+const ANKI_HEADER: &str = "#separator:tab\n#html:false\n#tags column:3\n";
+
+/// Statistics of one note over all recorded targets.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct NoteStats {
+    note: String,
+    n_played: usize,
+    n_correct: usize,
+    accuracy: f64,
+    /// Average response time in seconds.
+    avg_response_time: f64,
+}
+
+/// Everything written to the JSON export.
+#[derive(Debug, Serialize)]
+struct StatsExport<'a> {
+    sessions: &'a [SessionRecord],
+    notes: &'a [NoteStats],
+    targets: &'a [TargetRecord],
+}
+
+/// Aggregates the targets per note, ordered by note name.
+fn note_stats(targets: &[TargetRecord]) -> Vec<NoteStats> {
+    let mut per_note: BTreeMap<&str, Vec<&TargetRecord>> = BTreeMap::new();
+    for target in targets.iter() {
+        per_note.entry(&target.note).or_default().push(target);
+    }
+    per_note
+        .into_iter()
+        .map(|(note, targets)| {
+            let n_played = targets.len();
+            let n_correct = targets.iter().filter(|target| target.correct).count();
+            let total_time: f64 = targets.iter().map(|target| target.response_time).sum();
+            NoteStats {
+                note: String::from(note),
+                n_played,
+                n_correct,
+                accuracy: n_correct as f64 / n_played as f64,
+                avg_response_time: total_time / n_played as f64,
+            }
+        })
+        .collect()
+}
+
+/// Statistics of one fretboard position over all recorded targets.
+#[derive(Debug, Clone, PartialEq)]
+struct PositionStats {
+    note: String,
+    string_idx: usize,
+    fret_idx: usize,
+    n_played: usize,
+    n_correct: usize,
+}
+
+impl PositionStats {
+    fn accuracy(&self) -> f64 {
+        self.n_correct as f64 / self.n_played as f64
+    }
+}
+
+/// The `count` positions with the lowest accuracy, the weakest first. Of the
+/// positions with the same accuracy, the more often played come first.
+fn weakest_positions(targets: &[TargetRecord], count: usize) -> Vec<PositionStats> {
+    let mut per_position: BTreeMap<(usize, usize), PositionStats> = BTreeMap::new();
+    for target in targets.iter() {
+        let stats = per_position
+            .entry((target.string_idx, target.fret_idx))
+            .or_insert_with(|| PositionStats {
+                note: target.note.clone(),
+                string_idx: target.string_idx,
+                fret_idx: target.fret_idx,
+                n_played: 0,
+                n_correct: 0,
+            });
+        stats.n_played += 1;
+        stats.n_correct += target.correct as usize;
+    }
+    let mut positions: Vec<PositionStats> = per_position.values().cloned().collect();
+    positions.sort_by(|a, b| {
+        a.accuracy()
+            .partial_cmp(&b.accuracy())
+            .unwrap()
+            .then(b.n_played.cmp(&a.n_played))
+    });
+    positions.truncate(count);
+    positions
+}
+
+/// Cards asking for the note at the position and for the fret of the note on
+/// the string, as tab separated front, back and tags.
+fn anki_cards(position: &PositionStats) -> [String; 2] {
+    let tags = format!("libreguitar string{}", position.string_idx);
+    [
+        format!(
+            "Which note is on string {}, fret {}?\t{}\t{}",
+            position.string_idx, position.fret_idx, position.note, tags
+        ),
+        format!(
+            "On which fret is {} on string {}?\tFret {}\t{}",
+            position.note, position.string_idx, position.fret_idx, tags
+        ),
+    ]
+}
+
+/// Writes the `count` fretboard positions with the lowest accuracy in the
+/// recorded targets to `path` as an Anki deck that can be imported as a text
+/// file. Returns the number of positions written.
+pub fn export_anki(cfg: &AppCfg, path: &Path, count: usize) -> Result<usize, StatsExportError> {
+    let targets = match &cfg.targets_path {
+        Some(targets_path) => load_targets(targets_path)?,
+        None => Vec::new(),
+    };
+    let positions = weakest_positions(&targets, count);
+    let mut out = BufWriter::new(File::create(path)?);
+    out.write_all(ANKI_HEADER.as_bytes())?;
+    for position in positions.iter() {
+        for card in anki_cards(position).iter() {
+            writeln!(out, "{}", card)?;
+        }
+    }
+    out.flush()?;
+    Ok(positions.len())
+}
+
+/// Writes the recorded sessions, the recorded targets and their per-note
+/// statistics to `dir` as `sessions.csv`, `targets.csv`, `notes.csv` and all
+/// of them together as `stats.json`. Returns the paths of the written files.
+pub fn export_stats(cfg: &AppCfg, dir: &Path) -> Result<Vec<PathBuf>, StatsExportError> {
+    let sessions = match &cfg.history_path {
+        Some(path) => load_history(path)?,
+        None => Vec::new(),
+    };
+    let targets = match &cfg.targets_path {
+        Some(path) => load_targets(path)?,
+        None => Vec::new(),
+    };
+    let notes = note_stats(&targets);
+    fs::create_dir_all(dir)?;
+    let paths: Vec<PathBuf> = ["sessions.csv", "targets.csv", "notes.csv", "stats.json"]
+        .iter()
+        .map(|file_name| dir.join(file_name))
+        .collect();
+    write_csv(&paths[0], &sessions)?;
+    write_csv(&paths[1], &targets)?;
+    write_csv(&paths[2], &notes)?;
+    let export = StatsExport {
+        sessions: &sessions,
+        notes: &notes,
+        targets: &targets,
+    };
+    serde_json::to_writer_pretty(File::create(&paths[3])?, &export)?;
+    Ok(paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(note: &str, correct: bool, response_time: f64) -> TargetRecord {
+        positioned(note, 1, 0, correct, response_time)
+    }
+
+    fn positioned(
+        note: &str,
+        string_idx: usize,
+        fret_idx: usize,
+        correct: bool,
+        response_time: f64,
+    ) -> TargetRecord {
+        TargetRecord {
+            timestamp: 0,
+            note: String::from(note),
+            string_idx,
+            fret_idx,
+            correct,
+            response_time,
+        }
+    }
+
+    #[test]
+    fn test_note_stats() {
+        let targets = vec![
+            target("E4", true, 1.0),
+            target("A2", false, 4.0),
+            target("E4", false, 2.0),
+        ];
+        let stats = note_stats(&targets);
+        assert_eq!(2, stats.len());
+        assert_eq!("A2", stats[0].note);
+        assert_eq!(
+            NoteStats {
+                note: String::from("E4"),
+                n_played: 2,
+                n_correct: 1,
+                accuracy: 0.5,
+                avg_response_time: 1.5,
+            },
+            stats[1]
+        );
+    }
+
+    #[test]
+    fn test_weakest_positions() {
+        let targets = vec![
+            positioned("C4", 2, 1, true, 1.0),
+            positioned("G3", 3, 0, false, 1.0),
+            positioned("C4", 2, 1, false, 1.0),
+            positioned("E4", 1, 0, true, 1.0),
+            positioned("A3", 3, 2, false, 1.0),
+            positioned("A3", 3, 2, false, 1.0),
+        ];
+        let weakest: Vec<(String, usize)> = weakest_positions(&targets, 3)
+            .into_iter()
+            .map(|position| (position.note, position.n_played))
+            .collect();
+        assert_eq!(
+            vec![
+                (String::from("A3"), 2),
+                (String::from("G3"), 1),
+                (String::from("C4"), 2)
+            ],
+            weakest
+        );
+    }
+
+    #[test]
+    fn test_anki_cards() {
+        let position = PositionStats {
+            note: String::from("C4"),
+            string_idx: 2,
+            fret_idx: 1,
+            n_played: 2,
+            n_correct: 1,
+        };
+        assert_eq!(
+            [
+                String::from("Which note is on string 2, fret 1?\tC4\tlibreguitar string2"),
+                String::from("On which fret is C4 on string 2?\tFret 1\tlibreguitar string2"),
+            ],
+            anki_cards(&position)
+        );
+    }
+}
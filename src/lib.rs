@@ -1,20 +1,175 @@
+//! Note detection and fretboard training for the guitar.
+//!
+//! The supported API is re-exported here: the music model ([`Note`],
+//! [`NoteRegistry`], [`Tuning`], [`FretLoc`]) and the practice history are
+//! always built, the note detection ([`AudioAnalyzer`], [`AnalysisResult`])
+//! and the game rules ([`GameSession`], [`GameState`]) with the `analysis`
+//! feature, and the app with its audio input, visualizers and [`EventBus`]
+//! with the default `audio` feature. The modules themselves are private.
+//!
+//! The music model, the note detection and the game rules come from the
+//! `libreguitar-core` crate, which can be used on its own without the audio
+//! and user interface dependencies of the app.
+
+#[cfg(feature = "audio")]
 mod app;
-mod audio_analysis;
+#[cfg(feature = "audio")]
+mod audio_output;
+#[cfg(feature = "audio")]
+mod calibration;
 mod core;
+#[cfg(feature = "audio")]
+mod event_bus;
+#[cfg(feature = "tokio")]
+mod event_stream;
 mod game;
+#[cfg(feature = "audio")]
+mod metrics;
+#[cfg(feature = "audio")]
+mod metronome;
+#[cfg(feature = "audio")]
+mod note_source;
+#[cfg(feature = "audio")]
+mod reference_tone;
+#[cfg(all(test, feature = "audio"))]
+mod simulation;
+#[cfg(feature = "audio")]
+mod tuner;
+#[cfg(feature = "analysis")]
 mod visualization;
 
-use crate::app::{App, AppError};
-pub use crate::core::Cfg;
+#[cfg(feature = "analysis")]
+use libreguitar_core::audio_analysis;
+
+#[cfg(feature = "audio")]
+pub use crate::app::{App, AppBuilder, AppError, AppHandle};
+#[cfg(feature = "analysis")]
+pub use crate::audio_analysis::{
+    read_channel_buffered, AnalysisResult, AudioAnalyzer, DetectionInput, NoiseFloor,
+    NoiseFloorError, NotePeak, PitchDetector, TargetNotes, TooFewNotesError,
+};
+#[cfg(feature = "audio")]
+pub use crate::audio_output::OutputStreamError;
+pub use crate::core::{
+    AppCfg, AudioCfg, Cfg, ConsoleCfg, DuplicateNoteError, FretLoc, FretRange, GameCfg,
+    InvalidTuningError, Note, NoteName, NoteRegistry, NoteRegistryError, RangeError, RangePreset,
+    StringRange, Tuning, TuningError, TuningSpecification,
+};
+#[cfg(feature = "audio")]
+pub use crate::event_bus::{EventBus, Overflow, Subscription, Topic};
+#[cfg(feature = "tokio")]
+pub use crate::event_stream::{AsyncEventBus, EventStream};
+pub use crate::game::{
+    append_targets, append_to_history, load_exercise, load_history, load_targets, ActiveNotes,
+    ExerciseError, ExerciseNote, ExerciseSelector, GameState, LoadExerciseError, RandomSelector,
+    Score, SessionRecord, StatsExportError, TargetRecord, TargetSelector,
+};
+#[cfg(feature = "analysis")]
+pub use crate::game::{GameCommand, GameSession};
+#[cfg(feature = "audio")]
+pub use crate::game::{GameController, GameError};
+#[cfg(feature = "plugins")]
+pub use crate::game::{PluginError, ScriptSelector};
+#[cfg(feature = "sync")]
+pub use crate::game::{SyncError, SyncSummary};
+#[cfg(feature = "audio")]
+pub use crate::metronome::Beat;
+#[cfg(feature = "audio")]
+pub use crate::note_source::{
+    AudioSource, ChannelSource, NoteSource, SampleCallback, SourceErrorCallback,
+};
+#[cfg(feature = "gui")]
+pub use crate::visualization::GuiCfg;
+#[cfg(feature = "audio")]
+pub use crate::visualization::{FrameData, Visualizer};
+#[cfg(feature = "analysis")]
+pub use crate::visualization::{GameEvent, TargetResult, EVENT_VERSION};
 
+#[cfg(feature = "audio")]
 use cpal::Device;
+#[cfg(feature = "audio")]
 use cpal::StreamConfig;
+use std::path::{Path, PathBuf};
 
+/// Runs the game on the input `device` until a visualizer is closed. Host
+/// applications that manage the game themselves can use [`App::spawn`].
+#[cfg(feature = "audio")]
 pub fn run(
     device: Device,
     device_config: StreamConfig,
     app_config: core::Cfg,
 ) -> Result<(), AppError> {
-    let mut app = App::new(device, device_config, app_config)?;
-    app.run()
+    let mut note_source = AudioSource::new(device, device_config);
+    if app_config.app.calibration.on_start {
+        calibration::calibrate(&mut note_source, &app_config)?;
+    }
+    App::builder(app_config, Box::new(note_source))
+        .build()?
+        .run()
+}
+
+/// Measures the noise floor of the input `device` while nothing is played
+/// and saves it for the next games.
+#[cfg(feature = "audio")]
+pub fn calibrate_noise_floor(
+    device: Device,
+    device_config: StreamConfig,
+    app_config: core::Cfg,
+) -> Result<(), AppError> {
+    let mut note_source = AudioSource::new(device, device_config);
+    calibration::calibrate(&mut note_source, &app_config)?;
+    Ok(())
+}
+
+/// Runs the metronome on the output `device` in the terminal, at `tempo`
+/// beats per minute if given instead of the configured tempo.
+#[cfg(feature = "audio")]
+pub fn run_metronome(
+    device: Device,
+    mut app_config: core::Cfg,
+    tempo: Option<f64>,
+) -> Result<(), AppError> {
+    if let Some(tempo) = tempo {
+        app_config.app.metronome.tempo = tempo;
+    }
+    metronome::run_standalone(&device, &app_config.app.metronome)?;
+    Ok(())
+}
+
+/// Shows the note detected on the input `device` and its deviation in cents
+/// in the terminal instead of running the game.
+#[cfg(feature = "audio")]
+pub fn run_tuner(
+    device: Device,
+    device_config: StreamConfig,
+    app_config: core::Cfg,
+) -> Result<(), AppError> {
+    tuner::run_standalone(
+        Box::new(AudioSource::new(device, device_config)),
+        app_config,
+    )
+}
+
+/// Exports the recorded session history and targets to `dir` as CSV and
+/// JSON files, returning the paths of the written files.
+pub fn export_stats(app_config: &core::Cfg, dir: &Path) -> Result<Vec<PathBuf>, StatsExportError> {
+    game::export_stats(&app_config.app, dir)
+}
+
+/// Exports the `count` fretboard positions with the lowest accuracy in the
+/// recorded targets to `path` as an Anki deck, returning the number of
+/// exported positions.
+pub fn export_anki(
+    app_config: &core::Cfg,
+    path: &Path,
+    count: usize,
+) -> Result<usize, StatsExportError> {
+    game::export_anki(&app_config.app, path, count)
+}
+
+/// Syncs the recorded session history and targets with the server of the
+/// `[sync]` table, returning the number of records added to each side.
+#[cfg(feature = "sync")]
+pub fn sync_history(app_config: &core::Cfg) -> Result<SyncSummary, SyncError> {
+    game::sync_history(&app_config.app)
 }
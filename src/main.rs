@@ -5,6 +5,8 @@ use std::fmt::Display;
 use std::fs::OpenOptions;
 use std::io::{self, Write};
 use std::iter;
+use std::path::Path;
+use std::process;
 
 use cpal::traits::DeviceTrait;
 use cpal::traits::HostTrait;
@@ -14,9 +16,15 @@ use cpal::Host;
 use cpal::SampleRate;
 use cpal::StreamConfig;
 
-use libreguitar::{run, Cfg};
+#[cfg(feature = "sync")]
+use libreguitar::sync_history;
+use libreguitar::{
+    calibrate_noise_floor, export_anki, export_stats, run, run_metronome, run_tuner, Cfg,
+};
 
 const APP_CONFIG_PATH: &str = "cfg";
+/// Number of the weakest positions exported by `stats anki` by default.
+const DEFAULT_ANKI_POSITIONS: usize = 20;
 
 fn choose_via_user_input<T>(title_str: &str, options: Vec<T>) -> io::Result<usize>
 where
@@ -83,6 +91,22 @@ fn choose_device_config(_device: &Device) -> StreamConfig {
     }
 }
 
+/// Channels of the device to analyze, counted from 1: a single channel or the
+/// mixdown of all of them.
+fn choose_input_channels(n_channels: usize) -> Vec<usize> {
+    if n_channels <= 1 {
+        return vec![1];
+    }
+    let mut options: Vec<String> = (1..=n_channels)
+        .map(|channel| format!("Channel {}", channel))
+        .collect();
+    options.push(format!("Mixdown of channels 1-{}", n_channels));
+    match choose_via_user_input("Input channels", options).unwrap() {
+        choice if choice < n_channels => vec![choice + 1],
+        _ => (1..=n_channels).collect(),
+    }
+}
+
 fn set_up_logger(log_path: &str) {
     let cfg = LogConfigBuilder::new().set_time_format_str("%FT%T").build();
     let out_file = OpenOptions::new()
@@ -93,20 +117,135 @@ fn set_up_logger(log_path: &str) {
     CombinedLogger::init(vec![WriteLogger::new(LevelFilter::Debug, cfg, out_file)]).unwrap();
 }
 
+fn print_calibration_prompt(app_config: &Cfg) {
+    println!(
+        "Measuring the noise floor for {:.0}s, keep the strings muted",
+        app_config.app.calibration.duration
+    );
+}
+
+/// Removes `--tuning <tuning>` from `args` and returns the tuning.
+fn take_tuning_arg(args: &mut Vec<String>) -> Option<String> {
+    let idx = args.iter().position(|arg| arg == "--tuning")?;
+    if idx + 1 == args.len() {
+        eprintln!("--tuning expects a tuning such as EADGBE or \"D A D G A D\"");
+        process::exit(1);
+    }
+    let tuning = args.remove(idx + 1);
+    args.remove(idx);
+    Some(tuning)
+}
+
 fn main() {
-    let app_config = Cfg::new(APP_CONFIG_PATH).unwrap();
+    let mut app_config = Cfg::new(APP_CONFIG_PATH).unwrap();
     set_up_logger(&app_config.app.log_path);
 
     info!("Using app configs at {}", APP_CONFIG_PATH);
 
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(tuning) = take_tuning_arg(&mut args) {
+        info!("Using tuning {} given on the command line", tuning);
+        app_config.app.tuning = Some(tuning);
+    }
+    #[cfg(feature = "sync")]
+    if args.first().map(|arg| arg.as_str()) == Some("sync") {
+        match sync_history(&app_config) {
+            Ok(summary) => {
+                println!(
+                    "Downloaded {} and uploaded {} records",
+                    summary.downloaded, summary.uploaded
+                );
+                return;
+            }
+            Err(err) => {
+                eprintln!("Could not sync the history: {}", err);
+                process::exit(1);
+            }
+        }
+    }
+    if args.len() >= 2 && args[0] == "stats" && args[1] == "export" {
+        let dir = args.get(2).map_or(".", |dir| dir.as_str());
+        match export_stats(&app_config, Path::new(dir)) {
+            Ok(paths) => {
+                for path in paths.iter() {
+                    println!("Wrote {}", path.display());
+                }
+                return;
+            }
+            Err(err) => {
+                eprintln!("Could not export the statistics: {}", err);
+                process::exit(1);
+            }
+        }
+    }
+    if args.len() >= 2 && args[0] == "stats" && args[1] == "anki" {
+        let path = args
+            .get(2)
+            .map_or("libreguitar_anki.txt", |path| path.as_str());
+        let count = match args.get(3).map(|count| count.parse()) {
+            Some(Ok(count)) => count,
+            Some(Err(_)) => {
+                eprintln!("The number of positions must be a positive integer");
+                process::exit(1);
+            }
+            None => DEFAULT_ANKI_POSITIONS,
+        };
+        match export_anki(&app_config, Path::new(path), count) {
+            Ok(n_positions) => {
+                println!("Wrote {} positions to {}", n_positions, path);
+                return;
+            }
+            Err(err) => {
+                eprintln!("Could not export the Anki deck: {}", err);
+                process::exit(1);
+            }
+        }
+    }
+
     let host = choose_host();
     info!("Using host {}", host.id().name());
 
+    if args.first().map(|arg| arg.as_str()) == Some("metronome") {
+        let tempo = match args.get(1).map(|tempo| tempo.parse::<f64>()) {
+            Some(Ok(tempo)) if tempo.is_finite() && tempo > 0.0 => Some(tempo),
+            Some(_) => {
+                eprintln!("The tempo must be given in beats per minute");
+                process::exit(1);
+            }
+            None => None,
+        };
+        let device = host
+            .default_output_device()
+            .expect("No output device for the metronome");
+        info!("Using output device {}", device.name().unwrap());
+        run_metronome(device, app_config, tempo).unwrap();
+        return;
+    }
+
     let device = choose_device(&host);
     info!("Using device {}", device.name().unwrap());
 
     let device_config = choose_device_config(&device);
     info!("Using device config {:?}", device_config);
 
+    if app_config.app.input_channels.is_empty() {
+        app_config.app.input_channels = choose_input_channels(device_config.channels as usize);
+    }
+    info!("Using input channels {:?}", app_config.app.input_channels);
+
+    if args.first().map(|arg| arg.as_str()) == Some("calibrate") {
+        print_calibration_prompt(&app_config);
+        let path = app_config.app.calibration.path.clone();
+        calibrate_noise_floor(device, device_config, app_config).unwrap();
+        println!("Saved the noise floor to {}", path.display());
+        return;
+    }
+    if args.first().map(|arg| arg.as_str()) == Some("tuner") {
+        run_tuner(device, device_config, app_config).unwrap();
+        return;
+    }
+    if app_config.app.calibration.on_start {
+        print_calibration_prompt(&app_config);
+    }
     run(device, device_config, app_config).unwrap();
 }
@@ -0,0 +1,292 @@
+use log::*;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+/// Largest request that is read from a Prometheus client.
+const MAX_REQUEST_SIZE: usize = 8192;
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Performance counters updated from the audio, game and GUI threads.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    n_callbacks: AtomicU64,
+    callback_micros: AtomicU64,
+    max_callback_micros: AtomicU64,
+    analysis_micros: AtomicU64,
+    /// Errors reported by the input stream, mostly missed audio frames.
+    n_stream_errors: AtomicU64,
+    n_frames: AtomicU64,
+    frame_micros: AtomicU64,
+    max_frame_micros: AtomicU64,
+    /// Most game states waiting to be processed by the app loop at once.
+    max_state_backlog: AtomicU64,
+}
+
+fn micros(duration: Duration) -> u64 {
+    duration.as_micros() as u64
+}
+
+impl Metrics {
+    /// Records an audio callback that took `duration` in total.
+    pub fn record_callback(&self, duration: Duration) {
+        self.n_callbacks.fetch_add(1, Ordering::Relaxed);
+        self.callback_micros
+            .fetch_add(micros(duration), Ordering::Relaxed);
+        self.max_callback_micros
+            .fetch_max(micros(duration), Ordering::Relaxed);
+    }
+
+    /// Records the time the analysis of a block took.
+    pub fn record_analysis(&self, duration: Duration) {
+        self.analysis_micros
+            .fetch_add(micros(duration), Ordering::Relaxed);
+    }
+
+    pub fn record_stream_error(&self) {
+        self.n_stream_errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records a frame of a visualizer that took `duration` to draw.
+    pub fn record_frame(&self, duration: Duration) {
+        self.n_frames.fetch_add(1, Ordering::Relaxed);
+        self.frame_micros
+            .fetch_add(micros(duration), Ordering::Relaxed);
+        self.max_frame_micros
+            .fetch_max(micros(duration), Ordering::Relaxed);
+    }
+
+    /// Records `state_backlog` game states processed at once by the app loop.
+    pub fn record_state_backlog(&self, state_backlog: usize) {
+        self.max_state_backlog
+            .fetch_max(state_backlog as u64, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        MetricsSnapshot {
+            n_callbacks: load(&self.n_callbacks),
+            callback_micros: load(&self.callback_micros),
+            max_callback_micros: load(&self.max_callback_micros),
+            analysis_micros: load(&self.analysis_micros),
+            n_stream_errors: load(&self.n_stream_errors),
+            n_frames: load(&self.n_frames),
+            frame_micros: load(&self.frame_micros),
+            max_frame_micros: load(&self.max_frame_micros),
+            max_state_backlog: load(&self.max_state_backlog),
+        }
+    }
+}
+
+/// Values of the counters at one point in time.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MetricsSnapshot {
+    pub n_callbacks: u64,
+    pub callback_micros: u64,
+    pub max_callback_micros: u64,
+    pub analysis_micros: u64,
+    pub n_stream_errors: u64,
+    pub n_frames: u64,
+    pub frame_micros: u64,
+    pub max_frame_micros: u64,
+    pub max_state_backlog: u64,
+}
+
+/// Average of `total_micros` over `count` in milliseconds.
+fn avg_millis(total_micros: u64, count: u64) -> f64 {
+    if count == 0 {
+        0.0
+    } else {
+        total_micros as f64 / count as f64 / 1000.0
+    }
+}
+
+impl MetricsSnapshot {
+    /// Human readable lines, e.g. for the stats page.
+    pub fn lines(&self) -> Vec<String> {
+        vec![
+            format!(
+                "Audio callback: {:.2} ms avg, {:.2} ms max",
+                avg_millis(self.callback_micros, self.n_callbacks),
+                self.max_callback_micros as f64 / 1000.0
+            ),
+            format!(
+                "Analysis: {:.2} ms avg",
+                avg_millis(self.analysis_micros, self.n_callbacks)
+            ),
+            format!(
+                "Frame: {:.2} ms avg, {:.2} ms max",
+                avg_millis(self.frame_micros, self.n_frames),
+                self.max_frame_micros as f64 / 1000.0
+            ),
+            format!(
+                "Dropped audio frames: {}, state backlog: {}",
+                self.n_stream_errors, self.max_state_backlog
+            ),
+        ]
+    }
+
+    /// The counters in the Prometheus text exposition format.
+    pub fn to_prometheus(self) -> String {
+        let metrics: [(&str, &str, &str, u64); 9] = [
+            (
+                "audio_callbacks_total",
+                "counter",
+                "Audio callbacks",
+                self.n_callbacks,
+            ),
+            (
+                "audio_callback_microseconds_total",
+                "counter",
+                "Time spent in audio callbacks",
+                self.callback_micros,
+            ),
+            (
+                "audio_callback_max_microseconds",
+                "gauge",
+                "Longest audio callback",
+                self.max_callback_micros,
+            ),
+            (
+                "analysis_microseconds_total",
+                "counter",
+                "Time spent analyzing audio",
+                self.analysis_micros,
+            ),
+            (
+                "audio_stream_errors_total",
+                "counter",
+                "Input stream errors such as dropped frames",
+                self.n_stream_errors,
+            ),
+            (
+                "frames_total",
+                "counter",
+                "Frames drawn by the visualizers",
+                self.n_frames,
+            ),
+            (
+                "frame_microseconds_total",
+                "counter",
+                "Time spent drawing frames",
+                self.frame_micros,
+            ),
+            (
+                "frame_max_microseconds",
+                "gauge",
+                "Longest frame",
+                self.max_frame_micros,
+            ),
+            (
+                "state_backlog_max",
+                "gauge",
+                "Most game states waiting for a frame",
+                self.max_state_backlog,
+            ),
+        ];
+        metrics
+            .iter()
+            .map(|(name, kind, help, value)| {
+                format!(
+                    "# HELP libreguitar_{name} {help}\n# TYPE libreguitar_{name} {kind}\nlibreguitar_{name} {value}\n",
+                    name = name,
+                    help = help,
+                    kind = kind,
+                    value = value
+                )
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for MetricsSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.lines().join("; "))
+    }
+}
+
+fn serve_client(mut stream: TcpStream, metrics: &Metrics) -> io::Result<()> {
+    stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+    let mut buf = [0u8; MAX_REQUEST_SIZE];
+    let n_read = stream.read(&mut buf)?;
+    let request = String::from_utf8_lossy(&buf[..n_read]);
+    let target = request.split_whitespace().nth(1).unwrap_or("");
+    let (status, body) = if target == "/metrics" {
+        ("200 OK", metrics.snapshot().to_prometheus())
+    } else {
+        ("404 Not Found", String::from("Not found\n"))
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )
+}
+
+/// Serves the counters at `GET /metrics` on `address` from a new thread, for
+/// scraping by Prometheus.
+pub fn serve_prometheus(address: &str, metrics: Arc<Metrics>) -> io::Result<()> {
+    let listener = TcpListener::bind(address)?;
+    info!("Serving metrics on http://{}/metrics", address);
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let res = stream.and_then(|stream| serve_client(stream, &metrics));
+            if let Err(err) = res {
+                debug!("Could not serve metrics: {}", err);
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot() {
+        let metrics = Metrics::default();
+        metrics.record_callback(Duration::from_micros(300));
+        metrics.record_callback(Duration::from_micros(100));
+        metrics.record_analysis(Duration::from_micros(200));
+        metrics.record_frame(Duration::from_millis(5));
+        metrics.record_frame(Duration::from_millis(1));
+        metrics.record_state_backlog(3);
+        metrics.record_state_backlog(1);
+        metrics.record_stream_error();
+        let snapshot = metrics.snapshot();
+        assert_eq!(2, snapshot.n_callbacks);
+        assert_eq!(300, snapshot.max_callback_micros);
+        assert_eq!(3, snapshot.max_state_backlog);
+        assert_eq!(
+            vec![
+                "Audio callback: 0.20 ms avg, 0.30 ms max",
+                "Analysis: 0.10 ms avg",
+                "Frame: 3.00 ms avg, 5.00 ms max",
+                "Dropped audio frames: 1, state backlog: 3",
+            ],
+            snapshot.lines()
+        );
+    }
+
+    #[test]
+    fn test_to_prometheus() {
+        let snapshot = MetricsSnapshot {
+            n_callbacks: 7,
+            ..MetricsSnapshot::default()
+        };
+        let text = snapshot.to_prometheus();
+        assert!(text.starts_with(
+            "# HELP libreguitar_audio_callbacks_total Audio callbacks\n\
+             # TYPE libreguitar_audio_callbacks_total counter\n\
+             libreguitar_audio_callbacks_total 7\n"
+        ));
+        assert_eq!(9 * 3, text.lines().count());
+    }
+}
@@ -0,0 +1,338 @@
+use crate::app::AppError;
+use crate::audio_output::{start_output_stream, OutputStreamError};
+use crate::core::MetronomeCfg;
+use console::{Key, Term};
+use cpal::{Device, Stream};
+use std::collections::VecDeque;
+use std::f64::consts::PI;
+use std::io::Write;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Length of a click in seconds.
+const CLICK_LENGTH: f64 = 0.03;
+/// Time constant of the exponential decay of a click in seconds.
+const CLICK_DECAY: f64 = 0.006;
+const ACCENT_FREQUENCY: f64 = 1600.0;
+const BEAT_FREQUENCY: f64 = 1000.0;
+/// Loudness of unaccented clicks relative to the accented ones.
+const BEAT_GAIN: f64 = 0.6;
+const MIN_TEMPO: f64 = 20.0;
+const MAX_TEMPO: f64 = 400.0;
+/// Taps further apart than this start a new tap tempo measurement.
+const MAX_TAP_GAP: Duration = Duration::from_secs(2);
+/// Number of most recent taps the tempo is averaged over.
+const TAP_COUNT: usize = 4;
+/// Tempo change of the + and - keys in beats per minute.
+const TEMPO_STEP: f64 = 5.0;
+
+/// A beat of the metronome as it is clicked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Beat {
+    /// Position of the beat in its bar, counted from 0.
+    pub idx: usize,
+    pub accent: bool,
+}
+
+fn clamp_tempo(tempo: f64) -> f64 {
+    tempo.clamp(MIN_TEMPO, MAX_TEMPO)
+}
+
+/// Renders the clicks sample by sample and keeps time.
+#[derive(Debug)]
+struct ClickGenerator {
+    sample_rate: f64,
+    tempo: f64,
+    /// Whether each beat of the bar is accented.
+    accents: Vec<bool>,
+    volume: f64,
+    /// Samples since the start of the current beat.
+    pos: usize,
+    beat: Beat,
+}
+
+impl ClickGenerator {
+    fn new(sample_rate: f64, cfg: &MetronomeCfg) -> ClickGenerator {
+        let accents = (1..=cfg.beats_per_bar.max(1))
+            .map(|beat| cfg.accents.contains(&beat))
+            .collect::<Vec<bool>>();
+        let beat = Beat {
+            idx: 0,
+            accent: accents[0],
+        };
+        ClickGenerator {
+            sample_rate,
+            tempo: clamp_tempo(cfg.tempo),
+            accents,
+            volume: cfg.volume.clamp(0.0, 1.0),
+            pos: 0,
+            beat,
+        }
+    }
+
+    fn samples_per_beat(&self) -> usize {
+        (self.sample_rate * 60.0 / self.tempo).round() as usize
+    }
+
+    /// Next sample, and the beat if it starts with this sample.
+    fn next_sample(&mut self) -> (f64, Option<Beat>) {
+        if self.pos >= self.samples_per_beat() {
+            self.pos = 0;
+            let idx = (self.beat.idx + 1) % self.accents.len();
+            self.beat = Beat {
+                idx,
+                accent: self.accents[idx],
+            };
+        }
+        let started = if self.pos == 0 { Some(self.beat) } else { None };
+        let t = self.pos as f64 / self.sample_rate;
+        self.pos += 1;
+        if t >= CLICK_LENGTH {
+            return (0.0, started);
+        }
+        let (frequency, gain) = if self.beat.accent {
+            (ACCENT_FREQUENCY, 1.0)
+        } else {
+            (BEAT_FREQUENCY, BEAT_GAIN)
+        };
+        let envelope = (-t / CLICK_DECAY).exp();
+        let sample = self.volume * gain * envelope * (2.0 * PI * frequency * t).sin();
+        (sample, started)
+    }
+}
+
+/// Estimates the tempo from the intervals between key presses.
+#[derive(Debug, Default)]
+struct TapTempo {
+    taps: VecDeque<Instant>,
+}
+
+impl TapTempo {
+    /// Records a tap at `now` and returns the tempo of the recent taps, if
+    /// there are at least two of them.
+    fn tap(&mut self, now: Instant) -> Option<f64> {
+        if let Some(last) = self.taps.back() {
+            if now.duration_since(*last) > MAX_TAP_GAP {
+                self.taps.clear();
+            }
+        }
+        self.taps.push_back(now);
+        if self.taps.len() > TAP_COUNT {
+            self.taps.pop_front();
+        }
+        let first = self.taps.front()?;
+        let n_intervals = self.taps.len() - 1;
+        if n_intervals == 0 {
+            return None;
+        }
+        let avg_interval = now.duration_since(*first).as_secs_f64() / n_intervals as f64;
+        Some(clamp_tempo(60.0 / avg_interval))
+    }
+}
+
+/// Source of the samples of `generator` that follows the tempo changes
+/// received on `tempo_rx` and calls `on_beat` with every beat.
+fn click_source(
+    mut generator: ClickGenerator,
+    tempo_rx: mpsc::Receiver<f64>,
+    mut on_beat: impl FnMut(Beat) + Send + 'static,
+) -> impl FnMut() -> f64 + Send + 'static {
+    move || {
+        if let Some(tempo) = tempo_rx.try_iter().last() {
+            generator.tempo = tempo;
+        }
+        let (sample, beat) = generator.next_sample();
+        if let Some(beat) = beat {
+            on_beat(beat);
+        }
+        sample
+    }
+}
+
+/// A metronome clicking on an output device until dropped.
+pub struct Metronome {
+    // Clicks only as long as the stream lives.
+    _stream: Stream,
+    tempo_tx: mpsc::Sender<f64>,
+}
+
+impl Metronome {
+    /// Starts clicking on `device`. `on_beat` is called with every beat as it
+    /// is played, so that the metronome can be used as a clock.
+    pub fn start(
+        device: &Device,
+        cfg: &MetronomeCfg,
+        on_beat: impl FnMut(Beat) + Send + 'static,
+    ) -> Result<Metronome, OutputStreamError> {
+        let (tempo_tx, tempo_rx) = mpsc::channel();
+        let stream = start_output_stream(device, |sample_rate| {
+            click_source(ClickGenerator::new(sample_rate, cfg), tempo_rx, on_beat)
+        })?;
+        Ok(Metronome {
+            _stream: stream,
+            tempo_tx,
+        })
+    }
+
+    /// Changes the tempo from the next beat on.
+    pub fn set_tempo(&self, tempo: f64) {
+        // The stream outlives the sender, so the receiver can't be gone.
+        let _ = self.tempo_tx.send(clamp_tempo(tempo));
+    }
+}
+
+/// Beats of the bar with the current one highlighted, e.g. `○ ● ○ ○`.
+fn format_bar(beat: &Beat, beats_per_bar: usize) -> String {
+    (0..beats_per_bar)
+        .map(|idx| if idx == beat.idx { "●" } else { "○" })
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+/// Runs the metronome on `device` in the terminal until q or Escape is
+/// pressed. Space or t taps the tempo, + and - change it in steps.
+pub fn run_standalone(device: &Device, cfg: &MetronomeCfg) -> Result<(), AppError> {
+    let (beat_tx, beat_rx) = mpsc::channel();
+    let metronome = Metronome::start(device, cfg, move |beat| {
+        let _ = beat_tx.send(beat);
+    })?;
+    let (key_tx, key_rx) = mpsc::channel();
+    thread::spawn(move || {
+        let term = Term::stdout();
+        while let Ok(key) = term.read_key() {
+            if key_tx.send(key).is_err() {
+                return;
+            }
+        }
+    });
+    let mut term = Term::stdout();
+    let beats_per_bar = cfg.beats_per_bar.max(1);
+    let mut tempo = clamp_tempo(cfg.tempo);
+    let mut tap_tempo = TapTempo::default();
+    writeln!(term, "space/t: tap tempo, +/-: faster/slower, q: quit")?;
+    loop {
+        let key = match key_rx.recv_timeout(Duration::from_millis(10)) {
+            Ok(key) => Some(key),
+            Err(mpsc::RecvTimeoutError::Timeout) => None,
+            Err(mpsc::RecvTimeoutError::Disconnected) => return Ok(()),
+        };
+        let new_tempo = match key {
+            Some(Key::Escape) | Some(Key::Char('q')) => break,
+            Some(Key::Char(' ')) | Some(Key::Char('t')) => tap_tempo.tap(Instant::now()),
+            Some(Key::Char('+')) | Some(Key::Char('=')) => Some(tempo + TEMPO_STEP),
+            Some(Key::Char('-')) => Some(tempo - TEMPO_STEP),
+            _ => None,
+        };
+        if let Some(new_tempo) = new_tempo {
+            tempo = clamp_tempo(new_tempo);
+            metronome.set_tempo(tempo);
+        }
+        if let Some(beat) = beat_rx.try_iter().last() {
+            term.clear_line()?;
+            write!(
+                term,
+                "{:>5.1} BPM  {}",
+                tempo,
+                format_bar(&beat, beats_per_bar)
+            )?;
+        }
+    }
+    writeln!(term)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event_bus::EventBus;
+
+    fn cfg(tempo: f64) -> MetronomeCfg {
+        MetronomeCfg {
+            tempo,
+            beats_per_bar: 3,
+            accents: vec![1],
+            ..MetronomeCfg::default()
+        }
+    }
+
+    #[test]
+    fn test_click_timing() {
+        // 120 BPM at 100 samples per second is a beat every 50 samples.
+        let mut generator = ClickGenerator::new(100.0, &cfg(120.0));
+        let beats: Vec<(usize, Beat)> = (0..160)
+            .filter_map(|i| generator.next_sample().1.map(|beat| (i, beat)))
+            .collect();
+        let beat = |idx, accent| Beat { idx, accent };
+        assert_eq!(
+            vec![
+                (0, beat(0, true)),
+                (50, beat(1, false)),
+                (100, beat(2, false)),
+                (150, beat(0, true)),
+            ],
+            beats
+        );
+    }
+
+    #[test]
+    fn test_beats_on_event_bus() {
+        let bus = EventBus::default();
+        let beats = bus.beats.subscribe();
+        let topic = bus.beats.clone();
+        let (tempo_tx, tempo_rx) = mpsc::channel();
+        let mut source = click_source(
+            ClickGenerator::new(100.0, &cfg(120.0)),
+            tempo_rx,
+            move |beat| {
+                topic.publish(beat);
+            },
+        );
+        (0..100).for_each(|_| {
+            source();
+        });
+        // At 240 BPM the beats are 25 samples apart.
+        tempo_tx.send(240.0).unwrap();
+        (0..30).for_each(|_| {
+            source();
+        });
+        let beat = |idx, accent| Beat { idx, accent };
+        assert_eq!(beat(0, true), beats.try_recv().unwrap());
+        assert_eq!(beat(1, false), beats.try_recv().unwrap());
+        assert_eq!(beat(2, false), beats.try_recv().unwrap());
+        assert_eq!(beat(0, true), beats.try_recv().unwrap());
+        assert!(beats.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_click_decays() {
+        let mut generator = ClickGenerator::new(44100.0, &cfg(60.0));
+        let samples: Vec<f64> = (0..44100).map(|_| generator.next_sample().0).collect();
+        let click_end = (CLICK_LENGTH * 44100.0) as usize;
+        let peak = samples.iter().fold(0.0f64, |max, x| max.max(x.abs()));
+        assert!(peak > 0.3 && peak <= 0.5);
+        assert!(samples[click_end..].iter().all(|x| *x == 0.0));
+    }
+
+    #[test]
+    fn test_tap_tempo() {
+        let mut tap_tempo = TapTempo::default();
+        let start = Instant::now();
+        let at = |millis| start + Duration::from_millis(millis);
+        assert_eq!(None, tap_tempo.tap(at(0)));
+        assert_eq!(Some(120.0), tap_tempo.tap(at(500)));
+        assert_eq!(Some(120.0), tap_tempo.tap(at(1000)));
+        // A long pause starts over.
+        assert_eq!(None, tap_tempo.tap(at(5000)));
+        assert_eq!(Some(60.0), tap_tempo.tap(at(6000)));
+    }
+
+    #[test]
+    fn test_format_bar() {
+        let beat = Beat {
+            idx: 1,
+            accent: false,
+        };
+        assert_eq!("○ ● ○ ○", format_bar(&beat, 4));
+    }
+}
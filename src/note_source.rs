@@ -0,0 +1,176 @@
+use crate::app::AppError;
+use cpal::traits::{DeviceTrait, StreamTrait};
+use cpal::{Device, Stream, StreamConfig};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::thread;
+
+/// Receives the interleaved samples of every channel read by a note source.
+pub type SampleCallback = dyn FnMut(&[f32]) + Send;
+/// Receives the errors a note source recovers from, e.g. missed frames.
+pub type SourceErrorCallback = dyn FnMut(String) + Send;
+
+/// Source of the samples the played notes are detected in.
+pub trait NoteSource {
+    fn sample_rate(&self) -> usize;
+    fn n_channels(&self) -> usize;
+    /// Starts calling `callback` with every new block of samples until the
+    /// source is dropped. Only the first channel is analyzed.
+    fn start(
+        &mut self,
+        callback: Box<SampleCallback>,
+        on_error: Box<SourceErrorCallback>,
+    ) -> Result<(), AppError>;
+    /// Stops calling the callback of `start`.
+    fn stop(&mut self);
+}
+
+/// Reads the samples from an audio input device.
+pub struct AudioSource {
+    device: Device,
+    config: StreamConfig,
+    /// Keeps reading the device while it lives.
+    stream: Option<Stream>,
+}
+
+impl AudioSource {
+    pub fn new(device: Device, config: StreamConfig) -> AudioSource {
+        AudioSource {
+            device,
+            config,
+            stream: None,
+        }
+    }
+}
+
+impl NoteSource for AudioSource {
+    fn sample_rate(&self) -> usize {
+        self.config.sample_rate.0 as usize
+    }
+
+    fn n_channels(&self) -> usize {
+        self.config.channels as usize
+    }
+
+    fn start(
+        &mut self,
+        mut callback: Box<SampleCallback>,
+        mut on_error: Box<SourceErrorCallback>,
+    ) -> Result<(), AppError> {
+        let stream = self.device.build_input_stream(
+            &self.config,
+            move |data: &[f32], _: &cpal::InputCallbackInfo| callback(data),
+            move |err| on_error(err.to_string()),
+        )?;
+        stream.play()?;
+        self.stream = Some(stream);
+        Ok(())
+    }
+
+    fn stop(&mut self) {
+        // Dropping the stream closes it.
+        self.stream = None;
+    }
+}
+
+/// Reads the sample blocks sent by the host application, e.g. decoded from a
+/// WAV file, synthesized from MIDI notes or generated for a test. The source
+/// stops when the sender is dropped.
+pub struct ChannelSource {
+    rx: Option<mpsc::Receiver<Vec<f32>>>,
+    sample_rate: usize,
+    n_channels: usize,
+    /// Makes the reading thread drop the blocks that are still sent.
+    stopped: Arc<AtomicBool>,
+}
+
+impl ChannelSource {
+    pub fn new(
+        rx: mpsc::Receiver<Vec<f32>>,
+        sample_rate: usize,
+        n_channels: usize,
+    ) -> ChannelSource {
+        ChannelSource {
+            rx: Some(rx),
+            sample_rate,
+            n_channels,
+            stopped: Arc::new(AtomicBool::new(false)),
+        }
+    }
+}
+
+impl NoteSource for ChannelSource {
+    fn sample_rate(&self) -> usize {
+        self.sample_rate
+    }
+
+    fn n_channels(&self) -> usize {
+        self.n_channels
+    }
+
+    fn start(
+        &mut self,
+        mut callback: Box<SampleCallback>,
+        _: Box<SourceErrorCallback>,
+    ) -> Result<(), AppError> {
+        if let Some(rx) = self.rx.take() {
+            let stopped = self.stopped.clone();
+            thread::spawn(move || {
+                for block in rx.iter() {
+                    if stopped.load(Ordering::Relaxed) {
+                        return;
+                    }
+                    callback(&block);
+                }
+            });
+        }
+        Ok(())
+    }
+
+    fn stop(&mut self) {
+        self.stopped.store(true, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_channel_source() {
+        let (tx, rx) = mpsc::channel();
+        let mut source = ChannelSource::new(rx, 8000, 1);
+        let (block_tx, block_rx) = mpsc::channel();
+        source
+            .start(
+                Box::new(move |data: &[f32]| block_tx.send(data.to_vec()).unwrap()),
+                Box::new(|_| {}),
+            )
+            .unwrap();
+        tx.send(vec![0.5, -0.5]).unwrap();
+        tx.send(vec![1.0]).unwrap();
+        drop(tx);
+        let blocks: Vec<Vec<f32>> = block_rx.iter().collect();
+        assert_eq!(vec![vec![0.5, -0.5], vec![1.0]], blocks);
+    }
+
+    #[test]
+    fn test_channel_source_stop() {
+        let (tx, rx) = mpsc::channel();
+        let mut source = ChannelSource::new(rx, 8000, 1);
+        let (block_tx, block_rx) = mpsc::channel();
+        source
+            .start(
+                Box::new(move |data: &[f32]| block_tx.send(data.to_vec()).unwrap()),
+                Box::new(|_| {}),
+            )
+            .unwrap();
+        tx.send(vec![0.5]).unwrap();
+        assert_eq!(vec![0.5], block_rx.recv().unwrap());
+        source.stop();
+        tx.send(vec![1.0]).unwrap();
+        drop(tx);
+        assert_eq!(0, block_rx.iter().count());
+    }
+}
@@ -0,0 +1,201 @@
+use crate::audio_output::{start_output_stream, OutputStreamError};
+use crate::core::{Note, ReferenceToneCfg, Waveform};
+use cpal::{Device, Stream};
+use rand::Rng;
+use std::f64::consts::PI;
+use std::sync::mpsc;
+
+/// Fade in and out time of the sine tone in seconds, avoiding clicks.
+const SINE_FADE: f64 = 0.02;
+/// Loss of the Karplus-Strong feedback loop per period. Lower values make
+/// the pluck die out faster.
+const PLUCK_DECAY: f64 = 0.996;
+
+/// A single tone being played.
+#[derive(Debug)]
+pub enum Voice {
+    Sine {
+        frequency: f64,
+    },
+    /// Karplus-Strong string model: a period of noise filtered in a loop.
+    Pluck {
+        delay_line: Vec<f64>,
+        idx: usize,
+    },
+}
+
+impl Voice {
+    pub fn new(waveform: Waveform, frequency: f64, sample_rate: f64) -> Voice {
+        match waveform {
+            Waveform::Sine => Voice::Sine { frequency },
+            Waveform::Pluck => {
+                let period = ((sample_rate / frequency).round() as usize).max(2);
+                let mut rng = rand::thread_rng();
+                Voice::Pluck {
+                    delay_line: (0..period).map(|_| rng.gen_range(-1.0..1.0)).collect(),
+                    idx: 0,
+                }
+            }
+        }
+    }
+
+    /// Sample at `t` seconds into the tone, which lasts `duration` seconds.
+    pub fn next_sample(&mut self, t: f64, duration: f64) -> f64 {
+        match self {
+            Voice::Sine { frequency } => {
+                let fade = (t / SINE_FADE).min((duration - t) / SINE_FADE).min(1.0);
+                fade * (2.0 * PI * *frequency * t).sin()
+            }
+            Voice::Pluck { delay_line, idx } => {
+                let next_idx = (*idx + 1) % delay_line.len();
+                let sample = delay_line[*idx];
+                delay_line[*idx] = PLUCK_DECAY * 0.5 * (sample + delay_line[next_idx]);
+                *idx = next_idx;
+                sample
+            }
+        }
+    }
+}
+
+/// Renders the requested tones, a new tone cutting off the previous one.
+#[derive(Debug)]
+struct ToneGenerator {
+    sample_rate: f64,
+    waveform: Waveform,
+    volume: f64,
+    /// Length of a tone in samples.
+    n_samples: usize,
+    voice: Option<Voice>,
+    /// Samples since the start of the voice.
+    pos: usize,
+}
+
+impl ToneGenerator {
+    fn new(sample_rate: f64, cfg: &ReferenceToneCfg) -> ToneGenerator {
+        ToneGenerator {
+            sample_rate,
+            waveform: cfg.waveform,
+            volume: cfg.volume.clamp(0.0, 1.0),
+            n_samples: (cfg.duration.max(0.0) * sample_rate) as usize,
+            voice: None,
+            pos: 0,
+        }
+    }
+
+    fn play(&mut self, frequency: f64) {
+        self.voice = Some(Voice::new(self.waveform, frequency, self.sample_rate));
+        self.pos = 0;
+    }
+
+    fn next_sample(&mut self) -> f64 {
+        if self.pos >= self.n_samples {
+            self.voice = None;
+        }
+        let voice = match &mut self.voice {
+            Some(voice) => voice,
+            None => return 0.0,
+        };
+        let t = self.pos as f64 / self.sample_rate;
+        let duration = self.n_samples as f64 / self.sample_rate;
+        self.pos += 1;
+        self.volume * voice.next_sample(t, duration)
+    }
+}
+
+/// Handle for playing tones from other threads, e.g. from the GUI.
+#[derive(Debug, Clone)]
+pub struct TonePlayer {
+    frequency_tx: mpsc::Sender<f64>,
+}
+
+impl TonePlayer {
+    /// Plays the pitch of `note`, cutting off the tone that is playing.
+    pub fn play(&self, note: &Note) {
+        // Nothing is played once the output stream is gone.
+        let _ = self.frequency_tx.send(note.frequency);
+    }
+}
+
+/// Output stream synthesizing the reference tones until dropped.
+pub struct ReferenceTone {
+    _stream: Stream,
+    player: TonePlayer,
+}
+
+impl ReferenceTone {
+    pub fn start(
+        device: &Device,
+        cfg: &ReferenceToneCfg,
+    ) -> Result<ReferenceTone, OutputStreamError> {
+        let (frequency_tx, frequency_rx) = mpsc::channel();
+        let stream = start_output_stream(device, |sample_rate| {
+            let mut generator = ToneGenerator::new(sample_rate, cfg);
+            move || {
+                if let Some(frequency) = frequency_rx.try_iter().last() {
+                    generator.play(frequency);
+                }
+                generator.next_sample()
+            }
+        })?;
+        Ok(ReferenceTone {
+            _stream: stream,
+            player: TonePlayer { frequency_tx },
+        })
+    }
+
+    pub fn player(&self) -> TonePlayer {
+        self.player.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f64 = 8000.0;
+
+    fn generator(waveform: Waveform) -> ToneGenerator {
+        let cfg = ReferenceToneCfg {
+            waveform,
+            duration: 0.5,
+            volume: 0.5,
+            ..ReferenceToneCfg::default()
+        };
+        ToneGenerator::new(SAMPLE_RATE, &cfg)
+    }
+
+    /// Number of upward zero crossings in `samples`.
+    fn n_periods(samples: &[f64]) -> usize {
+        samples
+            .windows(2)
+            .filter(|pair| pair[0] < 0.0 && pair[1] >= 0.0)
+            .count()
+    }
+
+    #[test]
+    fn test_sine_tone() {
+        let mut generator = generator(Waveform::Sine);
+        assert_eq!(0.0, generator.next_sample());
+        generator.play(440.0);
+        let samples: Vec<f64> = (0..8000).map(|_| generator.next_sample()).collect();
+        let (tone, silence) = samples.split_at(4000);
+        assert!((219..=221).contains(&n_periods(tone)));
+        assert!(tone.iter().all(|x| x.abs() <= 0.5));
+        assert!(tone[0].abs() < 1e-9);
+        assert!(silence.iter().all(|x| *x == 0.0));
+    }
+
+    #[test]
+    fn test_pluck_is_periodic_and_decays() {
+        let mut generator = generator(Waveform::Pluck);
+        generator.play(100.0);
+        let samples: Vec<f64> = (0..4000).map(|_| generator.next_sample()).collect();
+        let energy =
+            |range: std::ops::Range<usize>| -> f64 { samples[range].iter().map(|x| x * x).sum() };
+        assert!(energy(3000..3800) < energy(0..800));
+        // The filtered noise repeats about every 80 samples at 100 Hz.
+        let window = 3000..3400;
+        let correlation: f64 = window.clone().map(|i| samples[i] * samples[i + 80]).sum();
+        assert!(correlation > 0.9 * energy(window));
+    }
+}
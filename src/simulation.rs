@@ -0,0 +1,312 @@
+use crate::audio_analysis::{read_channel_buffered, AnalysisResult, AudioAnalyzer};
+use crate::core::{
+    Cfg, FretLoc, FretRange, Note, NoteRegistry, PitchMethod, StringRange, Tuning, Waveform,
+};
+use crate::event_bus::EventBus;
+use crate::game::{ActiveNotes, GameLogic, GameState, Score, TargetSelector};
+use crate::reference_tone::Voice;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::VecDeque;
+use std::f64::consts::PI;
+
+/// Sample rate of the synthesized input.
+pub const SAMPLE_RATE: usize = 44100;
+/// Samples per audio callback, as delivered by a typical sound card.
+const CALLBACK_SIZE: usize = 512;
+/// Directory of the shipped configuration, relative to the crate root where
+/// the tests are run.
+const CFG_DIR: &str = "cfg";
+
+/// `duration` seconds of `frequency` played with `waveform` at full volume.
+/// Unlike the reference tones, the sine is held without fading in and out,
+/// and the noise a pluck starts from is seeded with the frequency, so that
+/// the same note always sounds the same.
+pub fn synthesize(waveform: Waveform, frequency: f64, duration: f64) -> Vec<f32> {
+    let n_samples = (duration * SAMPLE_RATE as f64) as usize;
+    let times = (0..n_samples).map(|i| i as f64 / SAMPLE_RATE as f64);
+    match waveform {
+        Waveform::Sine => times
+            .map(|t| (2.0 * PI * frequency * t).sin() as f32)
+            .collect(),
+        Waveform::Pluck => {
+            let period = ((SAMPLE_RATE as f64 / frequency).round() as usize).max(2);
+            let mut rng = StdRng::seed_from_u64(frequency.to_bits());
+            let mut voice = Voice::Pluck {
+                delay_line: (0..period).map(|_| rng.gen_range(-1.0..1.0)).collect(),
+                idx: 0,
+            };
+            times
+                .map(|t| voice.next_sample(t, duration) as f32)
+                .collect()
+        }
+    }
+}
+
+/// Shipped configuration along with its note table and tuning.
+fn load_cfg() -> (Cfg, NoteRegistry, Tuning) {
+    let cfg = Cfg::new(CFG_DIR).unwrap();
+    let note_registry = cfg.app.note_registry().unwrap();
+    let tuning = Tuning::from_csv(&cfg.app.tuning_path, &note_registry).unwrap();
+    (cfg, note_registry, tuning)
+}
+
+/// Plays synthesized notes to the analyzer and the game the way the audio
+/// input does, so that the detection and the game rules can be tested end to
+/// end without audio hardware.
+pub struct Simulation {
+    analyzer: AudioAnalyzer,
+    /// Sliding window of the last block of samples, as kept by the analysis
+    /// thread.
+    window: VecDeque<f64>,
+}
+
+impl Simulation {
+    /// Simulation with the shipped configuration and note tables.
+    pub fn new() -> Simulation {
+        Simulation::with_pitch_method(load_cfg().0.audio.pitch_method)
+    }
+
+    /// Simulation with the shipped configuration, but picking the pitch with
+    /// `pitch_method`.
+    pub fn with_pitch_method(pitch_method: PitchMethod) -> Simulation {
+        let (mut cfg, note_registry, _) = load_cfg();
+        cfg.audio.pitch_method = pitch_method;
+        Simulation {
+            analyzer: AudioAnalyzer::new(SAMPLE_RATE, note_registry.notes(), cfg.audio).unwrap(),
+            window: VecDeque::from(vec![0.0; cfg.app.block_size]),
+        }
+    }
+
+    /// Analysis of every callback of `samples`.
+    pub fn analyze(&mut self, samples: &[f32]) -> Vec<AnalysisResult> {
+        samples
+            .chunks(CALLBACK_SIZE)
+            .map(|callback| {
+                read_channel_buffered(callback, 1, 0, &mut self.window);
+                self.analyzer
+                    .identify_next_note(self.window.iter().cloned(), callback.len())
+            })
+            .collect()
+    }
+
+    /// Plays the notes of `targets` one after another for `duration` seconds
+    /// each to a game with these targets, and returns every state of the
+    /// game.
+    pub fn play_game(
+        &mut self,
+        targets: &[FretLoc],
+        waveform: Waveform,
+        duration: f64,
+    ) -> Vec<GameState> {
+        let (cfg, note_registry, tuning) = load_cfg();
+        let active_notes = ActiveNotes::new(
+            &note_registry,
+            &tuning,
+            cfg.game.strings().unwrap(),
+            cfg.game.frets().unwrap(),
+        );
+        let bus = EventBus::default();
+        let states = bus.states.subscribe();
+        let mut game_logic = GameLogic::new(
+            &bus,
+            note_registry,
+            tuning,
+            cfg.game,
+            Box::new(Sequence(targets.iter().cloned().collect())),
+        )
+        .unwrap();
+        game_logic.play().unwrap();
+        for loc in targets {
+            let note = active_notes.get(loc).unwrap();
+            let samples = synthesize(waveform, note.frequency, duration);
+            for analysis in self.analyze(&samples) {
+                bus.analysis.publish(analysis);
+            }
+        }
+        // The game ends once the detections run out.
+        drop(bus);
+        let mut played = Vec::new();
+        while let Ok(state) = states.recv() {
+            played.push(state);
+        }
+        played
+    }
+}
+
+/// Plays the given targets in order.
+struct Sequence(VecDeque<FretLoc>);
+
+impl TargetSelector for Sequence {
+    fn next_target(&mut self, notes: &ActiveNotes, _: &Score) -> Option<(Note, FretLoc)> {
+        let loc = self.0.pop_front()?;
+        Some((notes.get(&loc)?.clone(), loc))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::{App, AppHandle};
+    use crate::event_bus::{Subscription, SUBSCRIBER_CAPACITY};
+    use crate::note_source::ChannelSource;
+    use crate::visualization::GameEvent;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    fn loc(string_idx: usize, fret_idx: usize) -> FretLoc {
+        FretLoc {
+            string_idx,
+            fret_idx,
+        }
+    }
+
+    /// Share of the analyses in the second half of `duration` seconds of
+    /// `note` that detect it with `pitch_method`. The first half leaves time
+    /// for the analysis window to fill and for the attack of a pluck to pass.
+    fn detection_rate(
+        pitch_method: PitchMethod,
+        waveform: Waveform,
+        note: &Note,
+        duration: f64,
+    ) -> f64 {
+        let mut simulation = Simulation::with_pitch_method(pitch_method);
+        let analyses = simulation.analyze(&synthesize(waveform, note.frequency, duration));
+        let settled = &analyses[analyses.len() / 2..];
+        let n_detected = settled
+            .iter()
+            .filter(|analysis| analysis.note.as_ref() == Some(note))
+            .count();
+        n_detected as f64 / settled.len() as f64
+    }
+
+    /// Open string and seventh fret of every string.
+    fn played_notes() -> Vec<Note> {
+        let (_, note_registry, tuning) = load_cfg();
+        let active_notes = ActiveNotes::new(
+            &note_registry,
+            &tuning,
+            StringRange::new(1, 7).unwrap(),
+            FretRange::new(0, 8).unwrap(),
+        );
+        (1..7)
+            .flat_map(|string_idx| vec![loc(string_idx, 0), loc(string_idx, 7)])
+            .map(|loc| active_notes.get(&loc).unwrap().clone())
+            .collect()
+    }
+
+    fn assert_detects_notes(pitch_method: PitchMethod, waveform: Waveform, duration: f64) {
+        for note in played_notes() {
+            let rate = detection_rate(pitch_method, waveform, &note, duration);
+            assert!(rate > 0.9, "{} detected {}", note.name_octave(), rate);
+        }
+    }
+
+    #[test]
+    fn test_detects_sine_notes() {
+        let (cfg, _, _) = load_cfg();
+        assert_detects_notes(cfg.audio.pitch_method, Waveform::Sine, 0.3);
+    }
+
+    #[test]
+    fn test_harmonic_product_detects_sine_notes() {
+        assert_detects_notes(PitchMethod::HarmonicProduct, Waveform::Sine, 0.3);
+    }
+
+    /// The spectrum based methods detect many plucked notes an octave or
+    /// more too high, which the autocorrelation doesn't depend on.
+    #[test]
+    fn test_detects_plucked_notes() {
+        assert_detects_notes(PitchMethod::Autocorrelation, Waveform::Pluck, 0.5);
+    }
+
+    #[test]
+    fn test_silence() {
+        let mut simulation = Simulation::new();
+        let analyses = simulation.analyze(&vec![0.0; SAMPLE_RATE / 10]);
+        assert!(analyses.iter().all(|analysis| analysis.note.is_none()));
+    }
+
+    /// Plays two targets to a game analyzed by `simulation` and asserts
+    /// that both are played correctly.
+    fn assert_plays_game(mut simulation: Simulation) {
+        let targets = [loc(6, 3), loc(1, 5)];
+        let states = simulation.play_game(&targets, Waveform::Sine, 0.7);
+        let played: Vec<FretLoc> = states
+            .iter()
+            .map(|state| state.target_loc.clone())
+            .collect();
+        for target in &targets {
+            assert!(played.contains(target));
+        }
+        let score = &states.last().unwrap().score;
+        assert_eq!(2, score.n_correct);
+        assert_eq!(2, score.n_completed);
+    }
+
+    #[test]
+    fn test_game() {
+        assert_plays_game(Simulation::new());
+    }
+
+    #[test]
+    fn test_harmonic_product_game() {
+        assert_plays_game(Simulation::with_pitch_method(PitchMethod::HarmonicProduct));
+    }
+
+    fn spawn_app() -> (AppHandle, EventBus, mpsc::Sender<Vec<f32>>) {
+        let (mut cfg, _, _) = load_cfg();
+        // Nothing is written outside of the test.
+        cfg.app.practice_log_path = None;
+        cfg.app.history_path = None;
+        cfg.app.targets_path = None;
+        let (tx, rx) = mpsc::channel();
+        let builder = App::builder(cfg, Box::new(ChannelSource::new(rx, SAMPLE_RATE, 1)))
+            .console(false)
+            .gui(false);
+        let bus = builder.event_bus();
+        let handle = builder.build().unwrap().spawn().unwrap();
+        (handle, bus, tx)
+    }
+
+    /// Pauses the game and waits for `events` to tell.
+    fn pause(handle: &AppHandle, events: &Subscription<GameEvent>, tx: &mpsc::Sender<Vec<f32>>) {
+        handle.pause().unwrap();
+        // The game handles the commands along with the detections, so it
+        // needs input to pause.
+        let paused = (0..100).any(|_| {
+            tx.send(vec![0.0; CALLBACK_SIZE]).unwrap();
+            events
+                .recv_timeout(Duration::from_millis(20))
+                .into_iter()
+                .chain(events.try_iter())
+                .any(|event| event == GameEvent::Paused(true))
+        });
+        assert!(paused);
+    }
+
+    #[test]
+    fn test_spawned_app() {
+        let (handle, _, tx) = spawn_app();
+        let events = handle.events();
+        pause(&handle, &events, &tx);
+        handle.stop();
+        handle.wait();
+    }
+
+    #[test]
+    fn test_stop_with_stalled_events_subscription() {
+        let (handle, bus, tx) = spawn_app();
+        let stalled = handle.events();
+        let events = handle.events();
+        // A host that stopped reading its events, while another one reads.
+        for _ in 0..SUBSCRIBER_CAPACITY {
+            bus.events.publish(GameEvent::Paused(false));
+            events.try_iter().for_each(drop);
+        }
+        pause(&handle, &events, &tx);
+        handle.stop();
+        handle.wait();
+        drop(stalled);
+    }
+}
@@ -0,0 +1,112 @@
+use crate::app::{create_source_callbacks, AppError, CallbackFn};
+use crate::audio_analysis::{AnalysisResult, AudioAnalyzer};
+use crate::core::Cfg;
+use crate::metrics::Metrics;
+use crate::note_source::NoteSource;
+use crate::visualization::status_text::{draw_tuner_bar, format_detection};
+use crate::visualization::to_ascii;
+use console::{Key, Term};
+use std::io::Write;
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+/// Formats the detected note, its cents deviation, its frequency and the
+/// tuner bar on a single line, e.g. `A2 (+3¢)  110.2 Hz  ♭ --|*- ♯`.
+fn format_tuner_line(detection: Option<&AnalysisResult>, half_width: usize) -> String {
+    let frequency = match detection.and_then(|detection| detection.frequency) {
+        Some(frequency) => format!("{:.1} Hz", frequency),
+        None => String::from("-"),
+    };
+    let cents = detection.and_then(|detection| detection.cents());
+    format!(
+        "{:<10} {:>9}  {}",
+        format_detection(detection),
+        frequency,
+        draw_tuner_bar(cents, half_width)
+    )
+}
+
+/// Shows the note detected in `note_source` and its deviation in cents
+/// instead of running the game, until q or escape is pressed.
+pub fn run_standalone(mut note_source: Box<dyn NoteSource>, cfg: Cfg) -> Result<(), AppError> {
+    let note_registry = cfg.app.note_registry()?;
+    let mut analyzer =
+        AudioAnalyzer::new(note_source.sample_rate(), note_registry.notes(), cfg.audio)?;
+    let (analysis_tx, analysis_rx) = mpsc::channel();
+    let analysis_callback: Box<CallbackFn> = Box::new(
+        move |data: Box<dyn ExactSizeIterator<Item = f64>>, n_new_samples| {
+            let _ = analysis_tx.send(analyzer.identify_next_note(data, n_new_samples));
+        },
+    );
+    let (sample_callback, error_callback) = create_source_callbacks(
+        note_source.sample_rate(),
+        note_source.n_channels(),
+        &cfg.app.input_channels,
+        cfg.app.block_size,
+        analysis_callback,
+        Arc::new(Metrics::default()),
+    )?;
+    note_source.start(sample_callback, error_callback)?;
+    let (key_tx, key_rx) = mpsc::channel();
+    thread::spawn(move || {
+        let term = Term::stdout();
+        while let Ok(key) = term.read_key() {
+            if key_tx.send(key).is_err() {
+                return;
+            }
+        }
+    });
+    let mut term = Term::stdout();
+    let frame_period = Duration::from_secs_f64(1.0 / cfg.app.fps);
+    let mut detection = None;
+    writeln!(term, "q: quit")?;
+    loop {
+        match key_rx.recv_timeout(frame_period) {
+            Ok(Key::Escape) | Ok(Key::Char('q')) => break,
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+            _ => {}
+        }
+        if let Some(latest) = analysis_rx.try_iter().last() {
+            detection = Some(latest);
+        }
+        let mut line = format_tuner_line(detection.as_ref(), cfg.console.tuner_half_width);
+        if cfg.console.ascii {
+            line = to_ascii(&line);
+        }
+        term.clear_line()?;
+        write!(term, "{}", line)?;
+    }
+    note_source.stop();
+    writeln!(term)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{Note, NoteName};
+
+    #[test]
+    fn test_format_tuner_line() {
+        let detection = AnalysisResult {
+            note: Some(Note {
+                octave: 2,
+                name: NoteName::A,
+                frequency: 110.0,
+            }),
+            frequency: Some(110.2),
+            confidence: 1.0,
+            peaks: Vec::new(),
+        };
+        assert_eq!(
+            "A2 (+3¢)    110.2 Hz  ♭ --*-- ♯",
+            format_tuner_line(Some(&detection), 2)
+        );
+        assert_eq!(
+            "-                  -  ♭ --|-- ♯",
+            format_tuner_line(None, 2)
+        );
+    }
+}
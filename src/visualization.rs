@@ -1,7 +1,70 @@
+#[cfg(feature = "audio")]
+mod ascii;
+#[cfg(feature = "audio")]
+mod bell;
+#[cfg(feature = "audio")]
 mod console_visualizer;
+#[cfg(feature = "audio")]
+mod fretboard_drawer;
+#[cfg(feature = "http_api")]
+mod http_api;
+#[cfg(feature = "audio")]
+mod osc_sender;
+#[cfg(feature = "audio")]
+mod overlay_writer;
+#[cfg(feature = "audio")]
+mod plain_visualizer;
+#[cfg(feature = "audio")]
+mod practice_log;
+#[cfg(feature = "audio")]
+mod session_summary;
+#[cfg(feature = "audio")]
+mod sparkline;
+#[cfg(feature = "audio")]
+mod staff_drawer;
+#[cfg(feature = "audio")]
+mod tab_drawer;
+#[cfg(feature = "audio")]
+mod target_prompt;
+#[cfg(feature = "audio")]
+mod threaded_visualizer;
+#[cfg(feature = "tui")]
+mod tui_visualizer;
+#[cfg(feature = "audio")]
 mod visualizer;
+#[cfg(feature = "websocket")]
+mod websocket_server;
+#[cfg(feature = "audio")]
+pub use ascii::to_ascii;
+#[cfg(feature = "audio")]
 pub use console_visualizer::ConsoleVisualizer;
-pub use visualizer::Visualizer;
+#[cfg(feature = "http_api")]
+pub use http_api::HttpApi;
+#[cfg(feature = "audio")]
+pub use libreguitar_core::events::status_text;
+#[cfg(feature = "audio")]
+pub use libreguitar_core::events::GameEventTracker;
+pub use libreguitar_core::events::{GameEvent, TargetResult, EVENT_VERSION};
+#[cfg(feature = "audio")]
+pub use osc_sender::OscSender;
+#[cfg(feature = "audio")]
+pub use overlay_writer::OverlayWriter;
+#[cfg(feature = "audio")]
+pub use plain_visualizer::PlainVisualizer;
+#[cfg(feature = "audio")]
+pub use practice_log::PracticeLog;
+#[cfg(feature = "audio")]
+pub use sparkline::downsample_spectrum;
+#[cfg(feature = "gui")]
+pub use threaded_visualizer::EventLoop;
+#[cfg(feature = "audio")]
+pub use threaded_visualizer::ThreadedVisualizer;
+#[cfg(feature = "tui")]
+pub use tui_visualizer::TuiVisualizer;
+#[cfg(feature = "audio")]
+pub use visualizer::{FrameData, Visualizer};
+#[cfg(feature = "websocket")]
+pub use websocket_server::WebSocketServer;
 
 #[cfg(feature = "gui")]
 mod gui;
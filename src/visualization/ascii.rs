@@ -0,0 +1,36 @@
+use crate::visualization::sparkline::BAR_CHARS;
+
+/// Replacements of the sparkline bar characters, from lowest to highest.
+const ASCII_BAR_CHARS: [char; 8] = ['_', '.', ',', '-', '~', '=', '*', '#'];
+
+/// Replaces the non-ASCII symbols used in the console output with ASCII
+/// lookalikes for terminals and fonts that can't display them.
+pub fn to_ascii(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '♯' => out.push('#'),
+            '♭' => out.push('b'),
+            '¢' => out.push('c'),
+            '…' => out.push_str("..."),
+            _ => match BAR_CHARS.iter().position(|&bar| bar == c) {
+                Some(level) => out.push(ASCII_BAR_CHARS[level]),
+                None => out.push(c),
+            },
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_ascii() {
+        assert_eq!("C#4 (+12c)", to_ascii("C♯4 (+12¢)"));
+        assert_eq!("b --|-- #", to_ascii("♭ --|-- ♯"));
+        assert_eq!("_-#", to_ascii("▁▄█"));
+        assert_eq!("A|---|...", to_ascii("A|---|…"));
+    }
+}
@@ -0,0 +1,67 @@
+use crate::core::ConsoleCfg;
+use crate::visualization::status_text::TargetResult;
+use std::io::{self, Write};
+
+/// Rings the terminal bell when a target is finished, so that players don't
+/// have to look at the screen after every note.
+pub struct Bell {
+    on_success: bool,
+    on_failure: bool,
+}
+
+impl Bell {
+    pub fn new(config: &ConsoleCfg) -> Bell {
+        Bell {
+            on_success: config.bell_on_success,
+            on_failure: config.bell_on_failure,
+        }
+    }
+
+    fn should_ring(&self, result: &TargetResult) -> bool {
+        if result.correct {
+            self.on_success
+        } else {
+            self.on_failure
+        }
+    }
+
+    pub fn ring(&self, result: &TargetResult, out: &mut impl Write) -> io::Result<()> {
+        if self.should_ring(result) {
+            out.write_all(b"\x07")?;
+            out.flush()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::FretLoc;
+    use std::time::Duration;
+
+    fn result(correct: bool) -> TargetResult {
+        TargetResult {
+            note: String::from("E2"),
+            loc: FretLoc {
+                string_idx: 6,
+                fret_idx: 0,
+            },
+            correct,
+            response_time: Duration::from_secs(1),
+        }
+    }
+
+    #[test]
+    fn test_ring() {
+        let bell = Bell {
+            on_success: false,
+            on_failure: true,
+        };
+        let mut out = Vec::new();
+        bell.ring(&result(true), &mut out).unwrap();
+        assert!(out.is_empty());
+        bell.ring(&result(false), &mut out).unwrap();
+        assert_eq!(b"\x07".to_vec(), out);
+    }
+}
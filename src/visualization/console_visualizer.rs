@@ -1,207 +1,318 @@
-use crate::core::{ConsoleCfg, FretLoc, FretRange, StringRange, Tuning};
+use crate::audio_analysis::AnalysisResult;
+use crate::core::{ConsoleCfg, FretRange, PromptStyle, StringRange, Tuning};
+use crate::event_bus::Subscription;
 use crate::game::GameState;
+use crate::visualization::ascii::to_ascii;
+use crate::visualization::bell::Bell;
+use crate::visualization::fretboard_drawer::{FretboardDrawer, Marker, MarkerKind};
+use crate::visualization::session_summary::SessionSummary;
+use crate::visualization::sparkline::draw_sparkline;
+use crate::visualization::staff_drawer::draw_staff;
+use crate::visualization::status_text::{
+    draw_tuner_bar, format_detection, format_score, format_target_result, TargetResult,
+};
+use crate::visualization::tab_drawer::draw_tab;
+use crate::visualization::target_prompt::{TargetChange, TargetPrompt};
 use crate::visualization::Visualizer;
-use console::Term;
+use console::{Style, Term};
+use std::collections::VecDeque;
 use std::error::Error;
-use std::fmt;
 use std::fmt::Write;
-use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+/// Styles used to color the console output.
+struct Theme {
+    target: Style,
+    success: Style,
+    failure: Style,
+}
 
 pub struct ConsoleVisualizer {
-    rx: mpsc::Receiver<GameState>,
+    rx: Subscription<GameState>,
+    analysis_rx: Subscription<AnalysisResult>,
+    spectrum_rx: Subscription<Vec<f64>>,
     fret_range: FretRange,
     string_range: StringRange,
     term: Term,
-    previous_target: Option<FretLoc>,
-    curr_target: FretLoc,
+    targets: TargetPrompt,
+    staff_key_sharps: usize,
+    detection: Option<AnalysisResult>,
+    spectrum: Vec<f64>,
+    spectrum_max_magnitude: f64,
+    tuner_half_width: usize,
+    theme: Theme,
+    flash_duration: Duration,
+    /// Start time of the result flash and whether the last target was correct.
+    flash: Option<(Instant, bool)>,
+    /// Most recently finished targets, newest first.
+    history: VecDeque<TargetResult>,
+    history_length: usize,
+    summary: SessionSummary,
+    ascii: bool,
+    bell: Bell,
     fb_drawer: FretboardDrawer,
+    tuning: Tuning,
+    animation_frame: usize,
+    /// Terminal size used for the last render, if stdout is a terminal.
+    term_size: Option<(u16, u16)>,
 }
 
 impl ConsoleVisualizer {
     pub fn new(
-        rx: mpsc::Receiver<GameState>,
+        rx: Subscription<GameState>,
+        analysis_rx: Subscription<AnalysisResult>,
+        spectrum_rx: Subscription<Vec<f64>>,
         fret_range: FretRange,
         string_range: StringRange,
         config: ConsoleCfg,
         tuning: Tuning,
     ) -> ConsoleVisualizer {
         let term = Term::stdout();
-        let fb_drawer = FretboardDrawer {
-            fret_size: config.fret_size,
-            string_char: config.string_char,
-            fret_char: config.fret_char,
-            empty_char: config.empty_char,
-            sep_str: config.sep_str,
-            open_sep_str: config.open_sep_str,
-            frets_to_number: config.frets_to_number,
-            n_space_between_strings: config.n_space_between_strings,
-            tuning,
+        let fb_drawer = FretboardDrawer::new(&config, tuning.clone());
+        let theme = Theme {
+            target: Style::from_dotted_str(&config.theme.target),
+            success: Style::from_dotted_str(&config.theme.success),
+            failure: Style::from_dotted_str(&config.theme.failure),
         };
         ConsoleVisualizer {
             rx,
+            analysis_rx,
+            spectrum_rx,
             fret_range,
             string_range,
             term,
-            previous_target: None,
-            curr_target: FretLoc {
-                string_idx: 0,
-                fret_idx: 0,
-            },
+            targets: TargetPrompt::new(&config),
+            staff_key_sharps: config.staff_key_sharps,
+            detection: None,
+            spectrum: Vec::new(),
+            spectrum_max_magnitude: config.spectrum_max_magnitude,
+            tuner_half_width: config.tuner_half_width,
+            theme,
+            flash_duration: Duration::from_secs_f64(config.flash_duration),
+            flash: None,
+            history: VecDeque::with_capacity(config.history_length),
+            history_length: config.history_length,
+            summary: SessionSummary::default(),
+            ascii: config.ascii,
+            bell: Bell::new(&config),
             fb_drawer,
+            tuning,
+            animation_frame: 0,
+            term_size: None,
         }
     }
-}
 
-impl Visualizer for ConsoleVisualizer {
-    fn is_open(&self) -> bool {
-        true
+    fn is_flashing(&self) -> bool {
+        match self.flash {
+            Some((start, _)) => start.elapsed() < self.flash_duration,
+            None => false,
+        }
     }
 
-    fn draw(&mut self) {
-        let packet = self.rx.try_recv();
-        if let Ok(game_state) = packet {
-            self.term.clear_screen().unwrap();
-            self.term.write_line("Previously played note:").unwrap();
-            if self.curr_target != game_state.target_loc {
-                self.previous_target = Some(self.curr_target.clone());
-                self.curr_target = game_state.target_loc.clone();
-            }
-            self.term
-                .write_line(
-                    &self
-                        .fb_drawer
-                        .draw(&self.fret_range, &self.string_range, &self.previous_target)
-                        .unwrap(),
-                )
-                .unwrap();
-            self.term
-                .write_line(&format!(
-                    "Play {} on string {} (detection count: {}/{})",
-                    game_state.target_note.name_octave(),
-                    game_state.target_loc.string_idx,
-                    game_state.curr_detection_count,
-                    game_state.needed_detection_count
-                ))
-                .unwrap();
+    /// Marks the previous target as found or missed while the result flashes.
+    fn previous_marker_kind(&self) -> MarkerKind {
+        match self.flash {
+            Some((_, true)) if self.is_flashing() => MarkerKind::Completed,
+            Some((_, false)) if self.is_flashing() => MarkerKind::Wrong,
+            _ => MarkerKind::Target,
         }
     }
-}
 
-struct FretboardDrawer {
-    fret_size: usize,
-    string_char: String,
-    fret_char: String,
-    empty_char: String,
-    sep_str: String,
-    open_sep_str: String,
-    frets_to_number: Vec<usize>,
-    n_space_between_strings: usize,
-    tuning: Tuning,
-}
+    fn detection_style(&self, game_state: &GameState) -> &Style {
+        match self.detection.as_ref().and_then(|d| d.note.as_ref()) {
+            Some(note) if *note == game_state.target_note => &self.theme.success,
+            Some(_) => &self.theme.failure,
+            None => &self.theme.target,
+        }
+    }
 
-impl FretboardDrawer {
-    fn draw_fret(
-        &self,
-        out_str: &mut String,
-        string_char: &str,
-        fret_char: &str,
-        is_fretted: bool,
-    ) -> fmt::Result {
-        debug_assert!(self.fret_size > 0, "Fret size must be positive");
-        let left_side = self.fret_size / 2;
-        let right_side = self.fret_size - left_side - (is_fretted as usize);
-        write!(
-            out_str,
+    fn render(&self, game_state: &GameState) -> Result<String, Box<dyn Error>> {
+        let mut out = String::new();
+        let (title, shown_loc, kind) = if self.targets.is_hint_shown() {
+            (
+                "Current target:",
+                self.targets.curr_target(),
+                MarkerKind::Target,
+            )
+        } else {
+            (
+                "Previously played note:",
+                self.targets.previous_target(),
+                self.previous_marker_kind(),
+            )
+        };
+        let markers: Vec<Marker> = shown_loc
+            .into_iter()
+            .map(|loc| Marker::new(loc.clone(), kind))
+            .collect();
+        let marker_style = &self.theme.target;
+        writeln!(out, "{}", self.fb_drawer.tuning_header())?;
+        writeln!(out, "{}", title)?;
+        writeln!(
+            out,
             "{}",
-            (0..left_side).map(|_| string_char).collect::<String>()
+            match self.term_size {
+                Some((_, cols)) => self.fb_drawer.draw_fitted(
+                    &self.fret_range,
+                    &self.string_range,
+                    &markers,
+                    marker_style,
+                    cols as usize,
+                )?,
+                None => self.fb_drawer.draw(
+                    &self.fret_range,
+                    &self.string_range,
+                    &markers,
+                    marker_style,
+                )?,
+            }
         )?;
-        if is_fretted {
-            write!(out_str, "{}", fret_char)?;
+        if !game_state.upcoming_locs.is_empty() {
+            writeln!(out, "Up next:")?;
+            writeln!(
+                out,
+                "{}",
+                draw_tab(&game_state.upcoming_locs, &self.string_range, &self.tuning)
+            )?;
+        }
+        if !self.spectrum.is_empty() {
+            writeln!(
+                out,
+                "{}",
+                draw_sparkline(&self.spectrum, self.spectrum_max_magnitude)
+            )?;
+        }
+        if self.tuner_half_width > 0 {
+            let cents = self.detection.as_ref().and_then(|d| d.cents());
+            writeln!(out, "{}", draw_tuner_bar(cents, self.tuner_half_width))?;
         }
+        if self.targets.prompt() == PromptStyle::Staff {
+            writeln!(
+                out,
+                "{}",
+                draw_staff(&game_state.target_note, self.staff_key_sharps)
+            )?;
+        }
+        writeln!(
+            out,
+            "Play {} on string {} (detection count: {}/{})",
+            self.targets.target_text(game_state),
+            game_state.target_loc.string_idx,
+            game_state.curr_detection_count,
+            game_state.needed_detection_count
+        )?;
+        if let Some(timer) = self.targets.timer_text() {
+            writeln!(out, "{}", timer)?;
+        }
+        writeln!(
+            out,
+            "You are playing: {}",
+            self.detection_style(game_state)
+                .apply_to(format_detection(self.detection.as_ref()))
+        )?;
+        write!(out, "{}", format_score(&game_state.score))?;
+        if !self.history.is_empty() {
+            write!(out, "\nRecent targets:")?;
+            for result in self.history.iter() {
+                let style = if result.correct {
+                    &self.theme.success
+                } else {
+                    &self.theme.failure
+                };
+                write!(out, "\n  {}", style.apply_to(format_target_result(result)))?;
+            }
+        }
+        Ok(out)
+    }
+}
+
+impl ConsoleVisualizer {
+    fn render_summary(&self, game_state: &GameState) -> Result<String, Box<dyn Error>> {
+        let mut out = String::new();
+        writeln!(out, "Session summary")?;
+        writeln!(out, "{}", self.fb_drawer.tuning_header())?;
+        writeln!(
+            out,
+            "{}",
+            self.fb_drawer.draw(
+                &self.fret_range,
+                &self.string_range,
+                &self.summary.markers(),
+                &self.theme.target,
+            )?
+        )?;
         write!(
-            out_str,
+            out,
             "{}",
-            (0..right_side).map(|_| string_char).collect::<String>()
+            self.summary
+                .format(&game_state.score, self.targets.session_elapsed())?
         )?;
-        Ok(())
+        Ok(out)
     }
+}
 
-    fn draw_string(
-        &self,
-        out_str: &mut String,
-        fret_range: &FretRange,
-        played_fret: usize,
-        open_note: &str,
-    ) -> fmt::Result {
-        let first_sep_char = if fret_range.r().start == 0 {
-            &self.empty_char
-        } else {
-            &self.sep_str
-        };
-        write!(out_str, "{}", open_note)?;
-        write!(out_str, "{}", first_sep_char)?;
-        for i in fret_range.r() {
-            self.draw_fret(
-                out_str,
-                &self.string_char,
-                &self.fret_char,
-                i == played_fret,
-            )?;
-            let sep_str = if i > 0 {
-                &self.sep_str
-            } else {
-                &self.open_sep_str
-            };
-            write!(out_str, "{}", sep_str)?;
-        }
-        Ok(())
+impl Visualizer for ConsoleVisualizer {
+    fn is_open(&self) -> bool {
+        true
     }
 
-    fn draw_fret_numbers(&self, out_str: &mut String, fret_range: &FretRange) -> fmt::Result {
-        write!(out_str, "{}", self.empty_char)?;
-        for i in fret_range.r() {
-            let i_str = i.to_string();
-            let i_in_first_octave = i % 12;
-            self.draw_fret(
-                out_str,
-                &self.empty_char,
-                &i_str,
-                self.frets_to_number.contains(&i_in_first_octave),
-            )?;
-            write!(out_str, "{}", self.empty_char)?;
+    fn draw(&mut self) {
+        let mut needs_redraw = false;
+        for game_state in self.rx.try_iter() {
+            if let Some(TargetChange::Next(result)) = self.targets.update(game_state) {
+                self.flash = Some((Instant::now(), result.correct));
+                self.summary.record(&result);
+                self.bell.ring(&result, &mut self.term).unwrap();
+                if self.history_length > 0 {
+                    self.history.truncate(self.history_length - 1);
+                    self.history.push_front(result);
+                }
+            }
+            needs_redraw = true;
+        }
+        // The countdown changes every frame in timed mode.
+        if let Some(game_state) = self.targets.game_state() {
+            needs_redraw |= game_state.time_limit.is_some();
+        }
+        if let Some(analysis) = self.analysis_rx.try_iter().last() {
+            self.detection = Some(analysis);
+            needs_redraw = true;
+        }
+        if let Some(spectrum) = self.spectrum_rx.try_iter().last() {
+            self.spectrum = spectrum;
+            needs_redraw = true;
+        }
+        let animation_frame = self.targets.animation_frame();
+        if animation_frame != self.animation_frame {
+            self.animation_frame = animation_frame;
+            self.fb_drawer.set_animation_frame(animation_frame);
+            needs_redraw = true;
+        }
+        let term_size = self.term.size_checked();
+        if term_size != self.term_size {
+            self.term_size = term_size;
+            needs_redraw = true;
+        }
+        if !needs_redraw && !self.is_flashing() {
+            return;
+        }
+        if let Some(game_state) = self.targets.game_state() {
+            let mut screen = self.render(game_state).unwrap();
+            if self.ascii {
+                screen = to_ascii(&screen);
+            }
+            self.term.clear_screen().unwrap();
+            self.term.write_line(&screen).unwrap();
         }
-        Ok(())
     }
-
-    fn draw(
-        &self,
-        fret_range: &FretRange,
-        string_range: &StringRange,
-        target_loc: &Option<FretLoc>,
-    ) -> Result<String, Box<dyn Error>> {
-        let mut out = String::new();
-        let out_of_bounds_fret = fret_range.r().end;
-        let (fret_idx, string_idx) = match target_loc {
-            Some(loc) => (loc.fret_idx, loc.string_idx),
-            None => (out_of_bounds_fret, string_range.r().end),
-        };
-        for (i, open_note) in string_range.r().zip(self.tuning.iter()) {
-            let fret_idx = if i == string_idx {
-                fret_idx
-            } else {
-                out_of_bounds_fret
-            };
-            self.draw_string(&mut out, fret_range, fret_idx, &open_note.name.to_string())?;
-            writeln!(&mut out)?;
-            if i < string_range.r().end - 1 {
-                for _ in 0..self.n_space_between_strings {
-                    self.draw_string(&mut out, fret_range, out_of_bounds_fret, " ")?;
-                    writeln!(&mut out)?;
-                }
+    fn finish(&mut self) {
+        if let Some(game_state) = self.targets.game_state() {
+            let mut screen = self.render_summary(game_state).unwrap();
+            if self.ascii {
+                screen = to_ascii(&screen);
             }
+            self.term.clear_screen().unwrap();
+            self.term.write_line(&screen).unwrap();
         }
-        write!(&mut out, " ")?;
-        self.draw_fret_numbers(&mut out, &fret_range)?;
-        Ok(out)
     }
 }
@@ -0,0 +1,652 @@
+use crate::core::{ConsoleCfg, FretLoc, FretRange, Note, StringRange, Tuning};
+use console::{measure_text_width, Style};
+use std::error::Error;
+use std::fmt;
+use std::fmt::Write;
+
+/// Smallest fret size used when shrinking the diagram to the terminal width.
+const MIN_FRET_SIZE: usize = 3;
+
+/// Smallest cell width of the frets near the bridge in proportional mode,
+/// wide enough for two digit fret numbers.
+const MIN_PROPORTIONAL_FRET_SIZE: usize = 2;
+
+/// Appended to the lines of a diagram whose fret range had to be truncated.
+const ELLIPSIS: &str = "…";
+const ASCII_ELLIPSIS: &str = "...";
+
+/// Meaning of a marked location, which selects the glyph it is drawn with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MarkerKind {
+    Target,
+    Completed,
+    Wrong,
+}
+
+/// A location to mark on the fretboard diagram.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Marker {
+    pub loc: FretLoc,
+    pub kind: MarkerKind,
+}
+
+impl Marker {
+    pub fn new(loc: FretLoc, kind: MarkerKind) -> Marker {
+        Marker { loc, kind }
+    }
+}
+
+/// Draws the fretboard diagram as text.
+#[derive(Clone)]
+pub struct FretboardDrawer {
+    fret_size: usize,
+    string_char: String,
+    fret_char: String,
+    empty_char: String,
+    sep_str: String,
+    open_sep_str: String,
+    frets_to_number: Vec<usize>,
+    n_space_between_strings: usize,
+    label_notes: bool,
+    left_handed: bool,
+    proportional_frets: bool,
+    ascii: bool,
+    marker_frames: Vec<String>,
+    frame: usize,
+    completed_char: String,
+    wrong_char: String,
+    fretboard_style: Style,
+    completed_style: Style,
+    wrong_style: Style,
+    tuning: Tuning,
+}
+
+impl FretboardDrawer {
+    pub fn new(config: &ConsoleCfg, tuning: Tuning) -> FretboardDrawer {
+        FretboardDrawer {
+            fret_size: config.fret_size,
+            string_char: config.string_char.clone(),
+            fret_char: config.fret_char.clone(),
+            empty_char: config.empty_char.clone(),
+            sep_str: config.sep_str.clone(),
+            open_sep_str: config.open_sep_str.clone(),
+            frets_to_number: config.frets_to_number.clone(),
+            n_space_between_strings: config.n_space_between_strings,
+            label_notes: config.label_notes,
+            left_handed: config.left_handed,
+            proportional_frets: config.proportional_frets,
+            ascii: config.ascii,
+            marker_frames: config.marker_frames.clone(),
+            frame: 0,
+            completed_char: config.completed_char.clone(),
+            wrong_char: config.wrong_char.clone(),
+            fretboard_style: Style::from_dotted_str(&config.theme.fretboard),
+            completed_style: Style::from_dotted_str(&config.theme.success),
+            wrong_style: Style::from_dotted_str(&config.theme.failure),
+            tuning,
+        }
+    }
+
+    /// Draws the strings, frets and markers without any styling.
+    #[cfg(feature = "tui")]
+    pub fn disable_styles(&mut self) {
+        self.fretboard_style = Style::new();
+        self.completed_style = Style::new();
+        self.wrong_style = Style::new();
+    }
+
+    /// Width of the cell of fret `fret_idx`. In proportional mode the cells
+    /// shrink toward the bridge like the real fret spacing, starting from
+    /// `fret_size` at the first fret of the range.
+    fn fret_width(&self, fret_range: &FretRange, fret_idx: usize) -> usize {
+        let first_fret = fret_range.r().start.max(1);
+        if !self.proportional_frets || fret_idx <= first_fret {
+            return self.fret_size;
+        }
+        let ratio = 2f64.powf(-((fret_idx - first_fret) as f64) / 12.0);
+        let width = (self.fret_size as f64 * ratio).round() as usize;
+        width.max(MIN_PROPORTIONAL_FRET_SIZE.min(self.fret_size))
+    }
+
+    fn draw_fret(
+        &self,
+        out_str: &mut String,
+        fret_size: usize,
+        string_char: &str,
+        fret_char: Option<&str>,
+        string_style: &Style,
+        fret_style: &Style,
+    ) -> fmt::Result {
+        debug_assert!(fret_size > 0, "Fret size must be positive");
+        let fret_char_width = fret_char.map_or(0, measure_text_width);
+        let left_side = (fret_size + 1).saturating_sub(fret_char_width) / 2;
+        let right_side = fret_size.saturating_sub(left_side + fret_char_width);
+        write!(
+            out_str,
+            "{}",
+            string_style.apply_to((0..left_side).map(|_| string_char).collect::<String>())
+        )?;
+        if let Some(fret_char) = fret_char {
+            write!(out_str, "{}", fret_style.apply_to(fret_char))?;
+        }
+        write!(
+            out_str,
+            "{}",
+            string_style.apply_to((0..right_side).map(|_| string_char).collect::<String>())
+        )?;
+        Ok(())
+    }
+
+    /// Selects the marker character of the given animation frame.
+    pub fn set_animation_frame(&mut self, frame: usize) {
+        self.frame = frame;
+    }
+
+    fn marker_char(&self, kind: MarkerKind) -> &str {
+        match kind {
+            MarkerKind::Target if self.marker_frames.is_empty() => &self.fret_char,
+            MarkerKind::Target => &self.marker_frames[self.frame % self.marker_frames.len()],
+            MarkerKind::Completed => &self.completed_char,
+            MarkerKind::Wrong => &self.wrong_char,
+        }
+    }
+
+    fn marker_style<'a>(&'a self, kind: MarkerKind, target_style: &'a Style) -> &'a Style {
+        match kind {
+            MarkerKind::Target => target_style,
+            MarkerKind::Completed => &self.completed_style,
+            MarkerKind::Wrong => &self.wrong_style,
+        }
+    }
+
+    /// Writes the segments of a line, reversing their order for left-handed
+    /// diagrams so that the nut ends up on the right.
+    fn write_segments(&self, out_str: &mut String, segments: Vec<String>) -> fmt::Result {
+        if self.left_handed {
+            for segment in segments.iter().rev() {
+                write!(out_str, "{}", segment)?;
+            }
+        } else {
+            for segment in segments.iter() {
+                write!(out_str, "{}", segment)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn draw_string(
+        &self,
+        out_str: &mut String,
+        fret_range: &FretRange,
+        markers: &[&Marker],
+        open_note: Option<&Note>,
+        label_width: usize,
+        marker_style: &Style,
+    ) -> fmt::Result {
+        let mut segments = Vec::new();
+        let first_sep_char = if fret_range.r().start == 0 {
+            &self.empty_char
+        } else {
+            &self.sep_str
+        };
+        let label = match open_note {
+            Some(note) => note.name_octave(),
+            None => String::new(),
+        };
+        segments.push(format!("{:<width$}", label, width = label_width));
+        segments.push(self.fretboard_style.apply_to(first_sep_char).to_string());
+        for i in fret_range.r() {
+            let marker = markers.iter().find(|marker| marker.loc.fret_idx == i);
+            let label = match open_note {
+                Some(note) if self.label_notes => {
+                    Some(note.add_semitone(i as i32).name.to_string())
+                }
+                _ => None,
+            };
+            let mut fret = String::new();
+            let fret_size = self.fret_width(fret_range, i);
+            if let Some(marker) = marker {
+                self.draw_fret(
+                    &mut fret,
+                    fret_size,
+                    &self.string_char,
+                    Some(self.marker_char(marker.kind)),
+                    &self.fretboard_style,
+                    self.marker_style(marker.kind, marker_style),
+                )?;
+            } else if let Some(label) = label {
+                self.draw_fret(
+                    &mut fret,
+                    fret_size,
+                    &self.string_char,
+                    Some(&label),
+                    &self.fretboard_style,
+                    &Style::new(),
+                )?;
+            } else {
+                self.draw_fret(
+                    &mut fret,
+                    fret_size,
+                    &self.string_char,
+                    None,
+                    &self.fretboard_style,
+                    marker_style,
+                )?;
+            }
+            segments.push(fret);
+            let sep_str = if i > 0 {
+                &self.sep_str
+            } else {
+                &self.open_sep_str
+            };
+            segments.push(self.fretboard_style.apply_to(sep_str).to_string());
+        }
+        self.write_segments(out_str, segments)
+    }
+
+    fn draw_fret_numbers(
+        &self,
+        out_str: &mut String,
+        fret_range: &FretRange,
+        label_width: usize,
+    ) -> fmt::Result {
+        let mut segments = vec![" ".repeat(label_width), self.empty_char.clone()];
+        for i in fret_range.r() {
+            let i_str = i.to_string();
+            let i_in_first_octave = i % 12;
+            let mut fret = String::new();
+            let is_numbered = self.frets_to_number.contains(&i_in_first_octave);
+            self.draw_fret(
+                &mut fret,
+                self.fret_width(fret_range, i),
+                &self.empty_char,
+                if is_numbered { Some(&i_str) } else { None },
+                &Style::new(),
+                &Style::new(),
+            )?;
+            segments.push(fret);
+            segments.push(self.empty_char.clone());
+        }
+        self.write_segments(out_str, segments)
+    }
+
+    /// Names the tuning the open strings are labeled with.
+    pub fn tuning_header(&self) -> String {
+        format!("Tuning: {}", self.tuning.name())
+    }
+
+    /// Draws the diagram with the given locations marked. Target markers
+    /// are drawn with `marker_style`, the others with the theme styles.
+    pub fn draw(
+        &self,
+        fret_range: &FretRange,
+        string_range: &StringRange,
+        markers: &[Marker],
+        marker_style: &Style,
+    ) -> Result<String, Box<dyn Error>> {
+        let mut out = String::new();
+        let label_width = self
+            .tuning
+            .iter()
+            .take(string_range.r().len())
+            .map(|note| note.name_octave().chars().count())
+            .max()
+            .unwrap_or(0);
+        for (i, open_note) in string_range.r().zip(self.tuning.iter()) {
+            let string_markers: Vec<&Marker> = markers
+                .iter()
+                .filter(|marker| marker.loc.string_idx == i)
+                .collect();
+            self.draw_string(
+                &mut out,
+                fret_range,
+                &string_markers,
+                Some(open_note),
+                label_width,
+                marker_style,
+            )?;
+            writeln!(&mut out)?;
+            if i < string_range.r().end - 1 {
+                for _ in 0..self.n_space_between_strings {
+                    self.draw_string(&mut out, fret_range, &[], None, label_width, marker_style)?;
+                    writeln!(&mut out)?;
+                }
+            }
+        }
+        self.draw_fret_numbers(&mut out, fret_range, label_width)?;
+        Ok(out)
+    }
+
+    /// Draws the diagram so that no line is wider than `max_width`.
+    ///
+    /// The fret size is reduced first. If the diagram is still too wide, only
+    /// as many frets as fit are drawn, keeping the first marker visible, and
+    /// the lines are ended with an ellipsis.
+    pub fn draw_fitted(
+        &self,
+        fret_range: &FretRange,
+        string_range: &StringRange,
+        markers: &[Marker],
+        marker_style: &Style,
+        max_width: usize,
+    ) -> Result<String, Box<dyn Error>> {
+        let min_fret_size = MIN_FRET_SIZE.min(self.fret_size);
+        let mut drawer = self.clone();
+        for fret_size in (min_fret_size..=self.fret_size).rev() {
+            drawer.fret_size = fret_size;
+            let out = drawer.draw(fret_range, string_range, markers, marker_style)?;
+            if max_line_width(&out) <= max_width {
+                return Ok(out);
+            }
+        }
+
+        let range = fret_range.r();
+        let target_fret = match markers.first() {
+            Some(marker) if range.contains(&marker.loc.fret_idx) => marker.loc.fret_idx,
+            _ => range.start,
+        };
+        let ellipsis = if self.ascii { ASCII_ELLIPSIS } else { ELLIPSIS };
+        let ellipsis_width = measure_text_width(ellipsis);
+        let mut n_frets = range.len();
+        while n_frets > 1 {
+            n_frets -= 1;
+            let beg = if target_fret < range.start + n_frets {
+                range.start
+            } else {
+                target_fret + 1 - n_frets
+            };
+            let window = FretRange::new(beg, beg + n_frets).expect("The window has a fret");
+            let out = drawer.draw(&window, string_range, markers, marker_style)?;
+            if max_line_width(&out) + ellipsis_width <= max_width || n_frets == 1 {
+                return Ok(mark_truncation(&out, ellipsis, self.left_handed));
+            }
+        }
+        drawer.draw(fret_range, string_range, markers, marker_style)
+    }
+}
+
+fn max_line_width(s: &str) -> usize {
+    s.lines().map(measure_text_width).max().unwrap_or(0)
+}
+
+/// Puts an ellipsis on the side of the diagram where the high frets were cut.
+fn mark_truncation(s: &str, ellipsis: &str, left_handed: bool) -> String {
+    let width = max_line_width(s);
+    s.lines()
+        .map(|line| {
+            let padding = " ".repeat(width - measure_text_width(line));
+            if left_handed {
+                format!("{}{}{}", ellipsis, padding, line)
+            } else {
+                format!("{}{}{}", line, padding, ellipsis)
+            }
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{Note, NoteName, NoteRegistry, TuningSpecification};
+
+    fn test_drawer(label_notes: bool) -> FretboardDrawer {
+        let registry = NoteRegistry::from_notes(vec![
+            Note {
+                octave: 2,
+                name: NoteName::E,
+                frequency: 82.41,
+            },
+            Note {
+                octave: 2,
+                name: NoteName::A,
+                frequency: 110.0,
+            },
+        ])
+        .unwrap();
+        let tuning = Tuning::from_specification(
+            &[
+                TuningSpecification {
+                    string: 1,
+                    octave: 2,
+                    name: NoteName::A,
+                },
+                TuningSpecification {
+                    string: 2,
+                    octave: 2,
+                    name: NoteName::E,
+                },
+            ],
+            &registry,
+        )
+        .unwrap();
+        FretboardDrawer {
+            fret_size: 3,
+            string_char: String::from("-"),
+            fret_char: String::from("x"),
+            empty_char: String::from(" "),
+            sep_str: String::from("|"),
+            open_sep_str: String::from("O"),
+            frets_to_number: vec![0, 2],
+            n_space_between_strings: 0,
+            label_notes,
+            left_handed: false,
+            proportional_frets: false,
+            ascii: false,
+            marker_frames: Vec::new(),
+            frame: 0,
+            completed_char: String::from("o"),
+            wrong_char: String::from("#"),
+            fretboard_style: Style::new(),
+            completed_style: Style::new(),
+            wrong_style: Style::new(),
+            tuning,
+        }
+    }
+
+    #[test]
+    fn test_draw_fretboard() {
+        let drawer = test_drawer(false);
+        let target = vec![Marker::new(
+            FretLoc {
+                string_idx: 2,
+                fret_idx: 1,
+            },
+            MarkerKind::Target,
+        )];
+        let actual = drawer
+            .draw(
+                &FretRange::new(0, 3).unwrap(),
+                &StringRange::new(1, 3).unwrap(),
+                &target,
+                &Style::new(),
+            )
+            .unwrap();
+        let expected = "A2 ---O---|---|\n\
+                        E2 ---O-x-|---|\n    \
+                        0       2  ";
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn test_draw_fretboard_label_notes() {
+        let drawer = test_drawer(true);
+        let actual = drawer
+            .draw(
+                &FretRange::new(0, 3).unwrap(),
+                &StringRange::new(1, 3).unwrap(),
+                &[],
+                &Style::new(),
+            )
+            .unwrap();
+        let expected = "A2 -A-O-A♯|-B-|\n\
+                        E2 -E-O-F-|-F♯|\n    \
+                        0       2  ";
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn test_draw_fitted_shrinks_frets() {
+        let mut wide_drawer = test_drawer(false);
+        wide_drawer.fret_size = 5;
+        let actual = wide_drawer
+            .draw_fitted(
+                &FretRange::new(0, 3).unwrap(),
+                &StringRange::new(1, 3).unwrap(),
+                &[],
+                &Style::new(),
+                15,
+            )
+            .unwrap();
+        let expected = test_drawer(false)
+            .draw(
+                &FretRange::new(0, 3).unwrap(),
+                &StringRange::new(1, 3).unwrap(),
+                &[],
+                &Style::new(),
+            )
+            .unwrap();
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn test_draw_fitted_truncates_frets() {
+        let drawer = test_drawer(false);
+        let target = vec![Marker::new(
+            FretLoc {
+                string_idx: 2,
+                fret_idx: 2,
+            },
+            MarkerKind::Target,
+        )];
+        let actual = drawer
+            .draw_fitted(
+                &FretRange::new(0, 3).unwrap(),
+                &StringRange::new(1, 3).unwrap(),
+                &target,
+                &Style::new(),
+                13,
+            )
+            .unwrap();
+        let expected = "A2|---|---|…\n\
+                        E2|---|-x-|…\n        \
+                        2  …";
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn test_draw_fretboard_left_handed() {
+        let mut drawer = test_drawer(false);
+        drawer.left_handed = true;
+        let target = vec![Marker::new(
+            FretLoc {
+                string_idx: 2,
+                fret_idx: 1,
+            },
+            MarkerKind::Target,
+        )];
+        let actual = drawer
+            .draw(
+                &FretRange::new(0, 3).unwrap(),
+                &StringRange::new(1, 3).unwrap(),
+                &target,
+                &Style::new(),
+            )
+            .unwrap();
+        let expected = "|---|---O--- A2\n\
+                        |---|-x-O--- E2\n  \
+                        2       0    ";
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn test_draw_fretboard_animated_marker() {
+        let mut drawer = test_drawer(false);
+        drawer.marker_frames = vec![String::from("x"), String::from("X")];
+        let target = vec![Marker::new(
+            FretLoc {
+                string_idx: 1,
+                fret_idx: 2,
+            },
+            MarkerKind::Target,
+        )];
+        let draw = |drawer: &FretboardDrawer| {
+            drawer
+                .draw(
+                    &FretRange::new(0, 3).unwrap(),
+                    &StringRange::new(1, 2).unwrap(),
+                    &target,
+                    &Style::new(),
+                )
+                .unwrap()
+        };
+        drawer.set_animation_frame(0);
+        assert!(draw(&drawer).starts_with("A2 ---O---|-x-|"));
+        drawer.set_animation_frame(1);
+        assert!(draw(&drawer).starts_with("A2 ---O---|-X-|"));
+        drawer.set_animation_frame(2);
+        assert!(draw(&drawer).starts_with("A2 ---O---|-x-|"));
+    }
+
+    #[test]
+    fn test_draw_fretboard_multiple_markers() {
+        let drawer = test_drawer(false);
+        let markers = vec![
+            Marker::new(
+                FretLoc {
+                    string_idx: 1,
+                    fret_idx: 2,
+                },
+                MarkerKind::Target,
+            ),
+            Marker::new(
+                FretLoc {
+                    string_idx: 2,
+                    fret_idx: 0,
+                },
+                MarkerKind::Completed,
+            ),
+            Marker::new(
+                FretLoc {
+                    string_idx: 2,
+                    fret_idx: 1,
+                },
+                MarkerKind::Wrong,
+            ),
+        ];
+        let actual = drawer
+            .draw(
+                &FretRange::new(0, 3).unwrap(),
+                &StringRange::new(1, 3).unwrap(),
+                &markers,
+                &Style::new(),
+            )
+            .unwrap();
+        let expected = "A2 ---O---|-x-|\n\
+                        E2 -o-O-#-|---|\n    \
+                        0       2  ";
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn test_draw_fretboard_proportional_frets() {
+        let mut drawer = test_drawer(false);
+        drawer.fret_size = 5;
+        drawer.proportional_frets = true;
+        drawer.frets_to_number = vec![];
+        let actual = drawer
+            .draw(
+                &FretRange::new(0, 12).unwrap(),
+                &StringRange::new(1, 2).unwrap(),
+                &[],
+                &Style::new(),
+            )
+            .unwrap();
+        let first_line = actual.lines().next().unwrap();
+        let widths: Vec<usize> = first_line[3..]
+            .split(['|', 'O'])
+            .map(|cell| cell.len())
+            .collect();
+        assert_eq!(vec![5, 5, 5, 4, 4, 4, 4, 4, 3, 3, 3, 3, 0], widths);
+    }
+}
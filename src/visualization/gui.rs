@@ -1,5 +1,21 @@
+mod canvas;
+mod fretboard_view;
+mod gauge_view;
 mod gui_cfg;
 mod gui_visualizer;
+mod heatmap_view;
+mod key_bindings;
+mod piano_view;
+mod png_export;
+mod readout_view;
+mod settings_view;
+mod spectrum_scale;
+mod spectrum_trace;
+mod staff_view;
+mod stats_view;
+mod status_view;
+mod theme;
+mod waterfall_view;
 
 pub use gui_cfg::GuiCfg;
-pub use gui_visualizer::{FrameData, GUIVisualizer};
+pub use gui_visualizer::{GUIVisualizer, GuiChannels};
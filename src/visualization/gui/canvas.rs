@@ -0,0 +1,86 @@
+use eframe::egui::{ColorImage, Image, Response, Sense, TextureHandle, TextureOptions, Ui, Vec2};
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use plotters_bitmap::BitMapBackend;
+
+pub type Area<'a> = DrawingArea<BitMapBackend<'a>, Shift>;
+
+/// Part of the window drawn with plotters into an RGB buffer, which is shown
+/// as an egui texture. It is redrawn on every frame.
+#[derive(Default)]
+pub struct Canvas {
+    buf: Vec<u8>,
+    texture: Option<TextureHandle>,
+}
+
+impl Canvas {
+    /// Draws the canvas with `draw` at the resolution of the screen and shows
+    /// it on `ui` with a size of `size` points. Returns the response of the
+    /// canvas, which senses clicks and drags, along with the result of
+    /// `draw`.
+    pub fn show<T, F>(&mut self, ui: &mut Ui, size: Vec2, draw: F) -> (Response, T)
+    where
+        F: FnOnce(&Area) -> T,
+    {
+        let pixels_per_point = ui.ctx().pixels_per_point();
+        let width = (size.x * pixels_per_point).round().max(1.0) as usize;
+        let height = (size.y * pixels_per_point).round().max(1.0) as usize;
+        self.buf.resize(3 * width * height, 0);
+        let result = {
+            let root = BitMapBackend::with_buffer(&mut self.buf, (width as u32, height as u32))
+                .into_drawing_area();
+            draw(&root)
+        };
+        let image = ColorImage::from_rgb([width, height], &self.buf);
+        let texture = match &mut self.texture {
+            Some(texture) => {
+                texture.set(image, TextureOptions::LINEAR);
+                texture
+            }
+            None => self.texture.insert(ui.ctx().load_texture(
+                "canvas",
+                image,
+                TextureOptions::LINEAR,
+            )),
+        };
+        let response = ui.add(Image::new((texture.id(), size)).sense(Sense::click_and_drag()));
+        (response, result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use eframe::egui::{vec2, CentralPanel, Context, RawInput};
+
+    /// Size in pixels of the area drawn by `canvas` when it is shown at
+    /// `size` on a frame of `ctx`.
+    fn drawn_size(ctx: &Context, canvas: &mut Canvas, size: Vec2) -> (u32, u32) {
+        let mut drawn = (0, 0);
+        let _ = ctx.run(RawInput::default(), |ctx| {
+            CentralPanel::default().show(ctx, |ui| {
+                drawn = canvas.show(ui, size, |area| area.dim_in_pixel()).1;
+            });
+        });
+        drawn
+    }
+
+    #[test]
+    fn test_canvas_follows_window_size() {
+        let ctx = Context::default();
+        let mut canvas = Canvas::default();
+        assert_eq!(
+            (200, 100),
+            drawn_size(&ctx, &mut canvas, vec2(200.0, 100.0))
+        );
+        assert_eq!(3 * 200 * 100, canvas.buf.len());
+        // A larger window is drawn in full instead of the first size.
+        assert_eq!(
+            (640, 480),
+            drawn_size(&ctx, &mut canvas, vec2(640.0, 480.0))
+        );
+        assert_eq!(3 * 640 * 480, canvas.buf.len());
+        assert_eq!((50, 20), drawn_size(&ctx, &mut canvas, vec2(50.0, 20.0)));
+        assert_eq!(3 * 50 * 20, canvas.buf.len());
+    }
+}
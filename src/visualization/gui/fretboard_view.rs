@@ -0,0 +1,157 @@
+use crate::core::{FretLoc, FretRange, StringRange};
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use plotters::style::RGBAColor;
+
+/// Frets with a single inlay dot, counted within an octave.
+const SINGLE_INLAY_FRETS: [usize; 4] = [3, 5, 7, 9];
+/// Frets with a double inlay dot, counted within an octave.
+const DOUBLE_INLAY_FRETS: [usize; 1] = [0];
+
+/// Colors of the fretboard widget.
+pub struct FretboardColors {
+    pub board: RGBAColor,
+    pub fret: RGBAColor,
+    pub string: RGBAColor,
+    pub inlay: RGBAColor,
+}
+
+/// Pixel geometry of the fretboard diagram: one column per fret and one row
+/// per string, with the string drawn through the middle of its row.
+struct Layout<'a> {
+    fret_range: &'a FretRange,
+    string_range: &'a StringRange,
+    width: i32,
+    height: i32,
+    fret_width: i32,
+    string_gap: i32,
+}
+
+impl<'a> Layout<'a> {
+    /// Returns `None` if there is nothing to draw.
+    fn new(
+        (width, height): (u32, u32),
+        fret_range: &'a FretRange,
+        string_range: &'a StringRange,
+    ) -> Option<Layout<'a>> {
+        let (width, height) = (width as i32, height as i32);
+        let n_frets = fret_range.r().len() as i32;
+        let n_strings = string_range.r().len() as i32;
+        if n_frets == 0 || n_strings == 0 {
+            return None;
+        }
+        Some(Layout {
+            fret_range,
+            string_range,
+            width,
+            height,
+            fret_width: width / n_frets,
+            string_gap: height / n_strings,
+        })
+    }
+
+    fn fret_x(&self, fret_idx: usize) -> i32 {
+        (fret_idx - self.fret_range.r().start) as i32 * self.fret_width
+    }
+
+    fn string_y(&self, string_idx: usize) -> i32 {
+        (string_idx - self.string_range.r().start) as i32 * self.string_gap + self.string_gap / 2
+    }
+
+    fn contains(&self, loc: &FretLoc) -> bool {
+        self.fret_range.r().contains(&loc.fret_idx)
+            && self.string_range.r().contains(&loc.string_idx)
+    }
+}
+
+/// Draws the fretboard diagram on `area`, with strings as horizontal lines
+/// (first string of the range at the top) and a colored dot on each marked
+/// location.
+pub fn draw_fretboard<DB: DrawingBackend>(
+    area: &DrawingArea<DB, Shift>,
+    fret_range: &FretRange,
+    string_range: &StringRange,
+    markers: &[(FretLoc, RGBAColor)],
+    colors: &FretboardColors,
+) -> Result<(), DrawingAreaErrorKind<DB::ErrorType>> {
+    let layout = match Layout::new(area.dim_in_pixel(), fret_range, string_range) {
+        Some(layout) => layout,
+        None => return Ok(()),
+    };
+    let (width, height) = (layout.width, layout.height);
+    let fret_width = layout.fret_width;
+    let dot_radius = (layout.string_gap.min(fret_width) / 4).max(2);
+
+    area.fill(&colors.board)?;
+    for fret_idx in fret_range.r() {
+        let x = layout.fret_x(fret_idx) + fret_width;
+        // The nut is drawn thicker than the other frets.
+        let thickness = if fret_idx == 0 { 4 } else { 2 };
+        area.draw(&Rectangle::new(
+            [(x - thickness / 2, 0), (x + thickness / 2, height)],
+            colors.fret.filled(),
+        ))?;
+
+        let center_x = layout.fret_x(fret_idx) + fret_width / 2;
+        let fret_in_octave = fret_idx % 12;
+        if fret_idx > 0 && SINGLE_INLAY_FRETS.contains(&fret_in_octave) {
+            area.draw(&Circle::new(
+                (center_x, height / 2),
+                dot_radius,
+                colors.inlay.filled(),
+            ))?;
+        } else if fret_idx > 0 && DOUBLE_INLAY_FRETS.contains(&fret_in_octave) {
+            for y in [height / 4, 3 * height / 4].iter() {
+                area.draw(&Circle::new(
+                    (center_x, *y),
+                    dot_radius,
+                    colors.inlay.filled(),
+                ))?;
+            }
+        }
+    }
+    for string_idx in string_range.r() {
+        let y = layout.string_y(string_idx);
+        area.draw(&PathElement::new(
+            vec![(0, y), (width, y)],
+            colors.string.stroke_width(2),
+        ))?;
+    }
+    for (loc, color) in markers.iter() {
+        if !layout.contains(loc) {
+            continue;
+        }
+        let center = (
+            layout.fret_x(loc.fret_idx) + fret_width / 2,
+            layout.string_y(loc.string_idx),
+        );
+        area.draw(&Circle::new(center, dot_radius * 2, color.filled()))?;
+    }
+    Ok(())
+}
+
+/// Fills the cell of each given location, between its frets and halfway to
+/// the neighbouring strings, over a fretboard drawn on `area`.
+pub fn draw_fretboard_cells<DB: DrawingBackend>(
+    area: &DrawingArea<DB, Shift>,
+    fret_range: &FretRange,
+    string_range: &StringRange,
+    cells: &[(FretLoc, RGBAColor)],
+) -> Result<(), DrawingAreaErrorKind<DB::ErrorType>> {
+    let layout = match Layout::new(area.dim_in_pixel(), fret_range, string_range) {
+        Some(layout) => layout,
+        None => return Ok(()),
+    };
+    for (loc, color) in cells.iter() {
+        if !layout.contains(loc) {
+            continue;
+        }
+        let x = layout.fret_x(loc.fret_idx);
+        let y = layout.string_y(loc.string_idx) - layout.string_gap / 2;
+        area.draw(&Rectangle::new(
+            [(x, y), (x + layout.fret_width, y + layout.string_gap)],
+            color.filled(),
+        ))?;
+    }
+    Ok(())
+}
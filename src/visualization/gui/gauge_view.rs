@@ -0,0 +1,163 @@
+use crate::audio_analysis::AnalysisResult;
+use crate::core::Note;
+use crate::visualization::status_text::TUNER_RANGE_CENTS;
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use plotters::style::RGBAColor;
+use std::f64::consts::PI;
+use std::time::Duration;
+
+/// Angle of the gauge arc on each side of the vertical, in radians.
+const GAUGE_HALF_ANGLE: f64 = PI / 3.0;
+/// Number of ticks on each side of the center tick.
+const N_SIDE_TICKS: i32 = 5;
+/// Time the needle takes to cover about two thirds of the distance to a new
+/// reading, in seconds.
+const NEEDLE_TIME_CONSTANT: f64 = 0.08;
+
+/// Detected note and cents deviation shown by the gauge. The cents are eased
+/// towards the latest analysis on each frame so that the needle moves smoothly
+/// however often analyses arrive.
+#[derive(Debug, Default)]
+pub struct Needle {
+    reading: Option<(Note, f64)>,
+}
+
+impl Needle {
+    /// Moves the needle towards `analysis` for a frame `elapsed` after the
+    /// previous one. The needle jumps to a newly detected note.
+    pub fn update(&mut self, analysis: Option<&AnalysisResult>, elapsed: Duration) {
+        let target =
+            analysis.and_then(|analysis| Some((analysis.note.clone()?, analysis.cents()?)));
+        self.reading = match (self.reading.take(), target) {
+            (Some((note, cents)), Some((target_note, target_cents))) if note == target_note => {
+                let step = 1.0 - (-elapsed.as_secs_f64() / NEEDLE_TIME_CONSTANT).exp();
+                Some((note, cents + (target_cents - cents) * step))
+            }
+            (_, target) => target,
+        };
+    }
+
+    pub fn reading(&self) -> Option<(&Note, f64)> {
+        self.reading.as_ref().map(|(note, cents)| (note, *cents))
+    }
+}
+
+/// Draws a tuner gauge on `area`: an arc spanning ±`TUNER_RANGE_CENTS` with a
+/// needle pointing at the cents deviation of the detected pitch and the
+/// detected note name below it. The needle is hidden when nothing is detected.
+pub fn draw_tuner_gauge<DB: DrawingBackend>(
+    area: &DrawingArea<DB, Shift>,
+    reading: Option<(&Note, f64)>,
+    font: &TextStyle,
+    color: &RGBAColor,
+    needle_color: &RGBAColor,
+) -> Result<(), DrawingAreaErrorKind<DB::ErrorType>> {
+    let (width, height) = area.dim_in_pixel();
+    let (width, height) = (width as i32, height as i32);
+    let center = (width / 2, height * 3 / 4);
+    let radius = (width / 2).min(height / 2) as f64;
+    // 0 is straight up, negative angles are to the left.
+    let point = |angle: f64, r: f64| {
+        (
+            center.0 + (r * angle.sin()).round() as i32,
+            center.1 - (r * angle.cos()).round() as i32,
+        )
+    };
+
+    let arc: Vec<(i32, i32)> = (-50..=50)
+        .map(|i| point(GAUGE_HALF_ANGLE * i as f64 / 50.0, radius))
+        .collect();
+    area.draw(&PathElement::new(arc, color.stroke_width(2)))?;
+    for i in -N_SIDE_TICKS..=N_SIDE_TICKS {
+        let angle = GAUGE_HALF_ANGLE * i as f64 / N_SIDE_TICKS as f64;
+        let tick_len = if i == 0 { 0.2 } else { 0.1 };
+        area.draw(&PathElement::new(
+            vec![
+                point(angle, radius),
+                point(angle, radius * (1.0 - tick_len)),
+            ],
+            color.stroke_width(2),
+        ))?;
+    }
+    area.draw(&Text::new(
+        "♭",
+        point(-GAUGE_HALF_ANGLE, radius * 1.15),
+        font.clone(),
+    ))?;
+    area.draw(&Text::new(
+        "♯",
+        point(GAUGE_HALF_ANGLE, radius * 1.15),
+        font.clone(),
+    ))?;
+
+    if let Some((note, cents)) = reading {
+        let ratio = (cents / TUNER_RANGE_CENTS).clamp(-1.0, 1.0);
+        area.draw(&PathElement::new(
+            vec![center, point(ratio * GAUGE_HALF_ANGLE, radius * 0.95)],
+            needle_color.stroke_width(3),
+        ))?;
+        let label = format!("{} {:+.0}¢", note.name_octave(), cents);
+        area.draw(&Text::new(
+            label,
+            (center.0 - radius as i32 / 2, center.1 + 10),
+            font.clone(),
+        ))?;
+    }
+    area.draw(&Circle::new(center, 4, needle_color.filled()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::NoteName;
+
+    fn analysis(name: NoteName, cents: f64) -> AnalysisResult {
+        let note = Note {
+            name,
+            octave: 4,
+            frequency: 440.0,
+        };
+        AnalysisResult {
+            note: Some(note),
+            frequency: Some(440.0 * 2f64.powf(cents / 1200.0)),
+            confidence: 1.0,
+            peaks: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_needle_eases_towards_reading() {
+        let mut needle = Needle::default();
+        needle.update(Some(&analysis(NoteName::A, 0.0)), Duration::from_millis(10));
+        assert_eq!(0.0, needle.reading().unwrap().1);
+
+        needle.update(
+            Some(&analysis(NoteName::A, 20.0)),
+            Duration::from_millis(10),
+        );
+        let cents = needle.reading().unwrap().1;
+        assert!(cents > 0.0 && cents < 20.0);
+        needle.update(Some(&analysis(NoteName::A, 20.0)), Duration::from_secs(10));
+        assert!((needle.reading().unwrap().1 - 20.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_needle_jumps_to_new_note() {
+        let mut needle = Needle::default();
+        needle.update(
+            Some(&analysis(NoteName::A, 20.0)),
+            Duration::from_millis(10),
+        );
+        needle.update(
+            Some(&analysis(NoteName::B, -10.0)),
+            Duration::from_millis(10),
+        );
+        let (note, cents) = needle.reading().unwrap();
+        assert_eq!(NoteName::B, note.name);
+        assert!((cents + 10.0).abs() < 1e-6);
+        needle.update(None, Duration::from_millis(10));
+        assert!(needle.reading().is_none());
+    }
+}
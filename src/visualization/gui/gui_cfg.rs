@@ -1,17 +1,277 @@
+use crate::visualization::gui::theme::GuiTheme;
+use log::*;
 use serde::Deserialize;
+use std::env;
+use std::path::PathBuf;
 
+/// Environment variables the desktop scale factor is read from, in order of
+/// preference.
+const SCALE_FACTOR_VARS: [&str; 2] = ["GDK_SCALE", "QT_SCALE_FACTOR"];
+
+/// Scale factor of the display read with `get_var`, or `None` if none of the
+/// scale variables is set to a positive number.
+fn scale_factor_from_env<F: Fn(&str) -> Option<String>>(get_var: F) -> Option<f64> {
+    SCALE_FACTOR_VARS
+        .iter()
+        .filter_map(|var| get_var(var))
+        .filter_map(|value| value.trim().parse::<f64>().ok())
+        .find(|factor| *factor > 0.0)
+}
+
+/// Sizes of the plots in pixels of the screen, converted from the sizes in
+/// the config, which are in points.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlotSizes {
+    pub pixels_per_point: f32,
+    pub margin_size: u32,
+    pub label_area_size: u32,
+    pub font_size: i32,
+    pub fretboard_height: u32,
+    pub staff_width: u32,
+}
+
+impl PlotSizes {
+    /// Number of pixels covering `points`.
+    pub fn px(&self, points: i32) -> i32 {
+        (points as f32 * self.pixels_per_point).round() as i32
+    }
+}
+
+/// How the spectrum is shown below the fretboard.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SpectrumView {
+    /// Line plot of the most recent spectrum.
+    Line,
+    /// Scrolling heatmap of the recent spectra, the newest at the top.
+    Waterfall,
+}
+
+/// Page shown in the GUI window, switched with its tab or the 1, 2 and 3
+/// keys.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum GuiPage {
+    Spectrum,
+    Fretboard,
+    Stats,
+}
+
+/// GUI settings. Fields missing from `gui.toml` take their default values.
 #[derive(Debug, Deserialize)]
+#[serde(default)]
 pub struct GuiCfg {
     pub width: usize,
     pub height: usize,
+    /// Frame rate of the GUI, which is drawn on its own thread.
+    pub fps: f64,
+    /// Position of the top left corner of the window on the desktop.
+    pub position: Option<(isize, isize)>,
+    /// Shows the window fullscreen on the monitor it is placed on, e.g. with
+    /// `position`.
+    pub fullscreen: bool,
+    /// Keeps the window above the other windows. Ignored on Wayland, which
+    /// leaves it to the compositor.
+    pub always_on_top: bool,
+    /// Number of pixels per point, overriding the scale of the monitor the
+    /// window backend reports. Read from `GDK_SCALE` or `QT_SCALE_FACTOR` if
+    /// not given.
+    pub scale_factor: Option<f64>,
     pub margin_size: u32,
     pub label_area_size: u32,
+    pub spectrum_min_freq: f64,
     pub spectrum_max_freq: f64,
     pub spectrum_max_magnitude: f64,
+    pub log_frequency: bool,
+    pub db_magnitude: bool,
+    pub spectrum_min_db: f64,
     pub font_name: String,
     pub font_size: i32,
-    pub font_color: (u8, u8, u8, u8),
-    pub axis_color: (u8, u8, u8, u8),
-    pub background_color: (u8, u8, u8, u8),
-    pub line_color: (u8, u8, u8, u8),
+    /// Name of the theme used at startup.
+    pub theme: String,
+    /// Themes cycled through with the T key.
+    pub themes: Vec<GuiTheme>,
+    pub page: GuiPage,
+    /// Height of the piano keyboard strip below the control bar, 0 to hide
+    /// it.
+    pub piano_height: u32,
+    pub fretboard_height: u32,
+    pub gauge_width: u32,
+    /// Height of the pitch readout below the tuner gauge.
+    pub readout_height: u32,
+    pub show_peaks: bool,
+    /// Show the target note on a staff next to the fretboard instead of
+    /// naming it, for sight-reading practice.
+    pub show_staff: bool,
+    /// Number of sharps in the key signature of the staff.
+    pub staff_key_sharps: usize,
+    pub staff_width: u32,
+    /// Draw the exponential average of the spectra instead of the latest one.
+    pub average_spectrum: bool,
+    /// Weight of the previous average, between 0 and 1.
+    pub averaging_factor: f64,
+    /// Draw the highest recent magnitude of each bin over the spectrum.
+    pub peak_hold: bool,
+    /// Factor the held peaks are multiplied by on each spectrum.
+    pub peak_hold_decay: f64,
+    pub spectrum_view: SpectrumView,
+    pub waterfall_length: usize,
+    /// Directory the screenshots taken with the P key are saved to.
+    pub screenshot_dir: PathBuf,
+}
+
+impl Default for GuiCfg {
+    fn default() -> Self {
+        GuiCfg {
+            width: 1280,
+            height: 960,
+            fps: 60.0,
+            position: None,
+            fullscreen: false,
+            always_on_top: false,
+            scale_factor: None,
+            margin_size: 10,
+            label_area_size: 30,
+            spectrum_min_freq: 0.0,
+            spectrum_max_freq: 2000.0,
+            spectrum_max_magnitude: 0.01,
+            log_frequency: false,
+            db_magnitude: false,
+            spectrum_min_db: -100.0,
+            font_name: String::from("sans-serif"),
+            font_size: 15,
+            theme: String::from("dark"),
+            themes: vec![
+                GuiTheme::dark(),
+                GuiTheme::light(),
+                GuiTheme::high_contrast(),
+            ],
+            page: GuiPage::Fretboard,
+            piano_height: 50,
+            fretboard_height: 240,
+            gauge_width: 320,
+            readout_height: 80,
+            show_peaks: true,
+            show_staff: false,
+            staff_key_sharps: 0,
+            staff_width: 300,
+            average_spectrum: false,
+            averaging_factor: 0.8,
+            peak_hold: false,
+            peak_hold_decay: 0.99,
+            spectrum_view: SpectrumView::Line,
+            waterfall_length: 100,
+            screenshot_dir: PathBuf::from("."),
+        }
+    }
+}
+
+impl GuiCfg {
+    /// Scale factor set in the config or the environment, which overrides the
+    /// scale of the monitor, or `None` to use the scale of the monitor.
+    pub fn scale_override(&self) -> Option<f32> {
+        self.scale_override_with(|var| env::var(var).ok())
+    }
+
+    fn scale_override_with<F: Fn(&str) -> Option<String>>(&self, get_var: F) -> Option<f32> {
+        let factor = match self.scale_factor {
+            Some(factor) if factor > 0.0 => Some(factor),
+            Some(factor) => {
+                warn!("Invalid GUI scale factor {}, using the monitor's", factor);
+                None
+            }
+            None => scale_factor_from_env(get_var),
+        };
+        factor.map(|factor| factor as f32)
+    }
+
+    /// Sizes of the plots at `pixels_per_point`.
+    pub fn plot_sizes(&self, pixels_per_point: f32) -> PlotSizes {
+        let scale = |size: u32| (size as f32 * pixels_per_point).round() as u32;
+        PlotSizes {
+            pixels_per_point,
+            margin_size: scale(self.margin_size),
+            label_area_size: scale(self.label_area_size),
+            font_size: (self.font_size as f32 * pixels_per_point).round() as i32,
+            fretboard_height: scale(self.fretboard_height),
+            staff_width: scale(self.staff_width),
+        }
+    }
+
+    /// Index of the startup theme in `themes`, or the first theme if there is
+    /// no theme with that name.
+    pub fn theme_index(&self) -> usize {
+        match self
+            .themes
+            .iter()
+            .position(|theme| theme.name == self.theme)
+        {
+            Some(idx) => idx,
+            None => {
+                warn!("Unknown GUI theme {}, using the first one", self.theme);
+                0
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_theme_index() {
+        let mut cfg = GuiCfg {
+            theme: String::from("light"),
+            ..GuiCfg::default()
+        };
+        assert_eq!(1, cfg.theme_index());
+        cfg.theme = String::from("solarized");
+        assert_eq!(0, cfg.theme_index());
+    }
+
+    #[test]
+    fn test_scale_factor_from_env() {
+        assert_eq!(None, scale_factor_from_env(|_| None));
+        assert_eq!(
+            Some(2.0),
+            scale_factor_from_env(|var| match var {
+                "GDK_SCALE" => Some(String::from("2")),
+                _ => Some(String::from("1.5")),
+            })
+        );
+        assert_eq!(
+            Some(1.5),
+            scale_factor_from_env(|var| match var {
+                "GDK_SCALE" => Some(String::from("0")),
+                _ => Some(String::from(" 1.5")),
+            })
+        );
+    }
+
+    #[test]
+    fn test_scale_override() {
+        let gdk_scale = |var: &str| match var {
+            "GDK_SCALE" => Some(String::from("2")),
+            _ => None,
+        };
+        let mut cfg = GuiCfg::default();
+        assert_eq!(None, cfg.scale_override_with(|_| None));
+        assert_eq!(Some(2.0), cfg.scale_override_with(gdk_scale));
+        // The config takes precedence over the environment.
+        cfg.scale_factor = Some(1.5);
+        assert_eq!(Some(1.5), cfg.scale_override_with(gdk_scale));
+        cfg.scale_factor = Some(0.0);
+        assert_eq!(None, cfg.scale_override_with(gdk_scale));
+    }
+
+    #[test]
+    fn test_plot_sizes() {
+        let cfg = GuiCfg::default();
+        let sizes = cfg.plot_sizes(2.0);
+        assert_eq!(30, sizes.font_size);
+        assert_eq!(2 * cfg.fretboard_height, sizes.fretboard_height);
+        assert_eq!(2 * cfg.margin_size, sizes.margin_size);
+        assert_eq!(8, sizes.px(4));
+    }
 }
@@ -1,154 +1,855 @@
-// DISCLAIMER: Major parts of the frame handling in this file is adapted
-// from https://github.com/38/plotters/blob/master/examples/minifb-demo/src/main.rs
+use crate::audio_analysis::{AnalysisResult, AudioParams};
+use crate::core::{FretLoc, FretRange, KeysCfg, StringRange};
+use crate::event_bus::Subscription;
+use crate::game::{GameController, GameError, GameState, SessionRecord};
+use crate::metrics::Metrics;
+use crate::reference_tone::TonePlayer;
+use crate::visualization::gui::canvas::{Area, Canvas};
+use crate::visualization::gui::fretboard_view::{draw_fretboard, draw_fretboard_cells};
+use crate::visualization::gui::gauge_view::{draw_tuner_gauge, Needle};
+use crate::visualization::gui::gui_cfg::{GuiPage, PlotSizes, SpectrumView};
+use crate::visualization::gui::heatmap_view::{heat_color, HeatMetric};
+use crate::visualization::gui::key_bindings::{is_pressed, KeyBindings};
+use crate::visualization::gui::piano_view::draw_piano;
+use crate::visualization::gui::png_export::save_screenshot;
+use crate::visualization::gui::readout_view::draw_readout;
+use crate::visualization::gui::settings_view::show_audio_settings;
+use crate::visualization::gui::spectrum_scale::SpectrumScale;
+use crate::visualization::gui::spectrum_trace::SpectrumTrace;
+use crate::visualization::gui::staff_view::draw_staff_note;
+use crate::visualization::gui::stats_view::{
+    draw_history_chart, draw_string_chart, ChartStyle, N_HISTORY_SESSIONS,
+};
+use crate::visualization::gui::status_view::{
+    draw_text_lines, format_status, format_target_status,
+};
+use crate::visualization::gui::theme::{GuiTheme, Palette};
+use crate::visualization::gui::waterfall_view::draw_waterfall;
 use crate::visualization::gui::GuiCfg;
-use crate::visualization::Visualizer;
-use minifb::{Key, Window, WindowOptions};
-use plotters::chart::ChartState;
-use plotters::coord::types::RangedCoordf64;
+use crate::visualization::session_summary::SessionSummary;
+use crate::visualization::status_text::TargetResult;
+use crate::visualization::FrameData;
+use eframe::egui::{
+    self, CentralPanel, ColorImage, ComboBox, Context, Event, Key, Response, ScrollArea, SidePanel,
+    TopBottomPanel, Ui, ViewportBuilder, ViewportCommand,
+};
+use log::*;
 use plotters::prelude::*;
 use plotters::style::RGBAColor;
-use plotters_bitmap::bitmap_pixel::BGRXPixel;
-use plotters_bitmap::BitMapBackend;
-use std::borrow::{Borrow, BorrowMut};
-use std::sync::mpsc;
-
-struct BufferWrapper(Vec<u32>);
-impl Borrow<[u8]> for BufferWrapper {
-    fn borrow(&self) -> &[u8] {
-        // Safe for alignment: align_of(u8) <= align_of(u32)
-        // Safe for cast: u32 can be thought of as being transparent over [u8; 4]
-        unsafe { std::slice::from_raw_parts(self.0.as_ptr() as *const u8, self.0.len() * 4) }
-    }
-}
-impl BorrowMut<[u8]> for BufferWrapper {
-    fn borrow_mut(&mut self) -> &mut [u8] {
-        // Safe for alignment: align_of(u8) <= align_of(u32)
-        // Safe for cast: u32 can be thought of as being transparent over [u8; 4]
-        unsafe { std::slice::from_raw_parts_mut(self.0.as_mut_ptr() as *mut u8, self.0.len() * 4) }
-    }
+use std::collections::VecDeque;
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::time::{Duration, Instant};
+use triple_buffer::Output;
+use winit::event_loop::EventLoopBuilder;
+
+const WINDOW_TITLE: &str = "libreguitar";
+/// Factor the spectrum is zoomed in or out by on each key press or wheel
+/// step.
+const ZOOM_STEP_FACTOR: f64 = 1.25;
+/// Part of the shown spectrum width it is moved by on each pan key press.
+const PAN_STEP: f64 = 0.1;
+/// Pages with the keys that switch to them and their tab names.
+const PAGES: [(GuiPage, Key, &str); 3] = [
+    (GuiPage::Spectrum, Key::Num1, "Spectrum"),
+    (GuiPage::Fretboard, Key::Num2, "Fretboard"),
+    (GuiPage::Stats, Key::Num3, "Stats"),
+];
+
+/// Lets the event loop run on another thread than the main thread where
+/// winit allows it, for an app spawned in the background.
+fn allow_any_thread<T>(builder: &mut EventLoopBuilder<T>) {
+    #[cfg(target_os = "linux")]
+    winit::platform::x11::EventLoopBuilderExtX11::with_any_thread(builder, true);
+    #[cfg(windows)]
+    winit::platform::windows::EventLoopBuilderExtWindows::with_any_thread(builder, true);
+    #[cfg(not(any(target_os = "linux", windows)))]
+    let _ = builder;
 }
-impl Borrow<[u32]> for BufferWrapper {
-    fn borrow(&self) -> &[u32] {
-        self.0.as_slice()
-    }
+
+fn font<'a>(gui_cfg: &'a GuiCfg, sizes: &PlotSizes, palette: &Palette) -> TextStyle<'a> {
+    (&gui_cfg.font_name[..], sizes.font_size)
+        .into_font()
+        .color(&palette.font)
 }
-impl BorrowMut<[u32]> for BufferWrapper {
-    fn borrow_mut(&mut self) -> &mut [u32] {
-        self.0.as_mut_slice()
+
+fn warn_on_error(res: Result<(), GameError>) {
+    if let Err(err) = res {
+        warn!("{}", err);
     }
 }
 
-fn color_from_tup(rgb: (u8, u8, u8, u8)) -> RGBAColor {
-    let alpha = rgb.3 as f64 / 255.0;
-    RGBColor(rgb.0, rgb.1, rgb.2).mix(alpha)
+/// Connections of the GUI to the audio and game threads.
+pub struct GuiChannels {
+    /// Latest spectrum and analysis, written by the analysis callback.
+    pub frames: Output<FrameData>,
+    pub state_rx: Subscription<GameState>,
+    /// Sends the audio parameters changed on the settings panel.
+    pub params_tx: mpsc::Sender<AudioParams>,
+    pub controller: GameController,
+    /// Plays the current target on demand if reference tones are enabled.
+    pub tone_player: Option<TonePlayer>,
+    /// Performance counters shown on the stats page.
+    pub metrics: Arc<Metrics>,
 }
 
-pub struct FrameData {
-    pub spectrogram: Vec<f64>,
+/// Canvases drawn with plotters: the piano keyboard at the top, the active
+/// page and the tuner gauge and pitch readout in the side panel.
+#[derive(Default)]
+struct Canvases {
+    piano: Canvas,
+    page: Canvas,
+    gauge: Canvas,
+    readout: Canvas,
 }
 
+/// Window with the game controls, the audio settings and the pages, drawn
+/// with egui on the event loop of its thread.
 pub struct GUIVisualizer {
-    window: minifb::Window,
-    buf: BufferWrapper,
-    cs: ChartState<Cartesian2d<RangedCoordf64, RangedCoordf64>>,
+    /// Set when the app ends, which closes the window.
+    stop: Arc<AtomicBool>,
+    frame_period: Duration,
+    canvases: Canvases,
+    title: String,
+    /// Horizontal pixel range of the spectrum plot on its canvas.
+    plot_x_range: Range<i32>,
     xaxis: Vec<f64>,
-    rx: mpsc::Receiver<FrameData>,
+    delta_f: f64,
+    scale: SpectrumScale,
+    page: GuiPage,
+    keys: KeyBindings,
+    /// Whether the current target is marked on the fretboard and the piano.
+    show_target: bool,
+    heat_metric: HeatMetric,
+    spectrum_view: SpectrumView,
+    spectra: VecDeque<Vec<f64>>,
+    /// Latest analysis, drawn again on the frames until the next one arrives.
+    analysis: Option<AnalysisResult>,
+    needle: Needle,
+    last_frame: Instant,
+    trace: SpectrumTrace,
+    average_spectrum: bool,
+    peak_hold: bool,
+    frames: Output<FrameData>,
+    state_rx: Subscription<GameState>,
+    params_tx: mpsc::Sender<AudioParams>,
+    controller: GameController,
+    tone_player: Option<TonePlayer>,
+    metrics: Arc<Metrics>,
+    audio_params: AudioParams,
+    fret_range: FretRange,
+    string_range: StringRange,
+    game_state: Option<GameState>,
+    previous_target_loc: Option<FretLoc>,
+    summary: SessionSummary,
+    session_start: Option<Instant>,
+    /// Past sessions shown on the stats page, the oldest first.
+    history: Vec<SessionRecord>,
     gui_cfg: GuiCfg,
-    background_color: RGBAColor,
-    line_color: RGBAColor,
+    /// Pixels per point set in the config or the environment instead of the
+    /// scale of the monitor.
+    scale_override: Option<f32>,
+    plot_sizes: PlotSizes,
+    /// Index of the active theme in `gui_cfg.themes`.
+    theme_idx: usize,
+    palette: Palette,
 }
 
 impl GUIVisualizer {
+    /// Creates the visualizer. The window is opened by [`run`](Self::run).
     pub fn new(
-        rx: mpsc::Receiver<FrameData>,
+        channels: GuiChannels,
         xaxis_props: (f64, f64, f64),
-        gui_cfg: GuiCfg,
+        audio_params: AudioParams,
+        (fret_range, string_range): (FretRange, StringRange),
+        history: Vec<SessionRecord>,
+        keys: &KeysCfg,
+        mut gui_cfg: GuiCfg,
     ) -> GUIVisualizer {
-        let w = gui_cfg.width;
-        let h = gui_cfg.height;
-        let font_color = color_from_tup(gui_cfg.font_color);
-        let axis_color = color_from_tup(gui_cfg.axis_color);
-        let background_color = color_from_tup(gui_cfg.background_color);
-        let line_color = color_from_tup(gui_cfg.line_color);
-        let mut buf = BufferWrapper(vec![0u32; w * h]);
-
-        let window = Window::new("Default Plotter Window", w, h, WindowOptions::default()).unwrap();
-        let root = BitMapBackend::<BGRXPixel>::with_buffer_and_format(
-            buf.borrow_mut(),
-            (w as u32, h as u32),
-        )
-        .unwrap()
-        .into_drawing_area();
-        root.fill(&background_color).unwrap();
-
+        if gui_cfg.themes.is_empty() {
+            gui_cfg.themes.push(GuiTheme::default());
+        }
+        let theme_idx = gui_cfg.theme_index();
+        let palette = Palette::new(&gui_cfg.themes[theme_idx]);
         let (beg, end, step) = xaxis_props;
-        let mut chart = ChartBuilder::on(&root)
-            .margin(gui_cfg.margin_size)
-            .set_all_label_area_size(gui_cfg.label_area_size)
-            .build_cartesian_2d(
-                beg..gui_cfg.spectrum_max_freq,
-                0.0..gui_cfg.spectrum_max_magnitude,
-            )
-            .unwrap();
-
-        let fonttup = (&gui_cfg.font_name[..], gui_cfg.font_size);
-        chart
-            .configure_mesh()
-            .label_style(fonttup.into_font().color(&font_color))
-            .axis_style(&axis_color)
-            .draw()
-            .unwrap();
-
-        let cs = chart.into_chart_state();
-        drop(root);
         GUIVisualizer {
-            window,
-            buf,
-            cs,
+            stop: Arc::default(),
+            frame_period: Duration::from_secs_f64(1.0 / gui_cfg.fps),
+            canvases: Canvases::default(),
+            title: String::from(WINDOW_TITLE),
+            plot_x_range: 0..1,
             xaxis: (beg..end).step(step).values().collect(),
-            rx,
+            delta_f: step,
+            scale: SpectrumScale::new(&gui_cfg),
+            page: gui_cfg.page,
+            keys: KeyBindings::new(keys),
+            show_target: !gui_cfg.show_staff,
+            heat_metric: HeatMetric::ErrorRate,
+            spectrum_view: gui_cfg.spectrum_view,
+            spectra: VecDeque::with_capacity(gui_cfg.waterfall_length),
+            analysis: None,
+            needle: Needle::default(),
+            last_frame: Instant::now(),
+            trace: SpectrumTrace::default(),
+            average_spectrum: gui_cfg.average_spectrum,
+            peak_hold: gui_cfg.peak_hold,
+            frames: channels.frames,
+            state_rx: channels.state_rx,
+            params_tx: channels.params_tx,
+            controller: channels.controller,
+            tone_player: channels.tone_player,
+            metrics: channels.metrics,
+            audio_params,
+            fret_range,
+            string_range,
+            history,
+            game_state: None,
+            previous_target_loc: None,
+            summary: SessionSummary::default(),
+            session_start: None,
+            theme_idx,
+            palette,
+            scale_override: gui_cfg.scale_override(),
+            plot_sizes: gui_cfg.plot_sizes(1.0),
             gui_cfg,
-            background_color,
-            line_color,
         }
     }
-}
 
-impl Visualizer for GUIVisualizer {
-    fn is_open(&self) -> bool {
-        self.window.is_open() && !self.window.is_key_down(Key::Escape)
+    /// Opens the window and runs its event loop until the window is closed
+    /// or `stop` is set. Windows can only be opened once per process, and not
+    /// from another thread than the main thread on macOS.
+    pub fn run(mut self, stop: Arc<AtomicBool>) {
+        self.stop = stop;
+        let mut viewport = ViewportBuilder::default()
+            .with_title(WINDOW_TITLE)
+            .with_inner_size([self.gui_cfg.width as f32, self.gui_cfg.height as f32])
+            // Covers the monitor the window is placed on.
+            .with_fullscreen(self.gui_cfg.fullscreen);
+        if let Some((x, y)) = self.gui_cfg.position {
+            viewport = viewport.with_position([x as f32, y as f32]);
+        }
+        if self.gui_cfg.always_on_top {
+            viewport = viewport.with_always_on_top();
+        }
+        let options = eframe::NativeOptions {
+            viewport,
+            event_loop_builder: Some(Box::new(allow_any_thread)),
+            ..eframe::NativeOptions::default()
+        };
+        let res = eframe::run_native(
+            WINDOW_TITLE,
+            options,
+            Box::new(move |cc| {
+                cc.egui_ctx
+                    .set_visuals(self.gui_cfg.themes[self.theme_idx].visuals());
+                Ok(Box::new(self))
+            }),
+        );
+        if let Err(err) = res {
+            warn!("Could not open the GUI window: {}", err);
+        }
     }
 
-    fn draw(&mut self) {
-        let packet = self.rx.try_iter().last();
-        if packet.is_none() {
-            return;
+    fn set_title(&mut self, ctx: &Context, title: String) {
+        if title != self.title {
+            ctx.send_viewport_cmd(ViewportCommand::Title(title.clone()));
+            self.title = title;
         }
-        let arr = packet.unwrap().spectrogram;
-        let root = BitMapBackend::<BGRXPixel>::with_buffer_and_format(
-            self.buf.borrow_mut(),
-            (self.gui_cfg.width as u32, self.gui_cfg.height as u32),
-        )
-        .unwrap()
-        .into_drawing_area();
-        let mut chart = self.cs.clone().restore(&root);
-        chart.plotting_area().fill(&self.background_color).unwrap();
+    }
+
+    fn receive_states(&mut self, ctx: &Context) {
+        let states: Vec<GameState> = self.state_rx.try_iter().collect();
+        for game_state in states {
+            if let Some(last_state) = &self.game_state {
+                if last_state.target_loc != game_state.target_loc {
+                    self.previous_target_loc = Some(last_state.target_loc.clone());
+                    self.summary
+                        .record(&TargetResult::between(last_state, &game_state));
+                }
+            }
+            let title = format!(
+                "{} — {}",
+                WINDOW_TITLE,
+                format_target_status(&game_state, self.gui_cfg.show_staff)
+            );
+            self.set_title(ctx, title);
+            self.session_start.get_or_insert_with(Instant::now);
+            self.game_state = Some(game_state);
+        }
+    }
+
+    /// Moves the latest spectrum, if there is a new one, into the waterfall
+    /// and the trace, and moves the needle towards the latest analysis.
+    fn receive_frame(&mut self) {
+        if self.frames.update() {
+            let frame = self.frames.output_buffer();
+            self.trace.update(
+                &frame.spectrogram,
+                self.gui_cfg.averaging_factor,
+                self.gui_cfg.peak_hold_decay,
+            );
+            // The spectrum is moved into the waterfall, and the row that
+            // drops out of it goes back to the analysis as its next buffer.
+            let row = if self.spectra.len() == self.gui_cfg.waterfall_length {
+                self.spectra.pop_front().unwrap_or_default()
+            } else {
+                Vec::new()
+            };
+            self.spectra
+                .push_back(std::mem::replace(&mut frame.spectrogram, row));
+            self.analysis = Some(frame.analysis.clone());
+        }
+        let now = Instant::now();
+        self.needle
+            .update(self.analysis.as_ref(), now - self.last_frame);
+        self.last_frame = now;
+    }
+
+    fn is_paused(&self) -> bool {
+        matches!(&self.game_state, Some(state) if state.paused)
+    }
+
+    fn toggle_pause(&self) {
+        warn_on_error(if self.is_paused() {
+            self.controller.resume()
+        } else {
+            self.controller.pause()
+        });
+    }
+
+    fn play_reference_tone(&self) {
+        if let (Some(player), Some(state)) = (&self.tone_player, &self.game_state) {
+            player.play(&state.target_note);
+        }
+    }
 
+    fn set_theme(&mut self, ctx: &Context, theme_idx: usize) {
+        self.theme_idx = theme_idx;
+        let theme = &self.gui_cfg.themes[theme_idx];
+        info!("Switched to the {} GUI theme", theme.name);
+        self.palette = Palette::new(theme);
+        ctx.set_visuals(theme.visuals());
+    }
+
+    fn handle_keys(&mut self, ctx: &Context) {
+        if is_pressed(ctx, Key::Escape, false) || is_pressed(ctx, self.keys.quit, false) {
+            ctx.send_viewport_cmd(ViewportCommand::Close);
+        }
+        if self.page == GuiPage::Spectrum {
+            self.handle_zoom_keys(ctx);
+        }
+        if is_pressed(ctx, self.keys.screenshot, false) {
+            ctx.send_viewport_cmd(ViewportCommand::Screenshot);
+        }
+        if is_pressed(ctx, self.keys.pause, false) {
+            self.toggle_pause();
+        }
+        if is_pressed(ctx, self.keys.reference_tone, false) {
+            self.play_reference_tone();
+        }
+        if is_pressed(ctx, self.keys.skip, false) {
+            warn_on_error(self.controller.skip());
+        }
+        if is_pressed(ctx, self.keys.heatmap, false) {
+            self.heat_metric = self.heat_metric.next();
+        }
+        if is_pressed(ctx, self.keys.hint, false) {
+            self.show_target = !self.show_target;
+        }
+        if is_pressed(ctx, self.keys.view, false) {
+            self.spectrum_view = match self.spectrum_view {
+                SpectrumView::Line => SpectrumView::Waterfall,
+                SpectrumView::Waterfall => SpectrumView::Line,
+            };
+        }
+        if is_pressed(ctx, self.keys.average, false) {
+            self.average_spectrum = !self.average_spectrum;
+        }
+        if is_pressed(ctx, self.keys.peak_hold, false) {
+            self.peak_hold = !self.peak_hold;
+            self.trace.reset_hold();
+        }
+        if is_pressed(ctx, self.keys.theme, false) {
+            self.set_theme(ctx, (self.theme_idx + 1) % self.gui_cfg.themes.len());
+        }
+        for (page, key, _) in PAGES.iter() {
+            if is_pressed(ctx, *key, false) {
+                self.page = *page;
+            }
+        }
+    }
+
+    /// Zooms and pans the spectrum with the zoom and pan keys.
+    fn handle_zoom_keys(&mut self, ctx: &Context) {
+        if is_pressed(ctx, self.keys.zoom_in, true) {
+            self.scale.zoom(ZOOM_STEP_FACTOR, 0.5);
+        }
+        if is_pressed(ctx, self.keys.zoom_out, true) {
+            self.scale.zoom(1.0 / ZOOM_STEP_FACTOR, 0.5);
+        }
+        if is_pressed(ctx, self.keys.zoom_reset, false) {
+            self.scale.reset_zoom();
+        }
+        if is_pressed(ctx, self.keys.pan_left, true) {
+            self.scale.pan(-PAN_STEP);
+        }
+        if is_pressed(ctx, self.keys.pan_right, true) {
+            self.scale.pan(PAN_STEP);
+        }
+    }
+
+    /// Zooms the spectrum with the mouse wheel around the cursor and pans it
+    /// by dragging.
+    fn handle_mouse_zoom(&mut self, ctx: &Context, response: &Response) {
+        let pixels_per_point = ctx.pixels_per_point() as f64;
+        let plot_width = (self.plot_x_range.end - self.plot_x_range.start).max(1) as f64;
+        if let Some(pos) = response.hover_pos() {
+            let x = (pos.x - response.rect.left()) as f64 * pixels_per_point;
+            let ratio = (x - self.plot_x_range.start as f64) / plot_width;
+            let scroll = ctx.input(|input| input.raw_scroll_delta.y);
+            if scroll > 0.0 {
+                self.scale.zoom(ZOOM_STEP_FACTOR, ratio);
+            } else if scroll < 0.0 {
+                self.scale.zoom(1.0 / ZOOM_STEP_FACTOR, ratio);
+            }
+        }
+        if response.dragged() {
+            let dx = response.drag_delta().x as f64 * pixels_per_point;
+            self.scale.pan(-dx / plot_width);
+        }
+    }
+
+    /// Saves the screenshots taken since the last frame to PNG files.
+    fn save_screenshots(&mut self, ctx: &Context) {
+        let images: Vec<Arc<ColorImage>> = ctx.input(|input| {
+            input
+                .events
+                .iter()
+                .filter_map(|event| match event {
+                    Event::Screenshot { image, .. } => Some(image.clone()),
+                    _ => None,
+                })
+                .collect()
+        });
+        for image in images {
+            let pixels: Vec<u32> = image
+                .pixels
+                .iter()
+                .map(|color| u32::from_be_bytes([0, color.r(), color.g(), color.b()]))
+                .collect();
+            let dir = &self.gui_cfg.screenshot_dir;
+            match save_screenshot(dir, image.width(), image.height(), &pixels) {
+                Ok(path) => {
+                    info!("Saved a screenshot to {}", path.display());
+                    let title = format!("{} — saved {}", WINDOW_TITLE, path.display());
+                    self.set_title(ctx, title);
+                }
+                Err(err) => warn!("Could not save a screenshot to {}: {}", dir.display(), err),
+            }
+        }
+    }
+
+    /// Shows the game buttons, the page tabs and the window buttons above the
+    /// target status and the score.
+    fn show_controls(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            let pause_label = if self.is_paused() { "Resume" } else { "Pause" };
+            if ui.button(pause_label).clicked() {
+                self.toggle_pause();
+            }
+            if ui.button("Skip").clicked() {
+                warn_on_error(self.controller.skip());
+            }
+            if self.tone_player.is_some() && ui.button("Play target").clicked() {
+                self.play_reference_tone();
+            }
+            ui.separator();
+            for (page, _, name) in PAGES.iter() {
+                if ui.selectable_label(self.page == *page, *name).clicked() {
+                    self.page = *page;
+                }
+            }
+            ui.separator();
+            let mut theme_idx = self.theme_idx;
+            ComboBox::from_label("Theme")
+                .selected_text(&self.gui_cfg.themes[theme_idx].name)
+                .show_ui(ui, |ui| {
+                    for (idx, theme) in self.gui_cfg.themes.iter().enumerate() {
+                        ui.selectable_value(&mut theme_idx, idx, &theme.name);
+                    }
+                });
+            if theme_idx != self.theme_idx {
+                self.set_theme(ui.ctx(), theme_idx);
+            }
+            if ui.button("Screenshot").clicked() {
+                ui.ctx().send_viewport_cmd(ViewportCommand::Screenshot);
+            }
+        });
+        match &self.game_state {
+            Some(game_state) => {
+                let (target_status, score) = format_status(game_state, self.gui_cfg.show_staff);
+                ui.strong(target_status);
+                ui.label(score);
+            }
+            None => {
+                ui.label("Waiting for the game to start");
+            }
+        }
+    }
+
+    /// Shows the tuner gauge and the pitch readout above the audio settings.
+    fn show_side_panel(&mut self, ui: &mut Ui, canvases: &mut Canvases) {
+        let width = ui.available_width();
+        let font = font(&self.gui_cfg, &self.plot_sizes, &self.palette);
+        let gauge_size = egui::vec2(width, self.gui_cfg.fretboard_height as f32);
+        canvases.gauge.show(ui, gauge_size, |area| {
+            area.fill(&self.palette.background).unwrap();
+            draw_tuner_gauge(
+                area,
+                self.needle.reading(),
+                &font,
+                &self.palette.line,
+                &self.palette.needle,
+            )
+            .unwrap();
+        });
+        let readout_size = egui::vec2(width, self.gui_cfg.readout_height as f32);
+        canvases.readout.show(ui, readout_size, |area| {
+            area.fill(&self.palette.background).unwrap();
+            if let Some(analysis) = &self.analysis {
+                draw_readout(area, analysis, &font).unwrap();
+            }
+        });
+        ScrollArea::vertical().show(ui, |ui| {
+            ui.heading("Audio");
+            if show_audio_settings(ui, &mut self.audio_params)
+                && self.params_tx.send(self.audio_params).is_err()
+            {
+                warn!("Audio thread is not running");
+            }
+        });
+    }
+
+    fn show_piano(&self, ui: &mut Ui, canvas: &mut Canvas) {
+        let target = match (&self.game_state, self.show_target) {
+            (Some(game_state), true) => Some(&game_state.target_note),
+            _ => None,
+        };
+        let detected = self
+            .analysis
+            .as_ref()
+            .and_then(|analysis| analysis.note.as_ref());
+        canvas.show(ui, ui.available_size(), |area| {
+            area.fill(&self.palette.background).unwrap();
+            draw_piano(
+                area,
+                detected,
+                target,
+                &self.palette.needle,
+                &self.palette.target,
+            )
+            .unwrap();
+        });
+    }
+
+    /// Shows the options of the active page above it.
+    fn show_page(&mut self, ui: &mut Ui, canvas: &mut Canvas) {
+        ui.horizontal(|ui| match self.page {
+            GuiPage::Spectrum => {
+                ui.radio_value(&mut self.spectrum_view, SpectrumView::Line, "Line");
+                ui.radio_value(
+                    &mut self.spectrum_view,
+                    SpectrumView::Waterfall,
+                    "Waterfall",
+                );
+                ui.checkbox(&mut self.average_spectrum, "Average");
+                if ui.checkbox(&mut self.peak_hold, "Peak hold").changed() {
+                    self.trace.reset_hold();
+                }
+                if ui.button("Reset zoom").clicked() {
+                    self.scale.reset_zoom();
+                }
+            }
+            GuiPage::Fretboard => {
+                ui.checkbox(&mut self.show_target, "Show target");
+                if ui.button("Next heatmap").clicked() {
+                    self.heat_metric = self.heat_metric.next();
+                }
+            }
+            GuiPage::Stats => {}
+        });
+        let size = ui.available_size();
+        let font = font(&self.gui_cfg, &self.plot_sizes, &self.palette);
+        match self.page {
+            GuiPage::Spectrum => {
+                let (response, plot_x_range) =
+                    canvas.show(ui, size, |area| self.draw_spectrum_page(area, &font));
+                self.plot_x_range = plot_x_range;
+                self.handle_mouse_zoom(ui.ctx(), &response);
+            }
+            GuiPage::Fretboard => {
+                canvas.show(ui, size, |area| self.draw_fretboard_page(area, &font));
+            }
+            GuiPage::Stats => {
+                canvas.show(ui, size, |area| self.draw_stats_page(area, &font));
+            }
+        }
+    }
+
+    /// Draws the spectrum and returns the horizontal pixel range of its plot.
+    fn draw_spectrum_page(&self, area: &Area, font: &TextStyle) -> Range<i32> {
+        area.fill(&self.palette.background).unwrap();
+        let scale = &self.scale;
+        let mut chart = ChartBuilder::on(area)
+            .margin(self.plot_sizes.margin_size)
+            .set_all_label_area_size(self.plot_sizes.label_area_size)
+            .build_cartesian_2d(scale.x_range(), scale.y_range())
+            .unwrap();
         chart
             .configure_mesh()
-            .bold_line_style(&self.line_color)
-            .light_line_style(&TRANSPARENT)
+            .label_style(font.clone())
+            .axis_style(self.palette.axis)
+            .bold_line_style(self.palette.line)
+            .light_line_style(TRANSPARENT)
+            .x_label_formatter(&|x| scale.format_x(*x))
+            .y_label_formatter(&|y| scale.format_y(*y))
             .draw()
             .unwrap();
+        let (plot_x_range, _) = chart.plotting_area().get_pixel_range();
 
-        let data = self.xaxis.iter().cloned().zip(arr.iter().cloned());
-        chart
-            .draw_series(LineSeries::new(data, &self.line_color))
-            .unwrap();
+        let (analysis, arr) = match (&self.analysis, self.spectra.back()) {
+            (Some(analysis), Some(arr)) => (analysis, arr),
+            _ => return plot_x_range,
+        };
+        match self.spectrum_view {
+            SpectrumView::Line => {
+                let points = |spectrum: &'_ [f64]| {
+                    self.xaxis
+                        .iter()
+                        .cloned()
+                        .zip(spectrum.iter().cloned())
+                        .filter(|(freq, _)| scale.contains_freq(*freq))
+                        .map(|(freq, magnitude)| (scale.x(freq), scale.y(magnitude)))
+                        .collect::<Vec<(f64, f64)>>()
+                };
+                if self.peak_hold {
+                    chart
+                        .draw_series(LineSeries::new(
+                            points(self.trace.hold()),
+                            &self.palette.peak,
+                        ))
+                        .unwrap();
+                }
+                let spectrum = if self.average_spectrum {
+                    self.trace.average()
+                } else {
+                    arr
+                };
+                chart
+                    .draw_series(LineSeries::new(points(spectrum), &self.palette.line))
+                    .unwrap();
+                if self.gui_cfg.show_peaks {
+                    // The peak the note was detected from is drawn like the
+                    // tuner needle.
+                    let peaks = analysis
+                        .peaks
+                        .iter()
+                        .filter(|peak| scale.contains_freq(peak.frequency))
+                        .map(|peak| {
+                            let color = if analysis.frequency == Some(peak.frequency) {
+                                &self.palette.needle
+                            } else {
+                                &self.palette.peak
+                            };
+                            let sizes = &self.plot_sizes;
+                            EmptyElement::at((scale.x(peak.frequency), scale.y(peak.magnitude)))
+                                + Circle::new((0, 0), sizes.px(4), color.filled())
+                                + Text::new(
+                                    peak.note.name_octave(),
+                                    (sizes.px(6), sizes.px(-18)),
+                                    font.clone(),
+                                )
+                        });
+                    chart.draw_series(peaks).unwrap();
+                }
+            }
+            SpectrumView::Waterfall => draw_waterfall(
+                &chart.plotting_area().strip_coord_spec(),
+                &self.spectra,
+                self.gui_cfg.waterfall_length,
+                self.delta_f,
+                &self.scale,
+            )
+            .unwrap(),
+        }
+        plot_x_range
+    }
+
+    /// Draws the fretboard with the current and previous targets, and below
+    /// it the heatmap of the positions played in this session.
+    fn draw_fretboard_page(&self, area: &Area, font: &TextStyle) {
+        area.fill(&self.palette.background).unwrap();
+        let sizes = &self.plot_sizes;
+        let (fretboard_area, lower_area) = area.split_vertically(sizes.fretboard_height);
+        let caption_height = 2 * sizes.font_size.max(0) as u32;
+        let (caption_area, lower_area) = lower_area.split_vertically(caption_height);
+        let (heatmap_area, _) = lower_area.split_vertically(sizes.fretboard_height);
+        let fretboard_area = if self.gui_cfg.show_staff {
+            let (staff_area, fretboard_area) = fretboard_area.split_horizontally(sizes.staff_width);
+            if let Some(game_state) = &self.game_state {
+                draw_staff_note(
+                    &staff_area,
+                    &game_state.target_note,
+                    self.gui_cfg.staff_key_sharps,
+                    &self.palette.font,
+                )
+                .unwrap();
+            }
+            fretboard_area
+        } else {
+            fretboard_area
+        };
+        let mut markers = Vec::new();
+        if let Some(loc) = &self.previous_target_loc {
+            markers.push((loc.clone(), self.palette.previous_target));
+        }
+        if let (Some(game_state), true) = (&self.game_state, self.show_target) {
+            markers.push((game_state.target_loc.clone(), self.palette.target));
+        }
+        draw_fretboard(
+            &fretboard_area,
+            &self.fret_range,
+            &self.string_range,
+            &markers,
+            &self.palette.fretboard,
+        )
+        .unwrap();
+
+        let caption = format!(
+            "{} ({} to switch)",
+            self.heat_metric.caption(),
+            self.keys.heatmap.name()
+        );
+        draw_text_lines(&caption_area, &[&caption], font).unwrap();
+        let cells: Vec<(FretLoc, RGBAColor)> = self
+            .heat_metric
+            .heat(&self.summary)
+            .into_iter()
+            .map(|(loc, heat)| (loc, heat_color(heat)))
+            .collect();
+        draw_fretboard(
+            &heatmap_area,
+            &self.fret_range,
+            &self.string_range,
+            &[],
+            &self.palette.fretboard,
+        )
+        .unwrap();
+        draw_fretboard_cells(&heatmap_area, &self.fret_range, &self.string_range, &cells).unwrap();
+    }
 
-        drop(root);
-        drop(chart);
+    /// Draws the summary of the current session next to its accuracy on
+    /// each string, and the accuracy and response time of the past sessions
+    /// below them.
+    fn draw_stats_page(&self, area: &Area, font: &TextStyle) {
+        area.fill(&self.palette.background).unwrap();
+        let (width, height) = area.dim_in_pixel();
+        let (session_area, history_area) = area.split_vertically(height / 2);
+        let (summary_area, string_area) = session_area.split_horizontally(width / 2);
+        let (accuracy_area, response_area) = history_area.split_horizontally(width / 2);
+        let style = ChartStyle {
+            font: font.clone(),
+            line: self.palette.line,
+            axis: self.palette.axis,
+            sizes: self.plot_sizes,
+        };
 
-        self.window.update_with_buffer(self.buf.borrow()).unwrap();
+        let mut lines = Vec::new();
+        if let (Some(game_state), Some(session_start)) = (&self.game_state, self.session_start) {
+            let summary = self
+                .summary
+                .format(&game_state.score, session_start.elapsed())
+                .unwrap();
+            lines.extend(summary.lines().map(String::from));
+            lines.push(String::new());
+        }
+        lines.extend(self.metrics.snapshot().lines());
+        let lines: Vec<&str> = lines.iter().map(String::as_str).collect();
+        draw_text_lines(&summary_area, &lines, font).unwrap();
+        draw_string_chart(
+            &string_area,
+            "Accuracy per string (%)",
+            &self.summary.string_accuracy(),
+            &style,
+        )
+        .unwrap();
+
+        let n_skipped = self.history.len().saturating_sub(N_HISTORY_SESSIONS);
+        let sessions = self.history.iter().enumerate().skip(n_skipped);
+        let accuracy: Vec<(usize, f64)> = sessions
+            .clone()
+            .filter_map(|(i, record)| record.accuracy().map(|acc| (i + 1, acc * 100.0)))
+            .collect();
+        let response_time: Vec<(usize, f64)> = sessions
+            .filter(|(_, record)| record.n_completed > 0)
+            .map(|(i, record)| (i + 1, record.avg_response_time))
+            .collect();
+        let max_response_time = response_time
+            .iter()
+            .map(|(_, time)| *time)
+            .fold(1.0, f64::max);
+        draw_history_chart(
+            &accuracy_area,
+            "Accuracy in past sessions (%)",
+            &accuracy,
+            0.0..100.0,
+            &style,
+        )
+        .unwrap();
+        draw_history_chart(
+            &response_area,
+            "Avg. response time in past sessions (s)",
+            &response_time,
+            0.0..max_response_time,
+            &style,
+        )
+        .unwrap();
+    }
+}
+
+impl eframe::App for GUIVisualizer {
+    fn update(&mut self, ctx: &Context, _frame: &mut eframe::Frame) {
+        let frame_start = Instant::now();
+        if self.stop.load(Ordering::SeqCst) {
+            ctx.send_viewport_cmd(ViewportCommand::Close);
+        }
+        // The override is applied on every frame as the scale of the monitor
+        // changes when the window is moved to another one.
+        if let Some(pixels_per_point) = self.scale_override {
+            ctx.set_pixels_per_point(pixels_per_point);
+        }
+        self.plot_sizes = self.gui_cfg.plot_sizes(ctx.pixels_per_point());
+        self.receive_states(ctx);
+        self.receive_frame();
+        self.handle_keys(ctx);
+        self.save_screenshots(ctx);
+        // The canvases are taken out of self while drawing so that the views
+        // can borrow the rest of the visualizer.
+        let mut canvases = std::mem::take(&mut self.canvases);
+        TopBottomPanel::top("controls").show(ctx, |ui| self.show_controls(ui));
+        if self.gui_cfg.piano_height > 0 {
+            TopBottomPanel::top("piano")
+                .exact_height(self.gui_cfg.piano_height as f32)
+                .show(ctx, |ui| self.show_piano(ui, &mut canvases.piano));
+        }
+        SidePanel::right("settings")
+            .exact_width(self.gui_cfg.gauge_width as f32)
+            .resizable(false)
+            .show(ctx, |ui| self.show_side_panel(ui, &mut canvases));
+        CentralPanel::default().show(ctx, |ui| self.show_page(ui, &mut canvases.page));
+        self.canvases = canvases;
+        self.metrics.record_frame(frame_start.elapsed());
+        ctx.request_repaint_after(self.frame_period);
     }
 }
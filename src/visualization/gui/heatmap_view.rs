@@ -0,0 +1,95 @@
+use crate::core::FretLoc;
+use crate::visualization::session_summary::SessionSummary;
+use plotters::prelude::*;
+use plotters::style::RGBAColor;
+
+/// Opacity of the heatmap cells over the fretboard.
+const HEAT_ALPHA: f64 = 0.7;
+
+/// Statistic the fretboard positions are colored by on the heatmap.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HeatMetric {
+    ErrorRate,
+    ResponseTime,
+}
+
+impl HeatMetric {
+    pub fn next(self) -> HeatMetric {
+        match self {
+            HeatMetric::ErrorRate => HeatMetric::ResponseTime,
+            HeatMetric::ResponseTime => HeatMetric::ErrorRate,
+        }
+    }
+
+    pub fn caption(self) -> &'static str {
+        match self {
+            HeatMetric::ErrorRate => "Error rate per position",
+            HeatMetric::ResponseTime => "Avg. response time per position, relative to the slowest",
+        }
+    }
+
+    /// Heat of each played position between 0 for the best and 1 for the
+    /// worst. Response times are relative to the slowest position.
+    pub fn heat(self, summary: &SessionSummary) -> Vec<(FretLoc, f64)> {
+        match self {
+            HeatMetric::ErrorRate => summary.error_rates(),
+            HeatMetric::ResponseTime => {
+                let times = summary.response_times();
+                let slowest = times.iter().map(|(_, time)| *time).fold(0.0, f64::max);
+                if slowest == 0.0 {
+                    return times.into_iter().map(|(loc, _)| (loc, 0.0)).collect();
+                }
+                times
+                    .into_iter()
+                    .map(|(loc, time)| (loc, time / slowest))
+                    .collect()
+            }
+        }
+    }
+}
+
+/// Color of a position with the given heat: green for 0 through yellow to
+/// red for 1.
+pub fn heat_color(heat: f64) -> RGBAColor {
+    let hue = (1.0 - heat.clamp(0.0, 1.0)) / 3.0;
+    HSLColor(hue, 1.0, 0.5).mix(HEAT_ALPHA)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::visualization::status_text::TargetResult;
+    use std::time::Duration;
+
+    fn result(fret_idx: usize, correct: bool, secs: u64) -> TargetResult {
+        TargetResult {
+            note: String::from("C4"),
+            loc: FretLoc {
+                string_idx: 1,
+                fret_idx,
+            },
+            correct,
+            response_time: Duration::from_secs(secs),
+        }
+    }
+
+    #[test]
+    fn test_response_time_heat_is_relative_to_slowest() {
+        let mut summary = SessionSummary::default();
+        summary.record(&result(0, true, 1));
+        summary.record(&result(1, false, 4));
+        let mut heat = HeatMetric::ResponseTime.heat(&summary);
+        heat.sort_by_key(|(loc, _)| loc.fret_idx);
+        let heat: Vec<f64> = heat.into_iter().map(|(_, heat)| heat).collect();
+        assert_eq!(vec![0.25, 1.0], heat);
+    }
+
+    #[test]
+    fn test_heat_color() {
+        assert_eq!(
+            heat_color(0.0),
+            HSLColor(1.0 / 3.0, 1.0, 0.5).mix(HEAT_ALPHA)
+        );
+        assert_eq!(heat_color(2.0), HSLColor(0.0, 1.0, 0.5).mix(HEAT_ALPHA));
+    }
+}
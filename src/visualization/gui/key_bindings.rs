@@ -0,0 +1,195 @@
+use crate::core::KeysCfg;
+use eframe::egui::{Context, Event, Key};
+use log::*;
+
+const LETTER_KEYS: [Key; 26] = [
+    Key::A,
+    Key::B,
+    Key::C,
+    Key::D,
+    Key::E,
+    Key::F,
+    Key::G,
+    Key::H,
+    Key::I,
+    Key::J,
+    Key::K,
+    Key::L,
+    Key::M,
+    Key::N,
+    Key::O,
+    Key::P,
+    Key::Q,
+    Key::R,
+    Key::S,
+    Key::T,
+    Key::U,
+    Key::V,
+    Key::W,
+    Key::X,
+    Key::Y,
+    Key::Z,
+];
+const DIGIT_KEYS: [Key; 10] = [
+    Key::Num0,
+    Key::Num1,
+    Key::Num2,
+    Key::Num3,
+    Key::Num4,
+    Key::Num5,
+    Key::Num6,
+    Key::Num7,
+    Key::Num8,
+    Key::Num9,
+];
+const FUNCTION_KEYS: [Key; 12] = [
+    Key::F1,
+    Key::F2,
+    Key::F3,
+    Key::F4,
+    Key::F5,
+    Key::F6,
+    Key::F7,
+    Key::F8,
+    Key::F9,
+    Key::F10,
+    Key::F11,
+    Key::F12,
+];
+
+/// Returns the key with the given name, ignoring case.
+fn parse_key(name: &str) -> Option<Key> {
+    let name = name.trim().to_lowercase();
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return match c {
+            'a'..='z' => Some(LETTER_KEYS[c as usize - 'a' as usize]),
+            '0'..='9' => Some(DIGIT_KEYS[c as usize - '0' as usize]),
+            _ => None,
+        };
+    }
+    match &name[..] {
+        "space" => Some(Key::Space),
+        "tab" => Some(Key::Tab),
+        "enter" => Some(Key::Enter),
+        "backspace" => Some(Key::Backspace),
+        "minus" => Some(Key::Minus),
+        "equal" => Some(Key::Equals),
+        "comma" => Some(Key::Comma),
+        "period" => Some(Key::Period),
+        _ => name
+            .strip_prefix('f')
+            .and_then(|n| n.parse::<usize>().ok())
+            .and_then(|n| FUNCTION_KEYS.get(n.wrapping_sub(1)).cloned()),
+    }
+}
+
+/// Parses the key bound to `action`, falling back to `default` if the name
+/// is not a known key.
+fn bind(action: &str, name: &str, default: &str) -> Key {
+    parse_key(name).unwrap_or_else(|| {
+        warn!(
+            "Unknown key {:?} for {}, using {:?} instead",
+            name, action, default
+        );
+        parse_key(default).unwrap()
+    })
+}
+
+/// Whether `key` was pressed since the last frame, counting the repeats of a
+/// held key only if `repeat` is set.
+pub fn is_pressed(ctx: &Context, key: Key, repeat: bool) -> bool {
+    ctx.input(|input| {
+        input.events.iter().any(|event| match event {
+            Event::Key {
+                key: pressed_key,
+                pressed: true,
+                repeat: is_repeat,
+                ..
+            } => *pressed_key == key && (repeat || !is_repeat),
+            _ => false,
+        })
+    })
+}
+
+/// Keys of the GUI actions.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KeyBindings {
+    pub quit: Key,
+    pub pause: Key,
+    pub skip: Key,
+    pub hint: Key,
+    pub view: Key,
+    pub theme: Key,
+    pub average: Key,
+    pub peak_hold: Key,
+    pub screenshot: Key,
+    pub reference_tone: Key,
+    pub heatmap: Key,
+    pub zoom_in: Key,
+    pub zoom_out: Key,
+    pub zoom_reset: Key,
+    pub pan_left: Key,
+    pub pan_right: Key,
+}
+
+impl KeyBindings {
+    pub fn new(cfg: &KeysCfg) -> KeyBindings {
+        let defaults = KeysCfg::default();
+        KeyBindings {
+            quit: bind("quit", &cfg.quit, &defaults.quit),
+            pause: bind("pause", &cfg.pause, &defaults.pause),
+            skip: bind("skip", &cfg.skip, &defaults.skip),
+            hint: bind("hint", &cfg.hint, &defaults.hint),
+            view: bind("view", &cfg.view, &defaults.view),
+            theme: bind("theme", &cfg.theme, &defaults.theme),
+            average: bind("average", &cfg.average, &defaults.average),
+            peak_hold: bind("peak_hold", &cfg.peak_hold, &defaults.peak_hold),
+            screenshot: bind("screenshot", &cfg.screenshot, &defaults.screenshot),
+            reference_tone: bind(
+                "reference_tone",
+                &cfg.reference_tone,
+                &defaults.reference_tone,
+            ),
+            heatmap: bind("heatmap", &cfg.heatmap, &defaults.heatmap),
+            zoom_in: bind("zoom_in", &cfg.zoom_in, &defaults.zoom_in),
+            zoom_out: bind("zoom_out", &cfg.zoom_out, &defaults.zoom_out),
+            zoom_reset: bind("zoom_reset", &cfg.zoom_reset, &defaults.zoom_reset),
+            pan_left: bind("pan_left", &cfg.pan_left, &defaults.pan_left),
+            pan_right: bind("pan_right", &cfg.pan_right, &defaults.pan_right),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_key() {
+        assert_eq!(Some(Key::Q), parse_key("q"));
+        assert_eq!(Some(Key::Z), parse_key("Z"));
+        assert_eq!(Some(Key::Num7), parse_key("7"));
+        assert_eq!(Some(Key::Space), parse_key(" Space "));
+        assert_eq!(Some(Key::Equals), parse_key("equal"));
+        assert_eq!(Some(Key::F1), parse_key("f1"));
+        assert_eq!(Some(Key::F12), parse_key("F12"));
+        assert_eq!(None, parse_key("f0"));
+        assert_eq!(None, parse_key("f13"));
+        assert_eq!(None, parse_key("ctrl"));
+        assert_eq!(None, parse_key("!"));
+    }
+
+    #[test]
+    fn test_unknown_key_uses_default() {
+        let cfg = KeysCfg {
+            skip: String::from("s"),
+            pause: String::from("pause"),
+            ..KeysCfg::default()
+        };
+        let keys = KeyBindings::new(&cfg);
+        assert_eq!(Key::S, keys.skip);
+        assert_eq!(Key::Space, keys.pause);
+        assert_eq!(Key::Q, keys.quit);
+    }
+}
@@ -0,0 +1,102 @@
+use crate::core::Note;
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use plotters::style::RGBAColor;
+
+/// Whether each key of an octave from C is black, and the index of the white
+/// key it is on or to the right of.
+const OCTAVE_KEYS: [(bool, i32); 12] = [
+    (false, 0),
+    (true, 0),
+    (false, 1),
+    (true, 1),
+    (false, 2),
+    (false, 3),
+    (true, 3),
+    (false, 4),
+    (true, 4),
+    (false, 5),
+    (true, 5),
+    (false, 6),
+];
+const N_WHITE_KEYS: i32 = 7;
+/// Width and height of the black keys relative to the white ones.
+const BLACK_KEY_WIDTH: f64 = 0.6;
+const BLACK_KEY_HEIGHT: f64 = 0.6;
+
+/// Top left and bottom right corners of the key of `pitch_class` on a one
+/// octave keyboard of the given size. Black keys are centered on the border
+/// between two white keys.
+fn key_rect(pitch_class: usize, (width, height): (i32, i32)) -> [(i32, i32); 2] {
+    let (is_black, white_idx) = OCTAVE_KEYS[pitch_class % 12];
+    let key_width = width as f64 / N_WHITE_KEYS as f64;
+    if is_black {
+        let center = (white_idx + 1) as f64 * key_width;
+        let half = key_width * BLACK_KEY_WIDTH / 2.0;
+        let bottom = (height as f64 * BLACK_KEY_HEIGHT) as i32;
+        [
+            ((center - half) as i32, 0),
+            ((center + half) as i32, bottom),
+        ]
+    } else {
+        let left = white_idx as f64 * key_width;
+        [(left as i32, 0), ((left + key_width) as i32, height)]
+    }
+}
+
+/// Draws one octave of a piano keyboard on `area` with the key of the
+/// `detected` note's pitch class lit in `detected_color` and the key of the
+/// `target` marked with a dot in `target_color`.
+pub fn draw_piano<DB: DrawingBackend>(
+    area: &DrawingArea<DB, Shift>,
+    detected: Option<&Note>,
+    target: Option<&Note>,
+    detected_color: &RGBAColor,
+    target_color: &RGBAColor,
+) -> Result<(), DrawingAreaErrorKind<DB::ErrorType>> {
+    let (width, height) = area.dim_in_pixel();
+    let size = (width as i32, height as i32);
+    let detected = detected.map(|note| note.pitch_class());
+    let target = target.map(|note| note.pitch_class());
+    // The black keys are drawn over the white ones.
+    for is_black in [false, true].iter() {
+        for (pitch_class, _) in OCTAVE_KEYS
+            .iter()
+            .enumerate()
+            .filter(|(_, (black, _))| black == is_black)
+        {
+            let rect = key_rect(pitch_class, size);
+            let fill = match (detected == Some(pitch_class), *is_black) {
+                (true, _) => *detected_color,
+                (false, true) => BLACK.to_rgba(),
+                (false, false) => WHITE.to_rgba(),
+            };
+            area.draw(&Rectangle::new(rect, fill.filled()))?;
+            area.draw(&Rectangle::new(rect, BLACK.stroke_width(1)))?;
+            if target == Some(pitch_class) {
+                let [(x0, _), (x1, y1)] = rect;
+                let radius = ((x1 - x0) / 4).max(2);
+                area.draw(&Circle::new(
+                    ((x0 + x1) / 2, y1 - 2 * radius),
+                    radius,
+                    target_color.filled(),
+                ))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_rect() {
+        // C, C# and B on a keyboard with 100 pixel wide white keys.
+        assert_eq!([(0, 0), (100, 50)], key_rect(0, (700, 50)));
+        assert_eq!([(70, 0), (130, 30)], key_rect(1, (700, 50)));
+        assert_eq!([(600, 0), (700, 50)], key_rect(11, (700, 50)));
+        assert_eq!(key_rect(9, (700, 50)), key_rect(21, (700, 50)));
+    }
+}
@@ -0,0 +1,138 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+/// Largest block of an uncompressed deflate stream.
+const MAX_STORED_BLOCK: usize = 65535;
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (!(crc & 1)).wrapping_add(1);
+            crc = (crc >> 1) ^ (0xedb8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in bytes {
+        a = (a + *byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+/// Wraps `data` in a zlib stream of uncompressed deflate blocks. Screenshots
+/// are rare enough that their size doesn't matter.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x78, 0x01];
+    let blocks: Vec<&[u8]> = if data.is_empty() {
+        vec![data]
+    } else {
+        data.chunks(MAX_STORED_BLOCK).collect()
+    };
+    let n_blocks = blocks.len();
+    for (i, block) in blocks.into_iter().enumerate() {
+        let len = block.len() as u16;
+        out.push((i + 1 == n_blocks) as u8);
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+fn write_chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let crc_start = out.len();
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+    let crc = crc32(&out[crc_start..]);
+    out.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// Encodes a frame of `0RGB` pixels, as drawn into the window buffer, as an
+/// RGB PNG image.
+pub fn encode_png(width: usize, height: usize, pixels: &[u32]) -> Vec<u8> {
+    let mut raw = Vec::with_capacity(height * (1 + 3 * width));
+    for row in pixels.chunks(width.max(1)).take(height) {
+        // Each row starts with its filter type, which is none.
+        raw.push(0);
+        for pixel in row {
+            raw.extend_from_slice(&[(pixel >> 16) as u8, (pixel >> 8) as u8, *pixel as u8]);
+        }
+    }
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&(width as u32).to_be_bytes());
+    header.extend_from_slice(&(height as u32).to_be_bytes());
+    // 8 bits per channel, RGB, default compression, filtering and no
+    // interlacing.
+    header.extend_from_slice(&[8, 2, 0, 0, 0]);
+
+    let mut out = PNG_SIGNATURE.to_vec();
+    write_chunk(&mut out, b"IHDR", &header);
+    write_chunk(&mut out, b"IDAT", &zlib_stored(&raw));
+    write_chunk(&mut out, b"IEND", &[]);
+    out
+}
+
+/// Writes the frame to a PNG file in `dir` named after the current time and
+/// returns the path of the file.
+pub fn save_screenshot(
+    dir: &Path,
+    width: usize,
+    height: usize,
+    pixels: &[u32],
+) -> io::Result<PathBuf> {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since_epoch| since_epoch.as_millis());
+    fs::create_dir_all(dir)?;
+    let path = dir.join(format!("libreguitar_{}.png", millis));
+    fs::write(&path, encode_png(width, height, pixels))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checksums() {
+        assert_eq!(0xcbf4_3926, crc32(b"123456789"));
+        assert_eq!(0x11e6_0398, adler32(b"Wikipedia"));
+    }
+
+    #[test]
+    fn test_encode_png() {
+        let png = encode_png(2, 1, &[0x00ff_0000, 0x0000_00ff]);
+        assert_eq!(PNG_SIGNATURE, png[..8]);
+        assert_eq!(b"IHDR", &png[12..16]);
+        assert_eq!([0, 0, 0, 2, 0, 0, 0, 1, 8, 2, 0, 0, 0], png[16..29]);
+        // The IEND chunk is always the same.
+        assert_eq!(
+            [0, 0, 0, 0, b'I', b'E', b'N', b'D', 0xae, 0x42, 0x60, 0x82],
+            png[png.len() - 12..]
+        );
+        // Both pixels are stored uncompressed after the filter byte.
+        let raw = [0, 255, 0, 0, 0, 0, 255];
+        assert!(png.windows(raw.len()).any(|window| window == raw));
+    }
+
+    #[test]
+    fn test_zlib_stored_splits_blocks() {
+        let data = vec![7u8; MAX_STORED_BLOCK + 1];
+        let stream = zlib_stored(&data);
+        // Two block headers, the zlib header and the checksum.
+        assert_eq!(data.len() + 2 * 5 + 2 + 4, stream.len());
+        assert_eq!(0, stream[2]);
+        assert_eq!(1, stream[2 + 5 + MAX_STORED_BLOCK]);
+    }
+}
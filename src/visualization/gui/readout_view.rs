@@ -0,0 +1,76 @@
+use crate::audio_analysis::AnalysisResult;
+use crate::visualization::gui::status_view::draw_text_lines;
+use plotters::coord::Shift;
+use plotters::prelude::*;
+
+/// Formats the detected frequency, the closest note with the deviation from
+/// it in cents and the A4 reference of the note table, one per line.
+pub fn format_readout(analysis: &AnalysisResult) -> Vec<String> {
+    let pitch = match analysis.frequency {
+        Some(freq) => format!("{:.1} Hz", freq),
+        None => String::from("-"),
+    };
+    let note = match (&analysis.note, analysis.cents()) {
+        (Some(note), Some(cents)) => format!("{} ({:+.1}¢)", note.name_octave(), cents),
+        (Some(note), None) => note.name_octave(),
+        _ => String::from("-"),
+    };
+    let reference = match analysis.a4_reference() {
+        Some(freq) => format!("{:.1} Hz", freq),
+        None => String::from("-"),
+    };
+    vec![
+        format!("Pitch: {}", pitch),
+        format!("Note: {}", note),
+        format!("A4 reference: {}", reference),
+    ]
+}
+
+/// Draws the pitch readout of `analysis` on `area`.
+pub fn draw_readout<DB: DrawingBackend>(
+    area: &DrawingArea<DB, Shift>,
+    analysis: &AnalysisResult,
+    font: &TextStyle,
+) -> Result<(), DrawingAreaErrorKind<DB::ErrorType>> {
+    let lines = format_readout(analysis);
+    let lines: Vec<&str> = lines.iter().map(|line| &line[..]).collect();
+    draw_text_lines(area, &lines, font)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{Note, NoteName};
+
+    #[test]
+    fn test_format_readout() {
+        let analysis = AnalysisResult {
+            note: Some(Note {
+                octave: 3,
+                name: NoteName::A,
+                frequency: 220.0,
+            }),
+            frequency: Some(220.0 * 2f64.powf(5.0 / 1200.0)),
+            confidence: 1.0,
+            peaks: Vec::new(),
+        };
+        assert_eq!(
+            vec![
+                "Pitch: 220.6 Hz",
+                "Note: A3 (+5.0¢)",
+                "A4 reference: 440.0 Hz"
+            ],
+            format_readout(&analysis)
+        );
+        let silent = AnalysisResult {
+            note: None,
+            frequency: None,
+            confidence: 0.0,
+            peaks: Vec::new(),
+        };
+        assert_eq!(
+            vec!["Pitch: -", "Note: -", "A4 reference: -"],
+            format_readout(&silent)
+        );
+    }
+}
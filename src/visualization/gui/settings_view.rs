@@ -0,0 +1,76 @@
+use crate::audio_analysis::AudioParams;
+use eframe::egui::{Slider, Ui};
+
+/// Smallest RMS level of the silence gate above 0, the gate being off at 0.
+const MIN_SILENCE_RMS: f64 = 1e-4;
+/// Largest moving average window on the slider, in bins.
+const MAX_WINDOW_SIZE: usize = 51;
+
+fn format_silence_rms(rms: f64) -> String {
+    if rms < MIN_SILENCE_RMS {
+        String::from("off")
+    } else {
+        format!("{:.5}", rms)
+    }
+}
+
+/// Shows a slider for each audio analysis parameter and returns whether one of
+/// them was changed.
+pub fn show_audio_settings(ui: &mut Ui, params: &mut AudioParams) -> bool {
+    let mut changed = false;
+    changed |= ui
+        .add(
+            Slider::new(&mut params.peak_threshold, 1.0..=100_000.0)
+                .logarithmic(true)
+                .text("Peak threshold"),
+        )
+        .changed();
+    // The window is kept odd so that it stays centered on each bin.
+    let mut half_window = params.moving_avg_window_size / 2;
+    if ui
+        .add(
+            Slider::new(&mut half_window, 0..=MAX_WINDOW_SIZE / 2)
+                .custom_formatter(|half, _| format!("{}", 2 * half as usize + 1))
+                .text("Smoothing window"),
+        )
+        .changed()
+    {
+        params.moving_avg_window_size = 2 * half_window + 1;
+        changed = true;
+    }
+    changed |= ui
+        .add(
+            Slider::new(&mut params.fft_magnitude_gain, 0.1..=1000.0)
+                .logarithmic(true)
+                .text("Gain"),
+        )
+        .changed();
+    if ui
+        .add(
+            Slider::new(&mut params.silence_rms, 0.0..=0.1)
+                .logarithmic(true)
+                .smallest_positive(MIN_SILENCE_RMS)
+                .custom_formatter(|rms, _| format_silence_rms(rms))
+                .text("Silence gate"),
+        )
+        .changed()
+    {
+        if params.silence_rms < MIN_SILENCE_RMS {
+            params.silence_rms = 0.0;
+        }
+        changed = true;
+    }
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_silence_rms() {
+        assert_eq!("off", format_silence_rms(0.0));
+        assert_eq!("off", format_silence_rms(MIN_SILENCE_RMS / 2.0));
+        assert_eq!("0.00010", format_silence_rms(MIN_SILENCE_RMS));
+    }
+}
@@ -0,0 +1,199 @@
+use crate::visualization::gui::GuiCfg;
+use std::ops::Range;
+
+/// Maps spectrum frequencies and magnitudes to the coordinates of the
+/// spectrum chart, which are the values themselves on linear axes, `log10` of
+/// the frequency on a logarithmic axis and decibels on a dB axis. The x axis
+/// can be zoomed into and panned within the configured frequency range.
+#[derive(Debug, Clone)]
+pub struct SpectrumScale {
+    log_frequency: bool,
+    db_magnitude: bool,
+    /// Shown part of `full_x_range`.
+    x_range: Range<f64>,
+    full_x_range: Range<f64>,
+    y_range: Range<f64>,
+}
+
+/// Smallest magnitude converted to decibels, to avoid taking `log10(0)`.
+const MIN_DB_MAGNITUDE: f64 = 1e-12;
+/// Largest zoom, as the ratio of the full x axis to the shown part.
+const MAX_ZOOM: f64 = 100.0;
+
+impl SpectrumScale {
+    pub fn new(cfg: &GuiCfg) -> SpectrumScale {
+        let mut scale = SpectrumScale {
+            log_frequency: cfg.log_frequency,
+            db_magnitude: cfg.db_magnitude,
+            x_range: 0.0..0.0,
+            full_x_range: 0.0..0.0,
+            y_range: 0.0..0.0,
+        };
+        let min_freq = if cfg.log_frequency {
+            cfg.spectrum_min_freq.max(1.0)
+        } else {
+            cfg.spectrum_min_freq
+        };
+        scale.full_x_range = scale.x(min_freq)..scale.x(cfg.spectrum_max_freq);
+        scale.x_range = scale.full_x_range.clone();
+        scale.y_range = if cfg.db_magnitude {
+            cfg.spectrum_min_db..scale.y(cfg.spectrum_max_magnitude)
+        } else {
+            0.0..cfg.spectrum_max_magnitude
+        };
+        scale
+    }
+
+    pub fn x_range(&self) -> Range<f64> {
+        self.x_range.clone()
+    }
+
+    pub fn y_range(&self) -> Range<f64> {
+        self.y_range.clone()
+    }
+
+    /// Zooms the x axis in for `factor` above 1 or out for `factor` below 1,
+    /// keeping the coordinate at `center`, given as a position between 0 and 1
+    /// along the axis, in place.
+    pub fn zoom(&mut self, factor: f64, center: f64) {
+        let full_width = self.full_x_range.end - self.full_x_range.start;
+        let width = self.x_range.end - self.x_range.start;
+        let new_width = (width / factor).clamp(full_width / MAX_ZOOM, full_width);
+        let center_x = self.x_range.start + center.clamp(0.0, 1.0) * width;
+        self.set_x_start(center_x - center.clamp(0.0, 1.0) * new_width, new_width);
+    }
+
+    /// Moves the x axis by `ratio` times its width, to higher frequencies for
+    /// a positive `ratio`.
+    pub fn pan(&mut self, ratio: f64) {
+        let width = self.x_range.end - self.x_range.start;
+        self.set_x_start(self.x_range.start + ratio * width, width);
+    }
+
+    /// Shows the whole configured frequency range again.
+    pub fn reset_zoom(&mut self) {
+        self.x_range = self.full_x_range.clone();
+    }
+
+    /// Shows `width` of the x axis from `start`, moved inside the full range.
+    fn set_x_start(&mut self, start: f64, width: f64) {
+        let start = start
+            .min(self.full_x_range.end - width)
+            .max(self.full_x_range.start);
+        self.x_range = start..start + width;
+    }
+
+    /// Chart coordinate of the given frequency.
+    pub fn x(&self, freq: f64) -> f64 {
+        if self.log_frequency {
+            freq.log10()
+        } else {
+            freq
+        }
+    }
+
+    /// Chart coordinate of the given magnitude.
+    pub fn y(&self, magnitude: f64) -> f64 {
+        if self.db_magnitude {
+            20.0 * magnitude.max(MIN_DB_MAGNITUDE).log10()
+        } else {
+            magnitude
+        }
+    }
+
+    /// Position of the given frequency along the x axis, between 0 and 1 for
+    /// frequencies that are on the chart.
+    pub fn x_ratio(&self, freq: f64) -> f64 {
+        (self.x(freq) - self.x_range.start) / (self.x_range.end - self.x_range.start)
+    }
+
+    /// Position of the given magnitude along the y axis, clamped between 0
+    /// and 1.
+    pub fn y_ratio(&self, magnitude: f64) -> f64 {
+        let ratio =
+            (self.y(magnitude) - self.y_range.start) / (self.y_range.end - self.y_range.start);
+        ratio.clamp(0.0, 1.0)
+    }
+
+    /// Whether the given frequency is within the x axis.
+    pub fn contains_freq(&self, freq: f64) -> bool {
+        freq > 0.0 && self.x_range.contains(&self.x(freq))
+    }
+
+    /// Label of an x axis coordinate in Hz.
+    pub fn format_x(&self, x: f64) -> String {
+        if self.log_frequency {
+            format!("{:.0}", 10f64.powf(x))
+        } else {
+            format!("{:.0}", x)
+        }
+    }
+
+    /// Label of a y axis coordinate.
+    pub fn format_y(&self, y: f64) -> String {
+        if self.db_magnitude {
+            format!("{:.0} dB", y)
+        } else {
+            format!("{:.3}", y)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_linear_scale() {
+        let cfg = GuiCfg::default();
+        let scale = SpectrumScale::new(&cfg);
+        assert_eq!(0.0..cfg.spectrum_max_freq, scale.x_range());
+        assert_eq!(0.5, scale.x_ratio(cfg.spectrum_max_freq / 2.0));
+        assert_eq!(1.0, scale.y_ratio(2.0 * cfg.spectrum_max_magnitude));
+        assert!(!scale.contains_freq(0.0));
+    }
+
+    #[test]
+    fn test_log_db_scale() {
+        let cfg = GuiCfg {
+            log_frequency: true,
+            db_magnitude: true,
+            spectrum_min_freq: 10.0,
+            spectrum_max_freq: 1000.0,
+            spectrum_min_db: -80.0,
+            spectrum_max_magnitude: 1.0,
+            ..GuiCfg::default()
+        };
+        let scale = SpectrumScale::new(&cfg);
+        assert_eq!(1.0..3.0, scale.x_range());
+        assert!((scale.x_ratio(100.0) - 0.5).abs() < 1e-9);
+        assert_eq!("100", scale.format_x(2.0));
+        assert_eq!(-80.0..0.0, scale.y_range());
+        assert!((scale.y_ratio(0.01) - 0.5).abs() < 1e-9);
+        assert_eq!(0.0, scale.y_ratio(0.0));
+        assert!(!scale.contains_freq(5.0));
+    }
+
+    #[test]
+    fn test_zoom_and_pan() {
+        let cfg = GuiCfg {
+            spectrum_min_freq: 0.0,
+            spectrum_max_freq: 2000.0,
+            ..GuiCfg::default()
+        };
+        let mut scale = SpectrumScale::new(&cfg);
+        scale.zoom(4.0, 0.5);
+        assert_eq!(750.0..1250.0, scale.x_range());
+        scale.zoom(2.0, 0.0);
+        assert_eq!(750.0..1000.0, scale.x_range());
+        scale.pan(-10.0);
+        assert_eq!(0.0..250.0, scale.x_range());
+        assert!(!scale.contains_freq(300.0));
+        scale.zoom(1000.0, 0.0);
+        assert_eq!(0.0..20.0, scale.x_range());
+        scale.zoom(0.001, 1.0);
+        assert_eq!(0.0..2000.0, scale.x_range());
+        scale.pan(0.5);
+        assert_eq!(0.0..2000.0, scale.x_range());
+    }
+}
@@ -0,0 +1,74 @@
+/// Exponential average and peak-hold trace of the incoming spectra, so that
+/// short peaks stay visible for longer than a single frame.
+#[derive(Debug, Default)]
+pub struct SpectrumTrace {
+    average: Vec<f64>,
+    hold: Vec<f64>,
+}
+
+impl SpectrumTrace {
+    /// Adds `spectrum` to the traces. `averaging` is the weight of the
+    /// previous average between 0 and 1, and the held peaks are multiplied by
+    /// `hold_decay` before being raised to the new spectrum.
+    pub fn update(&mut self, spectrum: &[f64], averaging: f64, hold_decay: f64) {
+        if self.average.len() != spectrum.len() {
+            self.average = spectrum.to_vec();
+            self.hold = spectrum.to_vec();
+            return;
+        }
+        for ((avg, held), magnitude) in self
+            .average
+            .iter_mut()
+            .zip(self.hold.iter_mut())
+            .zip(spectrum.iter())
+        {
+            *avg = averaging * *avg + (1.0 - averaging) * magnitude;
+            *held = (*held * hold_decay).max(*magnitude);
+        }
+    }
+
+    pub fn average(&self) -> &[f64] {
+        &self.average
+    }
+
+    pub fn hold(&self) -> &[f64] {
+        &self.hold
+    }
+
+    /// Drops the held peaks so that the hold trace restarts from the next
+    /// spectrum.
+    pub fn reset_hold(&mut self) {
+        self.hold.clone_from(&self.average);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_average_and_hold() {
+        let mut trace = SpectrumTrace::default();
+        trace.update(&[0.0, 1.0], 0.5, 0.5);
+        assert_eq!(&[0.0, 1.0], trace.average());
+        assert_eq!(&[0.0, 1.0], trace.hold());
+
+        trace.update(&[1.0, 0.0], 0.5, 0.5);
+        assert_eq!(&[0.5, 0.5], trace.average());
+        assert_eq!(&[1.0, 0.5], trace.hold());
+
+        trace.update(&[0.0, 0.0], 0.5, 0.5);
+        assert_eq!(&[0.25, 0.25], trace.average());
+        assert_eq!(&[0.5, 0.25], trace.hold());
+    }
+
+    #[test]
+    fn test_reset_hold() {
+        let mut trace = SpectrumTrace::default();
+        trace.update(&[1.0], 0.0, 1.0);
+        trace.update(&[0.0], 0.0, 1.0);
+        assert_eq!(&[1.0], trace.hold());
+        trace.reset_hold();
+        assert_eq!(&[0.0], trace.hold());
+    }
+}
@@ -0,0 +1,146 @@
+use crate::core::Note;
+use crate::visualization::staff_drawer::{
+    staff_placement, Accidental, BOTTOM_LINE, CLEF_LINE, KEY_SHARP_STEPS, TOP_LINE,
+};
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use plotters::style::RGBAColor;
+
+/// Distance of the staff from the left and right edges of its area, in
+/// pixels.
+const STAFF_MARGIN: i32 = 10;
+
+/// Draws a sharp sign centered at `center`, `size` pixels high.
+fn draw_sharp<DB: DrawingBackend>(
+    area: &DrawingArea<DB, Shift>,
+    center: (i32, i32),
+    size: i32,
+    color: &RGBAColor,
+) -> Result<(), DrawingAreaErrorKind<DB::ErrorType>> {
+    let (x, y) = center;
+    let (half, quarter) = (size / 2, size / 4);
+    for dx in [-quarter / 2, quarter / 2].iter() {
+        area.draw(&PathElement::new(
+            vec![(x + dx, y - half), (x + dx, y + half)],
+            color.stroke_width(1),
+        ))?;
+    }
+    // The crossbars rise to the right.
+    for dy in [-quarter / 2, quarter / 2].iter() {
+        area.draw(&PathElement::new(
+            vec![(x - quarter, y + dy + 2), (x + quarter, y + dy - 2)],
+            color.stroke_width(2),
+        ))?;
+    }
+    Ok(())
+}
+
+/// Draws a natural sign centered at `center`, `size` pixels high.
+fn draw_natural<DB: DrawingBackend>(
+    area: &DrawingArea<DB, Shift>,
+    center: (i32, i32),
+    size: i32,
+    color: &RGBAColor,
+) -> Result<(), DrawingAreaErrorKind<DB::ErrorType>> {
+    let (x, y) = center;
+    let (half, quarter) = (size / 2, size / 4);
+    area.draw(&PathElement::new(
+        vec![
+            (x - quarter / 2, y - half),
+            (x - quarter / 2, y + quarter / 2),
+        ],
+        color.stroke_width(1),
+    ))?;
+    area.draw(&PathElement::new(
+        vec![
+            (x + quarter / 2, y - quarter / 2),
+            (x + quarter / 2, y + half),
+        ],
+        color.stroke_width(1),
+    ))?;
+    for dy in [-quarter / 2, quarter / 2].iter() {
+        area.draw(&PathElement::new(
+            vec![(x - quarter / 2, y + dy + 1), (x + quarter / 2, y + dy - 1)],
+            color.stroke_width(2),
+        ))?;
+    }
+    Ok(())
+}
+
+/// Draws `note` on a treble clef staff with `key_sharps` sharps in the key
+/// signature on `area`, placed like the console staff with ledger lines for
+/// notes outside of the staff. The staff is scaled so that the note fits in
+/// the area.
+pub fn draw_staff_note<DB: DrawingBackend>(
+    area: &DrawingArea<DB, Shift>,
+    note: &Note,
+    key_sharps: usize,
+    color: &RGBAColor,
+) -> Result<(), DrawingAreaErrorKind<DB::ErrorType>> {
+    let (width, height) = area.dim_in_pixel();
+    let (width, height) = (width as i32, height as i32);
+    let key_sharps = key_sharps.min(KEY_SHARP_STEPS.len());
+    let (note_step, accidental) = staff_placement(note, key_sharps);
+
+    // Leave room for the clef above and below the staff.
+    let low = note_step.min(BOTTOM_LINE - 2);
+    let high = note_step.max(TOP_LINE + 3);
+    let step_px = (height as f64 / (high - low + 2) as f64).min(height as f64 / 16.0);
+    let center_step = (low + high) as f64 / 2.0;
+    let y = |step: i32| (height as f64 / 2.0 - (step as f64 - center_step) * step_px) as i32;
+    let line_gap = (2.0 * step_px) as i32;
+    let (left, right) = (STAFF_MARGIN, width - STAFF_MARGIN);
+
+    for step in (BOTTOM_LINE..=TOP_LINE).step_by(2) {
+        area.draw(&PathElement::new(
+            vec![(left, y(step)), (right, y(step))],
+            color.stroke_width(1),
+        ))?;
+    }
+
+    // A simplified G clef: a loop around the G line crossed by a stem.
+    let clef_x = left + line_gap;
+    area.draw(&Circle::new(
+        (clef_x, y(CLEF_LINE)),
+        line_gap * 3 / 4,
+        color.stroke_width(2),
+    ))?;
+    area.draw(&PathElement::new(
+        vec![
+            (clef_x + line_gap / 3, y(BOTTOM_LINE - 2)),
+            (clef_x, y(TOP_LINE + 3)),
+        ],
+        color.stroke_width(2),
+    ))?;
+
+    let key_x = clef_x + 2 * line_gap;
+    for (i, step) in KEY_SHARP_STEPS[..key_sharps].iter().enumerate() {
+        let x = key_x + i as i32 * line_gap * 3 / 4;
+        draw_sharp(area, (x, y(*step)), 2 * line_gap, color)?;
+    }
+
+    let note_x = key_x + (key_sharps as i32 + 2) * line_gap;
+    let is_outside_staff = |step: i32| !(BOTTOM_LINE..=TOP_LINE).contains(&step);
+    for step in (low..=high).filter(|step| (step - BOTTOM_LINE) % 2 == 0) {
+        let is_between_staff_and_note =
+            (step > TOP_LINE && step <= note_step) || (step < BOTTOM_LINE && step >= note_step);
+        if is_outside_staff(step) && is_between_staff_and_note {
+            area.draw(&PathElement::new(
+                vec![(note_x - line_gap, y(step)), (note_x + line_gap, y(step))],
+                color.stroke_width(1),
+            ))?;
+        }
+    }
+    area.draw(&Circle::new(
+        (note_x, y(note_step)),
+        (line_gap / 2).max(2),
+        color.filled(),
+    ))?;
+    let accidental_center = (note_x - line_gap * 3 / 2, y(note_step));
+    match accidental {
+        Some(Accidental::Sharp) => draw_sharp(area, accidental_center, 2 * line_gap, color)?,
+        Some(Accidental::Natural) => draw_natural(area, accidental_center, 2 * line_gap, color)?,
+        None => (),
+    }
+    Ok(())
+}
@@ -0,0 +1,89 @@
+use crate::visualization::gui::gui_cfg::PlotSizes;
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use plotters::style::RGBAColor;
+use std::ops::Range;
+
+/// Colors, font and sizes shared by the charts of the stats page.
+pub struct ChartStyle<'a> {
+    pub font: TextStyle<'a>,
+    pub line: RGBAColor,
+    pub axis: RGBAColor,
+    pub sizes: PlotSizes,
+}
+
+/// Number of past sessions shown on the history charts.
+pub const N_HISTORY_SESSIONS: usize = 30;
+
+/// Draws `values` as a line chart over the session number on `area`.
+pub fn draw_history_chart<DB: DrawingBackend>(
+    area: &DrawingArea<DB, Shift>,
+    caption: &str,
+    values: &[(usize, f64)],
+    y_range: Range<f64>,
+    style: &ChartStyle,
+) -> Result<(), DrawingAreaErrorKind<DB::ErrorType>> {
+    let x_beg = values.first().map_or(0, |(session, _)| *session);
+    let x_end = values
+        .last()
+        .map_or(1, |(session, _)| *session)
+        .max(x_beg + 1);
+    let mut chart = ChartBuilder::on(area)
+        .margin(style.sizes.px(10) as u32)
+        .caption(caption, style.font.clone())
+        .set_all_label_area_size(style.sizes.px(40) as u32)
+        .build_cartesian_2d(x_beg..x_end, y_range)?;
+    chart
+        .configure_mesh()
+        .label_style(style.font.clone())
+        .axis_style(style.axis)
+        .bold_line_style(style.line.mix(0.2))
+        .light_line_style(TRANSPARENT)
+        .x_desc("Session")
+        .draw()?;
+    chart.draw_series(LineSeries::new(values.iter().cloned(), &style.line))?;
+    chart.draw_series(
+        values
+            .iter()
+            .map(|point| Circle::new(*point, style.sizes.px(3), style.line.filled())),
+    )?;
+    Ok(())
+}
+
+/// Draws the accuracy on each string as a bar chart on `area`.
+pub fn draw_string_chart<DB: DrawingBackend>(
+    area: &DrawingArea<DB, Shift>,
+    caption: &str,
+    string_accuracy: &[(usize, f64)],
+    style: &ChartStyle,
+) -> Result<(), DrawingAreaErrorKind<DB::ErrorType>> {
+    let first = string_accuracy
+        .first()
+        .map_or(1, |(string_idx, _)| *string_idx);
+    let last = string_accuracy
+        .last()
+        .map_or(6, |(string_idx, _)| *string_idx);
+    let mut chart = ChartBuilder::on(area)
+        .margin(style.sizes.px(10) as u32)
+        .caption(caption, style.font.clone())
+        .set_all_label_area_size(style.sizes.px(40) as u32)
+        .build_cartesian_2d(first as f64 - 0.5..last as f64 + 0.5, 0.0..100.0)?;
+    chart
+        .configure_mesh()
+        .label_style(style.font.clone())
+        .axis_style(style.axis)
+        .bold_line_style(style.line.mix(0.2))
+        .light_line_style(TRANSPARENT)
+        .x_labels(last - first + 1)
+        .x_label_formatter(&|x| format!("{:.0}", x))
+        .x_desc("String")
+        .draw()?;
+    chart.draw_series(string_accuracy.iter().map(|(string_idx, accuracy)| {
+        let x = *string_idx as f64;
+        Rectangle::new(
+            [(x - 0.35, 0.0), (x + 0.35, accuracy * 100.0)],
+            style.line.filled(),
+        )
+    }))?;
+    Ok(())
+}
@@ -0,0 +1,85 @@
+use crate::game::GameState;
+use crate::visualization::status_text::format_score;
+use plotters::coord::Shift;
+use plotters::prelude::*;
+
+/// Distance of the text from the edges of its area, in pixels.
+const TEXT_MARGIN: i32 = 5;
+/// Line height relative to the text height.
+const LINE_SPACING: f64 = 1.3;
+
+/// Formats the current target and its detection progress, e.g.
+/// `Play C4 on string 5 — 2/3`. The note isn't named if it is shown on a
+/// staff.
+pub fn format_target_status(game_state: &GameState, note_on_staff: bool) -> String {
+    let note = if note_on_staff {
+        String::from("the note on the staff")
+    } else {
+        game_state.target_note.name_octave()
+    };
+    format!(
+        "Play {} on string {} — {}/{}",
+        note,
+        game_state.target_loc.string_idx,
+        game_state.curr_detection_count,
+        game_state.needed_detection_count
+    )
+}
+
+/// Draws `lines` of text below each other on `area`.
+pub fn draw_text_lines<DB: DrawingBackend>(
+    area: &DrawingArea<DB, Shift>,
+    lines: &[&str],
+    font: &TextStyle,
+) -> Result<(), DrawingAreaErrorKind<DB::ErrorType>> {
+    let (_, line_height) = area.estimate_text_size("Ag", font)?;
+    let line_height = (line_height as f64 * LINE_SPACING) as i32;
+    for (i, line) in lines.iter().enumerate() {
+        area.draw(&Text::new(
+            *line,
+            (TEXT_MARGIN, TEXT_MARGIN + i as i32 * line_height),
+            font.clone(),
+        ))?;
+    }
+    Ok(())
+}
+
+/// Formats the target status, marked when paused, and the score shown at
+/// the top of the window.
+pub fn format_status(game_state: &GameState, note_on_staff: bool) -> (String, String) {
+    let mut target_status = format_target_status(game_state, note_on_staff);
+    if game_state.paused {
+        target_status = format!("Paused | {}", target_status);
+    }
+    (target_status, format_score(&game_state.score))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::FretLoc;
+
+    #[test]
+    fn test_format_target_status() {
+        let mut game_state = GameState {
+            curr_detection_count: 2,
+            ..GameState::fixture(FretLoc {
+                string_idx: 5,
+                fret_idx: 3,
+            })
+        };
+        assert_eq!(
+            "Play C4 on string 5 — 2/3",
+            format_target_status(&game_state, false)
+        );
+        assert_eq!(
+            "Play the note on the staff on string 5 — 2/3",
+            format_target_status(&game_state, true)
+        );
+        game_state.paused = true;
+        assert_eq!(
+            "Paused | Play C4 on string 5 — 2/3",
+            format_status(&game_state, false).0
+        );
+    }
+}
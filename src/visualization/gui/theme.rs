@@ -0,0 +1,142 @@
+use crate::visualization::gui::fretboard_view::FretboardColors;
+use eframe::egui::{Color32, Visuals};
+use plotters::prelude::*;
+use plotters::style::RGBAColor;
+use serde::Deserialize;
+
+/// Named set of the GUI colors, given as r, g, b, a.
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct GuiTheme {
+    pub name: String,
+    pub font_color: (u8, u8, u8, u8),
+    pub axis_color: (u8, u8, u8, u8),
+    pub background_color: (u8, u8, u8, u8),
+    pub line_color: (u8, u8, u8, u8),
+    pub fretboard_color: (u8, u8, u8, u8),
+    pub fret_color: (u8, u8, u8, u8),
+    pub string_color: (u8, u8, u8, u8),
+    pub inlay_color: (u8, u8, u8, u8),
+    pub target_color: (u8, u8, u8, u8),
+    pub previous_target_color: (u8, u8, u8, u8),
+    pub needle_color: (u8, u8, u8, u8),
+    pub peak_color: (u8, u8, u8, u8),
+}
+
+impl GuiTheme {
+    pub fn dark() -> GuiTheme {
+        GuiTheme {
+            name: String::from("dark"),
+            font_color: (0, 255, 0, 255),
+            axis_color: (0, 255, 0, 255),
+            background_color: (0, 0, 0, 255),
+            line_color: (0, 255, 0, 150),
+            fretboard_color: (60, 40, 20, 255),
+            fret_color: (200, 200, 200, 255),
+            string_color: (230, 210, 150, 255),
+            inlay_color: (240, 240, 240, 255),
+            target_color: (0, 255, 0, 255),
+            previous_target_color: (0, 120, 255, 200),
+            needle_color: (255, 80, 0, 255),
+            peak_color: (255, 255, 0, 255),
+        }
+    }
+
+    pub fn light() -> GuiTheme {
+        GuiTheme {
+            name: String::from("light"),
+            font_color: (0, 0, 0, 255),
+            axis_color: (60, 60, 60, 255),
+            background_color: (255, 255, 255, 255),
+            line_color: (0, 90, 200, 180),
+            fretboard_color: (200, 160, 110, 255),
+            fret_color: (90, 90, 90, 255),
+            string_color: (60, 60, 60, 255),
+            inlay_color: (255, 255, 255, 255),
+            target_color: (0, 160, 0, 255),
+            previous_target_color: (0, 90, 200, 200),
+            needle_color: (220, 40, 0, 255),
+            peak_color: (200, 120, 0, 255),
+        }
+    }
+
+    pub fn high_contrast() -> GuiTheme {
+        GuiTheme {
+            name: String::from("high-contrast"),
+            font_color: (255, 255, 255, 255),
+            axis_color: (255, 255, 255, 255),
+            background_color: (0, 0, 0, 255),
+            line_color: (255, 255, 255, 255),
+            fretboard_color: (0, 0, 0, 255),
+            fret_color: (255, 255, 255, 255),
+            string_color: (255, 255, 0, 255),
+            inlay_color: (255, 255, 255, 255),
+            target_color: (0, 255, 0, 255),
+            previous_target_color: (0, 255, 255, 255),
+            needle_color: (255, 0, 255, 255),
+            peak_color: (255, 255, 0, 255),
+        }
+    }
+
+    /// Colors of the egui widgets: the dark or light style, depending on the
+    /// background, with the panels in the background color and the text in the
+    /// font color.
+    pub fn visuals(&self) -> Visuals {
+        let (r, g, b, _) = self.background_color;
+        let luminance = 0.299 * r as f64 + 0.587 * g as f64 + 0.114 * b as f64;
+        let mut visuals = if luminance < 128.0 {
+            Visuals::dark()
+        } else {
+            Visuals::light()
+        };
+        visuals.panel_fill = Color32::from_rgb(r, g, b);
+        let (r, g, b, _) = self.font_color;
+        visuals.override_text_color = Some(Color32::from_rgb(r, g, b));
+        visuals
+    }
+}
+
+impl Default for GuiTheme {
+    fn default() -> Self {
+        GuiTheme::dark()
+    }
+}
+
+pub fn color_from_tup(rgb: (u8, u8, u8, u8)) -> RGBAColor {
+    let alpha = rgb.3 as f64 / 255.0;
+    RGBColor(rgb.0, rgb.1, rgb.2).mix(alpha)
+}
+
+/// Colors of a theme converted for drawing.
+pub struct Palette {
+    pub font: RGBAColor,
+    pub axis: RGBAColor,
+    pub background: RGBAColor,
+    pub line: RGBAColor,
+    pub target: RGBAColor,
+    pub previous_target: RGBAColor,
+    pub needle: RGBAColor,
+    pub peak: RGBAColor,
+    pub fretboard: FretboardColors,
+}
+
+impl Palette {
+    pub fn new(theme: &GuiTheme) -> Palette {
+        Palette {
+            font: color_from_tup(theme.font_color),
+            axis: color_from_tup(theme.axis_color),
+            background: color_from_tup(theme.background_color),
+            line: color_from_tup(theme.line_color),
+            target: color_from_tup(theme.target_color),
+            previous_target: color_from_tup(theme.previous_target_color),
+            needle: color_from_tup(theme.needle_color),
+            peak: color_from_tup(theme.peak_color),
+            fretboard: FretboardColors {
+                board: color_from_tup(theme.fretboard_color),
+                fret: color_from_tup(theme.fret_color),
+                string: color_from_tup(theme.string_color),
+                inlay: color_from_tup(theme.inlay_color),
+            },
+        }
+    }
+}
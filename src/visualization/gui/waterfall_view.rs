@@ -0,0 +1,49 @@
+use crate::visualization::gui::spectrum_scale::SpectrumScale;
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use std::collections::VecDeque;
+
+/// Color of a spectrum bin with the given magnitude relative to the maximum:
+/// dark blue for weak bins up to red for the strongest ones.
+fn heat_color(ratio: f64) -> HSLColor {
+    HSLColor((1.0 - ratio) * 2.0 / 3.0, 1.0, ratio * 0.5)
+}
+
+/// Draws the most recent spectra as a scrolling heatmap on `area`, with
+/// frequency on the horizontal axis and the newest spectrum at the top.
+/// `capacity` is the number of rows the area is divided into.
+pub fn draw_waterfall<DB: DrawingBackend>(
+    area: &DrawingArea<DB, Shift>,
+    spectra: &VecDeque<Vec<f64>>,
+    capacity: usize,
+    delta_f: f64,
+    scale: &SpectrumScale,
+) -> Result<(), DrawingAreaErrorKind<DB::ErrorType>> {
+    let (width, height) = area.dim_in_pixel();
+    if capacity == 0 {
+        return Ok(());
+    }
+    let row_height = height as f64 / capacity as f64;
+    for (row, spectrum) in spectra.iter().rev().take(capacity).enumerate() {
+        let y0 = (row as f64 * row_height) as i32;
+        let y1 = ((row + 1) as f64 * row_height) as i32;
+        for (bin, magnitude) in spectrum.iter().enumerate() {
+            let (freq_beg, freq_end) = (bin as f64 * delta_f, (bin + 1) as f64 * delta_f);
+            if !scale.contains_freq(freq_beg) {
+                continue;
+            }
+            let ratio = scale.y_ratio(*magnitude);
+            // Leave the background visible for silent bins.
+            if ratio < 0.01 {
+                continue;
+            }
+            let x0 = (scale.x_ratio(freq_beg) * width as f64) as i32;
+            let x1 = (scale.x_ratio(freq_end) * width as f64) as i32;
+            area.draw(&Rectangle::new(
+                [(x0, y0), (x1, y1)],
+                heat_color(ratio).filled(),
+            ))?;
+        }
+    }
+    Ok(())
+}
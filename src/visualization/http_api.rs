@@ -0,0 +1,301 @@
+use crate::core::HttpApiCfg;
+use crate::event_bus::Subscription;
+use crate::game::{GameController, GameState};
+use crate::visualization::Visualizer;
+use log::*;
+use serde_json::{json, Value};
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// Largest HTTP request header that is read from a client.
+const MAX_REQUEST_SIZE: usize = 8192;
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(1);
+/// Header that `POST /control` requires. Browsers only send it from other
+/// pages after a CORS preflight, which the API doesn't allow, so that web
+/// pages can't control the game.
+const CONTROL_HEADER: &str = "X-Libreguitar";
+
+/// Command sent to the game with `POST /control?command=<command>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ControlCommand {
+    Pause,
+    Resume,
+    Skip,
+}
+
+impl ControlCommand {
+    fn from_query(query: &str) -> Option<ControlCommand> {
+        let command = query.split('&').find_map(|param| {
+            let mut parts = param.splitn(2, '=');
+            match (parts.next(), parts.next()) {
+                (Some("command"), Some(command)) => Some(command),
+                _ => None,
+            }
+        })?;
+        match command {
+            "pause" => Some(ControlCommand::Pause),
+            "resume" => Some(ControlCommand::Resume),
+            "skip" => Some(ControlCommand::Skip),
+            _ => None,
+        }
+    }
+}
+
+fn state_json(state: &GameState) -> Value {
+    json!({
+        "target": {
+            "note": state.target_note.name_octave(),
+            "string": state.target_loc.string_idx,
+            "fret": state.target_loc.fret_idx,
+        },
+        "detections": state.curr_detection_count,
+        "needed_detections": state.needed_detection_count,
+        "paused": state.paused,
+        "time_limit": state.time_limit.map(|limit| limit.as_secs_f64()),
+    })
+}
+
+fn stats_json(state: &GameState) -> Value {
+    let score = &state.score;
+    json!({
+        "completed": score.n_completed,
+        "correct": score.n_correct,
+        "accuracy": score.accuracy(),
+        "avg_response_time": score.avg_response_time().map(|time| time.as_secs_f64()),
+        "streak": score.streak,
+        "best_streak": score.best_streak,
+    })
+}
+
+/// Whether the header `name` is in the HTTP request head `request`.
+fn has_header(request: &str, name: &str) -> bool {
+    request.lines().skip(1).any(|line| {
+        line.split(':')
+            .next()
+            .is_some_and(|header| header.trim().eq_ignore_ascii_case(name))
+    })
+}
+
+/// Status code and JSON body of the answer to the HTTP request head
+/// `request`. Control commands are sent to `controller`, and the latest
+/// state is only locked to answer the queries, so that a command waiting for
+/// the game doesn't hold up the visualizer.
+fn route(
+    request: &str,
+    state: &Mutex<Option<GameState>>,
+    controller: &GameController,
+) -> (u16, Value) {
+    let mut request_line = request.lines().next().unwrap_or("").split_whitespace();
+    let method = request_line.next().unwrap_or("");
+    let target = request_line.next().unwrap_or("");
+    let mut parts = target.splitn(2, '?');
+    let path = parts.next().unwrap_or("");
+    let query = parts.next().unwrap_or("");
+    let query_state = |to_json: fn(&GameState) -> Value| match state.lock().unwrap().as_ref() {
+        Some(state) => (200, to_json(state)),
+        None => (503, json!({"error": "The game hasn't started yet"})),
+    };
+    match (method, path) {
+        ("GET", "/state") => query_state(state_json),
+        ("GET", "/stats") => query_state(stats_json),
+        ("POST", "/control") if !has_header(request, CONTROL_HEADER) => (
+            403,
+            json!({ "error": format!("Commands need the {} header", CONTROL_HEADER) }),
+        ),
+        ("POST", "/control") => {
+            let res = match ControlCommand::from_query(query) {
+                Some(ControlCommand::Pause) => controller.pause(),
+                Some(ControlCommand::Resume) => controller.resume(),
+                Some(ControlCommand::Skip) => controller.skip(),
+                None => {
+                    return (
+                        400,
+                        json!({"error": "Expected command=pause, resume or skip"}),
+                    )
+                }
+            };
+            match res {
+                Ok(()) => (200, json!({"ok": true})),
+                Err(err) => (503, json!({"error": err.to_string()})),
+            }
+        }
+        (_, "/state") | (_, "/stats") | (_, "/control") => {
+            (405, json!({"error": "Method not allowed"}))
+        }
+        _ => (404, json!({"error": "Not found"})),
+    }
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        _ => "Service Unavailable",
+    }
+}
+
+/// Answers a single request. The request body is ignored.
+fn handle_client(
+    mut stream: TcpStream,
+    state: &Mutex<Option<GameState>>,
+    controller: &GameController,
+) -> io::Result<()> {
+    stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+    stream.set_write_timeout(Some(CLIENT_TIMEOUT))?;
+    let mut request = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !request.windows(4).any(|end| end == b"\r\n\r\n") && request.len() < MAX_REQUEST_SIZE {
+        match stream.read(&mut buf)? {
+            0 => break,
+            n_read => request.extend_from_slice(&buf[..n_read]),
+        }
+    }
+    let request = String::from_utf8_lossy(&request);
+    let (status, body) = route(&request, state, controller);
+    let body = body.to_string();
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\n\
+         Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason_phrase(status),
+        body.len(),
+        body
+    )
+}
+
+/// Local HTTP API for dashboards and scripts: `GET /state` and `GET /stats`
+/// return the current target and the session score as JSON, and
+/// `POST /control?command=pause|resume|skip` with an `X-Libreguitar` header
+/// controls the game.
+pub struct HttpApi {
+    rx: Subscription<GameState>,
+    /// Latest game state, shared with the server thread.
+    state: Arc<Mutex<Option<GameState>>>,
+}
+
+impl HttpApi {
+    /// Starts listening on the configured address.
+    pub fn new(
+        rx: Subscription<GameState>,
+        controller: GameController,
+        cfg: HttpApiCfg,
+    ) -> io::Result<HttpApi> {
+        let listener = TcpListener::bind(&cfg.address)?;
+        info!("Serving the HTTP API on {}", listener.local_addr()?);
+        let state = Arc::new(Mutex::new(None));
+        let server_state = Arc::clone(&state);
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let res =
+                    stream.and_then(|stream| handle_client(stream, &server_state, &controller));
+                if let Err(err) = res {
+                    debug!("HTTP API request failed: {}", err);
+                }
+            }
+        });
+        Ok(HttpApi { rx, state })
+    }
+}
+
+impl Visualizer for HttpApi {
+    fn is_open(&self) -> bool {
+        true
+    }
+
+    fn draw(&mut self) {
+        if let Some(state) = self.rx.try_iter().last() {
+            *self.state.lock().unwrap() = Some(state);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::FretLoc;
+    use crate::event_bus::Topic;
+    use crate::game::{GameCommand, Score};
+
+    fn game_state() -> GameState {
+        let mut score = Score::default();
+        score.record(true, Duration::from_secs(2));
+        score.record(false, Duration::from_secs(4));
+        GameState {
+            curr_detection_count: 1,
+            score,
+            ..GameState::fixture(FretLoc {
+                string_idx: 5,
+                fret_idx: 3,
+            })
+        }
+    }
+
+    #[test]
+    fn test_control_command_from_query() {
+        assert_eq!(
+            Some(ControlCommand::Skip),
+            ControlCommand::from_query("command=skip")
+        );
+        assert_eq!(
+            Some(ControlCommand::Pause),
+            ControlCommand::from_query("x=1&command=pause")
+        );
+        assert_eq!(None, ControlCommand::from_query("command=quit"));
+        assert_eq!(None, ControlCommand::from_query(""));
+    }
+
+    #[test]
+    fn test_state_and_stats_json() {
+        let state = game_state();
+        assert_eq!(
+            json!({
+                "target": {"note": "C4", "string": 5, "fret": 3},
+                "detections": 1,
+                "needed_detections": 3,
+                "paused": false,
+                "time_limit": null,
+            }),
+            state_json(&state)
+        );
+        let stats = stats_json(&state);
+        assert_eq!(json!(0.5), stats["accuracy"]);
+        assert_eq!(json!(3.0), stats["avg_response_time"]);
+        assert_eq!(json!(1), stats["best_streak"]);
+    }
+
+    #[test]
+    fn test_route() {
+        let control = Topic::default();
+        let controller = GameController::with_topic(control.clone());
+        let state = Mutex::new(None);
+        let status = |request: &str| route(request, &state, &controller).0;
+        let skip = "POST /control?command=skip HTTP/1.1\r\nX-Libreguitar: 1\r\n\r\n";
+        assert_eq!(503, status("GET /state HTTP/1.1\r\n\r\n"));
+        // Nothing receives the commands before the game starts.
+        assert_eq!(503, status(skip));
+        let commands = control.subscribe();
+        assert_eq!(200, status(skip));
+        assert_eq!(Ok(GameCommand::Skip), commands.try_recv());
+        let no_header = "POST /control?command=skip HTTP/1.1\r\nContent-Type: text/plain\r\n\r\n";
+        assert_eq!(403, status(no_header));
+        assert_eq!(
+            400,
+            status("POST /control?command=quit HTTP/1.1\r\nx-libreguitar: 1\r\n\r\n")
+        );
+        assert!(commands.try_recv().is_err());
+        assert_eq!(405, status("GET /control HTTP/1.1\r\n\r\n"));
+        assert_eq!(405, status("DELETE /stats HTTP/1.1\r\n\r\n"));
+        assert_eq!(404, status("GET /score HTTP/1.1\r\n\r\n"));
+        *state.lock().unwrap() = Some(game_state());
+        let (code, body) = route("GET /stats HTTP/1.1\r\n\r\n", &state, &controller);
+        assert_eq!(200, code);
+        assert_eq!(json!(1), body["best_streak"]);
+    }
+}
@@ -0,0 +1,177 @@
+use crate::audio_analysis::AnalysisResult;
+use crate::core::OscCfg;
+use crate::event_bus::Subscription;
+use crate::game::GameState;
+use crate::visualization::Visualizer;
+use crate::visualization::{GameEvent, GameEventTracker};
+use log::*;
+use std::io;
+use std::net::UdpSocket;
+
+/// Argument of an OSC message.
+#[derive(Debug, Clone, PartialEq)]
+enum OscArg {
+    Int(i32),
+    Float(f32),
+    Str(String),
+}
+
+/// Appends `s` as an OSC string: null terminated and padded with nulls to a
+/// multiple of 4 bytes.
+fn push_osc_string(buf: &mut Vec<u8>, s: &str) {
+    buf.extend_from_slice(s.as_bytes());
+    let n_padding = 4 - s.len() % 4;
+    buf.resize(buf.len() + n_padding, 0);
+}
+
+/// Encodes an OSC 1.0 message with the given address and arguments.
+fn encode_message(address: &str, args: &[OscArg]) -> Vec<u8> {
+    let mut buf = Vec::new();
+    push_osc_string(&mut buf, address);
+    let type_tags: String = std::iter::once(',')
+        .chain(args.iter().map(|arg| match arg {
+            OscArg::Int(_) => 'i',
+            OscArg::Float(_) => 'f',
+            OscArg::Str(_) => 's',
+        }))
+        .collect();
+    push_osc_string(&mut buf, &type_tags);
+    for arg in args.iter() {
+        match arg {
+            OscArg::Int(value) => buf.extend_from_slice(&value.to_be_bytes()),
+            OscArg::Float(value) => buf.extend_from_slice(&value.to_be_bytes()),
+            OscArg::Str(value) => push_osc_string(&mut buf, value),
+        }
+    }
+    buf
+}
+
+/// Address under `prefix` and arguments of the OSC message of `event`.
+fn event_message(prefix: &str, event: &GameEvent) -> (String, Vec<OscArg>) {
+    let (name, args) = match event {
+        GameEvent::Target { note, loc } => (
+            "target",
+            vec![
+                OscArg::Str(note.clone()),
+                OscArg::Int(loc.string_idx as i32),
+                OscArg::Int(loc.fret_idx as i32),
+            ],
+        ),
+        GameEvent::Progress { count, needed } => (
+            "progress",
+            vec![OscArg::Int(*count as i32), OscArg::Int(*needed as i32)],
+        ),
+        GameEvent::Result(result) => (
+            "result",
+            vec![
+                OscArg::Str(result.note.clone()),
+                OscArg::Int(result.correct as i32),
+                OscArg::Float(result.response_time.as_secs_f32()),
+            ],
+        ),
+        GameEvent::Paused(paused) => ("paused", vec![OscArg::Int(*paused as i32)]),
+        GameEvent::Detection {
+            note,
+            frequency,
+            cents,
+        } => (
+            "detection",
+            vec![
+                OscArg::Str(note.clone()),
+                OscArg::Float(*frequency as f32),
+                OscArg::Float(*cents as f32),
+            ],
+        ),
+    };
+    (format!("{}/{}", prefix, name), args)
+}
+
+/// Sends the game events and optionally the detections as OSC messages over
+/// UDP, e.g. to Pure Data, SuperCollider or a lighting controller.
+pub struct OscSender {
+    rx: Subscription<GameState>,
+    analysis_rx: Subscription<AnalysisResult>,
+    socket: UdpSocket,
+    tracker: GameEventTracker,
+    cfg: OscCfg,
+}
+
+impl OscSender {
+    /// Fails if the configured address can't be resolved.
+    pub fn new(
+        rx: Subscription<GameState>,
+        analysis_rx: Subscription<AnalysisResult>,
+        cfg: OscCfg,
+    ) -> io::Result<OscSender> {
+        let socket = UdpSocket::bind("0.0.0.0:0")?;
+        socket.connect(&cfg.address)?;
+        info!("Sending OSC messages to {}", cfg.address);
+        Ok(OscSender {
+            rx,
+            analysis_rx,
+            socket,
+            tracker: GameEventTracker::default(),
+            cfg,
+        })
+    }
+
+    fn send(&self, event: &GameEvent) {
+        let (address, args) = event_message(&self.cfg.prefix, event);
+        // Nobody listening is not an error for a broadcast.
+        if let Err(err) = self.socket.send(&encode_message(&address, &args)) {
+            debug!("Could not send OSC message {}: {}", address, err);
+        }
+    }
+}
+
+impl Visualizer for OscSender {
+    fn is_open(&self) -> bool {
+        true
+    }
+
+    fn draw(&mut self) {
+        let states: Vec<GameState> = self.rx.try_iter().collect();
+        for state in states {
+            for event in self.tracker.update(state) {
+                self.send(&event);
+            }
+        }
+        let analyses: Vec<AnalysisResult> = self.analysis_rx.try_iter().collect();
+        if self.cfg.send_detections {
+            for event in analyses.iter().filter_map(GameEvent::detection) {
+                self.send(&event);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_message() {
+        let message = encode_message(
+            "/lg/target",
+            &[
+                OscArg::Str(String::from("A2")),
+                OscArg::Int(5),
+                OscArg::Float(1.0),
+            ],
+        );
+        let mut expected = Vec::new();
+        expected.extend_from_slice(b"/lg/target\0\0");
+        expected.extend_from_slice(b",sif\0\0\0\0");
+        expected.extend_from_slice(b"A2\0\0");
+        expected.extend_from_slice(&[0, 0, 0, 5]);
+        expected.extend_from_slice(&[0x3F, 0x80, 0, 0]);
+        assert_eq!(expected, message);
+    }
+
+    #[test]
+    fn test_event_message() {
+        let (address, args) = event_message("/libreguitar", &GameEvent::Paused(true));
+        assert_eq!("/libreguitar/paused", address);
+        assert_eq!(vec![OscArg::Int(1)], args);
+    }
+}
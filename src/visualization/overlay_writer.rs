@@ -0,0 +1,201 @@
+use crate::core::OverlayCfg;
+use crate::event_bus::Subscription;
+use crate::game::GameState;
+use crate::visualization::{Visualizer, EVENT_VERSION};
+use log::*;
+use serde_json::json;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+const TEXT_FILE: &str = "overlay.txt";
+const JSON_FILE: &str = "overlay.json";
+const HTML_FILE: &str = "overlay.html";
+
+fn format_target(state: &GameState) -> String {
+    let paused = if state.paused { " (paused)" } else { "" };
+    format!(
+        "Target: {} (string {}, fret {}){}",
+        state.target_note.name_octave(),
+        state.target_loc.string_idx,
+        state.target_loc.fret_idx,
+        paused
+    )
+}
+
+fn format_score(state: &GameState) -> String {
+    let score = &state.score;
+    match score.accuracy() {
+        Some(accuracy) => format!(
+            "Score: {}/{} ({:.0}%)",
+            score.n_correct,
+            score.n_completed,
+            100.0 * accuracy
+        ),
+        None => String::from("Score: 0/0"),
+    }
+}
+
+fn format_streak(state: &GameState) -> String {
+    format!(
+        "Streak: {} (best {})",
+        state.score.streak, state.score.best_streak
+    )
+}
+
+/// Plain text overlay, one line each for the target, the score and the
+/// streak.
+fn overlay_text(state: &GameState) -> String {
+    format!(
+        "{}\n{}\n{}\n",
+        format_target(state),
+        format_score(state),
+        format_streak(state)
+    )
+}
+
+fn overlay_json(state: &GameState) -> String {
+    let score = &state.score;
+    json!({
+        "note": state.target_note.name_octave(),
+        "string": state.target_loc.string_idx,
+        "fret": state.target_loc.fret_idx,
+        "paused": state.paused,
+        "completed": score.n_completed,
+        "correct": score.n_correct,
+        "accuracy": score.accuracy(),
+        "streak": score.streak,
+        "best_streak": score.best_streak,
+        "version": EVENT_VERSION,
+    })
+    .to_string()
+}
+
+/// Page for an OBS browser source that reloads itself every second.
+fn overlay_html(state: &GameState) -> String {
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<meta http-equiv="refresh" content="1">
+<style>
+body {{ margin: 0; background: transparent; color: white; font: bold 32px sans-serif; text-shadow: 2px 2px 4px black; }}
+#note {{ font-size: 64px; }}
+</style>
+</head>
+<body>
+<div id="note">{}</div>
+<div>string {}, fret {}{}</div>
+<div>{}</div>
+<div>{}</div>
+</body>
+</html>
+"#,
+        state.target_note.name_octave(),
+        state.target_loc.string_idx,
+        state.target_loc.fret_idx,
+        if state.paused { " (paused)" } else { "" },
+        format_score(state),
+        format_streak(state)
+    )
+}
+
+/// Replaces the file at `path` with `contents` so that readers never see a
+/// partially written file.
+fn write_atomically(path: &Path, contents: &str) -> io::Result<()> {
+    let tmp_path = path.with_extension("tmp");
+    fs::write(&tmp_path, contents)?;
+    fs::rename(&tmp_path, path)
+}
+
+/// Keeps the current target, score and streak in text, JSON and optionally
+/// HTML files that streaming software such as OBS can overlay on a stream.
+pub struct OverlayWriter {
+    rx: Subscription<GameState>,
+    cfg: OverlayCfg,
+}
+
+impl OverlayWriter {
+    /// Fails if the overlay directory can't be created.
+    pub fn new(rx: Subscription<GameState>, cfg: OverlayCfg) -> io::Result<OverlayWriter> {
+        fs::create_dir_all(&cfg.dir)?;
+        info!("Writing the stream overlay to {}", cfg.dir.display());
+        Ok(OverlayWriter { rx, cfg })
+    }
+
+    fn write(&self, state: &GameState) -> io::Result<()> {
+        write_atomically(&self.cfg.dir.join(TEXT_FILE), &overlay_text(state))?;
+        write_atomically(&self.cfg.dir.join(JSON_FILE), &overlay_json(state))?;
+        if self.cfg.write_html {
+            write_atomically(&self.cfg.dir.join(HTML_FILE), &overlay_html(state))?;
+        }
+        Ok(())
+    }
+}
+
+impl Visualizer for OverlayWriter {
+    fn is_open(&self) -> bool {
+        true
+    }
+
+    fn draw(&mut self) {
+        if let Some(state) = self.rx.try_iter().last() {
+            if let Err(err) = self.write(&state) {
+                warn!("Could not write the stream overlay: {}", err);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::FretLoc;
+    use crate::event_bus::Topic;
+    use crate::game::Score;
+    use std::time::Duration;
+
+    fn state() -> GameState {
+        let mut score = Score::default();
+        score.record(true, Duration::from_secs(1));
+        score.record(false, Duration::from_secs(1));
+        score.record(true, Duration::from_secs(1));
+        GameState {
+            score,
+            paused: true,
+            ..GameState::fixture(FretLoc {
+                string_idx: 5,
+                fret_idx: 3,
+            })
+        }
+    }
+
+    #[test]
+    fn test_overlay_text() {
+        assert_eq!(
+            "Target: C4 (string 5, fret 3) (paused)\nScore: 2/3 (67%)\nStreak: 1 (best 1)\n",
+            overlay_text(&state())
+        );
+    }
+
+    #[test]
+    fn test_overlay_files() {
+        let dir = std::env::temp_dir().join("libreguitar_test_overlay");
+        let _ = fs::remove_dir_all(&dir);
+        let topic = Topic::default();
+        let cfg = OverlayCfg {
+            dir: dir.clone(),
+            write_html: false,
+        };
+        let mut writer = OverlayWriter::new(topic.subscribe(), cfg).unwrap();
+        topic.publish(state());
+        writer.draw();
+        let json: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(dir.join(JSON_FILE)).unwrap()).unwrap();
+        assert_eq!("C4", json["note"]);
+        assert_eq!(2, json["correct"]);
+        assert!(dir.join(TEXT_FILE).exists());
+        assert!(!dir.join(HTML_FILE).exists());
+    }
+}
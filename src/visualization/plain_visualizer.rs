@@ -0,0 +1,146 @@
+use crate::audio_analysis::AnalysisResult;
+use crate::core::ConsoleCfg;
+use crate::event_bus::Subscription;
+use crate::game::GameState;
+use crate::visualization::ascii::to_ascii;
+use crate::visualization::bell::Bell;
+use crate::visualization::session_summary::SessionSummary;
+use crate::visualization::status_text::TargetResult;
+use crate::visualization::Visualizer;
+use console::Term;
+use std::time::Instant;
+
+/// Screen reader friendly frontend. Never clears the screen and prints each
+/// prompt and result as a single line of plain text, which also makes the
+/// output easy to pipe into a log.
+pub struct PlainVisualizer {
+    rx: Subscription<GameState>,
+    analysis_rx: Subscription<AnalysisResult>,
+    spectrum_rx: Subscription<Vec<f64>>,
+    term: Term,
+    game_state: Option<GameState>,
+    ascii: bool,
+    bell: Bell,
+    summary: SessionSummary,
+    session_start: Option<Instant>,
+}
+
+impl PlainVisualizer {
+    pub fn new(
+        rx: Subscription<GameState>,
+        analysis_rx: Subscription<AnalysisResult>,
+        spectrum_rx: Subscription<Vec<f64>>,
+        config: ConsoleCfg,
+    ) -> PlainVisualizer {
+        PlainVisualizer {
+            rx,
+            analysis_rx,
+            spectrum_rx,
+            term: Term::stdout(),
+            game_state: None,
+            ascii: config.ascii,
+            bell: Bell::new(&config),
+            summary: SessionSummary::default(),
+            session_start: None,
+        }
+    }
+
+    fn write_line(&self, line: &str) {
+        if self.ascii {
+            self.term.write_line(&to_ascii(line)).unwrap();
+        } else {
+            self.term.write_line(line).unwrap();
+        }
+    }
+}
+
+fn format_prompt(game_state: &GameState) -> String {
+    format!(
+        "Play {} on string {}, fret {}",
+        game_state.target_note.name_octave(),
+        game_state.target_loc.string_idx,
+        game_state.target_loc.fret_idx
+    )
+}
+
+fn format_result(result: &TargetResult) -> String {
+    format!(
+        "{} after {:.1}s",
+        if result.correct { "Correct" } else { "Missed" },
+        result.response_time.as_secs_f64()
+    )
+}
+
+impl Visualizer for PlainVisualizer {
+    fn is_open(&self) -> bool {
+        true
+    }
+
+    fn draw(&mut self) {
+        // Detections and spectra are not printed, but the channels must not
+        // fill up.
+        self.analysis_rx.try_iter().for_each(drop);
+        self.spectrum_rx.try_iter().for_each(drop);
+        let states: Vec<GameState> = self.rx.try_iter().collect();
+        for game_state in states {
+            let is_new_target = match &self.game_state {
+                Some(last_state) => last_state.target_loc != game_state.target_loc,
+                None => true,
+            };
+            if is_new_target {
+                if let Some(last_state) = &self.game_state {
+                    let result = TargetResult::between(last_state, &game_state);
+                    self.write_line(&format_result(&result));
+                    self.summary.record(&result);
+                    self.bell.ring(&result, &mut self.term).unwrap();
+                }
+                self.write_line(&format_prompt(&game_state));
+            }
+            self.session_start.get_or_insert_with(Instant::now);
+            self.game_state = Some(game_state);
+        }
+    }
+
+    fn finish(&mut self) {
+        if let (Some(game_state), Some(session_start)) = (&self.game_state, self.session_start) {
+            let summary = self
+                .summary
+                .format(&game_state.score, session_start.elapsed())
+                .unwrap();
+            self.write_line("Session summary");
+            for line in summary.lines() {
+                self.write_line(line);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::FretLoc;
+    use std::time::Duration;
+
+    #[test]
+    fn test_format_prompt() {
+        let game_state = GameState::fixture(FretLoc {
+            string_idx: 5,
+            fret_idx: 3,
+        });
+        assert_eq!("Play C4 on string 5, fret 3", format_prompt(&game_state));
+    }
+
+    #[test]
+    fn test_format_result() {
+        let result = TargetResult {
+            note: String::from("C4"),
+            loc: FretLoc {
+                string_idx: 5,
+                fret_idx: 0,
+            },
+            correct: true,
+            response_time: Duration::from_millis(2100),
+        };
+        assert_eq!("Correct after 2.1s", format_result(&result));
+    }
+}
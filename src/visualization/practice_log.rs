@@ -0,0 +1,160 @@
+use crate::audio_analysis::AnalysisResult;
+use crate::event_bus::Subscription;
+use crate::game::GameState;
+use crate::visualization::Visualizer;
+use crate::visualization::{GameEvent, GameEventTracker, EVENT_VERSION};
+use log::*;
+use serde_json::{json, Value};
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Seconds since the Unix epoch.
+fn unix_time(time: SystemTime) -> f64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0.0, |since_epoch| since_epoch.as_secs_f64())
+}
+
+/// `value` with the time it happened at under `timestamp`.
+fn with_timestamp(mut value: Value, time: SystemTime) -> Value {
+    value["timestamp"] = json!(unix_time(time));
+    value
+}
+
+/// Appends a JSON Lines log of the practice sessions for other tools to
+/// consume. Every line is a JSON object with the `timestamp` it happened at
+/// in seconds since the Unix epoch, its kind under `type` and the
+/// [`EVENT_VERSION`] under `version`: `session_start` and `session_end` frame
+/// each session, and in between the game events of
+/// [`GameEvent::to_json_value`] are logged, with a `detection` whenever the
+/// detected note changes.
+pub struct PracticeLog {
+    rx: Subscription<GameState>,
+    analysis_rx: Subscription<AnalysisResult>,
+    tracker: GameEventTracker,
+    /// Note of the last logged detection, `None` during silence.
+    last_detected: Option<String>,
+    last_state: Option<GameState>,
+    out: BufWriter<File>,
+}
+
+impl PracticeLog {
+    /// Fails if the log file at `path` can't be opened for appending.
+    pub fn new(
+        rx: Subscription<GameState>,
+        analysis_rx: Subscription<AnalysisResult>,
+        path: &Path,
+    ) -> io::Result<PracticeLog> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        info!("Writing the practice log to {}", path.display());
+        let mut log = PracticeLog {
+            rx,
+            analysis_rx,
+            tracker: GameEventTracker::default(),
+            last_detected: None,
+            last_state: None,
+            out: BufWriter::new(file),
+        };
+        log.write(json!({ "type": "session_start", "version": EVENT_VERSION }))?;
+        Ok(log)
+    }
+
+    fn write(&mut self, value: Value) -> io::Result<()> {
+        let line = with_timestamp(value, SystemTime::now());
+        writeln!(self.out, "{}", line)
+    }
+
+    fn write_new_entries(&mut self) -> io::Result<()> {
+        let states: Vec<GameState> = self.rx.try_iter().collect();
+        for state in states {
+            self.last_state = Some(state.clone());
+            for event in self.tracker.update(state) {
+                self.write(event.to_json_value())?;
+            }
+        }
+        let analyses: Vec<AnalysisResult> = self.analysis_rx.try_iter().collect();
+        for analysis in analyses.iter() {
+            let detected = analysis.note.as_ref().map(|note| note.name_octave());
+            if detected == self.last_detected {
+                continue;
+            }
+            self.last_detected = detected;
+            if let Some(event) = GameEvent::detection(analysis) {
+                self.write(event.to_json_value())?;
+            }
+        }
+        self.out.flush()
+    }
+}
+
+impl Visualizer for PracticeLog {
+    fn is_open(&self) -> bool {
+        true
+    }
+
+    fn draw(&mut self) {
+        if let Err(err) = self.write_new_entries() {
+            warn!("Could not write the practice log: {}", err);
+        }
+    }
+
+    fn finish(&mut self) {
+        self.draw();
+        let score = self
+            .last_state
+            .as_ref()
+            .map(|state| state.score.clone())
+            .unwrap_or_default();
+        let end = json!({
+            "type": "session_end",
+            "version": EVENT_VERSION,
+            "completed": score.n_completed,
+            "correct": score.n_correct,
+            "best_streak": score.best_streak,
+        });
+        if let Err(err) = self.write(end).and_then(|_| self.out.flush()) {
+            warn!("Could not write the practice log: {}", err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event_bus::EventBus;
+    use std::fs;
+    use std::time::Duration;
+
+    #[test]
+    fn test_with_timestamp() {
+        let time = UNIX_EPOCH + Duration::from_millis(1500);
+        let value = with_timestamp(GameEvent::Paused(true).to_json_value(), time);
+        assert_eq!(
+            r#"{"paused":true,"timestamp":1.5,"type":"paused","version":1}"#,
+            value.to_string()
+        );
+    }
+
+    #[test]
+    fn test_session_lines() {
+        let dir = std::env::temp_dir().join("libreguitar_test_practice_log");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("practice.jsonl");
+        let _ = fs::remove_file(&path);
+        let bus = EventBus::default();
+        let mut log =
+            PracticeLog::new(bus.states.subscribe(), bus.analysis.subscribe(), &path).unwrap();
+        log.finish();
+        let lines: Vec<Value> = fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(2, lines.len());
+        assert_eq!("session_start", lines[0]["type"]);
+        assert_eq!("session_end", lines[1]["type"]);
+        assert_eq!(0, lines[1]["completed"]);
+        assert!(lines[1]["timestamp"].as_f64().unwrap() > 0.0);
+    }
+}
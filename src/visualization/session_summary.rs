@@ -0,0 +1,216 @@
+use crate::core::FretLoc;
+use crate::game::Score;
+use crate::visualization::fretboard_drawer::{Marker, MarkerKind};
+use crate::visualization::status_text::TargetResult;
+use std::collections::HashMap;
+use std::fmt::{self, Write};
+use std::time::Duration;
+
+/// Number of positions listed as the weakest ones in the summary.
+const N_WEAKEST_POSITIONS: usize = 5;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+struct PositionStats {
+    note: String,
+    n_attempts: usize,
+    n_correct: usize,
+    total_response_time: Duration,
+}
+
+impl PositionStats {
+    fn accuracy(&self) -> f64 {
+        self.n_correct as f64 / self.n_attempts as f64
+    }
+}
+
+/// Collects the results of every fretboard position during a session and
+/// reports them once the game is closed.
+#[derive(Debug, Default)]
+pub struct SessionSummary {
+    positions: HashMap<FretLoc, PositionStats>,
+}
+
+impl SessionSummary {
+    pub fn record(&mut self, result: &TargetResult) {
+        let stats = self.positions.entry(result.loc.clone()).or_default();
+        stats.note = result.note.clone();
+        stats.n_attempts += 1;
+        stats.total_response_time += result.response_time;
+        if result.correct {
+            stats.n_correct += 1;
+        }
+    }
+
+    /// Positions that were missed at least once, the least accurate first.
+    fn weakest(&self, n: usize) -> Vec<(&FretLoc, &PositionStats)> {
+        let mut missed: Vec<(&FretLoc, &PositionStats)> = self
+            .positions
+            .iter()
+            .filter(|(_, stats)| stats.n_correct < stats.n_attempts)
+            .collect();
+        missed.sort_by(|(loc_a, a), (loc_b, b)| {
+            a.accuracy()
+                .partial_cmp(&b.accuracy())
+                .unwrap()
+                .then(b.n_attempts.cmp(&a.n_attempts))
+                .then(loc_a.string_idx.cmp(&loc_b.string_idx))
+                .then(loc_a.fret_idx.cmp(&loc_b.fret_idx))
+        });
+        missed.truncate(n);
+        missed
+    }
+
+    /// Accuracy on each string that was played, ordered by string index.
+    #[cfg(any(feature = "gui", test))]
+    pub fn string_accuracy(&self) -> Vec<(usize, f64)> {
+        let mut per_string: HashMap<usize, (usize, usize)> = HashMap::new();
+        for (loc, stats) in self.positions.iter() {
+            let counts = per_string.entry(loc.string_idx).or_default();
+            counts.0 += stats.n_correct;
+            counts.1 += stats.n_attempts;
+        }
+        let mut out: Vec<(usize, f64)> = per_string
+            .into_iter()
+            .map(|(string_idx, (n_correct, n_attempts))| {
+                (string_idx, n_correct as f64 / n_attempts as f64)
+            })
+            .collect();
+        out.sort_by_key(|(string_idx, _)| *string_idx);
+        out
+    }
+
+    /// Share of the missed targets at each position that was played.
+    #[cfg(any(feature = "gui", test))]
+    pub fn error_rates(&self) -> Vec<(FretLoc, f64)> {
+        self.positions
+            .iter()
+            .map(|(loc, stats)| (loc.clone(), 1.0 - stats.accuracy()))
+            .collect()
+    }
+
+    /// Average response time at each position that was played, in seconds.
+    #[cfg(any(feature = "gui", test))]
+    pub fn response_times(&self) -> Vec<(FretLoc, f64)> {
+        self.positions
+            .iter()
+            .map(|(loc, stats)| {
+                let avg = stats.total_response_time.as_secs_f64() / stats.n_attempts as f64;
+                (loc.clone(), avg)
+            })
+            .collect()
+    }
+
+    /// Heat view of the session: the weakest positions are marked as wrong
+    /// and the positions that were never missed as completed.
+    pub fn markers(&self) -> Vec<Marker> {
+        let mut markers: Vec<Marker> = self
+            .weakest(N_WEAKEST_POSITIONS)
+            .into_iter()
+            .map(|(loc, _)| Marker::new(loc.clone(), MarkerKind::Wrong))
+            .collect();
+        markers.extend(
+            self.positions
+                .iter()
+                .filter(|(_, stats)| stats.n_correct == stats.n_attempts)
+                .map(|(loc, _)| Marker::new(loc.clone(), MarkerKind::Completed)),
+        );
+        markers
+    }
+
+    /// Formats the session statistics and the weakest positions as a table.
+    pub fn format(&self, score: &Score, session_elapsed: Duration) -> Result<String, fmt::Error> {
+        let mut out = String::new();
+        let secs = session_elapsed.as_secs();
+        let accuracy = match score.accuracy() {
+            Some(accuracy) => format!("{:.0}%", accuracy * 100.0),
+            None => String::from("-"),
+        };
+        let avg_response_time = match score.avg_response_time() {
+            Some(time) => format!("{:.1}s", time.as_secs_f64()),
+            None => String::from("-"),
+        };
+        writeln!(out, "Session time:   {:02}:{:02}", secs / 60, secs % 60)?;
+        writeln!(out, "Targets:        {}", score.n_completed)?;
+        writeln!(out, "Correct:        {} ({})", score.n_correct, accuracy)?;
+        writeln!(out, "Best streak:    {}", score.best_streak)?;
+        write!(out, "Avg. response:  {}", avg_response_time)?;
+        let weakest = self.weakest(N_WEAKEST_POSITIONS);
+        if !weakest.is_empty() {
+            write!(out, "\nWeakest positions:")?;
+            for (loc, stats) in weakest {
+                write!(
+                    out,
+                    "\n  {} on string {}, fret {}: {}/{} correct",
+                    stats.note, loc.string_idx, loc.fret_idx, stats.n_correct, stats.n_attempts
+                )?;
+            }
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(string_idx: usize, fret_idx: usize, correct: bool) -> TargetResult {
+        TargetResult {
+            note: String::from("C4"),
+            loc: FretLoc {
+                string_idx,
+                fret_idx,
+            },
+            correct,
+            response_time: Duration::from_secs(1),
+        }
+    }
+
+    #[test]
+    fn test_session_summary() {
+        let mut summary = SessionSummary::default();
+        let mut score = Score::default();
+        for res in [
+            result(5, 3, false),
+            result(5, 3, true),
+            result(2, 1, false),
+            result(1, 0, true),
+        ]
+        .iter()
+        {
+            summary.record(res);
+            score.record(res.correct, res.response_time);
+        }
+        let expected = "Session time:   01:05\n\
+                        Targets:        4\n\
+                        Correct:        2 (50%)\n\
+                        Best streak:    1\n\
+                        Avg. response:  1.0s\n\
+                        Weakest positions:\n  \
+                        C4 on string 2, fret 1: 0/1 correct\n  \
+                        C4 on string 5, fret 3: 1/2 correct";
+        assert_eq!(
+            expected,
+            summary.format(&score, Duration::from_secs(65)).unwrap()
+        );
+
+        assert_eq!(
+            vec![(1, 1.0), (2, 0.0), (5, 0.5)],
+            summary.string_accuracy()
+        );
+
+        let mut error_rates = summary.error_rates();
+        error_rates.sort_by_key(|(loc, _)| loc.string_idx);
+        let error_rates: Vec<f64> = error_rates.into_iter().map(|(_, rate)| rate).collect();
+        assert_eq!(vec![0.0, 1.0, 0.5], error_rates);
+        assert!(summary
+            .response_times()
+            .iter()
+            .all(|(_, time)| *time == 1.0));
+
+        let markers = summary.markers();
+        assert_eq!(3, markers.len());
+        assert_eq!(MarkerKind::Wrong, markers[0].kind);
+        assert_eq!(1, markers[0].loc.fret_idx);
+        assert_eq!(MarkerKind::Completed, markers[2].kind);
+    }
+}
@@ -0,0 +1,74 @@
+/// Characters used for increasing bar heights.
+pub const BAR_CHARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Reduces `spectrum` to `width` bins covering the frequencies up to
+/// `max_freq`, keeping the largest magnitude of each bin.
+pub fn downsample_spectrum(
+    spectrum: &[f64],
+    delta_f: f64,
+    max_freq: f64,
+    width: usize,
+) -> Vec<f64> {
+    let n_used = ((max_freq / delta_f).ceil() as usize).min(spectrum.len());
+    if width == 0 || n_used == 0 {
+        return Vec::new();
+    }
+    (0..width)
+        .map(|i| {
+            let beg = i * n_used / width;
+            let end = ((i + 1) * n_used / width).max(beg + 1).min(n_used);
+            spectrum[beg..end].iter().cloned().fold(0.0, f64::max)
+        })
+        .collect()
+}
+
+/// Draws one bar character per value, scaled so that `max_value` and above
+/// give a full bar.
+pub fn draw_sparkline(values: &[f64], max_value: f64) -> String {
+    let n_levels = BAR_CHARS.len();
+    values
+        .iter()
+        .map(|val| {
+            let level = (val / max_value * n_levels as f64) as usize;
+            BAR_CHARS[level.min(n_levels - 1)]
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_downsample_spectrum_empty() {
+        assert_eq!(Vec::<f64>::new(), downsample_spectrum(&[], 1.0, 10.0, 4));
+        assert_eq!(
+            Vec::<f64>::new(),
+            downsample_spectrum(&[1.0, 2.0], 1.0, 10.0, 0)
+        );
+    }
+
+    #[test]
+    fn test_downsample_spectrum() {
+        let spectrum = vec![1.0, 5.0, 2.0, 0.0, 3.0, 4.0, 100.0, 100.0];
+        assert_eq!(
+            vec![5.0, 2.0, 4.0],
+            downsample_spectrum(&spectrum, 1.0, 6.0, 3)
+        );
+    }
+
+    #[test]
+    fn test_downsample_spectrum_wider_than_spectrum() {
+        let spectrum = vec![1.0, 2.0];
+        assert_eq!(
+            vec![1.0, 1.0, 2.0, 2.0],
+            downsample_spectrum(&spectrum, 1.0, 10.0, 4)
+        );
+    }
+
+    #[test]
+    fn test_draw_sparkline() {
+        assert_eq!("", draw_sparkline(&[], 1.0));
+        assert_eq!("▁▅█▁█", draw_sparkline(&[0.0, 0.5, 1.0, -1.0, 3.0], 1.0));
+    }
+}
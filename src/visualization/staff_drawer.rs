@@ -0,0 +1,161 @@
+use crate::core::{Note, NoteName};
+
+/// Staff positions, in diatonic steps, of the bottom and top lines of the
+/// treble clef staff (E4 and F5).
+pub const BOTTOM_LINE: i32 = 4 * 7 + 2;
+pub const TOP_LINE: i32 = 5 * 7 + 3;
+/// Staff position of the line the G clef is drawn on (G4).
+pub const CLEF_LINE: i32 = 4 * 7 + 4;
+/// Letters of the key signature sharps in the order they are added
+/// (F, C, G, D, A, E, B) and the staff positions they are drawn at.
+const KEY_SHARP_LETTERS: [i32; 7] = [3, 0, 4, 1, 5, 2, 6];
+pub const KEY_SHARP_STEPS: [i32; 7] = [38, 35, 39, 36, 33, 37, 34];
+
+/// Accidental drawn before a note head.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Accidental {
+    Sharp,
+    Natural,
+}
+
+/// Letter of the note (0 for C up to 6 for B) and whether it is sharp.
+fn spelling(name: NoteName) -> (i32, bool) {
+    match name {
+        NoteName::C => (0, false),
+        NoteName::CSharp => (0, true),
+        NoteName::D => (1, false),
+        NoteName::DSharp => (1, true),
+        NoteName::E => (2, false),
+        NoteName::F => (3, false),
+        NoteName::FSharp => (3, true),
+        NoteName::G => (4, false),
+        NoteName::GSharp => (4, true),
+        NoteName::A => (5, false),
+        NoteName::ASharp => (5, true),
+        NoteName::B => (6, false),
+    }
+}
+
+/// Staff position of `note` in diatonic steps and the accidental it needs
+/// with `key_sharps` sharps in the key signature. Guitar music is written an
+/// octave above the sounding pitch, so the note is placed one octave higher.
+pub fn staff_placement(note: &Note, key_sharps: usize) -> (i32, Option<Accidental>) {
+    let key_sharps = key_sharps.min(KEY_SHARP_LETTERS.len());
+    let (letter, is_sharp) = spelling(note.name);
+    let note_step = (note.octave + 1) * 7 + letter;
+    let is_sharp_in_key = KEY_SHARP_LETTERS[..key_sharps].contains(&letter);
+    let accidental = match (is_sharp, is_sharp_in_key) {
+        (true, false) => Some(Accidental::Sharp),
+        (false, true) => Some(Accidental::Natural),
+        _ => None,
+    };
+    (note_step, accidental)
+}
+
+/// Draws `note` on a treble clef staff with `key_sharps` sharps in the key
+/// signature, placed as in `staff_placement`. Accidentals are drawn as `#`
+/// and naturals as `n`, and ledger lines are added for notes outside of the
+/// staff.
+pub fn draw_staff(note: &Note, key_sharps: usize) -> String {
+    let key_sharps = key_sharps.min(KEY_SHARP_LETTERS.len());
+    let (note_step, accidental) = staff_placement(note, key_sharps);
+    let accidental = accidental.map(|accidental| match accidental {
+        Accidental::Sharp => '#',
+        Accidental::Natural => 'n',
+    });
+    let top = note_step.max(TOP_LINE);
+    let bottom = note_step.min(BOTTOM_LINE);
+    (bottom..=top)
+        .rev()
+        .map(|step| {
+            let is_even = (step - BOTTOM_LINE) % 2 == 0;
+            let is_staff_line = is_even && (BOTTOM_LINE..=TOP_LINE).contains(&step);
+            let is_ledger_line = is_even
+                && ((step > TOP_LINE && step <= note_step)
+                    || (step < BOTTOM_LINE && step >= note_step));
+            let fill = if is_staff_line { '-' } else { ' ' };
+            let near_note = if is_staff_line || is_ledger_line {
+                '-'
+            } else {
+                ' '
+            };
+            let mut row = vec![fill, if step == CLEF_LINE { 'G' } else { fill }, fill];
+            for &sharp_step in KEY_SHARP_STEPS[..key_sharps].iter() {
+                row.push(fill);
+                row.push(if step == sharp_step { '#' } else { fill });
+            }
+            row.extend(&[fill, fill, near_note]);
+            if step == note_step {
+                row.push(accidental.unwrap_or(near_note));
+                row.push('o');
+            } else {
+                row.extend(&[near_note, near_note]);
+            }
+            row.extend(&[near_note, fill, fill]);
+            row.into_iter().collect::<String>().trim_end().to_string()
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(name: NoteName, octave: i32) -> Note {
+        Note {
+            name,
+            octave,
+            frequency: f64::NAN,
+        }
+    }
+
+    #[test]
+    fn test_staff_placement() {
+        // Open A sounds as A2 and is written as A3, below the staff.
+        assert_eq!((3 * 7 + 5, None), staff_placement(&note(NoteName::A, 2), 0));
+        assert_eq!(
+            (5 * 7 + 3, Some(Accidental::Sharp)),
+            staff_placement(&note(NoteName::FSharp, 4), 0)
+        );
+        assert_eq!(
+            (5 * 7 + 3, Some(Accidental::Natural)),
+            staff_placement(&note(NoteName::F, 4), 1)
+        );
+    }
+
+    #[test]
+    fn test_draw_staff() {
+        // Sounds as G3, written as G4 on the second line.
+        let expected = "-----------\n\
+                        \n\
+                        -----------\n\
+                        \n\
+                        -----------\n\
+                        \n\
+                        -G-----o---\n\
+                        \n\
+                        -----------";
+        assert_eq!(expected, draw_staff(&note(NoteName::G, 3), 0));
+    }
+
+    #[test]
+    fn test_draw_staff_ledger_lines_and_key_signature() {
+        // Low E is written as E3, hanging below three ledger lines. With one
+        // sharp in the key, F♯ needs no accidental but F needs a natural.
+        let staff = draw_staff(&note(NoteName::E, 2), 1);
+        let rows: Vec<&str> = staff.lines().collect();
+        assert_eq!(16, rows.len());
+        assert_eq!("----#--------", rows[0]);
+        assert_eq!("       ----", rows[10]);
+        assert_eq!("", rows[11]);
+        assert_eq!("       ----", rows[14]);
+        assert_eq!("         o", rows[15]);
+
+        let staff = draw_staff(&note(NoteName::F, 3), 1);
+        assert!(staff.lines().any(|row| row == "        no"));
+        let staff = draw_staff(&note(NoteName::FSharp, 3), 1);
+        assert!(staff.lines().any(|row| row == "         o"));
+        assert!(!staff.contains('n'));
+    }
+}
@@ -0,0 +1,97 @@
+use crate::core::{FretLoc, StringRange, Tuning};
+
+/// Draws a sequence of targets as a tablature line, one row per string with
+/// the fret numbers of the targets in playing order, e.g.
+///
+/// ```text
+/// A|--3--------|
+/// E|-------12--|
+/// ```
+pub fn draw_tab(locs: &[FretLoc], string_range: &StringRange, tuning: &Tuning) -> String {
+    let col_width = locs
+        .iter()
+        .map(|loc| loc.fret_idx.to_string().len())
+        .max()
+        .unwrap_or(1);
+    let labels: Vec<String> = string_range
+        .r()
+        .map(|i| match tuning.note(i) {
+            Some(note) => note.name.to_string(),
+            None => i.to_string(),
+        })
+        .collect();
+    let label_width = labels.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+    string_range
+        .r()
+        .zip(labels.iter())
+        .map(|(string_idx, label)| {
+            let mut line = format!("{:<width$}|", label, width = label_width);
+            for loc in locs.iter() {
+                line.push_str("--");
+                if loc.string_idx == string_idx {
+                    line.push_str(&format!("{:-<width$}", loc.fret_idx, width = col_width));
+                } else {
+                    line.push_str(&"-".repeat(col_width));
+                }
+            }
+            line.push_str("--|");
+            line
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{Note, NoteName, NoteRegistry, TuningSpecification};
+
+    #[test]
+    fn test_draw_tab() {
+        let registry = NoteRegistry::from_notes(vec![
+            Note {
+                octave: 2,
+                name: NoteName::E,
+                frequency: 82.41,
+            },
+            Note {
+                octave: 2,
+                name: NoteName::A,
+                frequency: 110.0,
+            },
+        ])
+        .unwrap();
+        let tuning = Tuning::from_specification(
+            &[
+                TuningSpecification {
+                    string: 1,
+                    octave: 2,
+                    name: NoteName::A,
+                },
+                TuningSpecification {
+                    string: 2,
+                    octave: 2,
+                    name: NoteName::E,
+                },
+            ],
+            &registry,
+        )
+        .unwrap();
+        let locs = vec![
+            FretLoc {
+                string_idx: 1,
+                fret_idx: 3,
+            },
+            FretLoc {
+                string_idx: 2,
+                fret_idx: 12,
+            },
+        ];
+        let expected = "A|--3-------|\n\
+                        E|------12--|";
+        assert_eq!(
+            expected,
+            draw_tab(&locs, &StringRange::new(1, 3).unwrap(), &tuning)
+        );
+    }
+}
@@ -0,0 +1,163 @@
+use crate::core::{ConsoleCfg, FretLoc, HintMode, PromptStyle};
+use crate::game::GameState;
+use crate::visualization::status_text::{format_timer, TargetResult};
+use std::time::{Duration, Instant};
+
+/// Change of the target seen in a new game state.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TargetChange {
+    /// The first target of the session.
+    First,
+    /// The next target, after the previous one finished with the result.
+    Next(TargetResult),
+}
+
+/// Latest game state with the targets, hint and marker animation timing
+/// shared by the terminal frontends.
+pub struct TargetPrompt {
+    game_state: Option<GameState>,
+    previous_target: Option<FretLoc>,
+    curr_target_start: Instant,
+    session_start: Option<Instant>,
+    animation_start: Instant,
+    marker_frame_duration: Duration,
+    prompt: PromptStyle,
+    hint_mode: HintMode,
+    hint_delay: Duration,
+}
+
+impl TargetPrompt {
+    pub fn new(config: &ConsoleCfg) -> TargetPrompt {
+        TargetPrompt {
+            game_state: None,
+            previous_target: None,
+            curr_target_start: Instant::now(),
+            session_start: None,
+            animation_start: Instant::now(),
+            marker_frame_duration: Duration::from_secs_f64(config.marker_frame_duration),
+            prompt: config.prompt,
+            hint_mode: config.hint_mode,
+            hint_delay: Duration::from_secs_f64(config.hint_delay),
+        }
+    }
+
+    /// Takes the next game state, returning how the target changed if it did.
+    pub fn update(&mut self, game_state: GameState) -> Option<TargetChange> {
+        self.session_start.get_or_insert_with(Instant::now);
+        let change = match self.game_state.take() {
+            None => Some(TargetChange::First),
+            Some(last_state) if last_state.target_loc != game_state.target_loc => {
+                let result = TargetResult::between(&last_state, &game_state);
+                self.previous_target = Some(last_state.target_loc);
+                Some(TargetChange::Next(result))
+            }
+            Some(_) => None,
+        };
+        if change.is_some() {
+            self.curr_target_start = Instant::now();
+        }
+        self.game_state = Some(game_state);
+        change
+    }
+
+    pub fn game_state(&self) -> Option<&GameState> {
+        self.game_state.as_ref()
+    }
+
+    pub fn prompt(&self) -> PromptStyle {
+        self.prompt
+    }
+
+    pub fn previous_target(&self) -> Option<&FretLoc> {
+        self.previous_target.as_ref()
+    }
+
+    pub fn curr_target(&self) -> Option<&FretLoc> {
+        self.game_state.as_ref().map(|state| &state.target_loc)
+    }
+
+    /// Frame of the marker animation, advanced every marker frame duration.
+    pub fn animation_frame(&self) -> usize {
+        if self.marker_frame_duration.as_secs_f64() > 0.0 {
+            (self.animation_start.elapsed().as_secs_f64()
+                / self.marker_frame_duration.as_secs_f64()) as usize
+        } else {
+            0
+        }
+    }
+
+    /// Names the target note unless it is shown on the staff.
+    pub fn target_text(&self, game_state: &GameState) -> String {
+        match self.prompt {
+            PromptStyle::Fret => game_state.target_note.name_octave(),
+            PromptStyle::Staff => String::from("the note on the staff"),
+        }
+    }
+
+    /// Whether the current target is marked instead of the previous one.
+    pub fn is_hint_shown(&self) -> bool {
+        self.hint_mode == HintMode::Target && self.curr_target_start.elapsed() >= self.hint_delay
+    }
+
+    /// Remaining time of the target and the session time in timed mode.
+    pub fn timer_text(&self) -> Option<String> {
+        let limit = self.game_state.as_ref()?.time_limit?;
+        let remaining = limit.saturating_sub(self.curr_target_start.elapsed());
+        Some(format_timer(remaining, self.session_elapsed()))
+    }
+
+    /// Time since the first game state, or zero before it.
+    pub fn session_elapsed(&self) -> Duration {
+        self.session_start
+            .map_or(Duration::from_secs(0), |start| start.elapsed())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Score;
+
+    fn game_state(fret_idx: usize, n_correct: usize) -> GameState {
+        GameState {
+            score: Score {
+                n_completed: n_correct,
+                n_correct,
+                ..Score::default()
+            },
+            ..GameState::fixture(FretLoc {
+                string_idx: 2,
+                fret_idx,
+            })
+        }
+    }
+
+    #[test]
+    fn test_target_changes() {
+        let mut targets = TargetPrompt {
+            game_state: None,
+            previous_target: None,
+            curr_target_start: Instant::now(),
+            session_start: None,
+            animation_start: Instant::now(),
+            marker_frame_duration: Duration::from_secs(0),
+            prompt: PromptStyle::Fret,
+            hint_mode: HintMode::Target,
+            hint_delay: Duration::from_secs(0),
+        };
+        assert_eq!(Some(TargetChange::First), targets.update(game_state(1, 0)));
+        assert_eq!(None, targets.update(game_state(1, 0)));
+        match targets.update(game_state(3, 1)) {
+            Some(TargetChange::Next(result)) => {
+                assert!(result.correct);
+                assert_eq!(1, result.loc.fret_idx);
+            }
+            change => panic!("Unexpected target change {:?}", change),
+        }
+        assert_eq!(Some(1), targets.previous_target().map(|loc| loc.fret_idx));
+        assert_eq!(Some(3), targets.curr_target().map(|loc| loc.fret_idx));
+        assert!(targets.is_hint_shown());
+        assert_eq!(0, targets.animation_frame());
+        assert_eq!(None, targets.timer_text());
+    }
+}
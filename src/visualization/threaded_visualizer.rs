@@ -0,0 +1,310 @@
+use crate::event_bus::Subscription;
+use crate::metrics::Metrics;
+use crate::visualization::Visualizer;
+use log::*;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Clears the flag when dropped, so that the flag is cleared even if the
+/// render thread panics.
+struct ClearOnDrop(Arc<AtomicBool>);
+
+impl Drop for ClearOnDrop {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::SeqCst);
+    }
+}
+
+/// Frame schedule of a render thread. Frames are kept on a fixed schedule
+/// so that the time spent drawing doesn't lower the frame rate, and a late
+/// frame restarts the schedule instead of being followed by a burst of
+/// frames.
+struct FrameScheduler {
+    period: Duration,
+    next_frame: Instant,
+}
+
+impl FrameScheduler {
+    fn new(fps: f64, now: Instant) -> FrameScheduler {
+        FrameScheduler {
+            period: Duration::from_secs_f64(1.0 / fps),
+            next_frame: now,
+        }
+    }
+
+    /// Time to wait until the next frame after a frame that ended at `now`.
+    fn wait_after_frame(&mut self, now: Instant) -> Duration {
+        self.next_frame += self.period;
+        if self.next_frame > now {
+            self.next_frame - now
+        } else {
+            self.next_frame = now;
+            Duration::from_secs(0)
+        }
+    }
+
+    /// Schedules the frames from `now` on, for a frame drawn out of schedule.
+    fn restart(&mut self, now: Instant) {
+        self.next_frame = now;
+    }
+}
+
+/// Waits `timeout` or until a message arrives on `wakeup`, in which case the
+/// queued messages are dropped so that a burst of them wakes up only once.
+/// Returns whether it was woken up.
+fn wait_for_wakeup<W>(wakeup: &mut Option<Subscription<W>>, timeout: Duration) -> bool {
+    let result = match wakeup {
+        Some(rx) => rx
+            .recv_timeout(timeout)
+            .map(|_| rx.try_iter().for_each(drop)),
+        None => Err(RecvTimeoutError::Disconnected),
+    };
+    match result {
+        Ok(()) => true,
+        Err(RecvTimeoutError::Timeout) => false,
+        Err(RecvTimeoutError::Disconnected) => {
+            *wakeup = None;
+            thread::sleep(timeout);
+            false
+        }
+    }
+}
+
+/// Runs a visualizer on its own thread at its own frame rate, so that a slow
+/// visualizer doesn't hold back the others. The visualizer is created on that
+/// thread since windows can't be moved between threads.
+pub struct ThreadedVisualizer {
+    open: Arc<AtomicBool>,
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl ThreadedVisualizer {
+    /// Draws the visualizer returned by `create` `fps` times a second and
+    /// records the time every frame takes in `metrics`.
+    pub fn spawn<V, F>(fps: f64, metrics: Arc<Metrics>, create: F) -> ThreadedVisualizer
+    where
+        V: Visualizer,
+        F: FnOnce() -> V + Send + 'static,
+    {
+        Self::spawn_thread(fps, None::<Subscription<()>>, metrics, create)
+    }
+
+    /// Like [`spawn`](Self::spawn), but also draws a frame as soon as a
+    /// message arrives on `wakeup`, e.g. to show a new target right away
+    /// instead of at the next frame when the frame rate is low.
+    pub fn spawn_with_wakeup<V, F, W>(
+        fps: f64,
+        wakeup: Subscription<W>,
+        metrics: Arc<Metrics>,
+        create: F,
+    ) -> ThreadedVisualizer
+    where
+        V: Visualizer,
+        F: FnOnce() -> V + Send + 'static,
+        W: Send + 'static,
+    {
+        Self::spawn_thread(fps, Some(wakeup), metrics, create)
+    }
+
+    #[cfg(feature = "gui")]
+    /// Visualizer whose toolkit drives its frames itself, e.g. the event loop
+    /// of a GUI. `run` is not started here but by [`EventLoop::run`] on the
+    /// thread that calls it, since some toolkits only run on the main thread.
+    /// `run` is given the flag that is set once the visualizer has to close,
+    /// and returns when it is closed.
+    pub fn event_loop<F>(run: F) -> (ThreadedVisualizer, EventLoop)
+    where
+        F: FnOnce(Arc<AtomicBool>) + Send + 'static,
+    {
+        let open = Arc::new(AtomicBool::new(true));
+        let stop = Arc::new(AtomicBool::new(false));
+        let event_loop = EventLoop {
+            open: Arc::clone(&open),
+            stop: Arc::clone(&stop),
+            run: Box::new(run),
+        };
+        let visualizer = ThreadedVisualizer {
+            open,
+            stop,
+            handle: None,
+        };
+        (visualizer, event_loop)
+    }
+
+    fn spawn_thread<V, F, W>(
+        fps: f64,
+        mut wakeup: Option<Subscription<W>>,
+        metrics: Arc<Metrics>,
+        create: F,
+    ) -> ThreadedVisualizer
+    where
+        V: Visualizer,
+        F: FnOnce() -> V + Send + 'static,
+        W: Send + 'static,
+    {
+        let open = Arc::new(AtomicBool::new(true));
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_open = ClearOnDrop(Arc::clone(&open));
+        let thread_stop = Arc::clone(&stop);
+        let handle = thread::spawn(move || {
+            let _open = thread_open;
+            let mut visualizer = create();
+            let mut scheduler = FrameScheduler::new(fps, Instant::now());
+            while visualizer.is_open() && !thread_stop.load(Ordering::SeqCst) {
+                let frame_start = Instant::now();
+                visualizer.draw();
+                metrics.record_frame(frame_start.elapsed());
+                let wait = scheduler.wait_after_frame(Instant::now());
+                if wait_for_wakeup(&mut wakeup, wait) {
+                    scheduler.restart(Instant::now());
+                }
+            }
+            visualizer.finish();
+        });
+        ThreadedVisualizer {
+            open,
+            stop,
+            handle: Some(handle),
+        }
+    }
+}
+
+/// Event loop of a visualizer created by [`ThreadedVisualizer::event_loop`].
+/// The visualizer stays open if it is dropped without being run.
+#[cfg(feature = "gui")]
+pub struct EventLoop {
+    open: Arc<AtomicBool>,
+    stop: Arc<AtomicBool>,
+    run: Box<dyn FnOnce(Arc<AtomicBool>) + Send>,
+}
+
+#[cfg(feature = "gui")]
+impl EventLoop {
+    /// Runs the event loop on the current thread until the visualizer is
+    /// closed or finished.
+    pub fn run(self) {
+        let _open = ClearOnDrop(self.open);
+        (self.run)(self.stop);
+    }
+}
+
+impl Visualizer for ThreadedVisualizer {
+    /// Frames are drawn by the render thread.
+    fn draw(&mut self) {}
+
+    fn is_open(&self) -> bool {
+        self.open.load(Ordering::SeqCst)
+    }
+
+    fn finish(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(handle) = self.handle.take() {
+            if handle.join().is_err() {
+                warn!("Visualizer thread panicked");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event_bus::Topic;
+    use std::sync::mpsc;
+
+    struct CountingVisualizer {
+        n_frames: usize,
+        tx: mpsc::Sender<usize>,
+    }
+
+    impl Visualizer for CountingVisualizer {
+        fn draw(&mut self) {
+            self.n_frames += 1;
+        }
+
+        fn is_open(&self) -> bool {
+            self.n_frames < 3
+        }
+
+        fn finish(&mut self) {
+            self.tx.send(self.n_frames).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_runs_until_closed() {
+        let (tx, rx) = mpsc::channel();
+        let metrics = Arc::new(Metrics::default());
+        let mut visualizer = ThreadedVisualizer::spawn(1000.0, metrics.clone(), move || {
+            CountingVisualizer { n_frames: 0, tx }
+        });
+        assert_eq!(3, rx.recv().unwrap());
+        visualizer.finish();
+        assert!(!visualizer.is_open());
+        assert_eq!(3, metrics.snapshot().n_frames);
+    }
+
+    #[cfg(feature = "gui")]
+    #[test]
+    fn test_event_loop_runs_until_stopped() {
+        let (tx, rx) = mpsc::channel();
+        let (mut visualizer, event_loop) = ThreadedVisualizer::event_loop(move |stop| {
+            tx.send(()).unwrap();
+            while !stop.load(Ordering::SeqCst) {
+                thread::sleep(Duration::from_millis(1));
+            }
+        });
+        let handle = thread::spawn(move || event_loop.run());
+        rx.recv().unwrap();
+        assert!(visualizer.is_open());
+        visualizer.finish();
+        handle.join().unwrap();
+        assert!(!visualizer.is_open());
+    }
+
+    #[test]
+    fn test_scheduler_accounts_for_draw_time() {
+        let start = Instant::now();
+        let mut scheduler = FrameScheduler::new(10.0, start);
+        let wait = scheduler.wait_after_frame(start + Duration::from_millis(30));
+        assert_eq!(Duration::from_millis(70), wait);
+    }
+
+    #[test]
+    fn test_scheduler_coalesces_missed_frames() {
+        let start = Instant::now();
+        let mut scheduler = FrameScheduler::new(10.0, start);
+        let late = start + Duration::from_millis(350);
+        assert_eq!(Duration::from_secs(0), scheduler.wait_after_frame(late));
+        let wait = scheduler.wait_after_frame(late + Duration::from_millis(20));
+        assert_eq!(Duration::from_millis(80), wait);
+    }
+
+    #[test]
+    fn test_wakeup_draws_right_away() {
+        let (tx, rx) = mpsc::channel();
+        let topic = Topic::default();
+        let wakeup = topic.subscribe();
+        let mut visualizer = ThreadedVisualizer::spawn_with_wakeup(
+            0.1,
+            wakeup,
+            Arc::new(Metrics::default()),
+            move || CountingVisualizer { n_frames: 0, tx },
+        );
+        // Without the wakeups the third frame would be drawn after 20s.
+        let start = Instant::now();
+        let n_frames = loop {
+            topic.publish(());
+            if let Ok(n_frames) = rx.recv_timeout(Duration::from_millis(10)) {
+                break n_frames;
+            }
+            assert!(start.elapsed() < Duration::from_secs(5));
+        };
+        assert_eq!(3, n_frames);
+        visualizer.finish();
+    }
+}
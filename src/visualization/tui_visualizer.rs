@@ -0,0 +1,351 @@
+use crate::audio_analysis::AnalysisResult;
+use crate::core::{ConsoleCfg, FretRange, PromptStyle, StringRange, Tuning};
+use crate::event_bus::Subscription;
+use crate::game::GameState;
+use crate::visualization::ascii::to_ascii;
+use crate::visualization::bell::Bell;
+use crate::visualization::fretboard_drawer::{FretboardDrawer, Marker, MarkerKind};
+use crate::visualization::session_summary::SessionSummary;
+use crate::visualization::sparkline::draw_sparkline;
+use crate::visualization::staff_drawer::draw_staff;
+use crate::visualization::status_text::{
+    draw_tuner_bar, format_detection, format_score, format_target_result,
+};
+use crate::visualization::tab_drawer::draw_tab;
+use crate::visualization::target_prompt::{TargetChange, TargetPrompt};
+use crate::visualization::Visualizer;
+use console::Style;
+use ratatui::backend::CrosstermBackend;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::crossterm::execute;
+use ratatui::crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use ratatui::layout::{Constraint, Direction, Layout};
+use ratatui::symbols::border;
+use ratatui::widgets::{Block, Borders, List, ListItem, Paragraph};
+use ratatui::Terminal;
+use std::collections::VecDeque;
+use std::error::Error;
+use std::io::{self, Stdout};
+use std::time::Duration;
+
+/// Number of messages kept in the log panel.
+const LOG_CAPACITY: usize = 100;
+
+/// Panel borders drawn with ASCII characters only.
+const ASCII_BORDER: border::Set = border::Set {
+    top_left: "+",
+    top_right: "+",
+    bottom_left: "+",
+    bottom_right: "+",
+    vertical_left: "|",
+    vertical_right: "|",
+    horizontal_top: "-",
+    horizontal_bottom: "-",
+};
+
+/// Full screen terminal frontend with separate panels for the fretboard, the
+/// current target, the live detection, the session statistics and a log of
+/// game events. Pressing `q`, `Esc` or `Ctrl-C` closes it.
+pub struct TuiVisualizer {
+    rx: Subscription<GameState>,
+    analysis_rx: Subscription<AnalysisResult>,
+    spectrum_rx: Subscription<Vec<f64>>,
+    fret_range: FretRange,
+    string_range: StringRange,
+    terminal: Terminal<CrosstermBackend<Stdout>>,
+    targets: TargetPrompt,
+    staff_key_sharps: usize,
+    detection: Option<AnalysisResult>,
+    spectrum: Vec<f64>,
+    spectrum_max_magnitude: f64,
+    tuner_half_width: usize,
+    log: VecDeque<String>,
+    is_open: bool,
+    ascii: bool,
+    bell: Bell,
+    summary: SessionSummary,
+    /// Whether the terminal has already been switched back to normal mode.
+    is_restored: bool,
+    fb_drawer: FretboardDrawer,
+    tuning: Tuning,
+}
+
+impl TuiVisualizer {
+    pub fn new(
+        rx: Subscription<GameState>,
+        analysis_rx: Subscription<AnalysisResult>,
+        spectrum_rx: Subscription<Vec<f64>>,
+        fret_range: FretRange,
+        string_range: StringRange,
+        config: ConsoleCfg,
+        tuning: Tuning,
+    ) -> io::Result<TuiVisualizer> {
+        enable_raw_mode()?;
+        let mut stdout = io::stdout();
+        execute!(stdout, EnterAlternateScreen)?;
+        let terminal = Terminal::new(CrosstermBackend::new(stdout))?;
+        // ANSI styles of the console frontend would garble the TUI buffer.
+        let mut fb_drawer = FretboardDrawer::new(&config, tuning.clone());
+        fb_drawer.disable_styles();
+        Ok(TuiVisualizer {
+            rx,
+            analysis_rx,
+            spectrum_rx,
+            fret_range,
+            string_range,
+            terminal,
+            targets: TargetPrompt::new(&config),
+            staff_key_sharps: config.staff_key_sharps,
+            detection: None,
+            spectrum: Vec::new(),
+            spectrum_max_magnitude: config.spectrum_max_magnitude,
+            tuner_half_width: config.tuner_half_width,
+            log: VecDeque::with_capacity(LOG_CAPACITY),
+            is_open: true,
+            ascii: config.ascii,
+            bell: Bell::new(&config),
+            summary: SessionSummary::default(),
+            is_restored: false,
+            fb_drawer,
+            tuning,
+        })
+    }
+
+    fn push_log(&mut self, msg: String) {
+        if self.log.len() == LOG_CAPACITY {
+            self.log.pop_front();
+        }
+        self.log.push_back(msg);
+    }
+
+    fn handle_input(&mut self) -> io::Result<()> {
+        while event::poll(Duration::from_secs(0))? {
+            if let Event::Key(key) = event::read()? {
+                if key.kind != KeyEventKind::Press {
+                    continue;
+                }
+                let is_ctrl_c =
+                    key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL);
+                if key.code == KeyCode::Char('q') || key.code == KeyCode::Esc || is_ctrl_c {
+                    self.is_open = false;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn receive(&mut self) -> io::Result<()> {
+        let states: Vec<GameState> = self.rx.try_iter().collect();
+        for game_state in states {
+            let new_target = format!(
+                "New target: {} on string {}",
+                game_state.target_note.name_octave(),
+                game_state.target_loc.string_idx
+            );
+            match self.targets.update(game_state) {
+                Some(TargetChange::Next(result)) => {
+                    self.push_log(format_target_result(&result));
+                    self.summary.record(&result);
+                    self.bell.ring(&result, self.terminal.backend_mut())?;
+                    self.push_log(new_target);
+                }
+                Some(TargetChange::First) => self.push_log(new_target),
+                None => {}
+            }
+        }
+        if let Some(analysis) = self.analysis_rx.try_iter().last() {
+            self.detection = Some(analysis);
+        }
+        if let Some(spectrum) = self.spectrum_rx.try_iter().last() {
+            self.spectrum = spectrum;
+        }
+        Ok(())
+    }
+
+    fn render(&mut self) -> io::Result<()> {
+        let (fb_title, shown_loc) = if self.targets.is_hint_shown() {
+            ("Current target", self.targets.curr_target())
+        } else {
+            ("Previously played note", self.targets.previous_target())
+        };
+        let markers: Vec<Marker> = shown_loc
+            .into_iter()
+            .map(|loc| Marker::new(loc.clone(), MarkerKind::Target))
+            .collect();
+        let animation_frame = self.targets.animation_frame();
+        self.fb_drawer.set_animation_frame(animation_frame);
+        // The fretboard panel spans the whole width minus its borders.
+        let max_width = self.terminal.size()?.width.saturating_sub(2) as usize;
+        let fretboard = self
+            .fb_drawer
+            .draw_fitted(
+                &self.fret_range,
+                &self.string_range,
+                &markers,
+                &Style::new(),
+                max_width,
+            )
+            .map_err(|e| io::Error::other(e.to_string()))?;
+        let mut fretboard = fretboard;
+        let game_state = self.targets.game_state();
+        if let Some(state) = game_state {
+            if !state.upcoming_locs.is_empty() {
+                let tab = draw_tab(&state.upcoming_locs, &self.string_range, &self.tuning);
+                fretboard = format!("{}\n\nUp next:\n{}", fretboard, tab);
+            }
+        }
+        if let (PromptStyle::Staff, Some(state)) = (self.targets.prompt(), game_state) {
+            let staff = draw_staff(&state.target_note, self.staff_key_sharps);
+            fretboard = format!("{}\n\nTarget:\n{}", fretboard, staff);
+        }
+        let fretboard_height = fretboard.lines().count() as u16 + 2;
+        let fb_title = format!("{} | {}", fb_title, self.fb_drawer.tuning_header());
+        let mut target = match game_state {
+            Some(state) => format!(
+                "Play {} on string {}\nDetection count: {}/{}",
+                self.targets.target_text(state),
+                state.target_loc.string_idx,
+                state.curr_detection_count,
+                state.needed_detection_count
+            ),
+            None => String::from("Waiting for the game to start..."),
+        };
+        if let Some(timer) = self.targets.timer_text() {
+            target.push('\n');
+            target.push_str(&timer.replace(" | ", "\n"));
+        }
+        let mut detection = format_detection(self.detection.as_ref());
+        if self.tuner_half_width > 0 {
+            let cents = self.detection.as_ref().and_then(|d| d.cents());
+            detection.push('\n');
+            detection.push_str(&draw_tuner_bar(cents, self.tuner_half_width));
+        }
+        let stats = match game_state {
+            Some(state) => format_score(&state.score).replace(" | ", "\n"),
+            None => String::new(),
+        };
+        let spectrum = draw_sparkline(&self.spectrum, self.spectrum_max_magnitude);
+        let spectrum_height = if self.spectrum.is_empty() { 0 } else { 3 };
+        let ascii = self.ascii;
+        let text = |s: String| if ascii { to_ascii(&s) } else { s };
+        let (fretboard, spectrum, target, detection, stats) = (
+            text(fretboard),
+            text(spectrum),
+            text(target),
+            text(detection),
+            text(stats),
+        );
+        let log_items: Vec<ListItem> = self
+            .log
+            .iter()
+            .rev()
+            .map(|msg| ListItem::new(text(msg.clone())))
+            .collect();
+        let border_set = if ascii { ASCII_BORDER } else { border::PLAIN };
+        let panel = |title| {
+            Block::default()
+                .borders(Borders::ALL)
+                .border_set(border_set)
+                .title(title)
+        };
+
+        self.terminal.draw(|frame| {
+            let rows = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([
+                    Constraint::Length(fretboard_height),
+                    Constraint::Length(spectrum_height),
+                    Constraint::Length(6),
+                    Constraint::Min(3),
+                ])
+                .split(frame.area());
+            let panels = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([
+                    Constraint::Ratio(1, 3),
+                    Constraint::Ratio(1, 3),
+                    Constraint::Ratio(1, 3),
+                ])
+                .split(rows[2]);
+            frame.render_widget(
+                Paragraph::new(fretboard).block(panel(fb_title.as_str())),
+                rows[0],
+            );
+            frame.render_widget(Paragraph::new(spectrum).block(panel("Spectrum")), rows[1]);
+            frame.render_widget(Paragraph::new(target).block(panel("Target")), panels[0]);
+            frame.render_widget(
+                Paragraph::new(detection).block(panel("You are playing")),
+                panels[1],
+            );
+            frame.render_widget(Paragraph::new(stats).block(panel("Stats")), panels[2]);
+            frame.render_widget(
+                List::new(log_items).block(panel("Log (q to quit)")),
+                rows[3],
+            );
+        })?;
+        Ok(())
+    }
+}
+
+impl TuiVisualizer {
+    fn restore_terminal(&mut self) -> io::Result<()> {
+        if self.is_restored {
+            return Ok(());
+        }
+        self.is_restored = true;
+        disable_raw_mode()?;
+        execute!(self.terminal.backend_mut(), LeaveAlternateScreen)?;
+        self.terminal.show_cursor()
+    }
+
+    /// Prints the session summary to the normal screen so that it stays
+    /// visible after the program has exited.
+    fn print_summary(&mut self) -> Result<(), Box<dyn Error>> {
+        let game_state = match self.targets.game_state() {
+            Some(game_state) => game_state,
+            None => return Ok(()),
+        };
+        let fretboard = self.fb_drawer.draw(
+            &self.fret_range,
+            &self.string_range,
+            &self.summary.markers(),
+            &Style::new(),
+        )?;
+        let mut summary = format!(
+            "Session summary\n{}\n{}\n{}",
+            self.fb_drawer.tuning_header(),
+            fretboard,
+            self.summary
+                .format(&game_state.score, self.targets.session_elapsed())?
+        );
+        if self.ascii {
+            summary = to_ascii(&summary);
+        }
+        self.restore_terminal()?;
+        println!("{}", summary);
+        Ok(())
+    }
+}
+
+impl Drop for TuiVisualizer {
+    fn drop(&mut self) {
+        let _ = self.restore_terminal();
+    }
+}
+
+impl Visualizer for TuiVisualizer {
+    fn is_open(&self) -> bool {
+        self.is_open
+    }
+
+    fn draw(&mut self) {
+        self.handle_input().unwrap();
+        self.receive().unwrap();
+        self.render().unwrap();
+    }
+    fn finish(&mut self) {
+        self.print_summary().unwrap();
+    }
+}
@@ -1,4 +1,45 @@
+use crate::audio_analysis::AnalysisResult;
+
+/// Frontend showing the game to the player.
+///
+/// Visualizers receive their data from the topics of the `EventBus` they
+/// subscribe to, e.g. a `Subscription<GameState>` with every state of the
+/// game and a `Subscription<FrameData>` with the analysis of every audio
+/// block. Since the messages queue up until they are read, `draw` should
+/// drain them with `try_iter` on every frame and draw only the latest ones.
+/// The queues are bounded, so a visualizer that falls behind misses the
+/// oldest frames and the intermediate game states. Dropping a subscription
+/// ends it.
+///
+/// Every visualizer is drawn on its own thread at its own frame rate, and the
+/// game stops once any of them is no longer open.
 pub trait Visualizer {
+    /// Draws one frame. Should return quickly, since the time it takes is
+    /// taken from the frame period of the visualizer.
     fn draw(&mut self);
     fn is_open(&self) -> bool;
+    /// Called once after the game has stopped, e.g. to show a summary.
+    fn finish(&mut self) {}
+}
+
+impl<V: Visualizer + ?Sized> Visualizer for Box<V> {
+    fn draw(&mut self) {
+        (**self).draw()
+    }
+
+    fn is_open(&self) -> bool {
+        (**self).is_open()
+    }
+
+    fn finish(&mut self) {
+        (**self).finish()
+    }
+}
+
+/// Analysis of one audio block along with the spectrum it was detected in.
+#[derive(Debug, Clone)]
+pub struct FrameData {
+    /// Magnitudes of the frequency bins, evenly spaced from 0 Hz.
+    pub spectrogram: Vec<f64>,
+    pub analysis: AnalysisResult,
 }
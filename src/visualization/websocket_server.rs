@@ -0,0 +1,306 @@
+use crate::audio_analysis::AnalysisResult;
+use crate::core::WebSocketCfg;
+use crate::event_bus::Subscription;
+use crate::game::GameState;
+use crate::visualization::Visualizer;
+use crate::visualization::{GameEvent, GameEventTracker};
+use log::*;
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+use tungstenite::handshake::derive_accept_key;
+use tungstenite::protocol::{Role, WebSocketConfig};
+use tungstenite::{Message, WebSocket};
+
+/// Page served to plain HTTP requests, which shows the events it receives
+/// over the WebSocket.
+const REMOTE_DISPLAY_PAGE: &str = include_str!("../../resources/remote_display.html");
+/// Largest HTTP request header that is read from a client.
+const MAX_REQUEST_SIZE: usize = 8192;
+/// Clients that don't send their request within this time are dropped.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(1);
+/// Clients with more unsent messages than this are dropped.
+const MAX_WRITE_BUFFER_SIZE: usize = 1 << 20;
+/// How often the frames of the clients are read when there is nothing to
+/// send, e.g. to answer their pings.
+const POLL_PERIOD: Duration = Duration::from_millis(50);
+
+/// Event sent to the client thread, which owns the connections.
+struct Broadcast {
+    json: String,
+    /// Target events are also sent to the clients connecting later.
+    is_target: bool,
+}
+
+/// `Sec-WebSocket-Key` of an HTTP request, or `None` for a plain request.
+fn websocket_key(request: &str) -> Option<&str> {
+    request.lines().find_map(|line| {
+        let mut parts = line.splitn(2, ':');
+        let name = parts.next()?;
+        if name.trim().eq_ignore_ascii_case("sec-websocket-key") {
+            parts.next().map(str::trim)
+        } else {
+            None
+        }
+    })
+}
+
+/// Reads the HTTP request of a new client and either completes the
+/// WebSocket handshake, returning the non-blocking connection, or answers
+/// with the remote display page.
+fn accept_client(mut stream: TcpStream) -> io::Result<Option<WebSocket<TcpStream>>> {
+    stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+    stream.set_write_timeout(Some(HANDSHAKE_TIMEOUT))?;
+    let mut request = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !request.ends_with(b"\r\n\r\n") && request.len() < MAX_REQUEST_SIZE {
+        match stream.read(&mut buf)? {
+            0 => return Ok(None),
+            n_read => request.extend_from_slice(&buf[..n_read]),
+        }
+    }
+    let request = String::from_utf8_lossy(&request);
+    match websocket_key(&request) {
+        Some(key) => {
+            write!(
+                stream,
+                "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\n\
+                 Connection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+                derive_accept_key(key.as_bytes())
+            )?;
+            stream.set_nonblocking(true)?;
+            let config = WebSocketConfig {
+                max_write_buffer_size: MAX_WRITE_BUFFER_SIZE,
+                ..WebSocketConfig::default()
+            };
+            Ok(Some(WebSocket::from_raw_socket(
+                stream,
+                Role::Server,
+                Some(config),
+            )))
+        }
+        None => {
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\n\
+                 Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                REMOTE_DISPLAY_PAGE.len(),
+                REMOTE_DISPLAY_PAGE
+            )?;
+            Ok(None)
+        }
+    }
+}
+
+fn would_block(err: &tungstenite::Error) -> bool {
+    matches!(err, tungstenite::Error::Io(err) if err.kind() == io::ErrorKind::WouldBlock)
+}
+
+/// Queues `json` to be sent to `client`. Returns false if the client has
+/// left or doesn't keep up with the messages.
+fn queue(client: &mut WebSocket<TcpStream>, json: &str) -> bool {
+    match client.write(Message::text(json)) {
+        Ok(()) => true,
+        // The message is queued and sent by a later flush.
+        Err(err) => would_block(&err),
+    }
+}
+
+/// Reads the frames of `client`, which answers its pings and close
+/// handshake, and sends what it can of the queued messages without blocking.
+/// Returns false once the client has left.
+fn poll(client: &mut WebSocket<TcpStream>) -> bool {
+    loop {
+        match client.read() {
+            // The messages of the clients aren't used.
+            Ok(_) => {}
+            Err(err) if would_block(&err) => break,
+            Err(_) => return false,
+        }
+    }
+    match client.flush() {
+        Ok(()) => true,
+        Err(err) => would_block(&err),
+    }
+}
+
+/// Owns the connections and sends them the events until the server is
+/// dropped, so that slow clients never hold up the visualizer.
+fn serve_clients(
+    rx: mpsc::Receiver<Broadcast>,
+    connected_rx: mpsc::Receiver<WebSocket<TcpStream>>,
+) {
+    let mut clients: Vec<WebSocket<TcpStream>> = Vec::new();
+    let mut target_event: Option<String> = None;
+    loop {
+        for mut client in connected_rx.try_iter() {
+            let greeted = match &target_event {
+                Some(target_event) => queue(&mut client, target_event),
+                None => true,
+            };
+            if greeted {
+                clients.push(client);
+            }
+        }
+        match rx.recv_timeout(POLL_PERIOD) {
+            Ok(Broadcast { json, is_target }) => {
+                clients.retain_mut(|client| queue(client, &json));
+                if is_target {
+                    target_event = Some(json);
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return,
+        }
+        clients.retain_mut(poll);
+    }
+}
+
+/// Embedded WebSocket server broadcasting the game events and optionally the
+/// detections as JSON, e.g. to a phone on a music stand. Browsers opening the
+/// server address get a page that shows them.
+pub struct WebSocketServer {
+    rx: Subscription<GameState>,
+    analysis_rx: Subscription<AnalysisResult>,
+    broadcast_tx: mpsc::Sender<Broadcast>,
+    local_addr: SocketAddr,
+    tracker: GameEventTracker,
+    send_detections: bool,
+}
+
+impl WebSocketServer {
+    /// Starts listening on the configured address.
+    pub fn new(
+        rx: Subscription<GameState>,
+        analysis_rx: Subscription<AnalysisResult>,
+        cfg: WebSocketCfg,
+    ) -> io::Result<WebSocketServer> {
+        let listener = TcpListener::bind(&cfg.address)?;
+        let local_addr = listener.local_addr()?;
+        let (connected_tx, connected_rx) = mpsc::channel();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let client = stream.and_then(accept_client);
+                match client {
+                    Ok(Some(client)) => {
+                        if connected_tx.send(client).is_err() {
+                            return;
+                        }
+                    }
+                    Ok(None) => {}
+                    Err(err) => debug!("WebSocket connection failed: {}", err),
+                }
+            }
+        });
+        let (broadcast_tx, broadcast_rx) = mpsc::channel();
+        thread::spawn(move || serve_clients(broadcast_rx, connected_rx));
+        Ok(WebSocketServer {
+            rx,
+            analysis_rx,
+            broadcast_tx,
+            local_addr,
+            tracker: GameEventTracker::default(),
+            send_detections: cfg.send_detections,
+        })
+    }
+
+    /// Address the server listens on, with the port picked by the system if
+    /// port 0 was configured.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Hands `event` to the client thread, which sends it to all clients.
+    fn broadcast(&self, event: &GameEvent) {
+        let broadcast = Broadcast {
+            json: event.to_json(),
+            is_target: matches!(event, GameEvent::Target { .. }),
+        };
+        // The client thread only stops with the server.
+        let _ = self.broadcast_tx.send(broadcast);
+    }
+}
+
+impl Visualizer for WebSocketServer {
+    fn is_open(&self) -> bool {
+        true
+    }
+
+    fn draw(&mut self) {
+        let states: Vec<GameState> = self.rx.try_iter().collect();
+        for state in states {
+            for event in self.tracker.update(state) {
+                self.broadcast(&event);
+            }
+        }
+        let analyses: Vec<AnalysisResult> = self.analysis_rx.try_iter().collect();
+        if self.send_detections {
+            for event in analyses.iter().filter_map(GameEvent::detection) {
+                self.broadcast(&event);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{Note, NoteName};
+    use crate::event_bus::{Overflow, Topic};
+
+    #[test]
+    fn test_websocket_key() {
+        // The example handshake of RFC 6455.
+        let request = "GET /chat HTTP/1.1\r\nHost: server.example.com\r\n\
+                       Upgrade: websocket\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\r\n";
+        assert_eq!(Some("dGhlIHNhbXBsZSBub25jZQ=="), websocket_key(request));
+        assert_eq!(None, websocket_key("GET / HTTP/1.1\r\nHost: a\r\n\r\n"));
+    }
+
+    #[test]
+    fn test_broadcast_and_ping() {
+        let states = Topic::new(4, Overflow::DropOldest);
+        let analysis = Topic::new(4, Overflow::DropOldest);
+        let cfg = WebSocketCfg {
+            address: String::from("127.0.0.1:0"),
+            send_detections: true,
+        };
+        let mut server =
+            WebSocketServer::new(states.subscribe(), analysis.subscribe(), cfg).unwrap();
+        let url = format!("ws://{}/", server.local_addr());
+        let stream = TcpStream::connect(server.local_addr()).unwrap();
+        let (mut client, _) = tungstenite::client(url, stream).unwrap();
+        client
+            .get_mut()
+            .set_read_timeout(Some(Duration::from_millis(20)))
+            .unwrap();
+        let detection = AnalysisResult {
+            frequency: Some(110.0),
+            note: Some(Note {
+                name: NoteName::A,
+                octave: 2,
+                frequency: 110.0,
+            }),
+            confidence: 1.0,
+            peaks: Vec::new(),
+        };
+        // The client is registered by the server threads in the meantime.
+        let received = (0..100).find_map(|_| {
+            analysis.publish(detection.clone());
+            server.draw();
+            match client.read() {
+                Ok(Message::Text(text)) => Some(text),
+                _ => None,
+            }
+        });
+        assert!(received.unwrap().contains("\"type\":\"detection\""));
+        client.send(Message::Ping(vec![1, 2])).unwrap();
+        let pong = (0..100).find_map(|_| match client.read() {
+            Ok(Message::Pong(payload)) => Some(payload),
+            _ => None,
+        });
+        assert_eq!(Some(vec![1, 2]), pong);
+    }
+}
@@ -0,0 +1,42 @@
+import init, { WebTrainer } from "./pkg/libreguitar.js";
+
+const $ = (id) => document.getElementById(id);
+
+async function start() {
+  await init();
+  const stream = await navigator.mediaDevices.getUserMedia({
+    audio: { echoCancellation: false, noiseSuppression: false, autoGainControl: false },
+  });
+  const context = new AudioContext();
+  await context.audioWorklet.addModule("sample_forwarder.js");
+  const trainer = new WebTrainer(context.sampleRate, $("tuning").value);
+  const forwarder = new AudioWorkletNode(context, "sample-forwarder");
+  forwarder.port.onmessage = (msg) => {
+    for (const e of JSON.parse(trainer.pushSamples(msg.data))) {
+      if (e.type === "result") {
+        $("last").textContent = e.note + (e.correct ? " found" : " missed") +
+          " after " + e.response_time.toFixed(1) + "s";
+      }
+    }
+  };
+  context.createMediaStreamSource(stream).connect(forwarder);
+
+  const canvas = $("fretboard");
+  const draw = () => {
+    trainer.draw(canvas);
+    requestAnimationFrame(draw);
+  };
+  requestAnimationFrame(draw);
+  document.addEventListener("keydown", (e) => {
+    if (e.key === " ") {
+      trainer.togglePause();
+    } else if (e.key === "n") {
+      trainer.skip();
+    }
+  });
+  $("setup").hidden = true;
+}
+
+$("start").onclick = () => start().catch((e) => {
+  $("last").textContent = "Could not start: " + e;
+});
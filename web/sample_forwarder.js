@@ -0,0 +1,29 @@
+// AudioWorklet processor posting the microphone samples to the main thread in
+// blocks of 512, about as often as a sound card calls the native app.
+const BLOCK_SIZE = 512;
+
+class SampleForwarder extends AudioWorkletProcessor {
+  constructor() {
+    super();
+    this.block = new Float32Array(BLOCK_SIZE);
+    this.length = 0;
+  }
+
+  process(inputs) {
+    const channel = inputs[0][0];
+    if (channel === undefined) {
+      return true;
+    }
+    for (let i = 0; i < channel.length; i++) {
+      this.block[this.length++] = channel[i];
+      if (this.length === BLOCK_SIZE) {
+        this.port.postMessage(this.block);
+        this.block = new Float32Array(BLOCK_SIZE);
+        this.length = 0;
+      }
+    }
+    return true;
+  }
+}
+
+registerProcessor("sample-forwarder", SampleForwarder);
@@ -0,0 +1,135 @@
+use libreguitar_core::audio_analysis::AnalysisResult;
+use libreguitar_core::core::{FretLoc, FretRange, StringRange};
+use libreguitar_core::events::status_text::{format_detection, format_score};
+use libreguitar_core::game::GameState;
+use std::f64::consts::PI;
+use wasm_bindgen::JsValue;
+use web_sys::CanvasRenderingContext2d;
+
+const BOARD_COLOR: &str = "#5c3a1e";
+const FRET_COLOR: &str = "#c0c0c0";
+const STRING_COLOR: &str = "#e8e0c8";
+const INLAY_COLOR: &str = "#e0d8c0";
+const TARGET_COLOR: &str = "#3cb371";
+const UPCOMING_COLOR: &str = "#4682b4";
+const TEXT_COLOR: &str = "#202020";
+/// Frets with a single inlay dot, counted within an octave.
+const SINGLE_INLAY_FRETS: [usize; 4] = [3, 5, 7, 9];
+/// Share of the canvas height taken by the fretboard, the rest holds the
+/// status lines.
+const FRETBOARD_HEIGHT: f64 = 0.7;
+
+/// Draws the game on a canvas with the layout of the GUI fretboard: one
+/// column per fret and one row per string, first string of the range at the
+/// top, with the prompt, the detected note and the score below it.
+pub struct CanvasDrawer {
+    fret_range: FretRange,
+    string_range: StringRange,
+}
+
+impl CanvasDrawer {
+    pub fn new(fret_range: FretRange, string_range: StringRange) -> CanvasDrawer {
+        CanvasDrawer {
+            fret_range,
+            string_range,
+        }
+    }
+
+    pub fn draw(
+        &self,
+        context: &CanvasRenderingContext2d,
+        (width, height): (f64, f64),
+        state: &GameState,
+        detection: Option<&AnalysisResult>,
+    ) -> Result<(), JsValue> {
+        let board_height = height * FRETBOARD_HEIGHT;
+        let fret_width = width / self.fret_range.r().len() as f64;
+        let string_gap = board_height / self.string_range.r().len() as f64;
+        let dot_radius = (string_gap.min(fret_width) / 4.0).max(2.0);
+        let center = |loc: &FretLoc| {
+            (
+                (loc.fret_idx - self.fret_range.r().start) as f64 * fret_width + fret_width / 2.0,
+                (loc.string_idx - self.string_range.r().start) as f64 * string_gap
+                    + string_gap / 2.0,
+            )
+        };
+
+        context.clear_rect(0.0, 0.0, width, height);
+        context.set_fill_style_str(BOARD_COLOR);
+        context.fill_rect(0.0, 0.0, width, board_height);
+        for fret_idx in self.fret_range.r() {
+            let x = (fret_idx - self.fret_range.r().start + 1) as f64 * fret_width;
+            // The nut is drawn thicker than the other frets.
+            let thickness = if fret_idx == 0 { 4.0 } else { 2.0 };
+            context.set_fill_style_str(FRET_COLOR);
+            context.fill_rect(x - thickness / 2.0, 0.0, thickness, board_height);
+            if fret_idx > 0 && SINGLE_INLAY_FRETS.contains(&(fret_idx % 12)) {
+                context.set_fill_style_str(INLAY_COLOR);
+                fill_circle(
+                    context,
+                    (x - fret_width / 2.0, board_height / 2.0),
+                    dot_radius,
+                )?;
+            }
+        }
+        context.set_stroke_style_str(STRING_COLOR);
+        context.set_line_width(2.0);
+        for string_idx in self.string_range.r() {
+            let y =
+                (string_idx - self.string_range.r().start) as f64 * string_gap + string_gap / 2.0;
+            context.begin_path();
+            context.move_to(0.0, y);
+            context.line_to(width, y);
+            context.stroke();
+        }
+        let in_range = |loc: &FretLoc| {
+            self.fret_range.r().contains(&loc.fret_idx)
+                && self.string_range.r().contains(&loc.string_idx)
+        };
+        context.set_fill_style_str(UPCOMING_COLOR);
+        for loc in state.upcoming_locs.iter().filter(|loc| in_range(loc)) {
+            fill_circle(context, center(loc), dot_radius)?;
+        }
+        if in_range(&state.target_loc) {
+            context.set_fill_style_str(TARGET_COLOR);
+            fill_circle(context, center(&state.target_loc), dot_radius * 2.0)?;
+        }
+
+        let line_height = (height - board_height) / 3.0;
+        context.set_fill_style_str(TEXT_COLOR);
+        context.set_font(&format!("{}px sans-serif", (line_height * 0.6).round()));
+        let prompt = if state.paused {
+            String::from("Paused")
+        } else {
+            format!(
+                "Play {} on string {}, fret {} — {}/{}",
+                state.target_note.name_octave(),
+                state.target_loc.string_idx,
+                state.target_loc.fret_idx,
+                state.curr_detection_count,
+                state.needed_detection_count
+            )
+        };
+        let lines = [
+            prompt,
+            format!("Detected: {}", format_detection(detection)),
+            format_score(&state.score),
+        ];
+        for (i, line) in lines.iter().enumerate() {
+            let y = board_height + (i as f64 + 0.75) * line_height;
+            context.fill_text(line, 10.0, y)?;
+        }
+        Ok(())
+    }
+}
+
+fn fill_circle(
+    context: &CanvasRenderingContext2d,
+    (x, y): (f64, f64),
+    radius: f64,
+) -> Result<(), JsValue> {
+    context.begin_path();
+    context.arc(x, y, radius, 0.0, 2.0 * PI)?;
+    context.fill();
+    Ok(())
+}
@@ -0,0 +1,162 @@
+//! Browser build of the note detection and the game of libreguitar, driven
+//! by the JS in this directory.
+
+mod canvas_drawer;
+
+use canvas_drawer::CanvasDrawer;
+use config::{Config, ConfigError, File, FileFormat};
+use libreguitar_core::audio_analysis::{read_channel_buffered, AnalysisResult, AudioAnalyzer};
+use libreguitar_core::core::{AudioCfg, GameCfg, NoteRegistry, Tuning};
+use libreguitar_core::events::GameEvent;
+use libreguitar_core::game::{ActiveNotes, GameCommand, GameSession, RandomSelector};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::fmt::Display;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement};
+#[cfg(target_arch = "wasm32")]
+use web_time::Instant;
+
+/// The browser has no config directory, so the shipped configuration and
+/// note table are built into the module.
+const APP_CFG: &str = include_str!("../../cfg/app.toml");
+const AUDIO_CFG: &str = include_str!("../../cfg/audio.toml");
+const GAME_CFG: &str = include_str!("../../cfg/game.toml");
+const FREQUENCIES_CSV: &str = include_str!("../../resources/guitar_frequencies.csv");
+
+/// Settings of `app.toml` that apply in the browser.
+#[derive(Deserialize)]
+struct WebAppCfg {
+    block_size: usize,
+}
+
+/// Parses the TOML `text` of a bundled config file. Includes are not
+/// supported.
+fn parse_cfg<T>(text: &str) -> Result<T, ConfigError>
+where
+    T: DeserializeOwned,
+{
+    let mut s = Config::default();
+    s.merge(File::from_str(text, FileFormat::Toml))?;
+    s.try_into()
+}
+
+fn js_error(e: impl Display) -> JsValue {
+    JsValue::from_str(&e.to_string())
+}
+
+/// JSON array of `events`, as read by the JS side.
+fn to_json(events: &[GameEvent]) -> String {
+    Value::Array(events.iter().map(GameEvent::to_json_value).collect()).to_string()
+}
+
+/// The game in the browser, with the same note detection and game rules as
+/// the native app. The JS in `web/` pushes the microphone samples from a Web
+/// Audio worklet and draws the game on a canvas every animation frame.
+#[wasm_bindgen]
+pub struct WebTrainer {
+    analyzer: AudioAnalyzer,
+    /// Last block of samples, analysed whenever new samples are pushed.
+    window: VecDeque<f64>,
+    session: GameSession,
+    drawer: CanvasDrawer,
+    /// Latest detected note, shown below the fretboard.
+    detection: Option<AnalysisResult>,
+}
+
+#[wasm_bindgen]
+impl WebTrainer {
+    /// Trainer for the input at `sample_rate` on a guitar tuned to
+    /// `tuning`, e.g. `"EADGBE"` or `"D2 A2 D3 G3 A3 D4"`.
+    #[wasm_bindgen(constructor)]
+    pub fn new(sample_rate: f32, tuning: &str) -> Result<WebTrainer, JsValue> {
+        let app_cfg: WebAppCfg = parse_cfg(APP_CFG).map_err(js_error)?;
+        let audio_cfg: AudioCfg = parse_cfg(AUDIO_CFG).map_err(js_error)?;
+        let game_cfg: GameCfg = parse_cfg(GAME_CFG).map_err(js_error)?;
+        let note_registry = NoteRegistry::from_csv_str(FREQUENCIES_CSV).map_err(js_error)?;
+        let tuning = Tuning::from_compact(tuning, &note_registry).map_err(js_error)?;
+        let active_notes = ActiveNotes::new(
+            &note_registry,
+            &tuning,
+            game_cfg.strings().map_err(js_error)?,
+            game_cfg.frets().map_err(js_error)?,
+        );
+        let drawer = CanvasDrawer::new(
+            active_notes.fret_range.clone(),
+            active_notes.string_range.clone(),
+        );
+        Ok(WebTrainer {
+            analyzer: AudioAnalyzer::new(sample_rate as usize, note_registry.notes(), audio_cfg)
+                .map_err(js_error)?,
+            window: VecDeque::from(vec![0.0; app_cfg.block_size]),
+            session: GameSession::new(
+                active_notes,
+                &game_cfg,
+                Box::new(RandomSelector::default()),
+                Instant::now(),
+            ),
+            drawer,
+            detection: None,
+        })
+    }
+
+    /// Analyses the last block of samples after appending the mono
+    /// `samples`, and returns the detection and the game events it caused as
+    /// a JSON array (see `GameEvent::to_json_value`).
+    #[wasm_bindgen(js_name = pushSamples)]
+    pub fn push_samples(&mut self, samples: &[f32]) -> String {
+        read_channel_buffered(samples, 1, 0, &mut self.window);
+        let analysis = self
+            .analyzer
+            .identify_next_note(self.window.iter().cloned(), samples.len());
+        let mut events: Vec<GameEvent> = GameEvent::detection(&analysis).into_iter().collect();
+        self.detection = Some(analysis.clone());
+        events.extend(self.session.handle_analysis(analysis));
+        to_json(&events)
+    }
+
+    #[wasm_bindgen(js_name = togglePause)]
+    pub fn toggle_pause(&mut self) -> String {
+        to_json(&self.session.handle_command(&GameCommand::TogglePause))
+    }
+
+    /// Skips the current target, which counts as missed.
+    pub fn skip(&mut self) -> String {
+        to_json(&self.session.handle_command(&GameCommand::Skip))
+    }
+
+    /// Draws the fretboard with the current target, the latest detection and
+    /// the score on `canvas`.
+    pub fn draw(&self, canvas: &HtmlCanvasElement) -> Result<(), JsValue> {
+        let context = canvas
+            .get_context("2d")?
+            .ok_or_else(|| js_error("Canvas has no 2D context"))?
+            .dyn_into::<CanvasRenderingContext2d>()?;
+        self.drawer.draw(
+            &context,
+            (canvas.width() as f64, canvas.height() as f64),
+            self.session.state(),
+            self.detection.as_ref(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundled_configuration() {
+        let mut trainer = WebTrainer::new(44100.0, "EADGBE").unwrap();
+        assert_eq!("[]", trainer.push_samples(&[0.0; 512]));
+        assert_eq!(
+            r#"[{"paused":true,"type":"paused","version":1}]"#,
+            trainer.toggle_pause()
+        );
+    }
+}